@@ -77,6 +77,7 @@ fn push_macro_block(b: &mut Bencher) {
     const EPOCHS: u64 = 10;
     let cfg = ChainConfig {
         stake_epochs: 100,
+        unbonding_epochs: 100,
         micro_blocks_in_epoch: 1,
         ..Default::default()
     };
@@ -112,6 +113,7 @@ fn recover_macro_block(b: &mut Bencher) {
     const EPOCHS: u64 = 10;
     let cfg = ChainConfig {
         stake_epochs: 100,
+        unbonding_epochs: 100,
         micro_blocks_in_epoch: 1,
         ..Default::default()
     };
@@ -19,7 +19,9 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 use crate::block::{MacroBlock, MacroBlockHeader, MicroBlock, MicroBlockHeader};
-use crate::output::{Output, PaymentOutput, PublicPaymentOutput, StakeOutput};
+use crate::output::{
+    HtlcOutput, MultisigOutput, Output, PaymentOutput, PublicPaymentOutput, StakeOutput,
+};
 use crate::transaction::Transaction;
 use bit_vec::BitVec;
 use serde_derive::{Deserialize, Serialize};
@@ -115,6 +117,8 @@ pub enum OriginalOutput {
     PaymentOutput(PaymentOutput),
     PublicPaymentOutput(PublicPaymentOutput),
     StakeOutput(StakeOutput),
+    MultisigOutput(MultisigOutput),
+    HtlcOutput(HtlcOutput),
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -130,6 +134,8 @@ impl From<Output> for OriginalOutput {
             Output::PaymentOutput(p) => OriginalOutput::PaymentOutput(p),
             Output::PublicPaymentOutput(p) => OriginalOutput::PublicPaymentOutput(p),
             Output::StakeOutput(p) => OriginalOutput::StakeOutput(p),
+            Output::MultisigOutput(p) => OriginalOutput::MultisigOutput(p),
+            Output::HtlcOutput(p) => OriginalOutput::HtlcOutput(p),
         }
     }
 }
@@ -139,6 +145,8 @@ impl From<OriginalOutput> for Output {
             OriginalOutput::PaymentOutput(p) => Output::PaymentOutput(p),
             OriginalOutput::PublicPaymentOutput(p) => Output::PublicPaymentOutput(p),
             OriginalOutput::StakeOutput(p) => Output::StakeOutput(p),
+            OriginalOutput::MultisigOutput(p) => Output::MultisigOutput(p),
+            OriginalOutput::HtlcOutput(p) => Output::HtlcOutput(p),
         }
     }
 }
@@ -25,7 +25,7 @@ use log::{debug, info, trace};
 use serde_derive::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::mem;
-use stegos_crypto::hash::Hash;
+use stegos_crypto::hash::{Hash, Hashable, Hasher};
 use stegos_crypto::scc::PublicKey;
 use stegos_crypto::utils::print_nbits;
 
@@ -139,22 +139,109 @@ impl Awards {
             .map(|(k, _)| k)
             .collect();
 
-        if array.is_empty() {
-            info!("No honest validators was found, for awarding.");
-            return None;
-        }
-        let winner_num = crate::election::shrink_hash(random);
-        let winner_num = winner_num.checked_abs().unwrap_or(0) as usize;
-        let winner = winner_num % array.len();
-        let winner_pk = array[winner];
-        trace!(
-            "Service award produce winner: num_validators={}, winner={}, winner_pk={}",
-            array.len(),
-            winner,
-            winner_pk
-        );
+        let winner_pk = select_winner(random, &array)?;
         Some((winner_pk, mem::replace(&mut self.budget, 0)))
     }
+
+    /// Returns validators which are eligible for the next draw.
+    pub fn eligible_validators(&self) -> Vec<PublicKey> {
+        self.validators_activity
+            .iter()
+            .filter(|(_, s)| *s == &ValidatorAwardState::Active)
+            .map(|(k, _)| *k)
+            .collect()
+    }
+
+    /// Same as `check_winners`, but returns the full record of the draw.
+    pub fn draw(&mut self, epoch: u64, random: Hash) -> AwardDraw {
+        let eligible = self.eligible_validators();
+        let difficulty = self.difficulty;
+        let winner = self.check_winners(random);
+        AwardDraw {
+            epoch,
+            random,
+            difficulty,
+            eligible,
+            winner: winner.map(|(winner_pk, _)| winner_pk),
+            payout: winner.map(|(_, amount)| amount).unwrap_or(0),
+        }
+    }
+}
+
+/// Record of a service award draw, performed at the end of some epoch.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AwardDraw {
+    /// Epoch of the macro block which performed the draw.
+    pub epoch: u64,
+    /// Random seed of the macro block.
+    pub random: Hash,
+    /// Number of bits to be zero in the seed.
+    pub difficulty: usize,
+    /// Validators which took part in the draw.
+    pub eligible: Vec<PublicKey>,
+    /// Winner of the draw, if any.
+    pub winner: Option<PublicKey>,
+    /// Amount paid to the winner.
+    pub payout: i64,
+}
+
+impl AwardDraw {
+    /// Re-evaluates the draw from its seed and returns true if the recorded winner is correct.
+    pub fn verify(&self) -> bool {
+        let winner = if chkbits(self.random.base_vector(), self.difficulty) {
+            select_winner(self.random, &self.eligible)
+        } else {
+            None
+        };
+        winner == self.winner && (self.winner.is_some() || self.payout == 0)
+    }
+}
+
+/// Picks the winner among eligible validators using the random seed.
+fn select_winner(random: Hash, eligible: &[PublicKey]) -> Option<PublicKey> {
+    if eligible.is_empty() {
+        info!("No honest validators was found, for awarding.");
+        return None;
+    }
+    let winner_num = crate::election::shrink_hash(random);
+    let winner_num = winner_num.checked_abs().unwrap_or(0) as usize;
+    let winner = winner_num % eligible.len();
+    let winner_pk = eligible[winner];
+    trace!(
+        "Service award produce winner: num_validators={}, winner={}, winner_pk={}",
+        eligible.len(),
+        winner,
+        winner_pk
+    );
+    Some(winner_pk)
+}
+
+impl Hashable for ValidatorAwardState {
+    fn hash(&self, state: &mut Hasher) {
+        match self {
+            ValidatorAwardState::Failed { epoch, offset } => {
+                "Failed".hash(state);
+                epoch.hash(state);
+                offset.hash(state);
+            }
+            ValidatorAwardState::Active => {
+                "Active".hash(state);
+            }
+        }
+    }
+}
+
+impl Hashable for Awards {
+    fn hash(&self, state: &mut Hasher) {
+        "Awards".hash(state);
+        self.budget.hash(state);
+        (self.difficulty as u64).hash(state);
+        (self.validators_activity.len() as u64).hash(state);
+        for (validator, activity) in self.validators_activity.iter() {
+            validator.hash(state);
+            activity.hash(state);
+        }
+    }
 }
 
 pub fn chkbits(h: &[u8], nbits: usize) -> bool {
@@ -377,4 +464,42 @@ mod test {
         assert_eq!(award.budget, 0);
         assert_eq!(award.validators_activity, BTreeMap::new());
     }
+
+    // check that a recorded draw can be re-verified from its seed.
+    #[test]
+    fn verify_draw() {
+        let _ = simple_logger::init();
+        let difficulty = 10;
+        let keys = testing_keys();
+
+        let first_epoch: BTreeMap<_, _> = keys
+            .into_iter()
+            .map(|k| (k, ValidatorAwardState::Active))
+            .collect();
+        let mut award = Awards::new(difficulty);
+        award.finalize_epoch(100, first_epoch.clone());
+
+        // a loosing draw keeps the budget.
+        let draw = award.draw(1, loosing_hash(Hash::digest("seed")));
+        assert!(draw.verify());
+        assert_eq!(draw.winner, None);
+        assert_eq!(draw.payout, 0);
+        assert_eq!(draw.eligible.len(), first_epoch.len());
+        assert_eq!(award.budget, 100);
+
+        // a winning draw pays out the budget.
+        let draw = award.draw(2, winning_hash(Hash::digest("seed"), difficulty));
+        assert!(draw.verify());
+        assert!(draw.winner.is_some());
+        assert_eq!(draw.payout, 100);
+        assert_eq!(award.budget, 0);
+
+        // tampered draws are detected.
+        let mut tampered = draw.clone();
+        tampered.winner = None;
+        assert!(!tampered.verify());
+        let mut tampered = draw.clone();
+        tampered.random = loosing_hash(Hash::digest("seed"));
+        assert!(!tampered.verify());
+    }
 }
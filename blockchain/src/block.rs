@@ -22,6 +22,7 @@
 // SOFTWARE.
 
 use crate::error::TransactionError;
+use crate::governance::ParameterChange;
 use crate::merkle::*;
 use crate::output::*;
 use crate::timestamp::Timestamp;
@@ -35,8 +36,10 @@ use stegos_crypto::hash::{Hash, Hashable, Hasher};
 use stegos_crypto::pbc;
 use stegos_crypto::scc::Fr;
 
-/// Blockchain version.
+/// Blockchain version, used by genesis and until the first hard fork.
 pub const VERSION: u64 = 1;
+/// The latest block version supported by this software.
+pub const MAX_VERSION: u64 = VERSION;
 
 //--------------------------------------------------------------------------------------------------
 // Micro Blocks.
@@ -352,6 +355,20 @@ pub struct MacroBlockHeader {
 
     /// Merklish root of all canary hashes.
     pub canaries_range_hash: Hash,
+
+    /// Commitment to the blockchain state after this block (UTXO, escrow, service awards).
+    /// Used to verify state snapshots. Missing in the genesis block.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_hash: Option<Hash>,
+
+    /// Merklish root of all UTXO hashes after this block.
+    /// Used to prove membership of outputs. Missing in the genesis block.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub utxo_range_hash: Option<Hash>,
+
+    /// Parameter change voted by validators signed this block, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proposal: Option<ParameterChange>,
 }
 
 impl Hashable for MacroBlockHeader {
@@ -378,6 +395,15 @@ impl Hashable for MacroBlockHeader {
         self.outputs_len.hash(state);
         self.outputs_range_hash.hash(state);
         self.canaries_range_hash.hash(state);
+        if let Some(state_hash) = &self.state_hash {
+            state_hash.hash(state);
+        }
+        if let Some(utxo_range_hash) = &self.utxo_range_hash {
+            utxo_range_hash.hash(state);
+        }
+        if let Some(proposal) = &self.proposal {
+            proposal.hash(state);
+        }
     }
 }
 
@@ -449,6 +475,8 @@ impl MacroBlock {
         block_reward: i64,
         activity_map: BitVec,
         validators: StakersGroup,
+        state_hash: Hash,
+        utxo_range_hash: Hash,
         transactions: &[Transaction],
     ) -> Result<MacroBlock, TransactionError> {
         //
@@ -486,7 +514,7 @@ impl MacroBlock {
         //
         let inputs: Vec<Hash> = inputs.into_iter().collect();
         let outputs: Vec<Output> = outputs.into_iter().map(|(_, o)| o).collect();
-        let block = Self::new(
+        let mut block = Self::new(
             previous,
             epoch,
             view_change,
@@ -501,6 +529,8 @@ impl MacroBlock {
             inputs,
             outputs,
         );
+        block.header.state_hash = Some(state_hash);
+        block.header.utxo_range_hash = Some(utxo_range_hash);
         Ok(block)
     }
 
@@ -568,6 +598,9 @@ impl MacroBlock {
             outputs_len,
             outputs_range_hash,
             canaries_range_hash,
+            state_hash: None,
+            utxo_range_hash: None,
+            proposal: None,
         };
 
         // Create the block.
@@ -21,7 +21,7 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use crate::awards::{Awards, ValidatorAwardState};
+use crate::awards::{AwardDraw, Awards, ValidatorAwardState};
 use crate::block::*;
 use crate::config::*;
 use crate::election::mix;
@@ -29,12 +29,18 @@ use crate::election::ElectionInfo;
 use crate::election::{self, ElectionResult};
 use crate::error::*;
 use crate::escrow::*;
-use crate::merkle::Merkle;
+use crate::governance::{Governance, ParameterChange};
+use crate::light::{output_proofs, LightBlock, LightMacroBlock, OutputProof};
+use crate::liveness::Liveness;
+use crate::merkle::{Merkle, MerkleProof};
 use crate::metrics;
+use crate::multisignature::check_multi_signature;
 use crate::mvcc::MultiVersionedMap;
 use crate::output::*;
+use crate::snapshot::{self, MacroBlockCertificate, StateSnapshot};
 use crate::timestamp::Timestamp;
 use crate::transaction::{CoinbaseTransaction, ServiceAwardTransaction, Transaction};
+use crate::utxo_tree::UtxoTree;
 use crate::view_changes::ViewChangeProof;
 use bit_vec::BitVec;
 use byteorder::{BigEndian, ByteOrder};
@@ -43,7 +49,7 @@ use rocksdb;
 use rocksdb::{ColumnFamily, Snapshot, WriteBatch};
 use serde_derive::{Deserialize, Serialize};
 use std::borrow::Cow;
-use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::path::Path;
 use stegos_crypto::bulletproofs::fee_a;
 use stegos_crypto::hash::*;
@@ -88,7 +94,7 @@ pub struct EpochInfo {
 }
 
 /// Information of current chain, that is used as proof of viewchange.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct ChainInfo {
     pub epoch: u64,
     pub offset: u32,
@@ -149,6 +155,43 @@ pub(crate) enum OutputKey {
         /// Output number.
         txout_id: u32,
     },
+    /// Output restored from a state snapshot.
+    Snapshot {
+        /// Epoch of the snapshot.
+        epoch: u64,
+    },
+}
+
+/// Location of a transaction in the blockchain.
+#[derive(Eq, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionKey {
+    /// Block Epoch.
+    pub epoch: u64,
+    /// Block Height.
+    pub offset: u32,
+    /// Transaction number.
+    pub tx_id: u32,
+}
+
+/// A transaction stored in the explorer index.
+/// Keeps the body of the transaction because micro blocks are removed at the end of epoch.
+#[derive(Debug, Clone)]
+pub struct IndexedTransaction {
+    /// Location of the transaction.
+    pub key: TransactionKey,
+    /// Hash of the micro block.
+    pub block_hash: Hash,
+    /// The transaction.
+    pub transaction: Transaction,
+}
+
+/// A persistent record about an incomplete operation with a micro block.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub(crate) enum UndoLogEntry {
+    /// The micro block has been written to the disk, but not registered yet.
+    Push(Hash),
+    /// The micro block is scheduled to be reverted.
+    Pop(Hash),
 }
 
 /// A helper to store the global monetary balance in MultiVersionedMap.
@@ -186,23 +229,44 @@ pub struct OutputRecovery {
     pub block_hash: Hash,
     pub is_final: bool,
     pub timestamp: Timestamp,
+    /// Proof of membership in utxo_range_hash of the last macro block.
+    /// None for outputs created after the last macro block.
+    pub utxo_proof: Option<MerkleProof>,
 }
 
 // colon families.
 const BLOCK_BY_HASH: &'static str = "block_by_hash";
 const OUTPUT_BY_HASH: &'static str = "output_by_hash";
 const ESCROW: &'static str = "escrow";
+const SNAPSHOT_OUTPUTS: &'static str = "snapshot_outputs";
+const UNDO_LOG: &'static str = "undo_log";
+const TX_BY_HASH: &'static str = "tx_by_hash";
+const TX_BY_INPUT: &'static str = "tx_by_input";
+const TX_BY_OUTPUT: &'static str = "tx_by_output";
+const OUTPUTS_BY_RECIPIENT: &'static str = "outputs_by_recipient";
 
 const SERVICE_AWARD: &'static str = "service_award";
 const EPOCH_INFOS: &'static str = "epoch_infos";
+const MEMPOOL: &'static str = "mempool";
+const COMMITTED_TXS: &'static str = "committed_txs";
+const CERTIFICATES: &'static str = "certificates";
 const META: &'static str = "META";
 
 const COLON_FAMILIES: &[&'static str] = &[
     BLOCK_BY_HASH,
     OUTPUT_BY_HASH,
     ESCROW,
+    SNAPSHOT_OUTPUTS,
+    UNDO_LOG,
+    TX_BY_HASH,
+    TX_BY_INPUT,
+    TX_BY_OUTPUT,
+    OUTPUTS_BY_RECIPIENT,
     SERVICE_AWARD,
     EPOCH_INFOS,
+    MEMPOOL,
+    COMMITTED_TXS,
+    CERTIFICATES,
     META,
 ];
 
@@ -211,13 +275,22 @@ const BALANCE: &'static str = "balance";
 const EPOCH: &'static str = "epoch";
 const ELECTION_RESULT: &'static str = "election_result";
 const AWARDS: &'static str = "awards";
+const SNAPSHOT: &'static str = "snapshot";
+const INDEXES: &'static str = "indexes";
+const GOVERNANCE: &'static str = "governance";
+const LIVENESS: &'static str = "liveness";
 
 /// The blockchain database.
 pub struct Blockchain {
     //
     // Configuration.
     //
+    /// Configuration of the genesis, including the hard-fork schedule.
+    genesis_cfg: ChainConfig,
+    /// Consensus rules of the current epoch.
     cfg: ChainConfig,
+    /// Parameter changes voted by validators.
+    governance: Governance,
     // Don't store consistency check into `ChainConfig`, because it can be different on nodes.
     consistency_check: ConsistencyCheck,
 
@@ -230,6 +303,8 @@ pub struct Blockchain {
     block_by_hash: BlockByHashMap,
     /// In-memory index to lookup UTXO by its hash.
     output_by_hash: OutputByHashMap,
+    /// Merkle tree of UTXO hashes as of the last macro block.
+    utxo_tree: UtxoTree,
     /// Global monetary balance.
     balance: BalanceMap,
     /// In-memory storage of stakes.
@@ -238,6 +313,12 @@ pub struct Blockchain {
     pub(crate) vdf: VDF,
     /// VDF difficulty,
     difficulty: u64,
+    /// Epoch of the state snapshot used to initialize this blockchain, if any.
+    /// Blocks before this epoch are not available, except the genesis.
+    snapshot_epoch: Option<u64>,
+    /// How many recent epochs of full blocks to keep on the disk, 0 to keep the full history.
+    /// A local storage option which is not a part of consensus.
+    pruning_epochs: u64,
     /// Starting info for each past epochs.
 
     //
@@ -271,7 +352,15 @@ pub struct Blockchain {
     awards: Awards,
     epoch_activity: ValidatorsActivity,
 
-    // Block ache
+    //
+    // Downtime jailing.
+    //
+    liveness: Liveness,
+
+    // Block cache.
+    // Contains only macro blocks of the last `stake_epochs + 1` epochs.
+    // The blocks themselves are persisted in the database, and the cache
+    // is reloaded from it on startup, so it doesn't need an undo log.
     cache: VecDeque<Block>,
 }
 
@@ -281,12 +370,19 @@ impl Blockchain {
     //----------------------------------------------------------------------------------------------
 
     pub fn new(
-        cfg: ChainConfig,
+        genesis_cfg: ChainConfig,
         chain_dir: &Path,
         consistency_check: ConsistencyCheck,
         genesis: MacroBlock,
         timestamp: Timestamp,
     ) -> Result<Blockchain, BlockchainError> {
+        //
+        // Configuration.
+        //
+        genesis_cfg.check()?;
+        let cfg = genesis_cfg.at_epoch(0);
+        let governance = Governance::default();
+
         //
         // Storage.
         //
@@ -295,6 +391,13 @@ impl Blockchain {
         opts.create_if_missing(true);
         opts.create_missing_column_families(true);
         let database = rocksdb::DB::open_cf(&opts, chain_dir, COLON_FAMILIES)?;
+        let snapshot_epoch = {
+            let cf_meta = database.cf_handle(META).unwrap();
+            match database.get_cf(cf_meta, SNAPSHOT.as_bytes())? {
+                Some(buffer) => Some(LSN::from_buffer(&buffer)?.0),
+                None => None,
+            }
+        };
         let block_by_hash: BlockByHashMap = BlockByHashMap::new();
         let output_by_hash: OutputByHashMap = OutputByHashMap::new();
         let mut balance: BalanceMap = BalanceMap::new();
@@ -327,19 +430,28 @@ impl Blockchain {
         //
         let awards = Awards::new(cfg.awards_difficulty);
         let epoch_activity = MultiVersionedMap::new();
+        let liveness = Liveness::default();
         // Block cache.
         let cache = VecDeque::with_capacity(cfg.stake_epochs as usize + 1);
 
+        // UTXO tree.
+        let utxo_tree = UtxoTree::new(&[]);
+
         let mut blockchain = Blockchain {
+            genesis_cfg,
             cfg,
+            governance,
             consistency_check,
             database,
             block_by_hash,
             output_by_hash,
+            utxo_tree,
             balance,
             escrow,
             vdf,
             difficulty,
+            snapshot_epoch,
+            pruning_epochs: 0,
             epoch,
             offset,
             election_result,
@@ -351,10 +463,12 @@ impl Blockchain {
             last_block_hash,
             awards,
             epoch_activity,
+            liveness,
             cache,
         };
 
         blockchain.init(genesis, timestamp, consistency_check)?;
+        blockchain.init_indexes()?;
         Ok(blockchain)
     }
 
@@ -430,6 +544,7 @@ impl Blockchain {
         let mut escrow = EscrowMap::new();
         recover_map!(cf_escrow, escrow, lsn);
         self.escrow.escrow = escrow;
+        self.rebuild_utxo_tree();
 
         let block = self.macro_block(lsn.0)?.into_owned();
 
@@ -443,6 +558,13 @@ impl Blockchain {
         self.difficulty = block.header.difficulty;
         debug!("Set difficulty to to {}", self.difficulty);
         self.awards = recover_meta!(AWARDS);
+        if let Some(buffer) = self.database.get_cf(cf_meta, GOVERNANCE.as_bytes())? {
+            self.governance = Governance::from_buffer(&buffer)?;
+        }
+        if let Some(buffer) = self.database.get_cf(cf_meta, LIVENESS.as_bytes())? {
+            self.liveness = Liveness::from_buffer(&buffer)?;
+        }
+        self.activate_forks();
         info!("Snapshot recovered, recovering microblocks of last epoch.");
         // microblocks starting index is (next epoch, and zero offset);
         let mut microblock_lsn = lsn;
@@ -573,15 +695,45 @@ impl Blockchain {
         timestamp: Timestamp,
         force_check: ConsistencyCheck,
     ) -> Result<(), BlockchainError> {
-        if force_check != ConsistencyCheck::Full && force_check != ConsistencyCheck::LoadChain {
+        if let Some(snapshot_epoch) = self.snapshot_epoch {
+            if force_check == ConsistencyCheck::Full || force_check == ConsistencyCheck::LoadChain {
+                warn!(
+                    "Blockchain was initialized from a state snapshot or pruned, \
+                     ignoring {:?} consistency check: snapshot_epoch={}",
+                    force_check, snapshot_epoch
+                );
+            }
+        }
+        self.recover_undo_log()?;
+
+        // Check genesis before any recovery.
+        let genesis_hash = Hash::digest(&genesis);
+        let genesis_key = Self::block_key(LSN(0, MACRO_BLOCK_OFFSET));
+        if let Some(buffer) = self.database.get(&genesis_key)? {
+            let stored_genesis = Block::from_buffer(&buffer)?.unwrap_macro();
+            let stored_genesis_hash = Hash::digest(&stored_genesis);
+            if genesis_hash != stored_genesis_hash {
+                return Err(BlockchainError::IncompatibleGenesis(
+                    genesis_hash,
+                    stored_genesis_hash,
+                )
+                .into());
+            }
+        }
+
+        if (force_check != ConsistencyCheck::Full && force_check != ConsistencyCheck::LoadChain)
+            || self.snapshot_epoch.is_some()
+        {
             if self.try_recover_fast(timestamp)? {
                 return Ok(());
+            } else if let Some(snapshot_epoch) = self.snapshot_epoch {
+                // Blocks before the snapshot are gone, replaying the rest is not possible.
+                return Err(SnapshotError::MissingHistory(snapshot_epoch).into());
             } else {
                 debug!("Failed to recover faster, try recover from blocks.");
             }
         }
 
-        let genesis_hash = Hash::digest(&genesis);
         if self.try_recover_blocks(genesis_hash, timestamp, force_check)? {
             return Ok(());
         }
@@ -595,6 +747,42 @@ impl Blockchain {
         Ok(())
     }
 
+    ///
+    /// Complete micro block rollbacks interrupted by a crash.
+    /// Blocks with unfinished push are checked later by recover_block().
+    ///
+    /// The undo log covers only micro blocks of the current epoch.
+    /// Macro blocks are final once committed, so rollbacks never cross
+    /// an epoch boundary.
+    ///
+    fn recover_undo_log(&mut self) -> Result<(), BlockchainError> {
+        let cf_undo_log = self.database.cf_handle(UNDO_LOG).unwrap();
+        let mut batch = WriteBatch::default();
+        for (key, value) in self
+            .database
+            .iterator_cf(cf_undo_log, rocksdb::IteratorMode::Start)?
+        {
+            let block_hash = match UndoLogEntry::from_buffer(&value)? {
+                UndoLogEntry::Push(_) => continue,
+                UndoLogEntry::Pop(block_hash) => block_hash,
+            };
+            if let Some(buffer) = self.database.get(&key)? {
+                let block = Block::from_buffer(&buffer)?;
+                let block = block.unwrap_micro();
+                assert_eq!(Hash::digest(&block), block_hash);
+                warn!(
+                    "Reverting a micro block after interrupted rollback: epoch={}, offset={}, block={}",
+                    block.header.epoch, block.header.offset, block_hash
+                );
+                batch.delete(&key)?;
+                self.unindex_micro_block(&mut batch, &block)?;
+            }
+            batch.delete_cf(cf_undo_log, &key)?;
+        }
+        self.database.write(batch)?;
+        Ok(())
+    }
+
     fn recover_block(
         &mut self,
         block: Block,
@@ -604,17 +792,51 @@ impl Blockchain {
         // Skip validate_macro_block()/validate_micro_block().
         match block {
             Block::MicroBlock(block) => {
+                let block_hash = Hash::digest(&block);
                 debug!(
                     "Recovering a micro block from the disk: epoch={}, offset={}, block={}",
-                    block.header.epoch,
-                    block.header.offset,
-                    Hash::digest(&block)
+                    block.header.epoch, block.header.offset, block_hash
                 );
-                if force_check == ConsistencyCheck::Full {
+                let lsn = LSN(block.header.epoch, block.header.offset);
+                let key = Self::block_key(lsn);
+                let cf_undo_log = self.database.cf_handle(UNDO_LOG).unwrap();
+                let is_pending = match self.database.get_cf(cf_undo_log, &key)? {
+                    Some(buffer) => {
+                        assert_eq!(
+                            UndoLogEntry::from_buffer(&buffer)?,
+                            UndoLogEntry::Push(block_hash)
+                        );
+                        true
+                    }
+                    None => false,
+                };
+                if is_pending {
+                    // The node crashed in the middle of push_micro_block().
+                    if let Err(e) = self.validate_micro_block(&block, timestamp, true) {
+                        warn!(
+                            "Reverting a partially applied micro block: epoch={}, offset={}, block={}, error={}",
+                            block.header.epoch, block.header.offset, block_hash, e
+                        );
+                        let cf_undo_log = self.database.cf_handle(UNDO_LOG).unwrap();
+                        let mut batch = WriteBatch::default();
+                        batch.delete(&key)?;
+                        batch.delete_cf(cf_undo_log, &key)?;
+                        self.unindex_micro_block(&mut batch, &block)?;
+                        self.database.write(batch)?;
+                        return Ok(());
+                    }
+                } else if force_check == ConsistencyCheck::Full {
                     self.validate_micro_block(&block, timestamp, true)?;
                 }
-                let lsn = LSN(block.header.epoch, block.header.offset);
                 let _ = self.register_micro_block(lsn, block);
+                if is_pending {
+                    info!(
+                        "Replayed a partially applied micro block: epoch={}, offset={}, block={}",
+                        lsn.0, lsn.1, block_hash
+                    );
+                    let cf_undo_log = self.database.cf_handle(UNDO_LOG).unwrap();
+                    self.database.delete_cf(cf_undo_log, &key)?;
+                }
             }
             Block::MacroBlock(block) => {
                 let block_hash = Hash::digest(&block);
@@ -655,6 +877,31 @@ impl Blockchain {
         self.epoch
     }
 
+    /// Returns the epoch of the state snapshot used to initialize this blockchain
+    /// or the first epoch kept after pruning, if any.
+    /// Blocks before this epoch are not available, except the genesis.
+    #[inline(always)]
+    pub fn snapshot_epoch(&self) -> Option<u64> {
+        self.snapshot_epoch
+    }
+
+    ///
+    /// Keep only the last `pruning_epochs` epochs of full blocks, 0 to keep the full history.
+    ///
+    /// Escrow, service awards and micro block rollback need the blocks of
+    /// the last `stake_epochs` epochs, so at least `stake_epochs + 1` epochs are kept.
+    ///
+    pub fn set_pruning_epochs(&mut self, pruning_epochs: u64) -> Result<(), BlockchainError> {
+        if pruning_epochs != 0 && pruning_epochs <= self.cfg.stake_epochs {
+            return Err(BlockchainError::InvalidPruningEpochs(
+                pruning_epochs,
+                self.cfg.stake_epochs,
+            ));
+        }
+        self.pruning_epochs = pruning_epochs;
+        Ok(())
+    }
+
     /// Returns the number of blocks in the current epoch.
     #[inline(always)]
     pub fn offset(&self) -> u32 {
@@ -671,13 +918,52 @@ impl Blockchain {
         self.output_by_hash.get(output_hash).is_some()
     }
 
+    /// Rebuild the Merkle tree of UTXO hashes from the current UTXO set.
+    /// Must be called when UTXO set is loaded without processing macro blocks.
+    fn rebuild_utxo_tree(&mut self) {
+        self.utxo_tree = UtxoTree::new(self.output_by_hash.keys());
+    }
+
+    /// Returns the Merklish root of all UTXO hashes as of the last macro block.
+    pub fn utxo_range_hash(&self) -> Hash {
+        self.utxo_tree.root_hash()
+    }
+
+    /// Returns the Merklish root of all UTXO hashes after a macro block
+    /// with `inputs` and `outputs`, without changing the current state.
+    pub(crate) fn utxo_range_hash_after(&self, inputs: &[Hash], outputs: &[Hash]) -> Hash {
+        // Annihilate UTXO which were created and spent in the same epoch.
+        let spent: HashSet<&Hash> = inputs.iter().collect();
+        let created: HashSet<&Hash> = outputs.iter().collect();
+        let inputs = inputs.iter().filter(|h| !created.contains(h));
+        let outputs = outputs.iter().filter(|h| !spent.contains(h));
+        self.utxo_tree.root_hash_after(inputs, outputs)
+    }
+
+    /// Returns a proof of membership of UTXO in utxo_range_hash of the last macro block.
+    fn utxo_proof(&self, output_hash: &Hash) -> Option<MerkleProof> {
+        self.utxo_tree.proof(output_hash)
+    }
+
     /// Resolve UTXO by hash.
     pub fn output_by_hash_with_proof(
         &self,
         output_hash: &Hash,
     ) -> Result<Option<OutputRecovery>, StorageError> {
         match self.output_by_hash.get(output_hash) {
-            Some(OutputKey::MacroBlock { epoch, output_id }) => {
+            Some(output_key) => self.output_by_key(output_hash, output_key),
+            None => Ok(None),
+        }
+    }
+
+    /// Resolve UTXO by its location.
+    fn output_by_key(
+        &self,
+        output_hash: &Hash,
+        output_key: &OutputKey,
+    ) -> Result<Option<OutputRecovery>, StorageError> {
+        match output_key {
+            OutputKey::MacroBlock { epoch, output_id } => {
                 let block = self.macro_block(*epoch)?;
                 assert_eq!(block.header.epoch, *epoch);
                 if let Some(output) = block.outputs.get(*output_id as usize) {
@@ -687,18 +973,19 @@ impl Blockchain {
                         block_hash: Hash::digest(block.as_ref()),
                         is_final: true,
                         timestamp: block.header.timestamp,
+                        utxo_proof: self.utxo_proof(output_hash),
                     };
                     Ok(Some(result))
                 } else {
                     Ok(None) // Pruned.
                 }
             }
-            Some(OutputKey::MicroBlock {
+            OutputKey::MicroBlock {
                 epoch,
                 offset,
                 tx_id,
                 txout_id,
-            }) => {
+            } => {
                 let block = self.micro_block(*epoch, *offset)?;
                 let tx = block
                     .transactions
@@ -714,10 +1001,32 @@ impl Blockchain {
                     block_hash: Hash::digest(block.as_ref()),
                     is_final: false,
                     timestamp: block.header.timestamp,
+                    utxo_proof: None,
+                };
+                Ok(Some(result))
+            }
+            OutputKey::Snapshot { epoch } => {
+                let cf_snapshot_outputs = self.database.cf_handle(SNAPSHOT_OUTPUTS).unwrap();
+                let output = match self
+                    .database
+                    .get_cf(cf_snapshot_outputs, output_hash.base_vector())?
+                {
+                    Some(buffer) => {
+                        Output::from_buffer(&buffer).expect("couldn't deserialize output.")
+                    }
+                    None => panic!("Corrupted outputs_by_hash (Snapshot)"),
+                };
+                let block = self.macro_block(*epoch)?;
+                let result = OutputRecovery {
+                    output,
+                    epoch: block.header.epoch,
+                    block_hash: Hash::digest(block.as_ref()),
+                    is_final: true,
+                    timestamp: block.header.timestamp,
+                    utxo_proof: self.utxo_proof(output_hash),
                 };
                 Ok(Some(result))
             }
-            None => Ok(None),
         }
     }
 
@@ -761,6 +1070,7 @@ impl Blockchain {
                                 block_hash: Hash::digest(&block),
                                 is_final: true,
                                 timestamp: block.header.timestamp,
+                                utxo_proof: None,
                             };
                             return Ok(Some(result));
                         }
@@ -776,6 +1086,7 @@ impl Blockchain {
                                     block_hash: Hash::digest(&block),
                                     is_final: false,
                                     timestamp: block.header.timestamp,
+                                    utxo_proof: None,
                                 };
                                 return Ok(Some(result));
                             }
@@ -864,6 +1175,75 @@ impl Blockchain {
             .transpose()?)
     }
 
+    /// Returns the history of service award draws, starting from the specified epoch.
+    pub fn award_draws(
+        &self,
+        starting_epoch: u64,
+        limit: usize,
+    ) -> Result<Vec<AwardDraw>, BlockchainError> {
+        let cf_service_award = self.database.cf_handle(SERVICE_AWARD).unwrap();
+        let key = Self::block_key(LSN(starting_epoch, MACRO_BLOCK_OFFSET));
+        let mode = rocksdb::IteratorMode::From(&key, rocksdb::Direction::Forward);
+        let mut draws = Vec::new();
+        for (_key, value) in self
+            .database
+            .iterator_cf(cf_service_award, mode)?
+            .take(limit)
+        {
+            draws.push(AwardDraw::from_buffer(&*value)?);
+        }
+        Ok(draws)
+    }
+
+    /// Replaces saved pending transactions by `txs`.
+    /// Used to keep mempool across restarts.
+    pub fn save_mempool<'a, I>(&self, txs: I) -> Result<(), BlockchainError>
+    where
+        I: Iterator<Item = (&'a Hash, &'a Transaction)>,
+    {
+        let cf_mempool = self.database.cf_handle(MEMPOOL).unwrap();
+        let mut batch = WriteBatch::default();
+        for (key, _value) in self
+            .database
+            .iterator_cf(cf_mempool, rocksdb::IteratorMode::Start)?
+        {
+            batch.delete_cf(cf_mempool, &key)?;
+        }
+        for (tx_hash, tx) in txs {
+            let data = tx.into_buffer()?;
+            batch.put_cf(cf_mempool, tx_hash.base_vector(), &data)?;
+        }
+        self.database.write(batch)?;
+        Ok(())
+    }
+
+    /// Returns pending transactions saved by save_mempool().
+    /// Transactions must be validated again before use.
+    /// Corrupted entries are skipped and removed from the disk.
+    pub fn load_mempool(&self) -> Result<Vec<Transaction>, BlockchainError> {
+        let cf_mempool = self.database.cf_handle(MEMPOOL).unwrap();
+        let mut txs = Vec::new();
+        let mut batch = WriteBatch::default();
+        for (key, value) in self
+            .database
+            .iterator_cf(cf_mempool, rocksdb::IteratorMode::Start)?
+        {
+            match Transaction::from_buffer(&*value) {
+                Ok(tx) => txs.push(tx),
+                Err(e) => {
+                    warn!(
+                        "Skipped a corrupted saved transaction: key={}, error={}",
+                        hex::encode(&key),
+                        e
+                    );
+                    batch.delete_cf(cf_mempool, &key)?;
+                }
+            }
+        }
+        self.database.write(batch)?;
+        Ok(txs)
+    }
+
     /// Returns current state of election result.
     /// Note:
     /// Election result changes on epoch start, and on slashing.
@@ -873,12 +1253,11 @@ impl Blockchain {
 
     /// Returns election result for the next epoch.
     pub(crate) fn next_election_result(&self, random: pbc::VRF) -> ElectionResult {
-        election::select_validators_slots(
-            self.escrow
-                .get_stakers_majority(self.epoch + 1, self.cfg.min_stake_amount),
-            random,
-            self.cfg.max_slot_count,
-        )
+        let stakers = self
+            .escrow
+            .get_stakers_majority(self.epoch + 1, self.cfg.min_stake_amount);
+        let stakers = self.liveness.filter_stakers(stakers, self.epoch + 1);
+        election::select_validators_slots(stakers, random, self.cfg.max_slot_count)
     }
 
     /// Returns leader public key for specific view_change number.
@@ -982,7 +1361,13 @@ impl Blockchain {
     where
         OutputIter: Iterator<Item = &'a Output>,
     {
-        self.escrow.validate_stakes(inputs, outputs, self.epoch)
+        self.escrow.validate_stakes(
+            inputs,
+            outputs,
+            self.epoch,
+            self.cfg.stake_epochs,
+            self.cfg.unbonding_epochs,
+        )
     }
 
     ///
@@ -996,6 +1381,53 @@ impl Blockchain {
         self.escrow.iter_validator_stakes(validator_pkey)
     }
 
+    ///
+    /// Iterate over stakes delegated to specified validator.
+    ///
+    #[inline]
+    pub fn iter_delegated_stakes(
+        &self,
+        validator_pkey: &pbc::PublicKey,
+    ) -> impl Iterator<Item = (&Hash, i64, &scc::PublicKey, u64)> {
+        self.escrow.iter_delegated_stakes(validator_pkey)
+    }
+
+    ///
+    /// Iterate over unbonding stakes of specified validator.
+    ///
+    #[inline]
+    pub fn iter_unbonding_stakes(
+        &self,
+        validator_pkey: &pbc::PublicKey,
+    ) -> impl Iterator<Item = (&Hash, i64, &scc::PublicKey, u64)> {
+        self.escrow.iter_unbonding_stakes(validator_pkey)
+    }
+
+    ///
+    /// Return shares of delegators in a reward of the validator.
+    ///
+    #[inline]
+    pub fn delegation_payouts(
+        &self,
+        validator_pkey: &pbc::PublicKey,
+        reward: i64,
+    ) -> Vec<(scc::PublicKey, i64)> {
+        self.escrow
+            .delegation_payouts(validator_pkey, reward, self.epoch)
+    }
+
+    ///
+    /// Return payouts of a service award, the winner goes first.
+    ///
+    #[inline]
+    pub fn award_payouts(
+        &self,
+        account_pkey: &scc::PublicKey,
+        reward: i64,
+    ) -> Vec<(scc::PublicKey, i64)> {
+        self.escrow.award_payouts(account_pkey, reward, self.epoch)
+    }
+
     ///
     /// Return an account key by network key.
     ///
@@ -1122,9 +1554,10 @@ impl Blockchain {
             let activity = if activity {
                 ValidatorAwardState::Active
             } else {
+                // Macro blocks are always registered at the zero offset.
                 ValidatorAwardState::Failed {
                     epoch: self.epoch,
-                    offset: self.offset(),
+                    offset: 0,
                 }
             };
 
@@ -1141,15 +1574,139 @@ impl Blockchain {
         Ok(validators_activity)
     }
 
-    /// Returns current blockchain config.
+    /// Returns service awards state after the macro block of the current epoch.
+    fn awards_after_macro_block(
+        &self,
+        activity_map: &BitVec,
+        random: &pbc::VRF,
+    ) -> Result<Awards, BlockchainError> {
+        let mut awards = self.awards.clone();
+        // Skip genesis.
+        if self.epoch > 0 {
+            let validators_activity = self.epoch_activity_from_macro_block(activity_map)?;
+            awards.finalize_epoch(self.cfg.service_award_per_epoch, validators_activity);
+            let _winner = awards.check_winners(random.rand);
+        }
+        Ok(awards)
+    }
+
+    /// Returns the commitment to the current state (UTXO, escrow, service awards).
+    fn state_hash(&self) -> Hash {
+        snapshot::state_hash(
+            self.output_by_hash.keys(),
+            self.escrow.escrow.iter(),
+            &self.awards,
+        )
+    }
+
+    /// Returns the commitment to the state after registering a macro block with these
+    /// `inputs` and `outputs`. Must be called when all micro blocks of the epoch are reverted.
+    fn state_hash_after(
+        &self,
+        inputs: &HashMap<Hash, Output>,
+        outputs: &HashMap<Hash, (Output, OutputKey)>,
+        activity_map: &BitVec,
+        random: &pbc::VRF,
+    ) -> Result<Hash, BlockchainError> {
+        let mut utxos: BTreeSet<Hash> = self.output_by_hash.keys().cloned().collect();
+        for input_hash in inputs.keys() {
+            utxos.remove(input_hash);
+        }
+        utxos.extend(outputs.keys().cloned());
+        let stakes = self.escrow.stakes_after(
+            inputs.iter(),
+            outputs.iter().map(|(h, (o, _k))| (h, o)),
+            self.epoch,
+            self.cfg.stake_epochs,
+            self.cfg.unbonding_epochs,
+        );
+        let awards = self.awards_after_macro_block(activity_map, random)?;
+        Ok(snapshot::state_hash(utxos.iter(), stakes.iter(), &awards))
+    }
+
+    ///
+    /// Returns the commitments to the state and to the UTXO set after
+    /// the macro block of the current epoch.
+    /// `epoch_transactions` are transactions from micro blocks of the epoch.
+    /// Must be called when all micro blocks of the epoch are applied.
+    ///
+    pub(crate) fn macro_block_commitments(
+        &self,
+        activity_map: &BitVec,
+        random: &pbc::VRF,
+        extra_transactions: &[Transaction],
+        epoch_transactions: &[Transaction],
+    ) -> Result<(Hash, Hash), BlockchainError> {
+        // Macro block contains the same UTXO as micro blocks of the epoch,
+        // plus outputs from coinbase and service award transactions.
+        let mut utxos: BTreeSet<Hash> = self.output_by_hash.keys().cloned().collect();
+        for tx in extra_transactions {
+            assert!(tx.txins().is_empty());
+            utxos.extend(tx.txouts().iter().map(Hash::digest));
+        }
+        let awards = self.awards_after_macro_block(activity_map, random)?;
+        let state_hash = snapshot::state_hash(utxos.iter(), self.escrow.escrow.iter(), &awards);
+
+        // UTXO tree is updated only by macro blocks.
+        let mut inputs: Vec<Hash> = Vec::new();
+        let mut outputs: Vec<Hash> = Vec::new();
+        for tx in extra_transactions.iter().chain(epoch_transactions) {
+            inputs.extend(tx.txins().iter().cloned());
+            outputs.extend(tx.txouts().iter().map(Hash::digest));
+        }
+        let utxo_range_hash = self.utxo_range_hash_after(&inputs, &outputs);
+        Ok((state_hash, utxo_range_hash))
+    }
+
+    /// Returns consensus rules of the current epoch.
     pub fn cfg(&self) -> &ChainConfig {
         &self.cfg
     }
 
+    /// Returns the block version required at the current epoch.
+    pub fn version(&self) -> u64 {
+        self.genesis_cfg.version_at_epoch(self.epoch)
+    }
+
+    /// Returns the block version required at the specified epoch.
+    pub fn version_at_epoch(&self, epoch: u64) -> u64 {
+        self.genesis_cfg.version_at_epoch(epoch)
+    }
+
+    /// Returns parameter changes activated by validators.
+    pub fn governance(&self) -> &Governance {
+        &self.governance
+    }
+
+    /// Returns missed epochs and jailed validators.
+    pub fn liveness(&self) -> &Liveness {
+        &self.liveness
+    }
+
+    /// Switch consensus rules according to the hard-fork schedule and on-chain governance.
+    /// Parameters changed by validators take precedence over the hard-fork schedule.
+    fn activate_forks(&mut self) {
+        let mut cfg = self.genesis_cfg.at_epoch(self.epoch);
+        self.governance.active.apply(&mut cfg);
+        if cfg != self.cfg {
+            info!(
+                "Activated new consensus rules: epoch={}, version={}",
+                self.epoch,
+                self.version()
+            );
+        }
+        self.awards.difficulty = cfg.awards_difficulty;
+        self.cfg = cfg;
+    }
+
     /// Returns number of total slots in current epoch.
-    /// Internally always return cfg.max_slot_count
+    /// Equals to cfg.max_slot_count used by the election of current validators,
+    /// which can differ from the current one after a parameter change.
     pub fn total_slots(&self) -> i64 {
-        self.cfg.max_slot_count
+        self.validators_at_epoch_start()
+            .iter()
+            .map(|(_, slots)| *slots)
+            .sum()
     }
     /// Sets new blockchain view_change.
     /// ## Panics
@@ -1239,6 +1796,7 @@ impl Blockchain {
     }
 
     /// Create a new macro block for current epoch.
+    /// `proposal` is a parameter change to vote for, if any.
     ///
     pub fn create_macro_block(
         &self,
@@ -1247,6 +1805,7 @@ impl Blockchain {
         network_skey: &pbc::SecretKey,
         network_pkey: pbc::PublicKey,
         timestamp: Timestamp,
+        proposal: Option<ParameterChange>,
     ) -> (MacroBlock, Vec<Transaction>) {
         assert!(self.is_epoch_full());
         let epoch = self.epoch();
@@ -1263,23 +1822,34 @@ impl Blockchain {
         //
         {
             let block_reward = self.cfg.block_reward;
+            let payouts = self.delegation_payouts(&network_pkey, block_reward);
+            let delegated: i64 = payouts.iter().map(|(_, amount)| amount).sum();
             let data = PaymentPayloadData::Comment("Block reward".to_string());
-            let (output, gamma, _rvalue) =
-                PaymentOutput::with_payload(None, &beneficiary_pkey, block_reward, data.clone())
-                    .expect("invalid keys");
+            let (output, gamma, _rvalue) = PaymentOutput::with_payload(
+                None,
+                &beneficiary_pkey,
+                block_reward - delegated,
+                data.clone(),
+            )
+            .expect("invalid keys");
 
             info!(
                 "Created reward UTXO: hash={}, amount={}, data={:?}",
                 Hash::digest(&output),
-                self.cfg.block_reward,
+                block_reward - delegated,
                 data
             );
 
+            let mut txouts: Vec<Output> = vec![output.into()];
+            for (delegator_pkey, amount) in payouts {
+                txouts.push(PublicPaymentOutput::new(&delegator_pkey, amount).into());
+            }
+
             let coinbase_tx = CoinbaseTransaction {
                 block_reward,
                 block_fee: 0,
                 gamma: -gamma,
-                txouts: vec![output.into()],
+                txouts,
             };
 
             transactions.push(coinbase_tx.into());
@@ -1293,10 +1863,12 @@ impl Blockchain {
         //
         let (activity_map, winner) = self.awards_from_active_epoch(&random);
         if let Some((k, reward)) = winner {
-            let output = PublicPaymentOutput::new(&k, reward);
-            let tx = ServiceAwardTransaction {
-                winner_reward: vec![output.into()],
-            };
+            let winner_reward = self
+                .award_payouts(&k, reward)
+                .into_iter()
+                .map(|(recipient, amount)| PublicPaymentOutput::new(&recipient, amount).into())
+                .collect();
+            let tx = ServiceAwardTransaction { winner_reward };
             full_reward += reward;
             transactions.push(tx.into());
         }
@@ -1304,6 +1876,7 @@ impl Blockchain {
         let extra_transactions = transactions.clone();
 
         // Collect transactions from epoch.
+        let mut epoch_transactions: Vec<Transaction> = Vec::new();
         let count = self.cfg.micro_blocks_in_epoch as usize;
         let blocks: Vec<Block> = self.blocks_starting(self.epoch, 0).take(count).collect();
         for (offset, block) in blocks.into_iter().enumerate() {
@@ -1316,12 +1889,23 @@ impl Blockchain {
                 );
             };
 
-            transactions.extend(block.transactions);
+            epoch_transactions.extend(block.transactions);
         }
 
+        // Calculate the state commitments.
+        let (state_hash, utxo_range_hash) = self
+            .macro_block_commitments(
+                &activity_map,
+                &random,
+                &extra_transactions,
+                &epoch_transactions,
+            )
+            .expect("activity map is valid");
+        transactions.extend(epoch_transactions);
+
         let validators = self.next_election_result(random).validators;
 
-        let block = MacroBlock::from_transactions(
+        let mut block = MacroBlock::from_transactions(
             previous,
             epoch,
             view_change,
@@ -1332,9 +1916,15 @@ impl Blockchain {
             full_reward,
             activity_map,
             validators,
+            state_hash,
+            utxo_range_hash,
             &transactions,
         )
         .expect("Transactions are valid");
+        block.header.version = self.version();
+        if self.cfg.governance_epochs > 0 {
+            block.header.proposal = proposal;
+        }
 
         (block, extra_transactions)
     }
@@ -1367,17 +1957,150 @@ impl Blockchain {
         // Write the macro block to the disk, for macroblock save batch for meta indexes processing.
         //
         let lsn = LSN(self.epoch, MACRO_BLOCK_OFFSET);
-        let batch = self.write_block(lsn, Block::MacroBlock(block.clone()))?;
+        let mut batch = self.write_block(lsn, Block::MacroBlock(block.clone()))?;
+        self.index_macro_block(&mut batch, &block)?;
 
         //
         // Update in-memory indexes and metadata.
         //
-        self.register_macro_block(batch.into(), lsn, block)
+        let r = self.register_macro_block(batch.into(), lsn, block)?;
+        Ok(r)
+    }
+
+    ///
+    /// Remove blocks older than `pruning_epochs` epochs from the disk.
+    ///
+    /// Unspent outputs of pruned blocks are moved to the snapshot storage and
+    /// attributed to the oldest remaining macro block, the same way as after
+    /// the initialization from a state snapshot.
+    ///
+    /// All changes are written to `batch` of the macro block, so pruning is
+    /// committed atomically with the block.
+    ///
+    fn prune_history(&mut self, batch: &mut WriteBatch, lsn: LSN) -> Result<(), BlockchainError> {
+        assert_eq!(lsn.1, MACRO_BLOCK_OFFSET);
+        if self.pruning_epochs == 0 {
+            return Ok(()); // Keep the full history.
+        }
+        // stake_epochs can be changed by a hard fork or governance.
+        let pruning_epochs = std::cmp::max(self.pruning_epochs, self.cfg.stake_epochs + 1);
+        if lsn.0 < pruning_epochs {
+            return Ok(()); // Nothing to prune yet.
+        }
+        let first_epoch = lsn.0 + 1 - pruning_epochs;
+        if first_epoch <= self.snapshot_epoch.unwrap_or(0) {
+            return Ok(()); // Already pruned.
+        }
+        let first_lsn = LSN(first_epoch, MACRO_BLOCK_OFFSET);
+        debug!(
+            "Pruning history: epoch={}, first_epoch={}",
+            lsn.0, first_epoch
+        );
+
+        let cf_snapshot_outputs = self.database.cf_handle(SNAPSHOT_OUTPUTS).unwrap();
+        let cf_committed_txs = self.database.cf_handle(COMMITTED_TXS).unwrap();
+        let cf_meta = self.database.cf_handle(META).unwrap();
+
+        //
+        // Load unspent outputs, hashes of pruned blocks and transactions before changing anything.
+        //
+        let pruned_outputs: Vec<(Hash, OutputKey)> = self
+            .output_by_hash
+            .iter()
+            .filter(|(_h, k)| match k {
+                OutputKey::MacroBlock { epoch, .. } => *epoch < first_epoch,
+                OutputKey::Snapshot { epoch } => *epoch < first_epoch,
+                OutputKey::MicroBlock { .. } => false,
+            })
+            .map(|(h, k)| (h.clone(), k.clone()))
+            .collect();
+        let mut moved_outputs: Vec<(Hash, Output)> = Vec::new();
+        for (output_hash, output_key) in &pruned_outputs {
+            if let OutputKey::MacroBlock { .. } = output_key {
+                let output = self
+                    .output_by_key(output_hash, output_key)?
+                    .expect("Corrupted outputs_by_hash (Prune)")
+                    .output;
+                moved_outputs.push((*output_hash, output));
+            }
+        }
+        // Genesis is kept to verify snapshots and recovery.
+        let start_key = Self::block_key(LSN(1, 0));
+        let first_key = Self::block_key(first_lsn);
+        let mut pruned_blocks: Vec<(Box<[u8]>, Hash)> = Vec::new();
+        for (key, value) in self.database.iterator(rocksdb::IteratorMode::From(
+            &start_key,
+            rocksdb::Direction::Forward,
+        )) {
+            if &*key >= &first_key[..] {
+                break;
+            }
+            let block = Block::from_buffer(&*value).expect("couldn't deserialize block.");
+            let block_hash = match block {
+                Block::MacroBlock(block) => Hash::digest(&block),
+                Block::MicroBlock(block) => Hash::digest(&block),
+            };
+            pruned_blocks.push((key, block_hash));
+        }
+        let mut pruned_txs: Vec<Box<[u8]>> = Vec::new();
+        for (key, value) in self
+            .database
+            .iterator_cf(cf_committed_txs, rocksdb::IteratorMode::Start)?
+        {
+            let LSN(epoch, _offset) = LSN::from_buffer(&*value)?;
+            if epoch < first_epoch {
+                pruned_txs.push(key);
+            }
+        }
+
+        //
+        // Move unspent outputs of pruned blocks to the snapshot storage.
+        //
+        for (output_hash, output) in moved_outputs {
+            batch.put_cf(
+                cf_snapshot_outputs,
+                output_hash.base_vector(),
+                &output.into_buffer()?,
+            )?;
+        }
+        for (output_hash, _output_key) in &pruned_outputs {
+            self.output_by_hash.insert(
+                lsn,
+                *output_hash,
+                OutputKey::Snapshot { epoch: first_epoch },
+            );
+        }
+
+        //
+        // Remove pruned blocks and their transactions.
+        //
+        for (key, block_hash) in &pruned_blocks {
+            self.block_by_hash.remove(lsn, block_hash);
+            batch.delete(key)?;
+        }
+        for key in &pruned_txs {
+            batch.delete_cf(cf_committed_txs, key)?;
+        }
+        self.cache.retain(|block| match block {
+            Block::MacroBlock(block) => block.header.epoch >= first_epoch,
+            Block::MicroBlock(_) => unreachable!("Only macro blocks are cached"),
+        });
+        self.snapshot_epoch = Some(first_epoch);
+        Self::write_meta(batch, cf_meta, SNAPSHOT, &first_lsn)?;
+
+        info!(
+            "Pruned history: first_epoch={}, blocks={}, outputs={}, transactions={}",
+            first_epoch,
+            pruned_blocks.len(),
+            pruned_outputs.len(),
+            pruned_txs.len()
+        );
+        Ok(())
     }
 
     ///
     /// Update indexes and metadata.
-    /// Must never fail.
+    /// Fails only if the block doesn't match the UTXO set, before any changes are made.
     ///
     fn register_macro_block(
         &mut self,
@@ -1385,7 +2108,7 @@ impl Blockchain {
         lsn: LSN,
         block: MacroBlock,
     ) -> Result<(Vec<Hash>, HashMap<Hash, Output>), BlockchainError> {
-        assert_eq!(block.header.version, VERSION);
+        assert_eq!(block.header.version, self.version());
         assert_eq!(self.epoch, block.header.epoch);
         assert_eq!(self.offset(), 0);
         assert_eq!(block.header.previous, self.last_macro_block_hash);
@@ -1435,19 +2158,61 @@ impl Blockchain {
             assert!(prev.is_none(), "duplicate input");
         }
 
-        let mut awards_at_end_epoch = self.awards.clone();
-        // update award (skip genesis).
-        let winner = if epoch > 0 {
-            let validators_activity = self
-                .epoch_activity_from_macro_block(&block.header.activity_map)
-                .unwrap();
-            self.awards
-                .finalize_epoch(self.cfg.service_award_per_epoch, validators_activity);
-
-            // save awards info at end of past epoch.
+        //
+        // Check UTXO.
+        //
+        if let Some(block_utxo_range_hash) = block.header.utxo_range_hash {
+            let utxo_range_hash = self
+                .utxo_tree
+                .root_hash_after(inputs.keys(), outputs.keys());
+            if block_utxo_range_hash != utxo_range_hash {
+                return Err(BlockError::InvalidMacroBlockUtxoHash(
+                    epoch,
+                    block_hash,
+                    utxo_range_hash,
+                    block_utxo_range_hash,
+                )
+                .into());
+            }
+        }
+
+        //
+        // Check state.
+        //
+        if let Some(block_state_hash) = block.header.state_hash {
+            let state_hash = self.state_hash_after(
+                &inputs,
+                &outputs,
+                &block.header.activity_map,
+                &block.header.random,
+            )?;
+            if block_state_hash != state_hash {
+                return Err(BlockError::InvalidMacroBlockStateHash(
+                    epoch,
+                    block_hash,
+                    state_hash,
+                    block_state_hash,
+                )
+                .into());
+            }
+        }
+
+        let mut awards_at_end_epoch = self.awards.clone();
+        let mut award_draw: Option<AwardDraw> = None;
+        // update award (skip genesis).
+        let winner = if epoch > 0 {
+            let validators_activity = self
+                .epoch_activity_from_macro_block(&block.header.activity_map)
+                .unwrap();
+            self.awards
+                .finalize_epoch(self.cfg.service_award_per_epoch, validators_activity);
+
+            // save awards info at end of past epoch.
             awards_at_end_epoch = self.awards.clone();
 
-            let winner = self.awards.check_winners(block.header.random.rand);
+            let draw = self.awards.draw(epoch, block.header.random.rand);
+            let winner = draw.winner.map(|winner_pk| (winner_pk, draw.payout));
+            award_draw = Some(draw);
             if let Some((winner_pk, amount)) = winner {
                 info!(
                     "Service award found a winner: winner_pk={}, amount={}",
@@ -1499,6 +2264,55 @@ impl Blockchain {
             );
         }
 
+        if let Some(block_state_hash) = block.header.state_hash {
+            debug_assert_eq!(self.state_hash(), block_state_hash);
+        }
+
+        //
+        // Update UTXO tree.
+        //
+        self.utxo_tree.update(inputs.keys(), outputs.keys());
+
+        //
+        // Count the vote for a parameter change (skip genesis).
+        //
+        if epoch > 0 {
+            self.governance.on_macro_block(
+                epoch,
+                &block.header.proposal,
+                self.cfg.governance_epochs,
+            );
+        }
+
+        //
+        // Keep the signed header to create snapshots after pruning (skip genesis).
+        //
+        let certificate = if epoch > 0 {
+            Some(MacroBlockCertificate {
+                header: block.header.clone(),
+                multisig: block.multisig.clone(),
+                multisigmap: block.multisigmap.clone(),
+                validators: self.validators_at_epoch_start(),
+            })
+        } else {
+            None
+        };
+
+        //
+        // Jail inactive validators (skip genesis).
+        //
+        if epoch > 0 {
+            let validators = self.validators_at_epoch_start();
+            self.liveness.on_macro_block(
+                epoch,
+                &validators,
+                &block.header.activity_map,
+                &block.multisigmap,
+                self.cfg.jail_missed_epochs,
+                self.cfg.jail_epochs,
+            );
+        }
+
         //
         // Update metadata.
         //
@@ -1515,6 +2329,7 @@ impl Blockchain {
         self.election_result.insert(lsn, (), election_result);
         self.difficulty = block.header.difficulty;
         debug!("Set difficulty to to {}", self.difficulty);
+        self.activate_forks();
 
         //
         // Update metrics.
@@ -1546,12 +2361,20 @@ impl Blockchain {
                 .set(*stake);
         }
 
+        //
+        // Drop old history if pruning is enabled.
+        //
+        let mut batch = batch.unwrap_or_default();
+        self.prune_history(&mut batch, lsn)?;
+
         let cf_block_by_hash = self.database.cf_handle(BLOCK_BY_HASH).unwrap();
         let cf_output_by_hash = self.database.cf_handle(OUTPUT_BY_HASH).unwrap();
         let cf_escrow = self.database.cf_handle(ESCROW).unwrap();
         let cf_epoch_infos = self.database.cf_handle(EPOCH_INFOS).unwrap();
+        let cf_service_award = self.database.cf_handle(SERVICE_AWARD).unwrap();
+        let cf_certificates = self.database.cf_handle(CERTIFICATES).unwrap();
         let cf_meta = self.database.cf_handle(META).unwrap();
-        let mut batch = batch.unwrap_or_default();
+
         //
         // Finalize storage.
         //
@@ -1582,26 +2405,9 @@ impl Blockchain {
             self.election_result(),
         )?;
         Self::write_meta(&mut batch, &cf_meta, AWARDS, &self.awards)?;
+        Self::write_meta(&mut batch, &cf_meta, GOVERNANCE, &self.governance)?;
+        Self::write_meta(&mut batch, &cf_meta, LIVENESS, &self.liveness)?;
 
-        let validators = self
-            .election_result()
-            .validators
-            .iter()
-            .map(|v| {
-                let network_pkey = v.0;
-                let account_pkey = self
-                    .account_by_network_key(&network_pkey)
-                    .expect("Validator should have wallet key at start of epoch");
-                let slots = v.1;
-                ValidatorKeyInfo {
-                    network_pkey,
-                    account_pkey,
-                    slots,
-                }
-            })
-            .collect();
-
-        let facilitator = self.election_result().facilitator;
         let awards = AwardsInfo {
             service_award_state: awards_at_end_epoch,
             payout: winner.map(|w| PayoutInfo {
@@ -1609,12 +2415,7 @@ impl Blockchain {
                 amount: w.1,
             }),
         };
-
-        let epoch_info = EpochInfo {
-            awards,
-            facilitator,
-            validators,
-        };
+        let epoch_info = self.current_epoch_info(awards);
 
         let data = epoch_info.into_buffer()?;
         batch.put_cf(
@@ -1622,6 +2423,20 @@ impl Blockchain {
             &Self::block_key(LSN(epoch, MACRO_BLOCK_OFFSET)),
             &data,
         )?;
+        if let Some(draw) = award_draw {
+            batch.put_cf(
+                cf_service_award,
+                &Self::block_key(LSN(epoch, MACRO_BLOCK_OFFSET)),
+                &draw.into_buffer()?,
+            )?;
+        }
+        if let Some(certificate) = certificate {
+            batch.put_cf(
+                cf_certificates,
+                &Self::block_key(LSN(epoch, MACRO_BLOCK_OFFSET)),
+                &certificate.into_buffer()?,
+            )?;
+        }
         self.epoch_activity.reset();
         self.database.write(batch)?;
 
@@ -1650,629 +2465,2995 @@ impl Blockchain {
         Ok((block.inputs, outputs))
     }
 
-    // ---------------------------------------------------------------------------------------------
-    // Micro Blocks
-    // ---------------------------------------------------------------------------------------------
-
-    ///
-    /// Add a new micro block into blockchain.
-    ///
-    /// # Arguments
-    ///
-    /// * `block` - a micro block to add.
-    /// * `timestamp` - block arrival timestamp.
-    ///
-    pub fn push_micro_block(
-        &mut self,
-        block: MicroBlock,
-        timestamp: Timestamp,
-    ) -> Result<(Vec<Hash>, HashMap<Hash, Output>, HashMap<Hash, Transaction>), StorageError> {
-        assert_eq!(self.epoch, block.header.epoch);
-        assert_eq!(self.offset, block.header.offset);
+    /// Returns information about validators of the current epoch.
+    fn current_epoch_info(&self, awards: AwardsInfo) -> EpochInfo {
+        let validators = self
+            .election_result()
+            .validators
+            .iter()
+            .map(|v| {
+                let network_pkey = v.0;
+                let account_pkey = self
+                    .account_by_network_key(&network_pkey)
+                    .expect("Validator should have wallet key at start of epoch");
+                let slots = v.1;
+                ValidatorKeyInfo {
+                    network_pkey,
+                    account_pkey,
+                    slots,
+                }
+            })
+            .collect();
 
-        //
-        // Double-check if debug.
-        //
-        if self.consistency_check >= ConsistencyCheck::Incoming {
-            self.validate_micro_block(&block, timestamp, true)
-                .expect("block is valid");
+        let facilitator = self.election_result().facilitator;
+        EpochInfo {
+            awards,
+            facilitator,
+            validators,
         }
-
-        //
-        // Write the micro block to the disk.
-        //
-        let lsn = LSN(self.epoch, self.offset);
-        let batch = self.write_block(lsn, Block::MicroBlock(block.clone()))?;
-        self.database.write(batch)?;
-
-        //
-        // Update in-memory indexes and metadata.
-        //
-        self.register_micro_block(lsn, block)
     }
 
-    ///
-    /// Common part of register_macro_block()/register_micro_block().
-    ///
-    fn register_inputs_and_outputs(
-        &mut self,
-        lsn: LSN,
-        block_hash: Hash,
-        inputs: &HashMap<Hash, Output>,
-        outputs: &HashMap<Hash, (Output, OutputKey)>,
-        gamma: Fr,
-        block_reward: i64,
-    ) {
-        let epoch = self.epoch;
+    //----------------------------------------------------------------------------------------------
+    // Light Nodes
+    //----------------------------------------------------------------------------------------------
 
-        //
-        // Update block_by_hash index.
-        //
-        if let Some(_) = self.block_by_hash.insert(lsn, block_hash, lsn) {
-            panic!(
-                "Block hash collision: epoch={}, block={}",
-                epoch, block_hash
-            );
+    /// Strips outputs from a block of this blockchain for light nodes.
+    pub fn light_block(&self, block: Block) -> Result<LightBlock, BlockchainError> {
+        match block {
+            Block::MacroBlock(block) => {
+                let epoch = block.header.epoch;
+                let validators = match self.epoch_info(epoch)? {
+                    Some(epoch_info) => epoch_info
+                        .validators
+                        .into_iter()
+                        .map(|v| (v.network_pkey, v.slots))
+                        .collect(),
+                    None => return Err(LightError::BlockNotFound(Hash::digest(&block)).into()),
+                };
+                Ok(LightBlock::LightMacroBlock(LightMacroBlock::new(
+                    block, validators,
+                )))
+            }
+            Block::MicroBlock(block) => Ok(LightBlock::LightMicroBlock(block.into())),
         }
-        assert_eq!(self.block_by_hash.current_lsn(), lsn);
-
-        let mut burned = Pt::identity();
-        let mut created = Pt::identity();
+    }
 
-        //
-        // Process inputs.
-        //
-        for (input_hash, input) in inputs.iter() {
-            if self.output_by_hash.remove(lsn, input_hash).is_none() {
-                panic!(
-                    "Missing input UTXO: epoch={}, block={}, utxo={}",
-                    epoch, block_hash, &input_hash
-                );
+    /// Returns outputs of a block together with proofs of inclusion.
+    pub fn output_proofs(
+        &self,
+        block_hash: &Hash,
+        output_ids: &[u32],
+    ) -> Result<Vec<OutputProof>, BlockchainError> {
+        let lsn = match self.block_by_hash.get(block_hash) {
+            Some(lsn) => *lsn,
+            None => return Err(LightError::BlockNotFound(*block_hash).into()),
+        };
+        let proofs = match self.block(lsn)?.into_owned() {
+            Block::MacroBlock(block) => output_proofs(block_hash, &block.outputs, output_ids)?,
+            Block::MicroBlock(block) => {
+                let outputs: Vec<Output> = block.outputs().cloned().collect();
+                output_proofs(block_hash, &outputs, output_ids)?
             }
+        };
+        Ok(proofs)
+    }
 
-            if cfg!(debug_assertions) {
-                input.validate().expect("valid UTXO");
-            }
-            burned += input
-                .pedersen_commitment()
-                .expect("valid Pedersen commitment");
+    //----------------------------------------------------------------------------------------------
+    // Secondary Indexes
+    //----------------------------------------------------------------------------------------------
 
-            match input {
-                Output::PaymentOutput(_o) => {}
-                Output::PublicPaymentOutput(_o) => {}
-                Output::StakeOutput(o) => {
-                    self.escrow
-                        .unstake(lsn, o.validator, input_hash.clone(), self.epoch);
-                    assert_eq!(self.escrow.current_lsn(), lsn);
-                }
+    /// Build or drop secondary indexes according to `cfg.explorer_indexes`.
+    /// Transactions of past epochs are not on the disk anymore, so a rebuilt index starts
+    /// from the micro blocks of the current epoch.
+    fn init_indexes(&self) -> Result<(), BlockchainError> {
+        let cf_meta = self.database.cf_handle(META).unwrap();
+        let is_indexed = self.database.get_cf(cf_meta, INDEXES.as_bytes())?.is_some();
+        if !self.cfg.explorer_indexes {
+            if is_indexed {
+                // Indexes are not maintained anymore, rebuild them on the next enabling.
+                info!("Secondary indexes are disabled");
+                self.database.delete_cf(cf_meta, INDEXES.as_bytes())?;
             }
-
-            debug!(
-                "Pruned UXTO: epoch={}, block={}, utxo={}",
-                epoch, block_hash, input_hash
-            );
+            return Ok(());
+        } else if is_indexed {
+            return Ok(());
         }
 
-        //
-        // Process outputs.
-        //
-        for (output_hash, (output, output_key)) in outputs.iter() {
-            // Update indexes.
-            if let Some(_) =
-                self.output_by_hash
-                    .insert(lsn, output_hash.clone(), output_key.clone())
+        info!("Building secondary indexes...");
+        let mut batch = WriteBatch::default();
+        for cf_name in &[TX_BY_HASH, TX_BY_INPUT, TX_BY_OUTPUT, OUTPUTS_BY_RECIPIENT] {
+            let cf = self.database.cf_handle(cf_name).unwrap();
+            for (key, _value) in self
+                .database
+                .iterator_cf(cf, rocksdb::IteratorMode::Start)?
             {
-                panic!(
-                    "UTXO hash collision: epoch={}, block={}, utxo={}",
-                    epoch, &block_hash, &output_hash
-                );
+                batch.delete_cf(cf, &key)?;
             }
-            assert_eq!(self.output_by_hash.current_lsn(), lsn);
-
-            created += output
-                .pedersen_commitment()
-                .expect("valid Pedersen commitment");
-
-            match output {
-                Output::PaymentOutput(_o) => {}
-                Output::PublicPaymentOutput(_o) => {}
-                Output::StakeOutput(o) => {
-                    self.escrow.stake(
-                        lsn,
-                        o.validator,
-                        o.recipient,
-                        output_hash.clone(),
-                        self.epoch,
-                        self.cfg.stake_epochs,
-                        o.amount,
-                    );
-                    assert_eq!(self.escrow.current_lsn(), lsn);
+        }
+        let mut blocks_count: usize = 0;
+        for block in self.blocks() {
+            match block {
+                Block::MacroBlock(block) => self.index_macro_block(&mut batch, &block)?,
+                Block::MicroBlock(block) => {
+                    let lsn = LSN(block.header.epoch, block.header.offset);
+                    self.index_micro_block(&mut batch, lsn, &block)?
                 }
             }
-
-            debug!(
-                "Registered UXTO: epoch={}, block={}, utxo={}",
-                epoch, &block_hash, &output_hash
-            );
+            blocks_count += 1;
         }
+        let lsn = LSN(self.epoch, self.offset);
+        Self::write_meta(&mut batch, cf_meta, INDEXES, &lsn)?;
+        self.database.write(batch)?;
+        info!("Built secondary indexes: blocks={}", blocks_count);
+        Ok(())
+    }
 
-        //
-        // Update monetary balance.
-        //
+    /// A key in OUTPUTS_BY_RECIPIENT index.
+    fn recipient_key(recipient: &scc::PublicKey, output_hash: &Hash) -> Vec<u8> {
+        let mut key = recipient.to_bytes().to_vec();
+        key.extend_from_slice(output_hash.base_vector());
+        key
+    }
 
-        // Check the block monetary balance.
-        if fee_a(block_reward) + burned - created != gamma * Pt::one() {
-            panic!(
-                "Invalid block monetary balance: epoch={}, block={}",
-                epoch, &block_hash
-            )
-        }
-
-        // Global monetary balance.
-        let orig_balance = self.balance();
-        let balance = Balance {
-            created: orig_balance.created + created,
-            burned: orig_balance.burned + burned,
-            gamma: orig_balance.gamma + gamma,
-            block_reward: orig_balance.block_reward + block_reward,
-        };
-        if fee_a(balance.block_reward) + balance.burned - balance.created
-            != balance.gamma * Pt::one()
-        {
-            panic!(
-                "Invalid global monetary balance: epoch={}, block={}",
-                epoch, &block_hash
-            );
+    /// Returns the recipient of PublicPayment and Stake outputs.
+    fn public_recipient(output: &Output) -> Option<&scc::PublicKey> {
+        match output {
+            Output::PublicPaymentOutput(o) => Some(&o.recipient),
+            Output::StakeOutput(o) => Some(&o.recipient),
+            _ => None,
         }
-        self.balance.insert(lsn, (), balance);
-        assert_eq!(self.balance.current_lsn(), lsn);
     }
 
-    ///
-    /// Register a new micro block.
-    ///
-    fn register_micro_block(
-        &mut self,
+    /// Add transactions, inputs and outputs of the micro block to secondary indexes.
+    fn index_micro_block(
+        &self,
+        batch: &mut WriteBatch,
         lsn: LSN,
-        block: MicroBlock,
-    ) -> Result<(Vec<Hash>, HashMap<Hash, Output>, HashMap<Hash, Transaction>), StorageError> {
-        assert_eq!(block.header.version, VERSION);
-        assert_eq!(self.epoch, block.header.epoch);
-        assert_eq!(self.offset, block.header.offset);
-        assert_eq!(block.header.previous, self.last_block_hash);
-        assert!(block.header.timestamp > self.last_block_timestamp);
-        assert!(!self.is_epoch_full());
-        let epoch = self.epoch;
-        let offset = self.offset;
-        let block_hash = Hash::digest(&block);
-
-        //
-        // Prepare inputs && outputs.
-        //
-        let mut inputs: HashMap<Hash, Output> = HashMap::new();
-        let mut outputs: HashMap<Hash, (Output, OutputKey)> = HashMap::new();
-        let mut gamma = Fr::zero();
-        let mut block_reward: i64 = 0;
-        let mut txs: HashMap<Hash, Transaction> = HashMap::new();
-        // Regular transactions.
-        for (tx_id, tx) in block.transactions.into_iter().enumerate() {
-            assert!(tx_id < std::u32::MAX as usize);
+        block: &MicroBlock,
+    ) -> Result<(), StorageError> {
+        // Transactions are committed by the macro block of the same epoch.
+        let cf_committed_txs = self.database.cf_handle(COMMITTED_TXS).unwrap();
+        let value = lsn.into_buffer().expect("couldn't serialize LSN.");
+        for tx in &block.transactions {
+            batch.put_cf(cf_committed_txs, Hash::digest(tx).base_vector(), &value)?;
+        }
 
-            let tx_hash = Hash::digest(&tx);
+        if !self.cfg.explorer_indexes {
+            return Ok(());
+        }
+        let cf_tx_by_hash = self.database.cf_handle(TX_BY_HASH).unwrap();
+        let cf_tx_by_input = self.database.cf_handle(TX_BY_INPUT).unwrap();
+        let cf_tx_by_output = self.database.cf_handle(TX_BY_OUTPUT).unwrap();
+        let cf_outputs_by_recipient = self.database.cf_handle(OUTPUTS_BY_RECIPIENT).unwrap();
+        let block_hash = Hash::digest(block);
+        for (tx_id, tx) in block.transactions.iter().enumerate() {
+            let tx_hash = Hash::digest(tx);
+            let indexed = IndexedTransaction {
+                key: TransactionKey {
+                    epoch: lsn.0,
+                    offset: lsn.1,
+                    tx_id: tx_id as u32,
+                },
+                block_hash,
+                transaction: tx.clone(),
+            };
+            let indexed = indexed
+                .into_buffer()
+                .expect("couldn't serialize transaction.");
+            batch.put_cf(cf_tx_by_hash, tx_hash.base_vector(), &indexed)?;
             for input_hash in tx.txins() {
-                assert!(
-                    !outputs.contains_key(&input_hash),
-                    "annihilation in micro block"
-                );
-                let input = self.output_by_hash(input_hash)?.expect("Missing output");
-                let prev = inputs.insert(input_hash.clone(), input);
-                assert!(prev.is_none(), "duplicate input");
+                batch.put_cf(
+                    cf_tx_by_input,
+                    input_hash.base_vector(),
+                    tx_hash.base_vector(),
+                )?;
             }
-            for (txout_id, output) in tx.txouts().iter().enumerate() {
+            for output in tx.txouts() {
                 let output_hash = Hash::digest(output);
-                assert!(txout_id < std::u32::MAX as usize);
-                let output_key = OutputKey::MicroBlock {
-                    epoch,
-                    offset,
-                    tx_id: tx_id as u32,
-                    txout_id: txout_id as u32,
-                };
-                let prev = outputs.insert(output_hash, (output.clone(), output_key));
-                assert!(prev.is_none(), "duplicate output");
-            }
-            match &tx {
-                Transaction::CoinbaseTransaction(tx) => {
-                    block_reward += tx.block_reward;
-                    gamma += tx.gamma;
-                }
-                Transaction::PaymentTransaction(tx) => {
-                    gamma += tx.gamma;
-                }
-                Transaction::RestakeTransaction(_tx) => {}
-                Transaction::SlashingTransaction(tx) => {
-                    info!(
-                        "Found slashing transaction, removing validator, from list: cheater={}",
-                        tx.cheater()
-                    );
-                    let validators = &self.election_result().validators;
-                    // remove cheater for current epoch.
-                    let new_validators = validators
-                        .into_iter()
-                        .filter_map(|(k, v)| {
-                            if k != &tx.cheater() {
-                                Some((*k, *v))
-                            } else {
-                                None
-                            }
-                        })
-                        .collect();
-                    let mut election_result = self.election_result().clone();
-                    election_result.validators = new_validators;
-                    self.election_result.insert(lsn, (), election_result);
+                batch.put_cf(
+                    cf_tx_by_output,
+                    output_hash.base_vector(),
+                    tx_hash.base_vector(),
+                )?;
+                if let Some(recipient) = Self::public_recipient(output) {
+                    let key = Self::recipient_key(recipient, &output_hash);
+                    batch.put_cf(cf_outputs_by_recipient, &key, b"")?;
                 }
-                Transaction::ServiceAwardTransaction(_tx) => {
-                    panic!("Found a ServiceAward transaction inside a MicroBlock")
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove transactions, inputs and outputs of the micro block from secondary indexes.
+    fn unindex_micro_block(
+        &self,
+        batch: &mut WriteBatch,
+        block: &MicroBlock,
+    ) -> Result<(), StorageError> {
+        let cf_committed_txs = self.database.cf_handle(COMMITTED_TXS).unwrap();
+        for tx in &block.transactions {
+            batch.delete_cf(cf_committed_txs, Hash::digest(tx).base_vector())?;
+        }
+
+        if !self.cfg.explorer_indexes {
+            return Ok(());
+        }
+        let cf_tx_by_hash = self.database.cf_handle(TX_BY_HASH).unwrap();
+        let cf_tx_by_input = self.database.cf_handle(TX_BY_INPUT).unwrap();
+        let cf_tx_by_output = self.database.cf_handle(TX_BY_OUTPUT).unwrap();
+        let cf_outputs_by_recipient = self.database.cf_handle(OUTPUTS_BY_RECIPIENT).unwrap();
+        for tx in &block.transactions {
+            let tx_hash = Hash::digest(tx);
+            batch.delete_cf(cf_tx_by_hash, tx_hash.base_vector())?;
+            for input_hash in tx.txins() {
+                batch.delete_cf(cf_tx_by_input, input_hash.base_vector())?;
+            }
+            for output in tx.txouts() {
+                let output_hash = Hash::digest(output);
+                batch.delete_cf(cf_tx_by_output, output_hash.base_vector())?;
+                if let Some(recipient) = Self::public_recipient(output) {
+                    let key = Self::recipient_key(recipient, &output_hash);
+                    batch.delete_cf(cf_outputs_by_recipient, &key)?;
                 }
             }
+        }
+        Ok(())
+    }
 
-            assert!(txs.insert(tx_hash, tx).is_none());
+    /// Add outputs of the macro block to secondary indexes.
+    /// Only awards and other outputs created by the macro block itself are new here.
+    fn index_macro_block(
+        &self,
+        batch: &mut WriteBatch,
+        block: &MacroBlock,
+    ) -> Result<(), StorageError> {
+        if !self.cfg.explorer_indexes {
+            return Ok(());
+        }
+        let cf_outputs_by_recipient = self.database.cf_handle(OUTPUTS_BY_RECIPIENT).unwrap();
+        for output in &block.outputs {
+            if let Some(recipient) = Self::public_recipient(output) {
+                let key = Self::recipient_key(recipient, &Hash::digest(output));
+                batch.put_cf(cf_outputs_by_recipient, &key, b"")?;
+            }
         }
+        Ok(())
+    }
 
-        //
-        // Update service awards
-        //
-        // Set skipped validators to inactive.
-        for skiped_view_change in 0..block.header.view_change {
-            let leader = self.election_result().select_leader(skiped_view_change);
-            self.epoch_activity.insert(
-                lsn,
-                leader,
-                ValidatorAwardState::Failed {
-                    epoch: self.epoch(),
-                    offset: self.offset(),
-                },
-            );
+    /// Returns a transaction included into a micro block, including past epochs.
+    pub fn indexed_transaction(
+        &self,
+        tx_hash: &Hash,
+    ) -> Result<Option<IndexedTransaction>, BlockchainError> {
+        if !self.cfg.explorer_indexes {
+            return Err(BlockchainError::IndexesDisabled);
+        }
+        let cf_tx_by_hash = self.database.cf_handle(TX_BY_HASH).unwrap();
+        match self.database.get_cf(cf_tx_by_hash, tx_hash.base_vector())? {
+            Some(buffer) => Ok(Some(IndexedTransaction::from_buffer(&buffer)?)),
+            None => Ok(None),
         }
+    }
 
-        // set current leader to active, if it was unknown.
-        let leader = self
-            .election_result()
-            .select_leader(block.header.view_change);
-        if self.epoch_activity.get(&leader).is_none() {
-            self.epoch_activity
-                .insert(lsn, leader, ValidatorAwardState::Active);
+    /// Returns the location of a transaction included into a micro block.
+    pub fn transaction_key(
+        &self,
+        tx_hash: &Hash,
+    ) -> Result<Option<TransactionKey>, BlockchainError> {
+        Ok(self
+            .indexed_transaction(tx_hash)?
+            .map(|indexed| indexed.key))
+    }
+
+    /// Returns the epoch of the macro block which committed the transaction.
+    ///
+    /// The index is written together with micro blocks and survives the end of epoch.
+    /// Transactions of the current epoch are not committed yet.
+    /// Epochs received as macro blocks only, i.e. from replication of past epochs
+    /// or from a state snapshot, are unknown, because macro blocks don't keep
+    /// transaction boundaries.
+    pub fn committed_transaction_epoch(
+        &self,
+        tx_hash: &Hash,
+    ) -> Result<Option<u64>, BlockchainError> {
+        let cf_committed_txs = self.database.cf_handle(COMMITTED_TXS).unwrap();
+        match self
+            .database
+            .get_cf(cf_committed_txs, tx_hash.base_vector())?
+        {
+            Some(buffer) => {
+                let LSN(epoch, _offset) = LSN::from_buffer(&buffer)?;
+                if epoch < self.epoch {
+                    Ok(Some(epoch))
+                } else {
+                    Ok(None)
+                }
+            }
+            None => Ok(None),
         }
+    }
 
-        //
-        // Register block.
-        //
-        self.register_inputs_and_outputs(lsn, block_hash, &inputs, &outputs, gamma, block_reward);
+    /// Mark transactions as committed by the macro block of the epoch.
+    ///
+    /// Used for transactions of another fork, which are missing in our micro blocks.
+    pub fn index_committed_transactions(
+        &self,
+        epoch: u64,
+        tx_hashes: &[Hash],
+    ) -> Result<(), StorageError> {
+        let cf_committed_txs = self.database.cf_handle(COMMITTED_TXS).unwrap();
+        let value = LSN(epoch, MACRO_BLOCK_OFFSET)
+            .into_buffer()
+            .expect("couldn't serialize LSN.");
+        let mut batch = WriteBatch::default();
+        for tx_hash in tx_hashes {
+            batch.put_cf(cf_committed_txs, tx_hash.base_vector(), &value)?;
+        }
+        self.database.write(batch)
+    }
 
-        //
-        // Update metadata.
-        //
-        self.last_block_timestamp = block.header.timestamp;
-        self.last_block_hash = block_hash;
-        self.offset += 1;
-        let mut election_result = self.election_result().clone();
-        election_result.view_change = 0;
-        election_result.random = block.header.random;
-        self.election_result.insert(lsn, (), election_result);
-        self.view_change_proof = None;
+    /// Returns the hash of a transaction which spent the output.
+    pub fn spent_by(&self, output_hash: &Hash) -> Result<Option<Hash>, BlockchainError> {
+        if !self.cfg.explorer_indexes {
+            return Err(BlockchainError::IndexesDisabled);
+        }
+        let cf_tx_by_input = self.database.cf_handle(TX_BY_INPUT).unwrap();
+        match self
+            .database
+            .get_cf(cf_tx_by_input, output_hash.base_vector())?
+        {
+            Some(buffer) => Ok(Some(Hash::try_from_bytes(&buffer)?)),
+            None => Ok(None),
+        }
+    }
 
-        //
-        // Update metrics.
-        //
-        metrics::OFFSET.set(self.offset as i64);
-        metrics::MICRO_BLOCK_INPUTS.set(inputs.len() as i64);
-        metrics::MICRO_BLOCK_OUTPUTS.set(outputs.len() as i64);
-        metrics::MICRO_BLOCK_TRANSACTIONS.set(txs.len() as i64);
-        metrics::MICRO_BLOCK_INPUTS_HG.observe(inputs.len() as f64);
-        metrics::MICRO_BLOCK_OUTPUTS_HG.observe(outputs.len() as f64);
-        metrics::MICRO_BLOCK_TRANSACTIONS_HG.observe(txs.len() as f64);
-        metrics::UTXO_LEN.set(self.output_by_hash.len() as i64);
-        metrics::EMISSION.set(self.balance().block_reward);
+    /// Returns the hash of a transaction which created the output.
+    /// Outputs created by macro blocks don't have a transaction.
+    pub fn created_by(&self, output_hash: &Hash) -> Result<Option<Hash>, BlockchainError> {
+        if !self.cfg.explorer_indexes {
+            return Err(BlockchainError::IndexesDisabled);
+        }
+        let cf_tx_by_output = self.database.cf_handle(TX_BY_OUTPUT).unwrap();
+        match self
+            .database
+            .get_cf(cf_tx_by_output, output_hash.base_vector())?
+        {
+            Some(buffer) => Ok(Some(Hash::try_from_bytes(&buffer)?)),
+            None => Ok(None),
+        }
+    }
 
-        info!(
-            "Registered a micro block: epoch={}, offset={}, block={}, txs={:?}, inputs={:?}, outputs={:?}",
-            epoch,
-            offset,
-            block_hash,
-            txs.keys()
-                .map(ToString::to_string)
-                .collect::<Vec<String>>(),
-            inputs.keys()
-                .map(ToString::to_string)
-                .collect::<Vec<String>>(),
-            outputs.keys()
-                .map(ToString::to_string)
-                .collect::<Vec<String>>(),
-        );
+    /// Returns hashes of all PublicPayment and Stake outputs sent to the recipient, including spent.
+    pub fn outputs_by_recipient(
+        &self,
+        recipient: &scc::PublicKey,
+    ) -> Result<Vec<Hash>, BlockchainError> {
+        if !self.cfg.explorer_indexes {
+            return Err(BlockchainError::IndexesDisabled);
+        }
+        let cf_outputs_by_recipient = self.database.cf_handle(OUTPUTS_BY_RECIPIENT).unwrap();
+        let prefix = recipient.to_bytes();
+        let mode = rocksdb::IteratorMode::From(&prefix, rocksdb::Direction::Forward);
+        let mut outputs = Vec::new();
+        for (key, _value) in self.database.iterator_cf(cf_outputs_by_recipient, mode)? {
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            outputs.push(Hash::try_from_bytes(&key[prefix.len()..])?);
+        }
+        Ok(outputs)
+    }
 
-        let outputs: HashMap<Hash, Output> =
-            outputs.into_iter().map(|(h, (o, _k))| (h, o)).collect();
-        let inputs = inputs.into_iter().map(|(h, _)| h).collect();
-        Ok((inputs, outputs, txs))
+    //----------------------------------------------------------------------------------------------
+    // State Snapshots
+    //----------------------------------------------------------------------------------------------
+
+    /// Returns validators elected by the macro block of the specified epoch.
+    fn elected_validators(&self, epoch: u64) -> Result<StakersGroup, BlockchainError> {
+        let epoch_info = self
+            .epoch_info(epoch)?
+            .expect("Expect epoch info for macro block");
+        Ok(epoch_info
+            .validators
+            .into_iter()
+            .map(|v| (v.network_pkey, v.slots))
+            .collect())
+    }
+
+    /// Returns the signed header of the macro block of the specified epoch.
+    fn macro_block_certificate(
+        &self,
+        epoch: u64,
+    ) -> Result<MacroBlockCertificate, BlockchainError> {
+        assert!(epoch > 0);
+        let cf_certificates = self.database.cf_handle(CERTIFICATES).unwrap();
+        let key = Self::block_key(LSN(epoch, MACRO_BLOCK_OFFSET));
+        if let Some(buffer) = self.database.get_cf(cf_certificates, &key)? {
+            return Ok(MacroBlockCertificate::from_buffer(&buffer)?);
+        }
+        // Databases created before certificates were stored.
+        if epoch <= self.snapshot_epoch.unwrap_or(0) {
+            return Err(SnapshotError::NotAvailable(epoch).into());
+        }
+        let block = self.macro_block(epoch)?.into_owned();
+        Ok(MacroBlockCertificate {
+            header: block.header,
+            multisig: block.multisig,
+            multisigmap: block.multisigmap,
+            validators: self.elected_validators(epoch - 1)?,
+        })
     }
 
     ///
-    /// Remove the last micro block.
+    /// Create a snapshot of the blockchain state as of the last macro block.
     ///
-    pub fn pop_micro_block(
-        &mut self,
-    ) -> Result<
-        (
-            Vec<Hash>,
-            HashMap<Hash, Output>,
-            Vec<Transaction>,
-            MicroBlock,
-        ),
-        StorageError,
-    > {
-        assert!(self.epoch > 0, "doesn't work for genesis");
-        assert!(self.offset > 0, "attempt to revert the macro block");
-        let offset = self.offset - 1;
+    pub fn create_snapshot(&self) -> Result<StateSnapshot, BlockchainError> {
+        let cf_output_by_hash = self.database.cf_handle(OUTPUT_BY_HASH).unwrap();
+        let cf_escrow = self.database.cf_handle(ESCROW).unwrap();
+        let cf_meta = self.database.cf_handle(META).unwrap();
+        macro_rules! read_meta {
+            ($key: ident) => {
+                ProtoConvert::from_buffer(
+                    &self
+                        .database
+                        .get_cf(cf_meta, $key.as_bytes())?
+                        .expect(concat!("Cannot find meta name = ", stringify!($key))),
+                )?
+            };
+        }
+
+        // The persistent state corresponds to the last macro block.
+        let lsn: LSN = read_meta!(EPOCH);
+        assert_eq!(lsn.1, MACRO_BLOCK_OFFSET);
+        let epoch = lsn.0;
+        if epoch == 0 {
+            // Nothing to share.
+            return Err(SnapshotError::NotAvailable(epoch).into());
+        }
+
+        let block = self.macro_block(epoch)?.into_owned();
+        if block.header.state_hash.is_none() {
+            return Err(SnapshotError::MissingStateHash(epoch, Hash::digest(&block)).into());
+        }
+
+        // Pruned blocks and blocks before a snapshot are available only as certificates.
+        let mut certificates = Vec::with_capacity(epoch as usize);
+        for epoch in 1..=epoch {
+            certificates.push(self.macro_block_certificate(epoch)?);
+        }
+        let validators = certificates.pop().unwrap().validators;
+
+        let mut outputs = Vec::new();
+        for (k, v) in self
+            .database
+            .iterator_cf(cf_output_by_hash, rocksdb::IteratorMode::Start)?
+        {
+            let output_hash: Hash = ProtoConvert::from_buffer(&k)?;
+            let output_key: OutputKey = ProtoConvert::from_buffer(&v)?;
+            let output = self
+                .output_by_key(&output_hash, &output_key)?
+                .expect("Missing UTXO")
+                .output;
+            outputs.push(output);
+        }
+
+        let mut stakes = Vec::new();
+        for (k, v) in self
+            .database
+            .iterator_cf(cf_escrow, rocksdb::IteratorMode::Start)?
+        {
+            let key: EscrowKey = ProtoConvert::from_buffer(&k)?;
+            let value: EscrowValue = ProtoConvert::from_buffer(&v)?;
+            stakes.push((key, value));
+        }
+
+        let awards: Awards = read_meta!(AWARDS);
+        let balance: Balance = read_meta!(BALANCE);
+
+        Ok(StateSnapshot {
+            certificates,
+            block,
+            validators,
+            outputs,
+            stakes,
+            awards,
+            gamma: balance.gamma,
+            block_reward: balance.block_reward,
+        })
+    }
+
+    ///
+    /// Initialize an empty blockchain from a state snapshot.
+    ///
+    /// Validators of the snapshot are verified using the chain of signed
+    /// macro block headers starting from the genesis. The state itself is
+    /// verified using the state commitment from the last macro block.
+    ///
+    pub fn apply_snapshot(&mut self, snapshot: StateSnapshot) -> Result<(), BlockchainError> {
+        if self.epoch != 1 || self.offset != 0 {
+            return Err(SnapshotError::NotEmpty(self.epoch, self.offset).into());
+        }
+        let block = snapshot.block;
+        let epoch = block.header.epoch;
+        let block_hash = Hash::digest(&block);
+
         //
-        // Remove from the disk.
+        // Check the chain of macro blocks.
         //
-        let block = self.micro_block(self.epoch, offset)?.into_owned();
-        let (previous, lsn, last_block_timestamp) = if offset == 0 {
-            // Previous block is Macro Block.
-            let block = self.macro_block(self.epoch - 1)?;
-            let lsn = LSN(self.epoch - 1, MACRO_BLOCK_OFFSET);
-            (Hash::digest(block.as_ref()), lsn, block.header.timestamp)
-        } else {
-            // Previous block is Micro Block.
-            let block = self.micro_block(self.epoch, offset - 1)?;
-            let lsn = LSN(self.epoch, offset - 1);
-            (Hash::digest(block.as_ref()), lsn, block.header.timestamp)
+        let genesis = self.macro_block(0)?;
+        let mut previous_epoch = genesis.header.epoch;
+        let mut previous_hash = Hash::digest(genesis.as_ref());
+        let mut previous_validators_range_hash = genesis.header.validators_range_hash;
+        drop(genesis);
+        let mut governance = Governance::default();
+        let mut liveness = Liveness::default();
+        let certificates = snapshot
+            .certificates
+            .iter()
+            .map(|c| (&c.header, &c.multisig, &c.multisigmap, &c.validators))
+            .chain(std::iter::once((
+                &block.header,
+                &block.multisig,
+                &block.multisigmap,
+                &snapshot.validators,
+            )));
+        for (header, multisig, multisigmap, validators) in certificates {
+            let hash = Hash::digest(header);
+            if header.epoch != previous_epoch + 1 {
+                return Err(SnapshotError::OutOfOrderMacroBlock(
+                    hash,
+                    header.epoch,
+                    previous_epoch + 1,
+                )
+                .into());
+            }
+            if header.previous != previous_hash {
+                return Err(SnapshotError::InvalidPreviousHash(
+                    header.epoch,
+                    hash,
+                    header.previous,
+                    previous_hash,
+                )
+                .into());
+            }
+            let validators_range_hash = Merkle::root_hash_from_array(validators);
+            if validators_range_hash != previous_validators_range_hash {
+                return Err(SnapshotError::InvalidValidators(
+                    header.epoch,
+                    hash,
+                    validators_range_hash,
+                    previous_validators_range_hash,
+                )
+                .into());
+            }
+            let total_slots = validators.iter().map(|(_, slots)| *slots).sum();
+            check_multi_signature(&hash, multisig, multisigmap, validators, total_slots)
+                .map_err(|e| BlockError::InvalidBlockSignature(e, header.epoch, hash))?;
+            if header.epoch < epoch {
+                // Votes of the snapshot block are counted after the election check.
+                let governance_epochs = self.genesis_cfg.governance_epochs;
+                governance.on_macro_block(header.epoch, &header.proposal, governance_epochs);
+                let cfg = self.genesis_cfg.at_epoch(header.epoch);
+                liveness.on_macro_block(
+                    header.epoch,
+                    validators,
+                    &header.activity_map,
+                    multisigmap,
+                    cfg.jail_missed_epochs,
+                    cfg.jail_epochs,
+                );
+            }
+            previous_epoch = header.epoch;
+            previous_hash = hash;
+            previous_validators_range_hash = header.validators_range_hash;
+        }
+
+        //
+        // Check the block body.
+        //
+        let inputs_range_hash = Merkle::root_hash_from_array(&block.inputs);
+        let output_hashes: Vec<Hash> = block.outputs.iter().map(Hash::digest).collect();
+        let outputs_range_hash = Merkle::root_hash_from_array(&output_hashes);
+        if block.header.inputs_len != block.inputs.len() as u32
+            || block.header.inputs_range_hash != inputs_range_hash
+            || block.header.outputs_len != block.outputs.len() as u32
+            || block.header.outputs_range_hash != outputs_range_hash
+        {
+            return Err(SnapshotError::InvalidBlockBody(epoch, block_hash).into());
+        }
+
+        //
+        // Check the state.
+        //
+        let expected_state_hash = match block.header.state_hash {
+            Some(state_hash) => state_hash,
+            None => return Err(SnapshotError::MissingStateHash(epoch, block_hash).into()),
+        };
+        let mut outputs: BTreeMap<Hash, Output> = BTreeMap::new();
+        for output in snapshot.outputs {
+            let output_hash = Hash::digest(&output);
+            if outputs.insert(output_hash, output).is_some() {
+                return Err(SnapshotError::DuplicateOutput(output_hash).into());
+            }
+        }
+        let stakes: BTreeMap<EscrowKey, EscrowValue> = snapshot.stakes.into_iter().collect();
+        let state_hash = snapshot::state_hash(outputs.keys(), stakes.iter(), &snapshot.awards);
+        if state_hash != expected_state_hash {
+            return Err(SnapshotError::InvalidStateHash(
+                epoch,
+                block_hash,
+                expected_state_hash,
+                state_hash,
+            )
+            .into());
+        }
+
+        //
+        // Check the monetary balance.
+        //
+        let mut created = Pt::identity();
+        for output in outputs.values() {
+            created += output.pedersen_commitment()?;
+        }
+        let balance = Balance {
+            created,
+            burned: Pt::identity(),
+            gamma: snapshot.gamma,
+            block_reward: snapshot.block_reward,
+        };
+        if fee_a(balance.block_reward) + balance.burned - balance.created
+            != balance.gamma * Pt::one()
+        {
+            return Err(SnapshotError::InvalidBalance(epoch, block_hash).into());
+        }
+
+        //
+        // Check the validators elected by the block.
+        //
+        let mut escrow = Escrow::new();
+        let lsn = LSN(epoch, MACRO_BLOCK_OFFSET);
+        for (key, value) in stakes.iter() {
+            escrow.escrow.insert(lsn, key.clone(), value.clone());
+        }
+        let mut cfg = self.genesis_cfg.at_epoch(epoch);
+        governance.active.apply(&mut cfg);
+        let stakers = escrow.get_stakers_majority(epoch + 1, cfg.min_stake_amount);
+        let stakers = liveness.filter_stakers(stakers, epoch + 1);
+        let election_result =
+            election::select_validators_slots(stakers, block.header.random, cfg.max_slot_count);
+        governance.on_macro_block(epoch, &block.header.proposal, cfg.governance_epochs);
+        liveness.on_macro_block(
+            epoch,
+            &snapshot.validators,
+            &block.header.activity_map,
+            &block.multisigmap,
+            cfg.jail_missed_epochs,
+            cfg.jail_epochs,
+        );
+        let validators_range_hash = Merkle::root_hash_from_array(&election_result.validators);
+        if block.header.validators_len != election_result.validators.len() as u32
+            || block.header.validators_range_hash != validators_range_hash
+        {
+            return Err(SnapshotError::InvalidValidators(
+                epoch,
+                block_hash,
+                validators_range_hash,
+                block.header.validators_range_hash,
+            )
+            .into());
+        }
+
+        //
+        // Replace the state.
+        //
+        let cf_block_by_hash = self.database.cf_handle(BLOCK_BY_HASH).unwrap();
+        let cf_output_by_hash = self.database.cf_handle(OUTPUT_BY_HASH).unwrap();
+        let cf_escrow = self.database.cf_handle(ESCROW).unwrap();
+        let cf_snapshot_outputs = self.database.cf_handle(SNAPSHOT_OUTPUTS).unwrap();
+        let cf_epoch_infos = self.database.cf_handle(EPOCH_INFOS).unwrap();
+        let cf_certificates = self.database.cf_handle(CERTIFICATES).unwrap();
+        let cf_meta = self.database.cf_handle(META).unwrap();
+        let mut batch = self.write_block(lsn, Block::MacroBlock(block.clone()))?;
+
+        let certificate = MacroBlockCertificate {
+            header: block.header.clone(),
+            multisig: block.multisig.clone(),
+            multisigmap: block.multisigmap.clone(),
+            validators: snapshot.validators.clone(),
+        };
+        for certificate in snapshot
+            .certificates
+            .iter()
+            .chain(std::iter::once(&certificate))
+        {
+            batch.put_cf(
+                cf_certificates,
+                &Self::block_key(LSN(certificate.header.epoch, MACRO_BLOCK_OFFSET)),
+                &certificate.into_buffer()?,
+            )?;
+        }
+
+        let stale_outputs: Vec<Hash> = self
+            .output_by_hash
+            .keys()
+            .filter(|h| !outputs.contains_key(h))
+            .cloned()
+            .collect();
+        for output_hash in stale_outputs {
+            self.output_by_hash.remove(lsn, &output_hash);
+        }
+        for (output_hash, output) in outputs.iter() {
+            batch.put_cf(
+                cf_snapshot_outputs,
+                output_hash.base_vector(),
+                &output.into_buffer()?,
+            )?;
+            self.output_by_hash
+                .insert(lsn, output_hash.clone(), OutputKey::Snapshot { epoch });
+        }
+        let stale_stakes: Vec<EscrowKey> = self
+            .escrow
+            .escrow
+            .keys()
+            .filter(|k| !stakes.contains_key(k))
+            .cloned()
+            .collect();
+        for key in stale_stakes {
+            self.escrow.escrow.remove(lsn, &key);
+        }
+        for (key, value) in stakes {
+            self.escrow.escrow.insert(lsn, key, value);
+        }
+        self.rebuild_utxo_tree();
+        self.block_by_hash.insert(lsn, block_hash, lsn);
+        self.balance.insert(lsn, (), balance);
+        self.election_result.insert(lsn, (), election_result);
+        self.awards = snapshot.awards;
+        self.governance = governance;
+        self.liveness = liveness;
+        self.epoch_activity.reset();
+        self.view_change_proof = None;
+
+        //
+        // Update metadata.
+        //
+        self.epoch = epoch + 1;
+        self.offset = 0;
+        self.last_block_timestamp = block.header.timestamp;
+        self.last_block_hash = block_hash;
+        self.last_macro_block_timestamp = block.header.timestamp;
+        self.last_macro_block_random = block.header.random.rand;
+        self.last_macro_block_hash = block_hash;
+        self.difficulty = block.header.difficulty;
+        self.snapshot_epoch = Some(epoch);
+        self.cache.clear();
+        self.cache_push_block(block.into());
+        self.activate_forks();
+
+        //
+        // Finalize storage.
+        //
+        Self::write_log(
+            &mut batch,
+            cf_block_by_hash,
+            self.block_by_hash.checkpoint(),
+        )?;
+        Self::write_log(
+            &mut batch,
+            cf_output_by_hash,
+            self.output_by_hash.checkpoint(),
+        )?;
+        Self::write_log(&mut batch, cf_escrow, self.escrow.checkpoint())?;
+        let _ = self.election_result.checkpoint();
+        let _ = self.balance.checkpoint();
+        Self::write_meta(&mut batch, cf_meta, BALANCE, self.balance())?;
+        Self::write_meta(&mut batch, cf_meta, EPOCH, &lsn)?;
+        Self::write_meta(
+            &mut batch,
+            &cf_meta,
+            ELECTION_RESULT,
+            self.election_result(),
+        )?;
+        Self::write_meta(&mut batch, &cf_meta, AWARDS, &self.awards)?;
+        Self::write_meta(&mut batch, &cf_meta, GOVERNANCE, &self.governance)?;
+        Self::write_meta(&mut batch, &cf_meta, LIVENESS, &self.liveness)?;
+        Self::write_meta(&mut batch, &cf_meta, SNAPSHOT, &lsn)?;
+        let awards = AwardsInfo {
+            service_award_state: self.awards.clone(),
+            payout: None,
         };
+        let epoch_info = self.current_epoch_info(awards);
+        batch.put_cf(
+            cf_epoch_infos,
+            &Self::block_key(lsn),
+            &epoch_info.into_buffer()?,
+        )?;
+        self.database.write(batch)?;
+
+        metrics::EPOCH.set(self.epoch as i64);
+        metrics::OFFSET.set(0);
+        metrics::UTXO_LEN.set(self.output_by_hash.len() as i64);
+        metrics::EMISSION.set(self.balance().block_reward);
+        metrics::DIFFICULTY.set(self.difficulty as i64);
+
+        info!(
+            "Initialized blockchain from a state snapshot: epoch={}, block={}, utxos={}, stakes={}",
+            epoch,
+            block_hash,
+            self.output_by_hash.len(),
+            self.escrow.escrow.len(),
+        );
+        Ok(())
+    }
+
+    // ---------------------------------------------------------------------------------------------
+    // Micro Blocks
+    // ---------------------------------------------------------------------------------------------
+
+    ///
+    /// Add a new micro block into blockchain.
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - a micro block to add.
+    /// * `timestamp` - block arrival timestamp.
+    ///
+    pub fn push_micro_block(
+        &mut self,
+        block: MicroBlock,
+        timestamp: Timestamp,
+    ) -> Result<(Vec<Hash>, HashMap<Hash, Output>, HashMap<Hash, Transaction>), StorageError> {
+        assert_eq!(self.epoch, block.header.epoch);
+        assert_eq!(self.offset, block.header.offset);
+
+        //
+        // Double-check if debug.
+        //
+        if self.consistency_check >= ConsistencyCheck::Incoming {
+            self.validate_micro_block(&block, timestamp, true)
+                .expect("block is valid");
+        }
+
+        //
+        // Write the micro block to the disk.
+        // The undo log entry is removed after registration.
+        //
+        let lsn = LSN(self.epoch, self.offset);
+        let cf_undo_log = self.database.cf_handle(UNDO_LOG).unwrap();
+        let mut batch = self.write_block(lsn, Block::MicroBlock(block.clone()))?;
+        self.index_micro_block(&mut batch, lsn, &block)?;
+        let entry = UndoLogEntry::Push(Hash::digest(&block));
+        let entry = entry
+            .into_buffer()
+            .expect("couldn't serialize undo log entry.");
+        batch.put_cf(cf_undo_log, &Self::block_key(lsn), &entry)?;
+        self.database.write(batch)?;
+
+        //
+        // Update in-memory indexes and metadata.
+        //
+        let r = self.register_micro_block(lsn, block)?;
+        let cf_undo_log = self.database.cf_handle(UNDO_LOG).unwrap();
         self.database
-            .delete(&Self::block_key(LSN(self.epoch, offset)))?;
-        let block_hash = Hash::digest(&block);
+            .delete_cf(cf_undo_log, &Self::block_key(lsn))?;
+        Ok(r)
+    }
+
+    ///
+    /// Schedule reverting of all micro blocks of the current epoch starting from `offset`.
+    ///
+    /// The schedule is persisted, so the rollback is completed on the next start
+    /// if the node crashes before all blocks are reverted by pop_micro_block().
+    ///
+    pub fn begin_rollback(&self, offset: u32) -> Result<(), StorageError> {
+        assert!(offset <= self.offset);
+        let cf_undo_log = self.database.cf_handle(UNDO_LOG).unwrap();
+        let mut batch = WriteBatch::default();
+        for offset in offset..self.offset {
+            let block = self.micro_block(self.epoch, offset)?;
+            let entry = UndoLogEntry::Pop(Hash::digest(block.as_ref()));
+            let entry = entry
+                .into_buffer()
+                .expect("couldn't serialize undo log entry.");
+            batch.put_cf(
+                cf_undo_log,
+                &Self::block_key(LSN(self.epoch, offset)),
+                &entry,
+            )?;
+        }
+        self.database.write(batch)
+    }
+
+    ///
+    /// Common part of register_macro_block()/register_micro_block().
+    ///
+    fn register_inputs_and_outputs(
+        &mut self,
+        lsn: LSN,
+        block_hash: Hash,
+        inputs: &HashMap<Hash, Output>,
+        outputs: &HashMap<Hash, (Output, OutputKey)>,
+        gamma: Fr,
+        block_reward: i64,
+    ) {
+        let epoch = self.epoch;
 
         //
-        // Revert metadata.
+        // Update block_by_hash index.
         //
-        self.block_by_hash.rollback_to_lsn(lsn);
-        self.output_by_hash.rollback_to_lsn(lsn);
-        self.balance.rollback_to_lsn(lsn);
+        if let Some(_) = self.block_by_hash.insert(lsn, block_hash, lsn) {
+            panic!(
+                "Block hash collision: epoch={}, block={}",
+                epoch, block_hash
+            );
+        }
+        assert_eq!(self.block_by_hash.current_lsn(), lsn);
+
+        let mut burned = Pt::identity();
+        let mut created = Pt::identity();
+
+        //
+        // Process inputs.
+        //
+        for (input_hash, input) in inputs.iter() {
+            if self.output_by_hash.remove(lsn, input_hash).is_none() {
+                panic!(
+                    "Missing input UTXO: epoch={}, block={}, utxo={}",
+                    epoch, block_hash, &input_hash
+                );
+            }
+
+            if cfg!(debug_assertions) {
+                input.validate().expect("valid UTXO");
+            }
+            burned += input
+                .pedersen_commitment()
+                .expect("valid Pedersen commitment");
+
+            match input {
+                Output::PaymentOutput(_o) => {}
+                Output::PublicPaymentOutput(_o) => {}
+                Output::MultisigOutput(_o) => {}
+                Output::HtlcOutput(_o) => {}
+                Output::StakeOutput(o) => {
+                    self.escrow
+                        .unstake(lsn, o.validator, input_hash.clone(), self.epoch);
+                    assert_eq!(self.escrow.current_lsn(), lsn);
+                }
+            }
+
+            debug!(
+                "Pruned UXTO: epoch={}, block={}, utxo={}",
+                epoch, block_hash, input_hash
+            );
+        }
+
+        //
+        // Process outputs.
+        //
+        for (output_hash, (output, output_key)) in outputs.iter() {
+            // Update indexes.
+            if let Some(_) =
+                self.output_by_hash
+                    .insert(lsn, output_hash.clone(), output_key.clone())
+            {
+                panic!(
+                    "UTXO hash collision: epoch={}, block={}, utxo={}",
+                    epoch, &block_hash, &output_hash
+                );
+            }
+            assert_eq!(self.output_by_hash.current_lsn(), lsn);
+
+            created += output
+                .pedersen_commitment()
+                .expect("valid Pedersen commitment");
+
+            match output {
+                Output::PaymentOutput(_o) => {}
+                Output::PublicPaymentOutput(_o) => {}
+                Output::MultisigOutput(_o) => {}
+                Output::HtlcOutput(_o) => {}
+                Output::StakeOutput(o) => {
+                    self.escrow.stake(
+                        lsn,
+                        output_hash.clone(),
+                        o,
+                        self.epoch,
+                        self.cfg.stake_epochs,
+                        self.cfg.unbonding_epochs,
+                    );
+                    assert_eq!(self.escrow.current_lsn(), lsn);
+                }
+            }
+
+            debug!(
+                "Registered UXTO: epoch={}, block={}, utxo={}",
+                epoch, &block_hash, &output_hash
+            );
+        }
+
+        //
+        // Update monetary balance.
+        //
+
+        // Check the block monetary balance.
+        if fee_a(block_reward) + burned - created != gamma * Pt::one() {
+            panic!(
+                "Invalid block monetary balance: epoch={}, block={}",
+                epoch, &block_hash
+            )
+        }
+
+        // Global monetary balance.
+        let orig_balance = self.balance();
+        let balance = Balance {
+            created: orig_balance.created + created,
+            burned: orig_balance.burned + burned,
+            gamma: orig_balance.gamma + gamma,
+            block_reward: orig_balance.block_reward + block_reward,
+        };
+        if fee_a(balance.block_reward) + balance.burned - balance.created
+            != balance.gamma * Pt::one()
+        {
+            panic!(
+                "Invalid global monetary balance: epoch={}, block={}",
+                epoch, &block_hash
+            );
+        }
+        self.balance.insert(lsn, (), balance);
+        assert_eq!(self.balance.current_lsn(), lsn);
+    }
+
+    ///
+    /// Register a new micro block.
+    ///
+    fn register_micro_block(
+        &mut self,
+        lsn: LSN,
+        block: MicroBlock,
+    ) -> Result<(Vec<Hash>, HashMap<Hash, Output>, HashMap<Hash, Transaction>), StorageError> {
+        assert_eq!(block.header.version, self.version());
+        assert_eq!(self.epoch, block.header.epoch);
+        assert_eq!(self.offset, block.header.offset);
+        assert_eq!(block.header.previous, self.last_block_hash);
+        assert!(block.header.timestamp > self.last_block_timestamp);
+        assert!(!self.is_epoch_full());
+        let epoch = self.epoch;
+        let offset = self.offset;
+        let block_hash = Hash::digest(&block);
+
+        //
+        // Prepare inputs && outputs.
+        //
+        let mut inputs: HashMap<Hash, Output> = HashMap::new();
+        let mut outputs: HashMap<Hash, (Output, OutputKey)> = HashMap::new();
+        let mut gamma = Fr::zero();
+        let mut block_reward: i64 = 0;
+        let mut txs: HashMap<Hash, Transaction> = HashMap::new();
+        // Regular transactions.
+        for (tx_id, tx) in block.transactions.into_iter().enumerate() {
+            assert!(tx_id < std::u32::MAX as usize);
+
+            let tx_hash = Hash::digest(&tx);
+            for input_hash in tx.txins() {
+                assert!(
+                    !outputs.contains_key(&input_hash),
+                    "annihilation in micro block"
+                );
+                let input = self.output_by_hash(input_hash)?.expect("Missing output");
+                let prev = inputs.insert(input_hash.clone(), input);
+                assert!(prev.is_none(), "duplicate input");
+            }
+            for (txout_id, output) in tx.txouts().iter().enumerate() {
+                let output_hash = Hash::digest(output);
+                assert!(txout_id < std::u32::MAX as usize);
+                let output_key = OutputKey::MicroBlock {
+                    epoch,
+                    offset,
+                    tx_id: tx_id as u32,
+                    txout_id: txout_id as u32,
+                };
+                let prev = outputs.insert(output_hash, (output.clone(), output_key));
+                assert!(prev.is_none(), "duplicate output");
+            }
+            match &tx {
+                Transaction::CoinbaseTransaction(tx) => {
+                    block_reward += tx.block_reward;
+                    gamma += tx.gamma;
+                }
+                Transaction::PaymentTransaction(tx) => {
+                    gamma += tx.gamma;
+                }
+                Transaction::RestakeTransaction(_tx) => {}
+                Transaction::SlashingTransaction(tx) => {
+                    info!(
+                        "Found slashing transaction, removing validator, from list: cheater={}",
+                        tx.cheater()
+                    );
+                    let validators = &self.election_result().validators;
+                    // remove cheater for current epoch.
+                    let new_validators = validators
+                        .into_iter()
+                        .filter_map(|(k, v)| {
+                            if k != &tx.cheater() {
+                                Some((*k, *v))
+                            } else {
+                                None
+                            }
+                        })
+                        .collect();
+                    let mut election_result = self.election_result().clone();
+                    election_result.validators = new_validators;
+                    self.election_result.insert(lsn, (), election_result);
+                }
+                Transaction::ServiceAwardTransaction(_tx) => {
+                    panic!("Found a ServiceAward transaction inside a MicroBlock")
+                }
+            }
+
+            assert!(txs.insert(tx_hash, tx).is_none());
+        }
+
+        //
+        // Update service awards
+        //
+        // Set skipped validators to inactive.
+        for skiped_view_change in 0..block.header.view_change {
+            let leader = self.election_result().select_leader(skiped_view_change);
+            self.epoch_activity.insert(
+                lsn,
+                leader,
+                ValidatorAwardState::Failed {
+                    epoch: self.epoch(),
+                    offset: self.offset(),
+                },
+            );
+        }
+
+        // set current leader to active, if it was unknown.
+        let leader = self
+            .election_result()
+            .select_leader(block.header.view_change);
+        if self.epoch_activity.get(&leader).is_none() {
+            self.epoch_activity
+                .insert(lsn, leader, ValidatorAwardState::Active);
+        }
+
+        //
+        // Register block.
+        //
+        self.register_inputs_and_outputs(lsn, block_hash, &inputs, &outputs, gamma, block_reward);
+
+        //
+        // Update metadata.
+        //
+        self.last_block_timestamp = block.header.timestamp;
+        self.last_block_hash = block_hash;
+        self.offset += 1;
+        let mut election_result = self.election_result().clone();
+        election_result.view_change = 0;
+        election_result.random = block.header.random;
+        self.election_result.insert(lsn, (), election_result);
+        self.view_change_proof = None;
+
+        //
+        // Update metrics.
+        //
+        metrics::OFFSET.set(self.offset as i64);
+        metrics::MICRO_BLOCK_INPUTS.set(inputs.len() as i64);
+        metrics::MICRO_BLOCK_OUTPUTS.set(outputs.len() as i64);
+        metrics::MICRO_BLOCK_TRANSACTIONS.set(txs.len() as i64);
+        metrics::MICRO_BLOCK_INPUTS_HG.observe(inputs.len() as f64);
+        metrics::MICRO_BLOCK_OUTPUTS_HG.observe(outputs.len() as f64);
+        metrics::MICRO_BLOCK_TRANSACTIONS_HG.observe(txs.len() as f64);
+        metrics::UTXO_LEN.set(self.output_by_hash.len() as i64);
+        metrics::EMISSION.set(self.balance().block_reward);
+
+        info!(
+            "Registered a micro block: epoch={}, offset={}, block={}, txs={:?}, inputs={:?}, outputs={:?}",
+            epoch,
+            offset,
+            block_hash,
+            txs.keys()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>(),
+            inputs.keys()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>(),
+            outputs.keys()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>(),
+        );
+
+        let outputs: HashMap<Hash, Output> =
+            outputs.into_iter().map(|(h, (o, _k))| (h, o)).collect();
+        let inputs = inputs.into_iter().map(|(h, _)| h).collect();
+        Ok((inputs, outputs, txs))
+    }
+
+    ///
+    /// Remove the last micro block.
+    ///
+    pub fn pop_micro_block(
+        &mut self,
+    ) -> Result<
+        (
+            Vec<Hash>,
+            HashMap<Hash, Output>,
+            Vec<Transaction>,
+            MicroBlock,
+        ),
+        StorageError,
+    > {
+        self.revert_micro_block(true)
+    }
+
+    ///
+    /// Remove the last micro block which is committed by the next macro block.
+    /// Unlike pop_micro_block(), keeps transactions of the block in secondary indexes.
+    ///
+    pub fn pop_committed_micro_block(
+        &mut self,
+    ) -> Result<
+        (
+            Vec<Hash>,
+            HashMap<Hash, Output>,
+            Vec<Transaction>,
+            MicroBlock,
+        ),
+        StorageError,
+    > {
+        self.revert_micro_block(false)
+    }
+
+    fn revert_micro_block(
+        &mut self,
+        unindex: bool,
+    ) -> Result<
+        (
+            Vec<Hash>,
+            HashMap<Hash, Output>,
+            Vec<Transaction>,
+            MicroBlock,
+        ),
+        StorageError,
+    > {
+        assert!(self.epoch > 0, "doesn't work for genesis");
+        assert!(self.offset > 0, "attempt to revert the macro block");
+        let offset = self.offset - 1;
+        //
+        // Remove from the disk.
+        //
+        let block = self.micro_block(self.epoch, offset)?.into_owned();
+        let (previous, lsn, last_block_timestamp) = if offset == 0 {
+            // Previous block is Macro Block.
+            let block = self.macro_block(self.epoch - 1)?;
+            let lsn = LSN(self.epoch - 1, MACRO_BLOCK_OFFSET);
+            (Hash::digest(block.as_ref()), lsn, block.header.timestamp)
+        } else {
+            // Previous block is Micro Block.
+            let block = self.micro_block(self.epoch, offset - 1)?;
+            let lsn = LSN(self.epoch, offset - 1);
+            (Hash::digest(block.as_ref()), lsn, block.header.timestamp)
+        };
+        let cf_undo_log = self.database.cf_handle(UNDO_LOG).unwrap();
+        let key = Self::block_key(LSN(self.epoch, offset));
+        let mut batch = WriteBatch::default();
+        batch.delete(&key)?;
+        batch.delete_cf(cf_undo_log, &key)?;
+        if unindex {
+            self.unindex_micro_block(&mut batch, &block)?;
+        }
+        self.database.write(batch)?;
+        let block_hash = Hash::digest(&block);
+
+        //
+        // Revert metadata.
+        //
+        self.block_by_hash.rollback_to_lsn(lsn);
+        self.output_by_hash.rollback_to_lsn(lsn);
+        self.balance.rollback_to_lsn(lsn);
         self.escrow.rollback_to_lsn(lsn);
         self.epoch_activity.rollback_to_lsn(lsn);
 
-        self.election_result.rollback_to_lsn(lsn);
-        assert_eq!(self.block_by_hash.current_lsn(), lsn);
-        assert_eq!(self.election_result.current_lsn(), lsn);
-        assert!(self.epoch_activity.current_lsn() <= lsn);
-        assert!(self.output_by_hash.current_lsn() <= lsn);
-        assert!(self.balance.current_lsn() <= lsn);
-        assert!(self.escrow.current_lsn() <= lsn);
-        self.offset = offset;
-        self.last_block_hash = previous;
-        self.last_block_timestamp = last_block_timestamp;
-        self.reset_view_change();
+        self.election_result.rollback_to_lsn(lsn);
+        assert_eq!(self.block_by_hash.current_lsn(), lsn);
+        assert_eq!(self.election_result.current_lsn(), lsn);
+        assert!(self.epoch_activity.current_lsn() <= lsn);
+        assert!(self.output_by_hash.current_lsn() <= lsn);
+        assert!(self.balance.current_lsn() <= lsn);
+        assert!(self.escrow.current_lsn() <= lsn);
+        self.offset = offset;
+        self.last_block_hash = previous;
+        self.last_block_timestamp = last_block_timestamp;
+        self.reset_view_change();
+
+        // Recover inputs that was removed, and remove outputs that was processed in this block.
+        let mut recovered: HashMap<Hash, Output> = HashMap::new();
+        let mut pruned: Vec<Hash> = Vec::new();
+        let mut removed = Vec::new();
+        for tx in block.clone().transactions {
+            for input_hash in tx.txins() {
+                let input = self.output_by_hash(input_hash)?.expect("exists");
+                recovered.insert(input_hash.clone(), input);
+                debug!(
+                    "Restored UXTO: epoch={}, block={}, utxo={}",
+                    self.epoch, &block_hash, &input_hash
+                );
+            }
+            for output in tx.txouts() {
+                let output_hash = Hash::digest(output);
+                pruned.push(output_hash);
+                debug!(
+                    "Reverted UTXO: epoch={}, block={}, utxo={}",
+                    self.epoch, &block_hash, &output_hash
+                );
+            }
+            match tx {
+                Transaction::PaymentTransaction(_) | Transaction::RestakeTransaction(_) => {
+                    removed.push(tx)
+                }
+                _ => continue,
+            }
+        }
+
+        //
+        // Update metrics.
+        //
+        metrics::OFFSET.set(self.offset as i64);
+        metrics::UTXO_LEN.set(self.output_by_hash.len() as i64);
+        metrics::EMISSION.set(self.balance().block_reward);
+
+        info!(
+            "Reverted a micro block: epoch={}, offset={}, block={}, inputs={:?}, outputs={:?}",
+            self.epoch,
+            offset,
+            &block_hash,
+            recovered
+                .keys()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>(),
+            pruned
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>(),
+        );
+
+        Ok((pruned, recovered, removed, block))
+    }
+
+    fn write_meta<V>(
+        batch: &mut WriteBatch,
+        meta_cf: &ColumnFamily,
+        key: &'static str,
+        value: &V,
+    ) -> Result<(), BlockchainError>
+    where
+        V: ProtoConvert + std::fmt::Debug,
+    {
+        let value = value.into_buffer()?;
+        batch.put_cf(meta_cf, key.as_bytes(), &value)?;
+        Ok(())
+    }
+
+    /// Undolog is actualy a patchset, so just apply it to the block.
+    fn write_log<K, V>(
+        batch: &mut WriteBatch,
+        cf: &ColumnFamily,
+        diff: BTreeMap<K, Option<V>>,
+    ) -> Result<(), BlockchainError>
+    where
+        K: ProtoConvert + std::fmt::Debug,
+        V: ProtoConvert + std::fmt::Debug,
+    {
+        trace!("Persist log");
+        for (key, value) in diff {
+            match value {
+                Some(value) => {
+                    trace!("New insert {:?}={:?}", key, value);
+                    let key = key.into_buffer()?;
+                    let value = value.into_buffer()?;
+                    batch.put_cf(cf, &key, &value)?
+                }
+                None => {
+                    trace!("Remove {:?}", key);
+                    let key = key.into_buffer()?;
+                    batch.delete_cf(cf, &key)?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Insert block into cache.
+    fn cache_push_block(&mut self, block: Block) {
+        trace!("Cache block = {}", block.unwrap_macro_ref().header.epoch);
+        if self.cache.len() == self.cfg.stake_epochs as usize + 1 {
+            trace!("Removed block");
+            assert!(self.cache.pop_front().is_some())
+        }
+        self.cache.push_back(block)
+    }
+
+    /// Try get block from cache.
+    fn get_block_cached(&self, epoch: u64) -> Option<&Block> {
+        trace!("get block = {}", epoch);
+        if epoch > self.epoch {
+            return None;
+        }
+        let lower_epoch = self.epoch.saturating_sub(self.cfg.stake_epochs + 1);
+        if epoch < lower_epoch {
+            return None;
+        }
+        // The cache can have gaps if the blockchain was initialized from a snapshot.
+        self.cache
+            .iter()
+            .rev()
+            .find(|b| b.unwrap_macro_ref().header.epoch == epoch)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    use crate::light::LightBlockchain;
+    use crate::multisignature::create_multi_signature;
+    use crate::slashing::{confiscate_tx, SlashingProof};
+    use crate::test;
+    use crate::timestamp::Timestamp;
+    use rand::Rng;
+    use simple_logger;
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+    use tempdir::TempDir;
+
+    #[test]
+    fn basic() {
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let timestamp = Timestamp::now();
+        let cfg: ChainConfig = Default::default();
+        let (keychains, block1) = test::fake_genesis(
+            cfg.min_stake_amount,
+            10 * cfg.min_stake_amount,
+            cfg.max_slot_count,
+            3,
+            timestamp,
+            None,
+        );
+        let chain_dir = TempDir::new("test").unwrap();
+        let blockchain = Blockchain::new(
+            cfg,
+            chain_dir.path(),
+            ConsistencyCheck::None,
+            block1.clone(),
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+        let outputs: Vec<Output> = block1.outputs.clone();
+        let mut unspent: Vec<Hash> = outputs.iter().map(|o| Hash::digest(o)).collect();
+        unspent.sort();
+        let mut unspent2: Vec<Hash> = blockchain.unspent().cloned().collect();
+        unspent2.sort();
+        assert_eq!(unspent, unspent2);
+
+        let validators = blockchain
+            .escrow
+            .get_stakers_majority(blockchain.epoch, blockchain.cfg.min_stake_amount);
+        assert_eq!(validators.len(), keychains.len());
+        let validators_map: BTreeMap<_, _> = validators.iter().cloned().collect();
+        for keychain in &keychains {
+            let stake = validators_map.get(&keychain.network_pkey).expect("exists");
+            assert_eq!(*stake, blockchain.cfg.min_stake_amount);
+        }
+        assert_eq!(blockchain.last_block_hash(), Hash::digest(&block1));
+
+        let blocks2: Vec<Block> = blockchain.blocks().collect();
+        assert_eq!(blocks2.len(), 1);
+        assert_eq!(Hash::digest(&blocks2[0]), Hash::digest(&block1));
+
+        assert!(blockchain.contains_block(&Hash::digest(&block1)));
+        assert!(!blockchain.contains_block(&Hash::digest("test")));
+
+        assert_eq!(
+            Hash::digest(
+                blockchain
+                    .block(LSN(0, MACRO_BLOCK_OFFSET))
+                    .unwrap()
+                    .as_ref()
+            ),
+            Hash::digest(&block1)
+        );
+
+        assert!(!blockchain.contains_output(&Hash::digest("test")));
+        assert!(blockchain
+            .output_by_hash(&Hash::digest("test"))
+            .expect("no disk errors")
+            .is_none());
+        for output in block1.outputs.iter() {
+            let output_hash = Hash::digest(&output);
+            let output2 = blockchain
+                .output_by_hash(&output_hash)
+                .expect("no disk errors")
+                .expect("exists");
+            assert_eq!(Hash::digest(&output2), output_hash);
+            assert!(blockchain.contains_output(&output_hash));
+        }
+    }
+
+    #[test]
+    fn iterate() {
+        const NUM_NODES: usize = 32;
+        const STAKE_EPOCHS: u64 = 2;
+        const EPOCHS: u64 = 10;
+
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let mut rng = rand::thread_rng();
+        let mut cfg: ChainConfig = Default::default();
+        cfg.stake_epochs = STAKE_EPOCHS;
+        cfg.micro_blocks_in_epoch = 2;
+        let mut timestamp = Timestamp::now();
+        let (keychains, genesis) = test::fake_genesis(
+            cfg.min_stake_amount,
+            (NUM_NODES as i64) * cfg.min_stake_amount + 100,
+            cfg.max_slot_count,
+            NUM_NODES,
+            timestamp,
+            None,
+        );
+        let chain_dir = TempDir::new("test").unwrap();
+        let mut chain = Blockchain::new(
+            cfg.clone(),
+            chain_dir.path(),
+            ConsistencyCheck::Full,
+            genesis.clone(),
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+
+        for _epoch in 0..EPOCHS {
+            let epoch = chain.epoch();
+
+            //
+            // Micro Block.
+            //
+            for offset in 0..chain.cfg().micro_blocks_in_epoch {
+                timestamp += Duration::from_secs(rng.gen_range(5, 10));
+                let (block, input_hashes, output_hashes) =
+                    test::create_fake_micro_block(&mut chain, &keychains, timestamp);
+                let hash = Hash::digest(&block);
+                chain
+                    .push_micro_block(block, timestamp)
+                    .expect("no I/O errors");
+                assert_eq!(hash, chain.last_block_hash());
+                assert_eq!(offset + 1, chain.offset());
+                for input_hash in input_hashes {
+                    assert!(!chain.contains_output(&input_hash));
+                }
+                for output_hash in output_hashes {
+                    assert!(chain.contains_output(&output_hash));
+                }
+            }
+
+            //
+            // Macro block.
+            //
+
+            // Create a macro block.
+            timestamp += Duration::from_secs(5 + rng.gen_range(0, 10));
+            let (block, extra_transactions) =
+                test::create_fake_macro_block(&chain, &keychains, timestamp);
+            let hash = Hash::digest(&block);
+
+            // Collect unspent outputs.
+            let mut unspent: Vec<Hash> = chain.unspent().cloned().collect();
+            for tx in &extra_transactions {
+                assert_eq!(tx.txins().len(), 0);
+                for output in tx.txouts() {
+                    let output_hash = Hash::digest(output);
+                    unspent.push(output_hash);
+                }
+            }
+            unspent.sort();
+
+            // Remove all micro blocks.
+            while chain.offset() > 0 {
+                chain.pop_micro_block().expect("Should be ok");
+            }
+            // Push the macro block.
+            chain
+                .push_macro_block(block, timestamp)
+                .expect("Invalid block");
+            let mut unspent2: Vec<Hash> = chain.unspent().cloned().collect();
+            unspent2.sort();
+            assert_eq!(unspent, unspent2);
+            assert_eq!(hash, chain.last_block_hash());
+            assert_eq!(epoch + 1, chain.epoch());
+            assert_eq!(0, chain.offset());
+        }
+
+        //
+        // Recovery.
+        //
+        let epoch = chain.epoch();
+        let offset = chain.offset();
+        let block_count = chain.blocks().count();
+        let block_hash = chain.last_block_hash();
+        let balance = chain.balance().clone();
+        drop(chain);
+        let chain = Blockchain::new(
+            cfg,
+            chain_dir.path(),
+            ConsistencyCheck::Full,
+            genesis,
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+        assert_eq!(epoch, chain.epoch());
+        assert_eq!(offset, chain.offset());
+        assert_eq!(block_hash, chain.last_block_hash());
+        assert_eq!(block_count, chain.blocks().count());
+        assert_eq!(&balance, chain.balance());
+
+        //
+        // Service award draws.
+        //
+        let draws = chain.award_draws(0, EPOCHS as usize + 1).unwrap();
+        assert_eq!(draws.len() as u64, EPOCHS);
+        for (i, draw) in draws.iter().enumerate() {
+            assert_eq!(draw.epoch, i as u64 + 1);
+            assert!(draw.verify());
+        }
+        let draws = chain.award_draws(3, 2).unwrap();
+        assert_eq!(
+            draws.iter().map(|d| d.epoch).collect::<Vec<_>>(),
+            vec![3, 4]
+        );
+    }
+
+    #[test]
+    fn rollback() {
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        //
+        // Create genesis block.
+        //
+        let mut timestamp = Timestamp::now();
+        let block_timestamp0 = timestamp;
+        let cfg: ChainConfig = Default::default();
+        let (keychains, genesis) = test::fake_genesis(
+            cfg.min_stake_amount,
+            10 * cfg.min_stake_amount,
+            cfg.max_slot_count,
+            1,
+            block_timestamp0,
+            None,
+        );
+        let block_hash0 = Hash::digest(&genesis);
+        let chain_dir = TempDir::new("test").unwrap();
+        timestamp += Duration::from_millis(1);
+        let mut chain = Blockchain::new(
+            cfg.clone(),
+            chain_dir.path(),
+            ConsistencyCheck::None,
+            genesis.clone(),
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+        assert_eq!(1, chain.epoch());
+        assert_eq!(0, chain.offset());
+        assert_eq!(1, chain.blocks().count());
+        assert_eq!(0, chain.view_change());
+        assert_eq!(block_hash0, chain.last_block_hash());
+        assert_eq!(block_hash0, chain.last_macro_block_hash());
+        assert_eq!(block_timestamp0, chain.last_block_timestamp());
+        assert_eq!(block_timestamp0, chain.last_macro_block_timestamp());
+        let epoch0 = chain.epoch();
+        let offset0 = chain.offset();
+        let count0 = chain.blocks().count();
+        let balance0 = chain.balance().clone();
+        let escrow0 = chain.escrow_info().clone();
+        let awards0 = chain.awards.clone();
+
+        //
+        // Register a micro block #1.
+        //
+        timestamp += Duration::from_millis(1);
+        let block_timestamp1 = timestamp;
+        let (block1, input_hashes1, output_hashes1) =
+            test::create_fake_micro_block(&mut chain, &keychains, block_timestamp1);
+        let block_hash1 = Hash::digest(&block1);
+        timestamp += Duration::from_millis(1);
+        chain
+            .push_micro_block(block1, timestamp)
+            .expect("no I/O errors");
+        assert_eq!(2, chain.blocks().count());
+        assert_eq!(1, chain.offset());
+        assert_eq!(0, chain.view_change());
+        assert_eq!(block_hash1, chain.last_block_hash());
+        assert_eq!(block_timestamp1, chain.last_block_timestamp());
+        assert_eq!(block_hash0, chain.last_macro_block_hash());
+        assert_eq!(block_timestamp0, chain.last_macro_block_timestamp());
+        assert_ne!(&balance0, chain.balance());
+        for input_hash in &input_hashes1 {
+            assert!(!chain.contains_output(input_hash));
+        }
+        for output_hash in &output_hashes1 {
+            assert!(chain.contains_output(output_hash));
+        }
+        let count1 = chain.blocks().count();
+        let offset1 = chain.offset();
+        let block_hash1 = chain.last_block_hash();
+        let balance1 = chain.balance().clone();
+        let escrow1 = chain.escrow_info().clone();
+
+        //
+        // Register a micro block #2.
+        //
+        timestamp += Duration::from_millis(1);
+        let block_timestamp2 = timestamp;
+        let (block2, input_hashes2, output_hashes2) =
+            test::create_fake_micro_block(&mut chain, &keychains, block_timestamp2);
+        let block_hash2 = Hash::digest(&block2);
+        timestamp += Duration::from_millis(1);
+        chain
+            .push_micro_block(block2, timestamp)
+            .expect("no I/O errors");
+        assert_eq!(1, chain.epoch());
+        assert_eq!(2, chain.offset());
+        assert_eq!(0, chain.view_change());
+        assert_eq!(3, chain.blocks().count());
+        assert_eq!(block_hash2, chain.last_block_hash());
+        assert_eq!(block_timestamp2, chain.last_block_timestamp());
+        assert_eq!(block_hash0, chain.last_macro_block_hash());
+        assert_eq!(block_timestamp0, chain.last_macro_block_timestamp());
+        assert_ne!(&balance1, chain.balance());
+        for input_hash in &input_hashes2 {
+            assert!(!chain.contains_output(input_hash));
+        }
+        for output_hash in &output_hashes2 {
+            assert!(chain.contains_output(output_hash));
+        }
+
+        //
+        // Pop micro block #2.
+        //
+        chain.pop_micro_block().expect("no disk errors");
+        assert_eq!(epoch0, chain.epoch());
+        assert_eq!(offset1, chain.offset());
+        assert_eq!(0, chain.view_change());
+        assert_eq!(count1, chain.blocks().count());
+        assert_eq!(block_hash1, chain.last_block_hash());
+        assert_eq!(block_timestamp1, chain.last_block_timestamp());
+        assert_eq!(block_hash0, chain.last_macro_block_hash());
+        assert_eq!(block_timestamp0, chain.last_macro_block_timestamp());
+        assert_eq!(&balance1, chain.balance());
+        assert_eq!(escrow1, chain.escrow_info());
+        for input_hash in &input_hashes2 {
+            assert!(chain.contains_output(input_hash));
+        }
+        for output_hash in &output_hashes2 {
+            assert!(!chain.contains_output(output_hash));
+        }
+
+        //
+        // Try to recover after popping micro block #2.
+        //
+        drop(chain);
+        timestamp += Duration::from_secs(60 * 60 * 24);
+        let mut chain = Blockchain::new(
+            cfg.clone(),
+            chain_dir.path(),
+            ConsistencyCheck::Full,
+            genesis.clone(),
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+        assert_eq!(epoch0, chain.epoch());
+        assert_eq!(offset1, chain.offset());
+        assert_eq!(0, chain.view_change());
+        assert_eq!(count1, chain.blocks().count());
+        assert_eq!(block_hash1, chain.last_block_hash());
+        assert_eq!(block_timestamp1, chain.last_block_timestamp());
+        assert_eq!(block_hash0, chain.last_macro_block_hash());
+        assert_eq!(block_timestamp0, chain.last_macro_block_timestamp());
+        assert_eq!(&balance1, chain.balance());
+        assert_eq!(escrow1, chain.escrow_info());
+        for input_hash in &input_hashes2 {
+            assert!(chain.contains_output(&input_hash));
+        }
+        for output_hash in &output_hashes2 {
+            assert!(!chain.contains_output(&output_hash));
+        }
+
+        //
+        // Pop micro block #1.
+        //
+        chain.pop_micro_block().expect("no disk errors");
+        assert_eq!(epoch0, chain.epoch());
+        assert_eq!(offset0, chain.offset());
+        assert_eq!(0, chain.view_change());
+        assert_eq!(count0, chain.blocks().count());
+        assert_eq!(block_hash0, chain.last_block_hash());
+        assert_eq!(block_timestamp0, chain.last_block_timestamp());
+        assert_eq!(block_hash0, chain.last_macro_block_hash());
+        assert_eq!(block_timestamp0, chain.last_macro_block_timestamp());
+        assert_eq!(&balance0, chain.balance());
+        assert_eq!(escrow0, chain.escrow_info());
+        assert_eq!(awards0, chain.awards.clone());
+        for input_hash in &input_hashes1 {
+            assert!(chain.contains_output(&input_hash));
+        }
+        for output_hash in &output_hashes1 {
+            assert!(!chain.contains_output(&output_hash));
+        }
+
+        //
+        // Try to recover after popping micro block #1.
+        //
+        drop(chain);
+        timestamp += Duration::from_secs(60 * 60 * 24);
+        let chain = Blockchain::new(
+            cfg.clone(),
+            chain_dir.path(),
+            ConsistencyCheck::None,
+            genesis.clone(),
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+        assert_eq!(epoch0, chain.epoch());
+        assert_eq!(offset0, chain.offset());
+        assert_eq!(0, chain.view_change());
+        assert_eq!(count0, chain.blocks().count());
+        assert_eq!(block_hash0, chain.last_block_hash());
+        assert_eq!(block_timestamp0, chain.last_block_timestamp());
+        assert_eq!(block_hash0, chain.last_macro_block_hash());
+        assert_eq!(block_timestamp0, chain.last_macro_block_timestamp());
+        assert_eq!(&balance0, chain.balance());
+        assert_eq!(escrow0, chain.escrow_info());
+        assert_eq!(awards0, chain.awards.clone());
+        for input_hash in &input_hashes1 {
+            assert!(chain.contains_output(&input_hash));
+        }
+        for output_hash in &output_hashes1 {
+            assert!(!chain.contains_output(&output_hash));
+        }
+        drop(chain);
+    }
+
+    #[test]
+    fn undo_log() {
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let mut timestamp = Timestamp::now();
+        let cfg: ChainConfig = Default::default();
+        let (keychains, genesis) = test::fake_genesis(
+            cfg.min_stake_amount,
+            10 * cfg.min_stake_amount,
+            cfg.max_slot_count,
+            1,
+            timestamp,
+            None,
+        );
+        let chain_dir = TempDir::new("test").unwrap();
+        timestamp += Duration::from_millis(1);
+        let mut chain = Blockchain::new(
+            cfg.clone(),
+            chain_dir.path(),
+            ConsistencyCheck::None,
+            genesis.clone(),
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+
+        let mut block_hashes = Vec::new();
+        for _offset in 0..3 {
+            timestamp += Duration::from_millis(1);
+            let (block, _input_hashes, _output_hashes) =
+                test::create_fake_micro_block(&chain, &keychains, timestamp);
+            block_hashes.push(Hash::digest(&block));
+            chain
+                .push_micro_block(block, timestamp)
+                .expect("no I/O errors");
+        }
+        let is_undo_log_empty = |chain: &Blockchain| {
+            let cf_undo_log = chain.database.cf_handle(UNDO_LOG).unwrap();
+            chain
+                .database
+                .iterator_cf(cf_undo_log, rocksdb::IteratorMode::Start)
+                .unwrap()
+                .next()
+                .is_none()
+        };
+        assert!(is_undo_log_empty(&chain));
+
+        //
+        // Interrupted rollback.
+        //
+        chain.begin_rollback(1).expect("no I/O errors");
+        assert!(!is_undo_log_empty(&chain));
+        chain.pop_micro_block().expect("no disk errors");
+        assert_eq!(2, chain.offset());
+        drop(chain);
+        let chain = Blockchain::new(
+            cfg.clone(),
+            chain_dir.path(),
+            ConsistencyCheck::None,
+            genesis.clone(),
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+        assert_eq!(1, chain.offset());
+        assert_eq!(2, chain.blocks().count());
+        assert_eq!(block_hashes[0], chain.last_block_hash());
+        assert!(is_undo_log_empty(&chain));
+
+        // Emulate a crash after writing of a micro block, but before its registration.
+        let write_pending_block = |chain: &Blockchain, block: MicroBlock| {
+            let lsn = LSN(block.header.epoch, block.header.offset);
+            let entry = UndoLogEntry::Push(Hash::digest(&block));
+            let mut batch = chain.write_block(lsn, Block::MicroBlock(block)).unwrap();
+            let cf_undo_log = chain.database.cf_handle(UNDO_LOG).unwrap();
+            batch
+                .put_cf(
+                    cf_undo_log,
+                    &Blockchain::block_key(lsn),
+                    &entry.into_buffer().unwrap(),
+                )
+                .unwrap();
+            chain.database.write(batch).unwrap();
+        };
+
+        //
+        // Partially applied valid micro block is replayed.
+        //
+        timestamp += Duration::from_millis(1);
+        let (block, input_hashes, output_hashes) =
+            test::create_fake_micro_block(&chain, &keychains, timestamp);
+        let block_hash = Hash::digest(&block);
+        write_pending_block(&chain, block);
+        drop(chain);
+        let chain = Blockchain::new(
+            cfg.clone(),
+            chain_dir.path(),
+            ConsistencyCheck::None,
+            genesis.clone(),
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+        assert_eq!(2, chain.offset());
+        assert_eq!(3, chain.blocks().count());
+        assert_eq!(block_hash, chain.last_block_hash());
+        for input_hash in &input_hashes {
+            assert!(!chain.contains_output(input_hash));
+        }
+        for output_hash in &output_hashes {
+            assert!(chain.contains_output(output_hash));
+        }
+        assert!(is_undo_log_empty(&chain));
+
+        //
+        // Partially applied invalid micro block is reverted.
+        //
+        timestamp += Duration::from_millis(1);
+        let (mut block, _input_hashes, output_hashes) =
+            test::create_fake_micro_block(&chain, &keychains, timestamp);
+        block.header.timestamp += Duration::from_millis(1);
+        write_pending_block(&chain, block);
+        drop(chain);
+        let chain = Blockchain::new(
+            cfg.clone(),
+            chain_dir.path(),
+            ConsistencyCheck::None,
+            genesis.clone(),
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+        assert_eq!(2, chain.offset());
+        assert_eq!(3, chain.blocks().count());
+        assert_eq!(block_hash, chain.last_block_hash());
+        for output_hash in &output_hashes {
+            assert!(!chain.contains_output(output_hash));
+        }
+        assert!(is_undo_log_empty(&chain));
+    }
+
+    #[test]
+    fn explorer_indexes() {
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let mut timestamp = Timestamp::now();
+        let mut cfg: ChainConfig = Default::default();
+        cfg.stake_epochs = 1;
+        let (keychains, genesis) = test::fake_genesis(
+            cfg.min_stake_amount,
+            10 * cfg.min_stake_amount,
+            cfg.max_slot_count,
+            1,
+            timestamp,
+            None,
+        );
+        let account_pkey = keychains[0].account_pkey;
+        let chain_dir = TempDir::new("test").unwrap();
+        timestamp += Duration::from_millis(1);
+        let mut chain = Blockchain::new(
+            cfg.clone(),
+            chain_dir.path(),
+            ConsistencyCheck::None,
+            genesis.clone(),
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+        match chain.transaction_key(&Hash::digest("test")) {
+            Err(BlockchainError::IndexesDisabled) => {}
+            e => panic!("Unexpected result: {:?}", e),
+        }
+
+        let check_indexes = |chain: &Blockchain, block: &MicroBlock, is_indexed: bool| {
+            for (tx_id, tx) in block.transactions.iter().enumerate() {
+                let tx_hash = Hash::digest(tx);
+                let tx_key = chain.transaction_key(&tx_hash).unwrap();
+                if !is_indexed {
+                    assert_eq!(tx_key, None);
+                    continue;
+                }
+                let tx_key = tx_key.expect("indexed");
+                assert_eq!(tx_key.epoch, block.header.epoch);
+                assert_eq!(tx_key.offset, block.header.offset);
+                assert_eq!(tx_key.tx_id, tx_id as u32);
+                for input_hash in tx.txins() {
+                    assert_eq!(chain.spent_by(input_hash).unwrap(), Some(tx_hash));
+                }
+                for output in tx.txouts() {
+                    let output_hash = Hash::digest(output);
+                    assert_eq!(chain.created_by(&output_hash).unwrap(), Some(tx_hash));
+                    if let Output::StakeOutput(_) = output {
+                        assert!(chain
+                            .outputs_by_recipient(&account_pkey)
+                            .unwrap()
+                            .contains(&output_hash));
+                    }
+                }
+            }
+        };
+
+        // Build indexes for the existing blockchain.
+        timestamp += Duration::from_millis(1);
+        let (block1, _input_hashes, _output_hashes) =
+            test::create_fake_micro_block(&chain, &keychains, timestamp);
+        chain
+            .push_micro_block(block1.clone(), timestamp)
+            .expect("no I/O errors");
+        drop(chain);
+        cfg.explorer_indexes = true;
+        let mut chain = Blockchain::new(
+            cfg.clone(),
+            chain_dir.path(),
+            ConsistencyCheck::None,
+            genesis.clone(),
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+        check_indexes(&chain, &block1, true);
+        for output in &genesis.outputs {
+            if let Output::StakeOutput(_) = output {
+                assert!(chain
+                    .outputs_by_recipient(&account_pkey)
+                    .unwrap()
+                    .contains(&Hash::digest(output)));
+            }
+        }
+
+        // Push and pop.
+        timestamp += Duration::from_millis(1);
+        let (block2, _input_hashes, _output_hashes) =
+            test::create_fake_micro_block(&chain, &keychains, timestamp);
+        chain
+            .push_micro_block(block2.clone(), timestamp)
+            .expect("no I/O errors");
+        check_indexes(&chain, &block2, true);
+        chain.pop_micro_block().expect("no disk errors");
+        check_indexes(&chain, &block2, false);
+        check_indexes(&chain, &block1, true);
+        let (_skey, pkey) = scc::make_random_keys();
+        assert_eq!(
+            chain.outputs_by_recipient(&pkey).unwrap(),
+            Vec::<Hash>::new()
+        );
+    }
+
+    #[test]
+    fn committed_transactions() {
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+        let mut timestamp = Timestamp::now();
+        let mut cfg: ChainConfig = Default::default();
+        cfg.stake_epochs = 1;
+        cfg.micro_blocks_in_epoch = 2;
+        let (keychains, genesis) = test::fake_genesis(
+            cfg.min_stake_amount,
+            10 * cfg.min_stake_amount,
+            cfg.max_slot_count,
+            1,
+            timestamp,
+            None,
+        );
+        let chain_dir = TempDir::new("test").unwrap();
+        let mut chain = Blockchain::new(
+            cfg.clone(),
+            chain_dir.path(),
+            ConsistencyCheck::Full,
+            genesis.clone(),
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+
+        // Pushes a full epoch and returns hashes of committed transactions.
+        let push_epoch = |chain: &mut Blockchain, timestamp: &mut Timestamp| -> Vec<Hash> {
+            let mut tx_hashes: Vec<Hash> = Vec::new();
+            for _offset in 0..chain.cfg().micro_blocks_in_epoch {
+                *timestamp += Duration::from_secs(1);
+                let (block, _input_hashes, _output_hashes) =
+                    test::create_fake_micro_block(&chain, &keychains, *timestamp);
+                tx_hashes.extend(block.transactions.iter().map(Hash::digest));
+                chain
+                    .push_micro_block(block, *timestamp)
+                    .expect("Invalid block");
+            }
+            *timestamp += Duration::from_secs(1);
+            let (block, _extra_transactions) =
+                test::create_fake_macro_block(&chain, &keychains, *timestamp);
+            while chain.offset() > 0 {
+                chain.pop_committed_micro_block().expect("Should be ok");
+            }
+            chain
+                .push_macro_block(block, *timestamp)
+                .expect("Invalid block");
+            tx_hashes
+        };
+
+        //
+        // Reverted micro blocks are not committed.
+        //
+        timestamp += Duration::from_secs(1);
+        let (block, _input_hashes, _output_hashes) =
+            test::create_fake_micro_block(&chain, &keychains, timestamp);
+        let reverted: Vec<Hash> = block.transactions.iter().map(Hash::digest).collect();
+        assert!(!reverted.is_empty());
+        chain
+            .push_micro_block(block, timestamp)
+            .expect("Invalid block");
+        for tx_hash in &reverted {
+            // Not committed until the end of epoch.
+            assert_eq!(chain.committed_transaction_epoch(tx_hash).unwrap(), None);
+        }
+        chain.pop_micro_block().expect("Should be ok");
+
+        //
+        // Transactions of the epoch are committed by the macro block.
+        //
+        let epoch = chain.epoch();
+        let committed = push_epoch(&mut chain, &mut timestamp);
+        assert!(!committed.is_empty());
+        for tx_hash in &committed {
+            assert_eq!(
+                chain.committed_transaction_epoch(tx_hash).unwrap(),
+                Some(epoch)
+            );
+        }
+        for tx_hash in &reverted {
+            assert_eq!(chain.committed_transaction_epoch(tx_hash).unwrap(), None);
+        }
+
+        //
+        // Recovery.
+        //
+        drop(chain);
+        let mut chain = Blockchain::new(
+            cfg,
+            chain_dir.path(),
+            ConsistencyCheck::Full,
+            genesis,
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+        for tx_hash in &committed {
+            assert_eq!(
+                chain.committed_transaction_epoch(tx_hash).unwrap(),
+                Some(epoch)
+            );
+        }
+        for tx_hash in &reverted {
+            assert_eq!(chain.committed_transaction_epoch(tx_hash).unwrap(), None);
+        }
+
+        //
+        // Pruning.
+        //
+        chain.set_pruning_epochs(2).expect("valid pruning_epochs");
+        let epoch2 = chain.epoch();
+        let committed2 = push_epoch(&mut chain, &mut timestamp);
+        for tx_hash in &committed {
+            assert_eq!(
+                chain.committed_transaction_epoch(tx_hash).unwrap(),
+                Some(epoch)
+            );
+        }
+        push_epoch(&mut chain, &mut timestamp);
+        assert_eq!(chain.snapshot_epoch(), Some(epoch2));
+        for tx_hash in &committed {
+            assert_eq!(chain.committed_transaction_epoch(tx_hash).unwrap(), None);
+        }
+        for tx_hash in &committed2 {
+            assert_eq!(
+                chain.committed_transaction_epoch(tx_hash).unwrap(),
+                Some(epoch2)
+            );
+        }
+    }
+
+    #[test]
+    fn saved_mempool() {
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+        let timestamp = Timestamp::now();
+        let cfg: ChainConfig = Default::default();
+        let (keychains, genesis) = test::fake_genesis(
+            cfg.min_stake_amount,
+            10 * cfg.min_stake_amount,
+            cfg.max_slot_count,
+            1,
+            timestamp,
+            None,
+        );
+        let chain_dir = TempDir::new("test").unwrap();
+        let chain = Blockchain::new(
+            cfg.clone(),
+            chain_dir.path(),
+            ConsistencyCheck::None,
+            genesis.clone(),
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+        assert!(chain.load_mempool().unwrap().is_empty());
+
+        let skey = &keychains[0].account_skey;
+        let pkey = &keychains[0].account_pkey;
+        let mut txs: BTreeMap<Hash, Transaction> = BTreeMap::new();
+        for fee in 1..3 {
+            let (tx, _inputs, _outputs) =
+                PaymentTransaction::new_test(skey, pkey, 10, 1, 10 - fee, 1, fee).unwrap();
+            let tx: Transaction = tx.into();
+            txs.insert(Hash::digest(&tx), tx);
+        }
+        chain.save_mempool(txs.iter()).unwrap();
+        drop(chain);
+
+        // Saved transactions survive a restart.
+        let chain = Blockchain::new(
+            cfg,
+            chain_dir.path(),
+            ConsistencyCheck::None,
+            genesis,
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+        let saved = chain.load_mempool().unwrap();
+        assert_eq!(saved.len(), txs.len());
+        for tx in &saved {
+            assert!(txs.contains_key(&Hash::digest(tx)));
+        }
+
+        // Corrupted entries are skipped and removed.
+        let cf_mempool = chain.database.cf_handle(MEMPOOL).unwrap();
+        chain
+            .database
+            .put_cf(cf_mempool, b"corrupted", &[1u8, 2, 3])
+            .unwrap();
+        assert_eq!(chain.load_mempool().unwrap().len(), txs.len());
+        assert!(chain
+            .database
+            .get_cf(cf_mempool, b"corrupted")
+            .unwrap()
+            .is_none());
+
+        // Saving replaces the previous state.
+        chain.save_mempool(txs.iter().take(1)).unwrap();
+        assert_eq!(chain.load_mempool().unwrap().len(), 1);
+        chain.save_mempool(std::iter::empty()).unwrap();
+        assert!(chain.load_mempool().unwrap().is_empty());
+    }
+
+    #[test]
+    fn block_iter_limit() {
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+        let mut timestamp = Timestamp::now();
+        let mut cfg: ChainConfig = Default::default();
+        cfg.micro_blocks_in_epoch = 100500;
+        let stake = cfg.min_stake_amount;
+        let (keychains, blocks) = test::fake_genesis(
+            stake,
+            10 * cfg.min_stake_amount,
+            cfg.max_slot_count,
+            1,
+            timestamp,
+            None,
+        );
+        let chain_dir = TempDir::new("test").unwrap();
+        let mut blockchain = Blockchain::new(
+            cfg,
+            chain_dir.path(),
+            ConsistencyCheck::None,
+            blocks,
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+        let epoch = blockchain.epoch();
+        let starting_offset = blockchain.offset();
+        // len of genesis
+        assert!(blockchain.epoch() > 0);
+        for _offset in 2..12 {
+            timestamp += Duration::from_millis(1);
+            let block = test::create_micro_block_with_coinbase(&blockchain, &keychains, timestamp);
+            blockchain
+                .push_micro_block(block, timestamp)
+                .expect("Invalid block");
+        }
+
+        assert_eq!(
+            blockchain
+                .blocks_starting(epoch, starting_offset)
+                .take(1)
+                .count(),
+            1
+        );
+
+        assert_eq!(
+            blockchain
+                .blocks_starting(epoch, starting_offset)
+                .take(4)
+                .count(),
+            4
+        );
+        // limit
+        assert_eq!(
+            blockchain
+                .blocks_starting(epoch, starting_offset)
+                .take(20)
+                .count(),
+            10
+        );
+        // empty
+        assert_eq!(
+            blockchain
+                .blocks_starting(epoch, blockchain.offset())
+                .take(1)
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn historic_output_by_hash() {
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let mut cfg: ChainConfig = Default::default();
+        cfg.stake_epochs = 1000;
+        cfg.unbonding_epochs = 1000;
+        cfg.micro_blocks_in_epoch = 1;
+        let mut timestamp = Timestamp::now();
+        let (keychains, genesis) = test::fake_genesis(
+            cfg.min_stake_amount,
+            cfg.min_stake_amount + 100,
+            cfg.max_slot_count,
+            1,
+            timestamp,
+            None,
+        );
+        let genesis_timestamp = timestamp;
+        let genesis_hash = Hash::digest(&genesis);
+        let chain_dir = TempDir::new("test").unwrap();
+        let mut chain = Blockchain::new(
+            cfg.clone(),
+            chain_dir.path(),
+            ConsistencyCheck::None,
+            genesis.clone(),
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+
+        //
+        // Spent by Micro Block.
+        //
+        timestamp += Duration::from_secs(1);
+        let (block, _input_hashes, _output_hashes) =
+            test::create_fake_micro_block(&mut chain, &keychains, timestamp);
+        let (inputs, outputs, _txs) = chain
+            .push_micro_block(block, timestamp)
+            .expect("no I/O errors");
+
+        let historic_output_hash = inputs.iter().next().unwrap();
+        let historic_output_proof = chain
+            .historic_output_by_hash_with_proof(historic_output_hash)
+            .expect("no I/O errors")
+            .expect("exists");
+        assert_eq!(
+            Hash::digest(&historic_output_proof.output),
+            *historic_output_hash
+        );
+        assert_eq!(historic_output_proof.epoch, 0);
+        assert_eq!(&historic_output_proof.block_hash, &genesis_hash);
+        assert_eq!(historic_output_proof.is_final, true);
+        assert_eq!(historic_output_proof.timestamp, genesis_timestamp);
+
+        //
+        // Created by Micro Block.
+        //
+        let unspent_output = outputs.values().next().unwrap();
+        let unspent_output_proof = chain
+            .historic_output_by_hash_with_proof(&Hash::digest(unspent_output))
+            .expect("no I/O errors")
+            .expect("exists");
+        assert_eq!(&unspent_output_proof.output, unspent_output);
+        assert_eq!(unspent_output_proof.epoch, 1);
+        assert_eq!(unspent_output_proof.block_hash, chain.last_block_hash());
+        assert_eq!(unspent_output_proof.is_final, false);
+        assert_eq!(unspent_output_proof.timestamp, chain.last_block_timestamp());
+
+        //
+        // Spent by Macro Block.
+        //
+        timestamp += Duration::from_secs(1);
+        let (block, _extra_transactions) =
+            test::create_fake_macro_block(&chain, &keychains, timestamp);
+        while chain.offset() > 0 {
+            chain.pop_micro_block().expect("Should be ok");
+        }
+        let (inputs, _outputs) = chain
+            .push_macro_block(block, timestamp)
+            .expect("Invalid block");
+        let historic_output_hash = inputs.iter().next().unwrap();
+        let historic_output_proof = chain
+            .historic_output_by_hash_with_proof(historic_output_hash)
+            .expect("no I/O errors")
+            .expect("exists");
+        assert_eq!(
+            Hash::digest(&historic_output_proof.output),
+            *historic_output_hash
+        );
+        assert_eq!(historic_output_proof.epoch, 0);
+        assert_eq!(historic_output_proof.block_hash, genesis_hash);
+        assert_eq!(historic_output_proof.is_final, true);
+        assert_eq!(historic_output_proof.timestamp, genesis_timestamp);
+
+        //
+        // Created by Macro Block.
+        //
+        let unspent_output = outputs.values().next().unwrap();
+        let unspent_output_proof = chain
+            .historic_output_by_hash_with_proof(&Hash::digest(&unspent_output))
+            .expect("no I/O errors")
+            .expect("exists");
+        assert_eq!(&unspent_output_proof.output, unspent_output);
+        assert_eq!(unspent_output_proof.epoch, 1);
+        assert_eq!(unspent_output_proof.block_hash, chain.last_block_hash());
+        assert_eq!(unspent_output_proof.is_final, true);
+        assert_eq!(
+            unspent_output_proof.timestamp,
+            chain.last_macro_block_timestamp()
+        );
+    }
+
+    #[test]
+    fn snapshot() {
+        const NUM_NODES: usize = 4;
+        const EPOCHS: u64 = 4;
+
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let mut cfg: ChainConfig = Default::default();
+        cfg.stake_epochs = 1;
+        cfg.micro_blocks_in_epoch = 2;
+        let mut timestamp = Timestamp::now();
+        let (keychains, genesis) = test::fake_genesis(
+            cfg.min_stake_amount,
+            (NUM_NODES as i64) * cfg.min_stake_amount + 100,
+            cfg.max_slot_count,
+            NUM_NODES,
+            timestamp,
+            None,
+        );
+        let chain_dir = TempDir::new("test").unwrap();
+        let mut chain = Blockchain::new(
+            cfg.clone(),
+            chain_dir.path(),
+            ConsistencyCheck::Full,
+            genesis.clone(),
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+
+        // Nothing to share yet.
+        assert!(chain.create_snapshot().is_err());
+
+        let push_epoch = |chain: &mut Blockchain, timestamp: &mut Timestamp| {
+            for _offset in 0..chain.cfg().micro_blocks_in_epoch {
+                *timestamp += Duration::from_secs(1);
+                let (block, _input_hashes, _output_hashes) =
+                    test::create_fake_micro_block(chain, &keychains, *timestamp);
+                chain
+                    .push_micro_block(block, *timestamp)
+                    .expect("Invalid block");
+            }
+            *timestamp += Duration::from_secs(1);
+            let (block, _extra_transactions) =
+                test::create_fake_macro_block(chain, &keychains, *timestamp);
+            while chain.offset() > 0 {
+                chain.pop_micro_block().expect("Should be ok");
+            }
+            chain
+                .push_macro_block(block, *timestamp)
+                .expect("Invalid block");
+        };
+
+        for _epoch in 0..EPOCHS {
+            push_epoch(&mut chain, &mut timestamp);
+        }
+        // Micro blocks must not affect the snapshot.
+        timestamp += Duration::from_secs(1);
+        let (block, _input_hashes, _output_hashes) =
+            test::create_fake_micro_block(&chain, &keychains, timestamp);
+        chain
+            .push_micro_block(block, timestamp)
+            .expect("Invalid block");
+        chain.pop_micro_block().expect("Should be ok");
+
+        let snapshot = chain.create_snapshot().expect("snapshot is available");
+        assert_eq!(snapshot.epoch(), EPOCHS);
+        assert_eq!(snapshot.block_hash(), chain.last_macro_block_hash());
+        let snapshot = StateSnapshot::from_buffer(&snapshot.into_buffer().unwrap()).unwrap();
+
+        //
+        // Tampered snapshot.
+        //
+        let chain2_dir = TempDir::new("test").unwrap();
+        let mut chain2 = Blockchain::new(
+            cfg.clone(),
+            chain2_dir.path(),
+            ConsistencyCheck::Full,
+            genesis.clone(),
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+        let mut invalid = snapshot.clone();
+        invalid.outputs.pop().unwrap();
+        match chain2.apply_snapshot(invalid) {
+            Err(BlockchainError::SnapshotError(SnapshotError::InvalidStateHash(..))) => {}
+            e => panic!("Unexpected result: {:?}", e),
+        }
+        let mut invalid = snapshot.clone();
+        invalid.certificates.remove(0);
+        match chain2.apply_snapshot(invalid) {
+            Err(BlockchainError::SnapshotError(SnapshotError::OutOfOrderMacroBlock(..))) => {}
+            e => panic!("Unexpected result: {:?}", e),
+        }
+        assert_eq!(chain2.epoch(), 1);
+        assert_eq!(chain2.snapshot_epoch(), None);
+
+        //
+        // Valid snapshot.
+        //
+        chain2.apply_snapshot(snapshot).expect("valid snapshot");
+        assert_eq!(chain2.snapshot_epoch(), Some(EPOCHS));
+        assert_eq!(chain2.epoch(), chain.epoch());
+        assert_eq!(chain2.offset(), chain.offset());
+        assert_eq!(chain2.last_block_hash(), chain.last_block_hash());
+        assert_eq!(chain2.balance(), chain.balance());
+        assert_eq!(chain2.election_result(), chain.election_result());
+        assert_eq!(chain2.service_awards(), chain.service_awards());
+        assert_eq!(chain2.state_hash(), chain.state_hash());
+        let utxo_range_hash = chain
+            .macro_block(EPOCHS)
+            .unwrap()
+            .header
+            .utxo_range_hash
+            .expect("utxo_range_hash");
+        assert_eq!(chain.utxo_range_hash(), utxo_range_hash);
+        assert_eq!(chain2.utxo_range_hash(), utxo_range_hash);
+        for output_hash in chain.unspent() {
+            let output = chain.output_by_hash(output_hash).unwrap().unwrap();
+            let output2 = chain2.output_by_hash(output_hash).unwrap().unwrap();
+            assert_eq!(Hash::digest(&output), Hash::digest(&output2));
+            let proof = chain2
+                .output_by_hash_with_proof(output_hash)
+                .unwrap()
+                .unwrap()
+                .utxo_proof
+                .expect("utxo proof");
+            proof
+                .validate(output_hash, &utxo_range_hash)
+                .expect("valid utxo proof");
+        }
+        // Nodes initialized from a snapshot can share it further.
+        let snapshot2 = chain2.create_snapshot().expect("snapshot is available");
+        assert_eq!(snapshot2.epoch(), EPOCHS);
+        assert_eq!(
+            snapshot2.into_buffer().unwrap(),
+            chain.create_snapshot().unwrap().into_buffer().unwrap()
+        );
+
+        //
+        // Continue from the snapshot.
+        //
+        for _offset in 0..chain.cfg().micro_blocks_in_epoch {
+            timestamp += Duration::from_secs(1);
+            let (block, _input_hashes, _output_hashes) =
+                test::create_fake_micro_block(&chain, &keychains, timestamp);
+            chain2
+                .push_micro_block(block.clone(), timestamp)
+                .expect("Invalid block");
+            chain
+                .push_micro_block(block, timestamp)
+                .expect("Invalid block");
+        }
+        timestamp += Duration::from_secs(1);
+        let (block, _extra_transactions) =
+            test::create_fake_macro_block(&chain, &keychains, timestamp);
+        while chain.offset() > 0 {
+            chain.pop_micro_block().expect("Should be ok");
+            chain2.pop_micro_block().expect("Should be ok");
+        }
+        chain2
+            .push_macro_block(block.clone(), timestamp)
+            .expect("Invalid block");
+        chain
+            .push_macro_block(block, timestamp)
+            .expect("Invalid block");
+        assert_eq!(chain2.last_block_hash(), chain.last_block_hash());
+        assert_eq!(chain2.state_hash(), chain.state_hash());
+
+        //
+        // Recovery.
+        //
+        let epoch = chain2.epoch();
+        let block_hash = chain2.last_block_hash();
+        let balance = chain2.balance().clone();
+        drop(chain2);
+        let chain2 = Blockchain::new(
+            cfg,
+            chain2_dir.path(),
+            ConsistencyCheck::Full,
+            genesis,
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+        assert_eq!(chain2.snapshot_epoch(), Some(EPOCHS));
+        assert_eq!(epoch, chain2.epoch());
+        assert_eq!(block_hash, chain2.last_block_hash());
+        assert_eq!(&balance, chain2.balance());
+        assert_eq!(chain2.state_hash(), chain.state_hash());
+    }
+
+    #[test]
+    fn pruning() {
+        const NUM_NODES: usize = 4;
+        const EPOCHS: u64 = 5;
+        const PRUNING_EPOCHS: u64 = 2;
 
-        // Recover inputs that was removed, and remove outputs that was processed in this block.
-        let mut recovered: HashMap<Hash, Output> = HashMap::new();
-        let mut pruned: Vec<Hash> = Vec::new();
-        let mut removed = Vec::new();
-        for tx in block.clone().transactions {
-            for input_hash in tx.txins() {
-                let input = self.output_by_hash(input_hash)?.expect("exists");
-                recovered.insert(input_hash.clone(), input);
-                debug!(
-                    "Restored UXTO: epoch={}, block={}, utxo={}",
-                    self.epoch, &block_hash, &input_hash
-                );
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let mut cfg: ChainConfig = Default::default();
+        cfg.stake_epochs = 1;
+        cfg.micro_blocks_in_epoch = 2;
+        let mut timestamp = Timestamp::now();
+        let (keychains, genesis) = test::fake_genesis(
+            cfg.min_stake_amount,
+            (NUM_NODES as i64) * cfg.min_stake_amount + 100,
+            cfg.max_slot_count,
+            NUM_NODES,
+            timestamp,
+            None,
+        );
+        let genesis_hash = Hash::digest(&genesis);
+        let chain_dir = TempDir::new("test").unwrap();
+        let mut chain = Blockchain::new(
+            cfg.clone(),
+            chain_dir.path(),
+            ConsistencyCheck::Full,
+            genesis.clone(),
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+        let chain2_dir = TempDir::new("test").unwrap();
+        let mut chain2 = Blockchain::new(
+            cfg.clone(),
+            chain2_dir.path(),
+            ConsistencyCheck::Full,
+            genesis.clone(),
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+        match chain2.set_pruning_epochs(cfg.stake_epochs) {
+            Err(BlockchainError::InvalidPruningEpochs(..)) => {}
+            e => panic!("Unexpected result: {:?}", e),
+        }
+        chain2
+            .set_pruning_epochs(PRUNING_EPOCHS)
+            .expect("valid pruning_epochs");
+
+        for epoch in 1..=EPOCHS {
+            for _offset in 0..chain.cfg().micro_blocks_in_epoch {
+                timestamp += Duration::from_secs(1);
+                let (block, _input_hashes, _output_hashes) =
+                    test::create_fake_micro_block(&chain, &keychains, timestamp);
+                chain2
+                    .push_micro_block(block.clone(), timestamp)
+                    .expect("Invalid block");
+                chain
+                    .push_micro_block(block, timestamp)
+                    .expect("Invalid block");
             }
-            for output in tx.txouts() {
-                let output_hash = Hash::digest(output);
-                pruned.push(output_hash);
-                debug!(
-                    "Reverted UTXO: epoch={}, block={}, utxo={}",
-                    self.epoch, &block_hash, &output_hash
-                );
+            timestamp += Duration::from_secs(1);
+            let (block, _extra_transactions) =
+                test::create_fake_macro_block(&chain, &keychains, timestamp);
+            while chain.offset() > 0 {
+                chain.pop_micro_block().expect("Should be ok");
+                chain2.pop_micro_block().expect("Should be ok");
             }
-            match tx {
-                Transaction::PaymentTransaction(_) | Transaction::RestakeTransaction(_) => {
-                    removed.push(tx)
-                }
-                _ => continue,
+            chain2
+                .push_macro_block(block.clone(), timestamp)
+                .expect("Invalid block");
+            chain
+                .push_macro_block(block, timestamp)
+                .expect("Invalid block");
+            if epoch < PRUNING_EPOCHS {
+                assert_eq!(chain2.snapshot_epoch(), None);
+            } else {
+                assert_eq!(chain2.snapshot_epoch(), Some(epoch + 1 - PRUNING_EPOCHS));
             }
         }
 
         //
-        // Update metrics.
+        // Only the last PRUNING_EPOCHS macro blocks are kept.
         //
-        metrics::OFFSET.set(self.offset as i64);
-        metrics::UTXO_LEN.set(self.output_by_hash.len() as i64);
-        metrics::EMISSION.set(self.balance().block_reward);
-
-        info!(
-            "Reverted a micro block: epoch={}, offset={}, block={}, inputs={:?}, outputs={:?}",
-            self.epoch,
-            offset,
-            &block_hash,
-            recovered
-                .keys()
-                .map(ToString::to_string)
-                .collect::<Vec<String>>(),
-            pruned
-                .iter()
-                .map(ToString::to_string)
-                .collect::<Vec<String>>(),
-        );
-
-        Ok((pruned, recovered, removed, block))
-    }
+        assert_eq!(chain.snapshot_epoch(), None);
+        assert_eq!(chain.blocks().count() as u64, EPOCHS + 1);
+        assert_eq!(chain2.blocks().count() as u64, PRUNING_EPOCHS + 1);
+        assert!(chain.contains_block(&genesis_hash));
+        assert!(chain2.contains_block(&genesis_hash));
+        assert!(chain2.contains_block(&chain.last_block_hash()));
 
-    fn write_meta<V>(
-        batch: &mut WriteBatch,
-        meta_cf: &ColumnFamily,
-        key: &'static str,
-        value: &V,
-    ) -> Result<(), BlockchainError>
-    where
-        V: ProtoConvert + std::fmt::Debug,
-    {
-        let value = value.into_buffer()?;
-        batch.put_cf(meta_cf, key.as_bytes(), &value)?;
-        Ok(())
-    }
+        //
+        // Snapshots are still available.
+        //
+        let snapshot = chain2.create_snapshot().expect("snapshot is available");
+        assert_eq!(snapshot.epoch(), EPOCHS);
+        let chain3_dir = TempDir::new("test").unwrap();
+        let mut chain3 = Blockchain::new(
+            cfg.clone(),
+            chain3_dir.path(),
+            ConsistencyCheck::Full,
+            genesis.clone(),
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+        chain3.apply_snapshot(snapshot).expect("valid snapshot");
+        assert_eq!(chain3.last_block_hash(), chain.last_block_hash());
+        assert_eq!(chain3.state_hash(), chain.state_hash());
 
-    /// Undolog is actualy a patchset, so just apply it to the block.
-    fn write_log<K, V>(
-        batch: &mut WriteBatch,
-        cf: &ColumnFamily,
-        diff: BTreeMap<K, Option<V>>,
-    ) -> Result<(), BlockchainError>
-    where
-        K: ProtoConvert + std::fmt::Debug,
-        V: ProtoConvert + std::fmt::Debug,
-    {
-        trace!("Persist log");
-        for (key, value) in diff {
-            match value {
-                Some(value) => {
-                    trace!("New insert {:?}={:?}", key, value);
-                    let key = key.into_buffer()?;
-                    let value = value.into_buffer()?;
-                    batch.put_cf(cf, &key, &value)?
-                }
-                None => {
-                    trace!("Remove {:?}", key);
-                    let key = key.into_buffer()?;
-                    batch.delete_cf(cf, &key)?
-                }
+        //
+        // The state is the same.
+        //
+        assert_eq!(chain2.last_block_hash(), chain.last_block_hash());
+        assert_eq!(chain2.balance(), chain.balance());
+        assert_eq!(chain2.state_hash(), chain.state_hash());
+        assert_eq!(chain2.utxo_range_hash(), chain.utxo_range_hash());
+        let utxo_range_hash = chain.utxo_range_hash();
+        let first_block = chain.macro_block(EPOCHS + 1 - PRUNING_EPOCHS).unwrap();
+        let first_block_hash = Hash::digest(first_block.as_ref());
+        for output_hash in chain.unspent() {
+            let output = chain.output_by_hash(output_hash).unwrap().unwrap();
+            let recovery = chain2
+                .output_by_hash_with_proof(output_hash)
+                .unwrap()
+                .expect("output is available");
+            assert_eq!(Hash::digest(&output), Hash::digest(&recovery.output));
+            assert!(recovery.is_final);
+            assert!(recovery.epoch > EPOCHS - PRUNING_EPOCHS);
+            if recovery.epoch == EPOCHS + 1 - PRUNING_EPOCHS {
+                assert_eq!(recovery.block_hash, first_block_hash);
             }
+            recovery
+                .utxo_proof
+                .expect("utxo proof")
+                .validate(output_hash, &utxo_range_hash)
+                .expect("valid utxo proof");
         }
-        Ok(())
-    }
 
-    /// Insert block into cache.
-    fn cache_push_block(&mut self, block: Block) {
-        trace!("Cache block = {}", block.unwrap_macro_ref().header.epoch);
-        if self.cache.len() == self.cfg.stake_epochs as usize + 1 {
-            trace!("Removed block");
-            assert!(self.cache.pop_front().is_some())
+        //
+        // Recovery.
+        //
+        let epoch = chain2.epoch();
+        let block_hash = chain2.last_block_hash();
+        let balance = chain2.balance().clone();
+        drop(chain2);
+        let chain2 = Blockchain::new(
+            cfg.clone(),
+            chain2_dir.path(),
+            ConsistencyCheck::Full,
+            genesis,
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+        assert_eq!(chain2.snapshot_epoch(), Some(EPOCHS + 1 - PRUNING_EPOCHS));
+        assert_eq!(
+            Hash::digest(chain2.macro_block(0).unwrap().as_ref()),
+            genesis_hash
+        );
+        assert_eq!(epoch, chain2.epoch());
+        assert_eq!(block_hash, chain2.last_block_hash());
+        assert_eq!(&balance, chain2.balance());
+        assert_eq!(chain2.state_hash(), chain.state_hash());
+        for output_hash in chain.unspent() {
+            assert!(chain2.output_by_hash(output_hash).unwrap().is_some());
         }
-        self.cache.push_back(block)
-    }
 
-    /// Try get block from cache.
-    fn get_block_cached(&self, epoch: u64) -> Option<&Block> {
-        trace!("get block = {}", epoch);
-        if epoch > self.epoch {
-            return None;
-        }
-        let lower_epoch = self.epoch.saturating_sub(self.cfg.stake_epochs + 1);
-        if epoch < lower_epoch {
-            return None;
-        } else {
-            let idx = epoch - lower_epoch;
-            self.cache.get(idx as usize)
+        //
+        // Incompatible genesis.
+        //
+        drop(chain2);
+        let (_keychains, genesis2) = test::fake_genesis(
+            cfg.min_stake_amount,
+            (NUM_NODES as i64) * cfg.min_stake_amount + 100,
+            cfg.max_slot_count,
+            NUM_NODES,
+            timestamp,
+            None,
+        );
+        match Blockchain::new(
+            cfg,
+            chain2_dir.path(),
+            ConsistencyCheck::Full,
+            genesis2,
+            timestamp,
+        ) {
+            Err(BlockchainError::IncompatibleGenesis(..)) => {}
+            Err(e) => panic!("Unexpected error: {:?}", e),
+            Ok(_) => panic!("Unexpected success"),
         }
     }
-}
-
-#[cfg(test)]
-pub mod tests {
-    use super::*;
-
-    use crate::test;
-    use crate::timestamp::Timestamp;
-    use rand::Rng;
-    use simple_logger;
-    use std::collections::BTreeMap;
-    use std::time::Duration;
-    use tempdir::TempDir;
 
     #[test]
-    fn basic() {
+    fn light() {
+        const NUM_NODES: usize = 4;
+        const EPOCHS: u64 = 3;
+
         simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
 
-        let timestamp = Timestamp::now();
-        let cfg: ChainConfig = Default::default();
-        let (keychains, block1) = test::fake_genesis(
+        let mut cfg: ChainConfig = Default::default();
+        cfg.stake_epochs = 1;
+        cfg.micro_blocks_in_epoch = 2;
+        let mut timestamp = Timestamp::now();
+        let (keychains, genesis) = test::fake_genesis(
             cfg.min_stake_amount,
-            10 * cfg.min_stake_amount,
+            (NUM_NODES as i64) * cfg.min_stake_amount + 100,
             cfg.max_slot_count,
-            3,
+            NUM_NODES,
             timestamp,
             None,
         );
         let chain_dir = TempDir::new("test").unwrap();
-        let blockchain = Blockchain::new(
-            cfg,
+        let mut chain = Blockchain::new(
+            cfg.clone(),
             chain_dir.path(),
-            ConsistencyCheck::None,
-            block1.clone(),
+            ConsistencyCheck::Full,
+            genesis.clone(),
             timestamp,
         )
         .expect("Failed to create blockchain");
-        let outputs: Vec<Output> = block1.outputs.clone();
-        let mut unspent: Vec<Hash> = outputs.iter().map(|o| Hash::digest(o)).collect();
-        unspent.sort();
-        let mut unspent2: Vec<Hash> = blockchain.unspent().cloned().collect();
-        unspent2.sort();
-        assert_eq!(unspent, unspent2);
-
-        let validators = blockchain
-            .escrow
-            .get_stakers_majority(blockchain.epoch, blockchain.cfg.min_stake_amount);
-        assert_eq!(validators.len(), keychains.len());
-        let validators_map: BTreeMap<_, _> = validators.iter().cloned().collect();
-        for keychain in &keychains {
-            let stake = validators_map.get(&keychain.network_pkey).expect("exists");
-            assert_eq!(*stake, blockchain.cfg.min_stake_amount);
-        }
-        assert_eq!(blockchain.last_block_hash(), Hash::digest(&block1));
+        let light_chain_dir = TempDir::new("test").unwrap();
+        let mut light_chain =
+            LightBlockchain::new(cfg.clone(), light_chain_dir.path(), genesis.clone())
+                .expect("Failed to create light blockchain");
+        assert_eq!(light_chain.epoch(), chain.epoch());
+        assert_eq!(light_chain.last_block_hash(), chain.last_block_hash());
+        assert_eq!(light_chain.validators(), &chain.validators_at_epoch_start());
 
-        let blocks2: Vec<Block> = blockchain.blocks().collect();
-        assert_eq!(blocks2.len(), 1);
-        assert_eq!(Hash::digest(&blocks2[0]), Hash::digest(&block1));
+        for _epoch in 0..EPOCHS {
+            for _offset in 0..cfg.micro_blocks_in_epoch {
+                timestamp += Duration::from_secs(1);
+                let (block, _input_hashes, _output_hashes) =
+                    test::create_fake_micro_block(&chain, &keychains, timestamp);
+                let light_block = match chain.light_block(Block::MicroBlock(block.clone())) {
+                    Ok(LightBlock::LightMicroBlock(light_block)) => light_block,
+                    _ => panic!("Expected a light micro block"),
+                };
+                chain
+                    .push_micro_block(block, timestamp)
+                    .expect("Invalid block");
+                light_chain
+                    .push_micro_block(light_block)
+                    .expect("Invalid light block");
+                assert_eq!(light_chain.offset(), chain.offset());
+                assert_eq!(light_chain.last_block_hash(), chain.last_block_hash());
+
+                // Outputs of micro blocks.
+                let block_hash = chain.last_block_hash();
+                let proofs = chain.output_proofs(&block_hash, &[0]).unwrap();
+                light_chain
+                    .validate_output_proof(&block_hash, 0, &proofs[0])
+                    .expect("Valid proof");
+                assert!(light_chain
+                    .validate_output_proof(&block_hash, 1, &proofs[0])
+                    .is_err());
+            }
 
-        assert!(blockchain.contains_block(&Hash::digest(&block1)));
-        assert!(!blockchain.contains_block(&Hash::digest("test")));
+            timestamp += Duration::from_secs(1);
+            let (block, _extra_transactions) =
+                test::create_fake_macro_block(&chain, &keychains, timestamp);
+            while chain.offset() > 0 {
+                chain.pop_micro_block().expect("Should be ok");
+            }
+            chain
+                .push_macro_block(block.clone(), timestamp)
+                .expect("Invalid block");
+            let light_block = match chain.light_block(Block::MacroBlock(block)).unwrap() {
+                LightBlock::LightMacroBlock(light_block) => light_block,
+                _ => panic!("Expected a light macro block"),
+            };
 
-        assert_eq!(
-            Hash::digest(
-                blockchain
-                    .block(LSN(0, MACRO_BLOCK_OFFSET))
-                    .unwrap()
-                    .as_ref()
-            ),
-            Hash::digest(&block1)
-        );
+            // Tampered validators.
+            let mut invalid = light_block.clone();
+            invalid.validators.pop().unwrap();
+            match light_chain.push_macro_block(invalid) {
+                Err(BlockchainError::LightError(LightError::InvalidValidators(..))) => {}
+                e => panic!("Expected InvalidValidators, got {:?}", e),
+            }
 
-        assert!(!blockchain.contains_output(&Hash::digest("test")));
-        assert!(blockchain
-            .output_by_hash(&Hash::digest("test"))
-            .expect("no disk errors")
-            .is_none());
-        for output in block1.outputs.iter() {
-            let output_hash = Hash::digest(&output);
-            let output2 = blockchain
-                .output_by_hash(&output_hash)
-                .expect("no disk errors")
-                .expect("exists");
-            assert_eq!(Hash::digest(&output2), output_hash);
-            assert!(blockchain.contains_output(&output_hash));
+            light_chain
+                .push_macro_block(light_block)
+                .expect("Invalid light block");
+            assert_eq!(light_chain.epoch(), chain.epoch());
+            assert_eq!(light_chain.offset(), 0);
+            assert_eq!(light_chain.last_block_hash(), chain.last_block_hash());
+            assert_eq!(light_chain.validators(), &chain.validators_at_epoch_start());
+
+            // Outputs of macro blocks.
+            let block_hash = chain.last_block_hash();
+            let proofs = chain.output_proofs(&block_hash, &[0]).unwrap();
+            light_chain
+                .validate_output_proof(&block_hash, 0, &proofs[0])
+                .expect("Valid proof");
         }
+
+        // Recovery.
+        let epoch = light_chain.epoch();
+        let last_block_hash = light_chain.last_block_hash();
+        drop(light_chain);
+        let light_chain = LightBlockchain::new(cfg.clone(), light_chain_dir.path(), genesis)
+            .expect("Failed to recover light blockchain");
+        assert_eq!(light_chain.epoch(), epoch);
+        assert_eq!(light_chain.last_block_hash(), last_block_hash);
+        assert_eq!(light_chain.validators(), &chain.validators_at_epoch_start());
     }
 
     #[test]
-    fn iterate() {
-        const NUM_NODES: usize = 32;
-        const STAKE_EPOCHS: u64 = 2;
-        const EPOCHS: u64 = 10;
+    fn hard_fork() {
+        const NUM_NODES: usize = 4;
+        const FORK_EPOCH: u64 = 3;
+        const EPOCHS: u64 = 4;
 
         simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
 
-        let mut rng = rand::thread_rng();
         let mut cfg: ChainConfig = Default::default();
-        cfg.stake_epochs = STAKE_EPOCHS;
-        cfg.micro_blocks_in_epoch = 2;
+        cfg.stake_epochs = 1;
+        cfg.micro_blocks_in_epoch = 1;
+        cfg.forks.push(Fork {
+            epoch: FORK_EPOCH,
+            micro_blocks_in_epoch: Some(3),
+            block_reward: Some(cfg.block_reward / 2),
+            ..Default::default()
+        });
         let mut timestamp = Timestamp::now();
         let (keychains, genesis) = test::fake_genesis(
             cfg.min_stake_amount,
@@ -2282,6 +5463,31 @@ pub mod tests {
             timestamp,
             None,
         );
+
+        //
+        // Invalid schedules.
+        //
+        let mut invalid_cfg = cfg.clone();
+        invalid_cfg.forks[0].version = MAX_VERSION + 1;
+        assert!(invalid_cfg.check_forks().is_err());
+        let mut invalid_cfg = cfg.clone();
+        invalid_cfg.forks[0].epoch = 0;
+        assert!(invalid_cfg.check_forks().is_err());
+        let mut invalid_cfg = cfg.clone();
+        invalid_cfg.forks.push(cfg.forks[0].clone());
+        assert!(invalid_cfg.check_forks().is_err());
+        let mut invalid_cfg = cfg.clone();
+        invalid_cfg.unbonding_epochs = invalid_cfg.stake_epochs - 1;
+        assert!(invalid_cfg.check().is_err());
+        let mut invalid_cfg = cfg.clone();
+        invalid_cfg.forks[0].stake_epochs = Some(invalid_cfg.unbonding_epochs + 1);
+        match invalid_cfg.check() {
+            Err(BlockchainError::InvalidUnbondingEpochs(epoch, ..)) => {
+                assert_eq!(epoch, cfg.forks[0].epoch)
+            }
+            e => panic!("{:?}", e),
+        }
+
         let chain_dir = TempDir::new("test").unwrap();
         let mut chain = Blockchain::new(
             cfg.clone(),
@@ -2292,221 +5498,245 @@ pub mod tests {
         )
         .expect("Failed to create blockchain");
 
-        for _epoch in 0..EPOCHS {
-            let epoch = chain.epoch();
-
-            //
-            // Micro Block.
-            //
-            for offset in 0..chain.cfg().micro_blocks_in_epoch {
-                timestamp += Duration::from_secs(rng.gen_range(5, 10));
-                let (block, input_hashes, output_hashes) =
-                    test::create_fake_micro_block(&mut chain, &keychains, timestamp);
-                let hash = Hash::digest(&block);
+        for epoch in 1..=EPOCHS {
+            if epoch < FORK_EPOCH {
+                assert_eq!(chain.cfg().micro_blocks_in_epoch, 1);
+                assert_eq!(chain.cfg().block_reward, cfg.block_reward);
+            } else {
+                assert_eq!(chain.cfg().micro_blocks_in_epoch, 3);
+                assert_eq!(chain.cfg().block_reward, cfg.block_reward / 2);
+            }
+            assert_eq!(chain.version(), VERSION);
+            for _offset in 0..chain.cfg().micro_blocks_in_epoch {
+                timestamp += Duration::from_secs(1);
+                let (block, _input_hashes, _output_hashes) =
+                    test::create_fake_micro_block(&chain, &keychains, timestamp);
                 chain
                     .push_micro_block(block, timestamp)
-                    .expect("no I/O errors");
-                assert_eq!(hash, chain.last_block_hash());
-                assert_eq!(offset + 1, chain.offset());
-                for input_hash in input_hashes {
-                    assert!(!chain.contains_output(&input_hash));
-                }
-                for output_hash in output_hashes {
-                    assert!(chain.contains_output(&output_hash));
-                }
+                    .expect("Invalid block");
             }
-
-            //
-            // Macro block.
-            //
-
-            // Create a macro block.
-            timestamp += Duration::from_secs(5 + rng.gen_range(0, 10));
-            let (block, extra_transactions) =
+            assert!(chain.is_epoch_full());
+            timestamp += Duration::from_secs(1);
+            let (block, _extra_transactions) =
                 test::create_fake_macro_block(&chain, &keychains, timestamp);
-            let hash = Hash::digest(&block);
-
-            // Collect unspent outputs.
-            let mut unspent: Vec<Hash> = chain.unspent().cloned().collect();
-            for tx in &extra_transactions {
-                assert_eq!(tx.txins().len(), 0);
-                for output in tx.txouts() {
-                    let output_hash = Hash::digest(output);
-                    unspent.push(output_hash);
-                }
-            }
-            unspent.sort();
-
-            // Remove all micro blocks.
             while chain.offset() > 0 {
                 chain.pop_micro_block().expect("Should be ok");
             }
-            // Push the macro block.
             chain
                 .push_macro_block(block, timestamp)
                 .expect("Invalid block");
-            let mut unspent2: Vec<Hash> = chain.unspent().cloned().collect();
-            unspent2.sort();
-            assert_eq!(unspent, unspent2);
-            assert_eq!(hash, chain.last_block_hash());
-            assert_eq!(epoch + 1, chain.epoch());
-            assert_eq!(0, chain.offset());
         }
 
         //
-        // Recovery.
+        // A block with a wrong version is rejected.
         //
-        let epoch = chain.epoch();
-        let offset = chain.offset();
-        let block_count = chain.blocks().count();
-        let block_hash = chain.last_block_hash();
-        let balance = chain.balance().clone();
+        timestamp += Duration::from_secs(1);
+        let (mut block, _input_hashes, _output_hashes) =
+            test::create_fake_micro_block(&chain, &keychains, timestamp);
+        block.header.version = VERSION + 1;
+        assert!(chain.validate_micro_block(&block, timestamp, true).is_err());
+
+        //
+        // Recovery keeps the rules of the current epoch.
+        //
+        let last_block_hash = chain.last_block_hash();
         drop(chain);
         let chain = Blockchain::new(
-            cfg,
+            cfg.clone(),
             chain_dir.path(),
             ConsistencyCheck::Full,
-            genesis,
+            genesis.clone(),
             timestamp,
         )
         .expect("Failed to create blockchain");
-        assert_eq!(epoch, chain.epoch());
-        assert_eq!(offset, chain.offset());
-        assert_eq!(block_hash, chain.last_block_hash());
-        assert_eq!(block_count, chain.blocks().count());
-        assert_eq!(&balance, chain.balance());
+        assert_eq!(chain.epoch(), EPOCHS + 1);
+        assert_eq!(chain.last_block_hash(), last_block_hash);
+        assert_eq!(chain.cfg().micro_blocks_in_epoch, 3);
+        assert_eq!(chain.cfg(), &cfg.at_epoch(EPOCHS + 1));
     }
 
     #[test]
-    fn rollback() {
+    fn governance() {
+        const NUM_NODES: usize = 4;
+        const GOVERNANCE_EPOCHS: u64 = 2;
+        const EPOCHS: u64 = 5;
+
         simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
 
-        //
-        // Create genesis block.
-        //
+        let mut cfg: ChainConfig = Default::default();
+        cfg.stake_epochs = 1;
+        cfg.micro_blocks_in_epoch = 1;
+        cfg.governance_epochs = GOVERNANCE_EPOCHS;
+        let change = ParameterChange {
+            block_reward: Some(cfg.block_reward / 2),
+            max_slot_count: Some(cfg.max_slot_count / 2),
+            ..Default::default()
+        };
         let mut timestamp = Timestamp::now();
-        let block_timestamp0 = timestamp;
-        let cfg: ChainConfig = Default::default();
         let (keychains, genesis) = test::fake_genesis(
             cfg.min_stake_amount,
-            10 * cfg.min_stake_amount,
+            (NUM_NODES as i64) * cfg.min_stake_amount + 100,
             cfg.max_slot_count,
-            1,
-            block_timestamp0,
+            NUM_NODES,
+            timestamp,
             None,
         );
-        let block_hash0 = Hash::digest(&genesis);
         let chain_dir = TempDir::new("test").unwrap();
-        timestamp += Duration::from_millis(1);
         let mut chain = Blockchain::new(
             cfg.clone(),
             chain_dir.path(),
-            ConsistencyCheck::None,
+            ConsistencyCheck::Full,
             genesis.clone(),
             timestamp,
         )
         .expect("Failed to create blockchain");
-        assert_eq!(1, chain.epoch());
-        assert_eq!(0, chain.offset());
-        assert_eq!(1, chain.blocks().count());
-        assert_eq!(0, chain.view_change());
-        assert_eq!(block_hash0, chain.last_block_hash());
-        assert_eq!(block_hash0, chain.last_macro_block_hash());
-        assert_eq!(block_timestamp0, chain.last_block_timestamp());
-        assert_eq!(block_timestamp0, chain.last_macro_block_timestamp());
-        let epoch0 = chain.epoch();
-        let offset0 = chain.offset();
-        let count0 = chain.blocks().count();
-        let balance0 = chain.balance().clone();
-        let escrow0 = chain.escrow_info().clone();
-        let awards0 = chain.awards.clone();
 
-        //
-        // Register a micro block #1.
-        //
-        timestamp += Duration::from_millis(1);
-        let block_timestamp1 = timestamp;
-        let (block1, input_hashes1, output_hashes1) =
-            test::create_fake_micro_block(&mut chain, &keychains, block_timestamp1);
-        let block_hash1 = Hash::digest(&block1);
-        timestamp += Duration::from_millis(1);
-        chain
-            .push_micro_block(block1, timestamp)
-            .expect("no I/O errors");
-        assert_eq!(2, chain.blocks().count());
-        assert_eq!(1, chain.offset());
-        assert_eq!(0, chain.view_change());
-        assert_eq!(block_hash1, chain.last_block_hash());
-        assert_eq!(block_timestamp1, chain.last_block_timestamp());
-        assert_eq!(block_hash0, chain.last_macro_block_hash());
-        assert_eq!(block_timestamp0, chain.last_macro_block_timestamp());
-        assert_ne!(&balance0, chain.balance());
-        for input_hash in &input_hashes1 {
-            assert!(!chain.contains_output(input_hash));
-        }
-        for output_hash in &output_hashes1 {
-            assert!(chain.contains_output(output_hash));
+        // Vote at epochs 2 and 3, the change is activated since epoch 4.
+        for epoch in 1..=EPOCHS {
+            if epoch < 4 {
+                assert_eq!(chain.cfg().block_reward, cfg.block_reward);
+            } else {
+                assert_eq!(chain.cfg().block_reward, cfg.block_reward / 2);
+            }
+            // Validators are elected with the parameters of the previous epoch.
+            if epoch < 5 {
+                assert_eq!(chain.total_slots(), cfg.max_slot_count);
+            } else {
+                assert_eq!(chain.total_slots(), cfg.max_slot_count / 2);
+            }
+            for _offset in 0..chain.cfg().micro_blocks_in_epoch {
+                timestamp += Duration::from_secs(1);
+                let (block, _input_hashes, _output_hashes) =
+                    test::create_fake_micro_block(&chain, &keychains, timestamp);
+                chain
+                    .push_micro_block(block, timestamp)
+                    .expect("Invalid block");
+            }
+            timestamp += Duration::from_secs(1);
+            let proposal = if epoch == 2 || epoch == 3 {
+                Some(change.clone())
+            } else {
+                None
+            };
+            let view_change = chain.view_change();
+            let key = chain.select_leader(view_change);
+            let keys = keychains.iter().find(|p| p.network_pkey == key).unwrap();
+            let (mut block, extra_transactions) = chain.create_macro_block(
+                view_change,
+                &keys.account_pkey,
+                &keys.network_skey,
+                keys.network_pkey,
+                timestamp,
+                proposal,
+            );
+            let block_hash = Hash::digest(&block);
+            chain
+                .validate_proposed_macro_block(
+                    view_change,
+                    &block_hash,
+                    &block.header,
+                    &extra_transactions,
+                )
+                .expect("Invalid proposal");
+            // An invalid parameter change is rejected.
+            let mut header = block.header.clone();
+            header.proposal = Some(ParameterChange {
+                min_stake_amount: Some(0),
+                ..Default::default()
+            });
+            let hash = Hash::digest(&header);
+            assert!(chain
+                .validate_proposed_macro_block(view_change, &hash, &header, &extra_transactions)
+                .is_err());
+            test::sign_fake_macro_block(&mut block, &chain, &keychains);
+            while chain.offset() > 0 {
+                chain.pop_micro_block().expect("Should be ok");
+            }
+            chain
+                .push_macro_block(block, timestamp)
+                .expect("Invalid block");
         }
-        let count1 = chain.blocks().count();
-        let offset1 = chain.offset();
-        let block_hash1 = chain.last_block_hash();
-        let balance1 = chain.balance().clone();
-        let escrow1 = chain.escrow_info().clone();
+        assert_eq!(chain.governance().active, change);
+        assert_eq!(chain.governance().pending, None);
 
         //
-        // Register a micro block #2.
+        // Recovery keeps activated parameters.
         //
-        timestamp += Duration::from_millis(1);
-        let block_timestamp2 = timestamp;
-        let (block2, input_hashes2, output_hashes2) =
-            test::create_fake_micro_block(&mut chain, &keychains, block_timestamp2);
-        let block_hash2 = Hash::digest(&block2);
-        timestamp += Duration::from_millis(1);
-        chain
-            .push_micro_block(block2, timestamp)
-            .expect("no I/O errors");
-        assert_eq!(1, chain.epoch());
-        assert_eq!(2, chain.offset());
-        assert_eq!(0, chain.view_change());
-        assert_eq!(3, chain.blocks().count());
-        assert_eq!(block_hash2, chain.last_block_hash());
-        assert_eq!(block_timestamp2, chain.last_block_timestamp());
-        assert_eq!(block_hash0, chain.last_macro_block_hash());
-        assert_eq!(block_timestamp0, chain.last_macro_block_timestamp());
-        assert_ne!(&balance1, chain.balance());
-        for input_hash in &input_hashes2 {
-            assert!(!chain.contains_output(input_hash));
-        }
-        for output_hash in &output_hashes2 {
-            assert!(chain.contains_output(output_hash));
-        }
+        drop(chain);
+        let chain = Blockchain::new(
+            cfg.clone(),
+            chain_dir.path(),
+            ConsistencyCheck::Full,
+            genesis.clone(),
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+        assert_eq!(chain.epoch(), EPOCHS + 1);
+        assert_eq!(chain.governance().active, change);
+        assert_eq!(chain.cfg().block_reward, cfg.block_reward / 2);
+        assert_eq!(chain.cfg().max_slot_count, cfg.max_slot_count / 2);
+        assert_eq!(chain.total_slots(), cfg.max_slot_count / 2);
+    }
 
-        //
-        // Pop micro block #2.
-        //
-        chain.pop_micro_block().expect("no disk errors");
-        assert_eq!(epoch0, chain.epoch());
-        assert_eq!(offset1, chain.offset());
-        assert_eq!(0, chain.view_change());
-        assert_eq!(count1, chain.blocks().count());
-        assert_eq!(block_hash1, chain.last_block_hash());
-        assert_eq!(block_timestamp1, chain.last_block_timestamp());
-        assert_eq!(block_hash0, chain.last_macro_block_hash());
-        assert_eq!(block_timestamp0, chain.last_macro_block_timestamp());
-        assert_eq!(&balance1, chain.balance());
-        assert_eq!(escrow1, chain.escrow_info());
-        for input_hash in &input_hashes2 {
-            assert!(chain.contains_output(input_hash));
+    /// Pushes an epoch, where the `offline` validator doesn't sign the macro block.
+    fn push_epoch_offline(
+        chain: &mut Blockchain,
+        keychains: &[test::KeyChain],
+        offline: &pbc::PublicKey,
+        timestamp: &mut Timestamp,
+    ) {
+        for _offset in 0..chain.cfg().micro_blocks_in_epoch {
+            *timestamp += Duration::from_secs(1);
+            let (block, _input_hashes, _output_hashes) =
+                test::create_fake_micro_block(chain, keychains, *timestamp);
+            chain
+                .push_micro_block(block, *timestamp)
+                .expect("Invalid block");
         }
-        for output_hash in &output_hashes2 {
-            assert!(!chain.contains_output(output_hash));
+        *timestamp += Duration::from_secs(1);
+        let (mut block, _extra_transactions) =
+            test::create_fake_macro_block(chain, keychains, *timestamp);
+        let block_hash = Hash::digest(&block);
+        let mut signatures = BTreeMap::new();
+        for keychain in keychains.iter().filter(|k| &k.network_pkey != offline) {
+            let sig = pbc::sign_hash(&block_hash, &keychain.network_skey);
+            signatures.insert(keychain.network_pkey, sig);
+        }
+        let (multisig, multisigmap) =
+            create_multi_signature(&chain.validators_at_epoch_start(), &signatures);
+        block.multisig = multisig;
+        block.multisigmap = multisigmap;
+        while chain.offset() > 0 {
+            chain.pop_micro_block().expect("Should be ok");
         }
+        chain
+            .push_macro_block(block, *timestamp)
+            .expect("Invalid block");
+    }
+
+    #[test]
+    fn jailing() {
+        const NUM_NODES: usize = 4;
+        const EPOCHS: u64 = 5;
 
-        //
-        // Try to recover after popping micro block #2.
-        //
-        drop(chain);
-        timestamp += Duration::from_secs(60 * 60 * 24);
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let mut cfg: ChainConfig = Default::default();
+        cfg.stake_epochs = 1;
+        cfg.micro_blocks_in_epoch = 1;
+        cfg.jail_missed_epochs = 2;
+        cfg.jail_epochs = 2;
+        cfg.jail_penalty = 50;
+        let mut timestamp = Timestamp::now();
+        let (keychains, genesis) = test::fake_genesis(
+            cfg.min_stake_amount,
+            (NUM_NODES as i64) * cfg.min_stake_amount + 100,
+            cfg.max_slot_count,
+            NUM_NODES,
+            timestamp,
+            None,
+        );
+        let chain_dir = TempDir::new("test").unwrap();
         let mut chain = Blockchain::new(
             cfg.clone(),
             chain_dir.path(),
@@ -2515,254 +5745,204 @@ pub mod tests {
             timestamp,
         )
         .expect("Failed to create blockchain");
-        assert_eq!(epoch0, chain.epoch());
-        assert_eq!(offset1, chain.offset());
-        assert_eq!(0, chain.view_change());
-        assert_eq!(count1, chain.blocks().count());
-        assert_eq!(block_hash1, chain.last_block_hash());
-        assert_eq!(block_timestamp1, chain.last_block_timestamp());
-        assert_eq!(block_hash0, chain.last_macro_block_hash());
-        assert_eq!(block_timestamp0, chain.last_macro_block_timestamp());
-        assert_eq!(&balance1, chain.balance());
-        assert_eq!(escrow1, chain.escrow_info());
-        for input_hash in &input_hashes2 {
-            assert!(chain.contains_output(&input_hash));
-        }
-        for output_hash in &output_hashes2 {
-            assert!(!chain.contains_output(&output_hash));
-        }
 
-        //
-        // Pop micro block #1.
-        //
-        chain.pop_micro_block().expect("no disk errors");
-        assert_eq!(epoch0, chain.epoch());
-        assert_eq!(offset0, chain.offset());
-        assert_eq!(0, chain.view_change());
-        assert_eq!(count0, chain.blocks().count());
-        assert_eq!(block_hash0, chain.last_block_hash());
-        assert_eq!(block_timestamp0, chain.last_block_timestamp());
-        assert_eq!(block_hash0, chain.last_macro_block_hash());
-        assert_eq!(block_timestamp0, chain.last_macro_block_timestamp());
-        assert_eq!(&balance0, chain.balance());
-        assert_eq!(escrow0, chain.escrow_info());
-        assert_eq!(awards0, chain.awards.clone());
-        for input_hash in &input_hashes1 {
-            assert!(chain.contains_output(&input_hash));
-        }
-        for output_hash in &output_hashes1 {
-            assert!(!chain.contains_output(&output_hash));
+        // The first validator never signs macro blocks.
+        let offline = keychains[0].network_pkey;
+        for epoch in 1..=EPOCHS {
+            // Jailed at epoch 2, excluded from elections of epochs 4 and 5.
+            let jailed = epoch == 4 || epoch == 5;
+            assert_eq!(chain.is_validator(&offline), !jailed);
+            push_epoch_offline(&mut chain, &keychains, &offline, &mut timestamp);
+
+            if epoch == 2 {
+                let jailed = chain.liveness().jailed.get(&offline).expect("jailed");
+                assert_eq!(jailed.epoch, 2);
+                assert_eq!(jailed.release_epoch, 6);
+
+                // A half of the stake is confiscated, the rest is unlocked.
+                let proof = SlashingProof::Downtime {
+                    validator: offline,
+                    epoch,
+                };
+                let leader = chain.leader();
+                let tx = confiscate_tx(&chain, &leader, proof).expect("penalty");
+                tx.validate(&chain, leader).expect("valid penalty");
+                let account = chain.account_by_network_key(&offline).unwrap();
+                let amounts: Vec<i64> = tx
+                    .txouts
+                    .iter()
+                    .map(|output| match output {
+                        Output::PublicPaymentOutput(o) if o.recipient == account => o.amount,
+                        Output::PublicPaymentOutput(_o) => 0,
+                        _ => panic!("unexpected output"),
+                    })
+                    .collect();
+                assert_eq!(amounts.iter().sum::<i64>(), cfg.min_stake_amount / 2);
+
+                // Only the last jail can be punished.
+                let proof = SlashingProof::Downtime {
+                    validator: offline,
+                    epoch: epoch - 1,
+                };
+                assert!(proof.validate(&chain).is_err());
+            }
         }
+        assert!(chain.is_validator(&offline));
 
         //
-        // Try to recover after popping micro block #1.
+        // Recovery keeps the jail.
         //
+        let liveness = chain.liveness().clone();
         drop(chain);
-        timestamp += Duration::from_secs(60 * 60 * 24);
         let chain = Blockchain::new(
             cfg.clone(),
             chain_dir.path(),
-            ConsistencyCheck::None,
+            ConsistencyCheck::Full,
             genesis.clone(),
             timestamp,
         )
         .expect("Failed to create blockchain");
-        assert_eq!(epoch0, chain.epoch());
-        assert_eq!(offset0, chain.offset());
-        assert_eq!(0, chain.view_change());
-        assert_eq!(count0, chain.blocks().count());
-        assert_eq!(block_hash0, chain.last_block_hash());
-        assert_eq!(block_timestamp0, chain.last_block_timestamp());
-        assert_eq!(block_hash0, chain.last_macro_block_hash());
-        assert_eq!(block_timestamp0, chain.last_macro_block_timestamp());
-        assert_eq!(&balance0, chain.balance());
-        assert_eq!(escrow0, chain.escrow_info());
-        assert_eq!(awards0, chain.awards.clone());
-        for input_hash in &input_hashes1 {
-            assert!(chain.contains_output(&input_hash));
-        }
-        for output_hash in &output_hashes1 {
-            assert!(!chain.contains_output(&output_hash));
-        }
-        drop(chain);
+        assert_eq!(chain.epoch(), EPOCHS + 1);
+        assert_eq!(chain.liveness(), &liveness);
     }
 
     #[test]
-    fn block_iter_limit() {
+    fn jailing_snapshot() {
+        const NUM_NODES: usize = 4;
+        const EPOCHS: u64 = 3;
+
         simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
-        let mut timestamp = Timestamp::now();
+
         let mut cfg: ChainConfig = Default::default();
-        cfg.micro_blocks_in_epoch = 100500;
-        let stake = cfg.min_stake_amount;
-        let (keychains, blocks) = test::fake_genesis(
-            stake,
-            10 * cfg.min_stake_amount,
+        cfg.stake_epochs = 1;
+        cfg.micro_blocks_in_epoch = 1;
+        cfg.jail_missed_epochs = 2;
+        cfg.jail_epochs = 2;
+        let mut timestamp = Timestamp::now();
+        let (keychains, genesis) = test::fake_genesis(
+            cfg.min_stake_amount,
+            (NUM_NODES as i64) * cfg.min_stake_amount + 100,
             cfg.max_slot_count,
-            1,
+            NUM_NODES,
             timestamp,
             None,
         );
         let chain_dir = TempDir::new("test").unwrap();
-        let mut blockchain = Blockchain::new(
-            cfg,
+        let mut chain = Blockchain::new(
+            cfg.clone(),
             chain_dir.path(),
-            ConsistencyCheck::None,
-            blocks,
+            ConsistencyCheck::Full,
+            genesis.clone(),
             timestamp,
         )
         .expect("Failed to create blockchain");
-        let epoch = blockchain.epoch();
-        let starting_offset = blockchain.offset();
-        // len of genesis
-        assert!(blockchain.epoch() > 0);
-        for _offset in 2..12 {
-            timestamp += Duration::from_millis(1);
-            let block = test::create_micro_block_with_coinbase(&blockchain, &keychains, timestamp);
-            blockchain
-                .push_micro_block(block, timestamp)
-                .expect("Invalid block");
-        }
 
-        assert_eq!(
-            blockchain
-                .blocks_starting(epoch, starting_offset)
-                .take(1)
-                .count(),
-            1
-        );
+        // Jailed at epoch 2, still missed epoch 3.
+        let offline = keychains[0].network_pkey;
+        for _epoch in 1..=EPOCHS {
+            push_epoch_offline(&mut chain, &keychains, &offline, &mut timestamp);
+        }
+        let liveness = chain.liveness().clone();
+        assert_eq!(liveness.jailed.get(&offline).expect("jailed").epoch, 2);
+        assert_eq!(liveness.missed.get(&offline), Some(&3));
 
-        assert_eq!(
-            blockchain
-                .blocks_starting(epoch, starting_offset)
-                .take(4)
-                .count(),
-            4
-        );
-        // limit
-        assert_eq!(
-            blockchain
-                .blocks_starting(epoch, starting_offset)
-                .take(20)
-                .count(),
-            10
-        );
-        // empty
-        assert_eq!(
-            blockchain
-                .blocks_starting(epoch, blockchain.offset())
-                .take(1)
-                .count(),
-            0
-        );
+        let snapshot = chain.create_snapshot().expect("snapshot is available");
+        let chain2_dir = TempDir::new("test").unwrap();
+        let mut chain2 = Blockchain::new(
+            cfg.clone(),
+            chain2_dir.path(),
+            ConsistencyCheck::Full,
+            genesis.clone(),
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+        chain2.apply_snapshot(snapshot).expect("valid snapshot");
+        assert_eq!(chain2.epoch(), chain.epoch());
+        assert_eq!(chain2.liveness(), &liveness);
+        assert_eq!(chain2.validators(), chain.validators());
+
+        // The restored state survives the restart.
+        drop(chain2);
+        let chain2 = Blockchain::new(
+            cfg.clone(),
+            chain2_dir.path(),
+            ConsistencyCheck::Full,
+            genesis.clone(),
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+        assert_eq!(chain2.liveness(), &liveness);
     }
 
     #[test]
-    fn historic_output_by_hash() {
+    fn invalid_utxo_range_hash() {
         simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
-
         let mut cfg: ChainConfig = Default::default();
-        cfg.stake_epochs = 1000;
-        cfg.micro_blocks_in_epoch = 1;
+        cfg.micro_blocks_in_epoch = 2;
         let mut timestamp = Timestamp::now();
         let (keychains, genesis) = test::fake_genesis(
             cfg.min_stake_amount,
-            cfg.min_stake_amount + 100,
+            10 * cfg.min_stake_amount,
             cfg.max_slot_count,
-            1,
+            3,
             timestamp,
             None,
         );
-        let genesis_timestamp = timestamp;
-        let genesis_hash = Hash::digest(&genesis);
         let chain_dir = TempDir::new("test").unwrap();
         let mut chain = Blockchain::new(
-            cfg.clone(),
+            cfg,
             chain_dir.path(),
             ConsistencyCheck::None,
-            genesis.clone(),
+            genesis,
             timestamp,
         )
         .expect("Failed to create blockchain");
 
-        //
-        // Spent by Micro Block.
-        //
-        timestamp += Duration::from_secs(1);
-        let (block, _input_hashes, _output_hashes) =
-            test::create_fake_micro_block(&mut chain, &keychains, timestamp);
-        let (inputs, outputs, _txs) = chain
-            .push_micro_block(block, timestamp)
-            .expect("no I/O errors");
-
-        let historic_output_hash = inputs.iter().next().unwrap();
-        let historic_output_proof = chain
-            .historic_output_by_hash_with_proof(historic_output_hash)
-            .expect("no I/O errors")
-            .expect("exists");
-        assert_eq!(
-            Hash::digest(&historic_output_proof.output),
-            *historic_output_hash
-        );
-        assert_eq!(historic_output_proof.epoch, 0);
-        assert_eq!(&historic_output_proof.block_hash, &genesis_hash);
-        assert_eq!(historic_output_proof.is_final, true);
-        assert_eq!(historic_output_proof.timestamp, genesis_timestamp);
-
-        //
-        // Created by Micro Block.
-        //
-        let unspent_output = outputs.values().next().unwrap();
-        let unspent_output_proof = chain
-            .historic_output_by_hash_with_proof(&Hash::digest(unspent_output))
-            .expect("no I/O errors")
-            .expect("exists");
-        assert_eq!(&unspent_output_proof.output, unspent_output);
-        assert_eq!(unspent_output_proof.epoch, 1);
-        assert_eq!(unspent_output_proof.block_hash, chain.last_block_hash());
-        assert_eq!(unspent_output_proof.is_final, false);
-        assert_eq!(unspent_output_proof.timestamp, chain.last_block_timestamp());
-
-        //
-        // Spent by Macro Block.
-        //
-        timestamp += Duration::from_secs(1);
+        for _offset in 0..chain.cfg().micro_blocks_in_epoch {
+            timestamp += Duration::from_millis(1);
+            let (block, _input_hashes, _output_hashes) =
+                test::create_fake_micro_block(&chain, &keychains, timestamp);
+            chain
+                .push_micro_block(block, timestamp)
+                .expect("Invalid block");
+        }
+        timestamp += Duration::from_millis(1);
         let (block, _extra_transactions) =
             test::create_fake_macro_block(&chain, &keychains, timestamp);
         while chain.offset() > 0 {
             chain.pop_micro_block().expect("Should be ok");
         }
-        let (inputs, _outputs) = chain
+        let utxo_range_hash = chain.utxo_range_hash();
+
+        // A malformed block is rejected without changing the state.
+        let mut invalid = block.clone();
+        invalid.header.utxo_range_hash = Some(Hash::digest("invalid"));
+        test::sign_fake_macro_block(&mut invalid, &chain, &keychains);
+        match chain.push_macro_block(invalid, timestamp) {
+            Err(BlockchainError::BlockError(BlockError::InvalidMacroBlockUtxoHash(..))) => {}
+            e => panic!("Unexpected result: {:?}", e),
+        }
+        assert_eq!(chain.epoch(), 1);
+        assert_eq!(chain.utxo_range_hash(), utxo_range_hash);
+        let mut invalid = block.clone();
+        invalid.header.state_hash = Some(Hash::digest("invalid"));
+        test::sign_fake_macro_block(&mut invalid, &chain, &keychains);
+        match chain.push_macro_block(invalid, timestamp) {
+            Err(BlockchainError::BlockError(BlockError::InvalidMacroBlockStateHash(..))) => {}
+            e => panic!("Unexpected result: {:?}", e),
+        }
+        assert_eq!(chain.epoch(), 1);
+        assert_eq!(chain.utxo_range_hash(), utxo_range_hash);
+
+        // The tree is updated incrementally.
+        chain
             .push_macro_block(block, timestamp)
             .expect("Invalid block");
-        let historic_output_hash = inputs.iter().next().unwrap();
-        let historic_output_proof = chain
-            .historic_output_by_hash_with_proof(historic_output_hash)
-            .expect("no I/O errors")
-            .expect("exists");
-        assert_eq!(
-            Hash::digest(&historic_output_proof.output),
-            *historic_output_hash
-        );
-        assert_eq!(historic_output_proof.epoch, 0);
-        assert_eq!(historic_output_proof.block_hash, genesis_hash);
-        assert_eq!(historic_output_proof.is_final, true);
-        assert_eq!(historic_output_proof.timestamp, genesis_timestamp);
-
-        //
-        // Created by Macro Block.
-        //
-        let unspent_output = outputs.values().next().unwrap();
-        let unspent_output_proof = chain
-            .historic_output_by_hash_with_proof(&Hash::digest(&unspent_output))
-            .expect("no I/O errors")
-            .expect("exists");
-        assert_eq!(&unspent_output_proof.output, unspent_output);
-        assert_eq!(unspent_output_proof.epoch, 1);
-        assert_eq!(unspent_output_proof.block_hash, chain.last_block_hash());
-        assert_eq!(unspent_output_proof.is_final, true);
+        assert_eq!(chain.epoch(), 2);
+        assert_ne!(chain.utxo_range_hash(), utxo_range_hash);
         assert_eq!(
-            unspent_output_proof.timestamp,
-            chain.last_macro_block_timestamp()
+            chain.utxo_range_hash(),
+            UtxoTree::new(chain.unspent()).root_hash()
         );
     }
 }
@@ -21,6 +21,8 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use crate::block::{MAX_VERSION, VERSION};
+use crate::error::BlockchainError;
 use serde_derive::{Deserialize, Serialize};
 use std::time::Duration;
 
@@ -47,6 +49,9 @@ pub struct ChainConfig {
     pub min_stake_amount: i64,
     /// How many epochs stake is valid.
     pub stake_epochs: u64,
+    /// How many epochs an unstaked amount stays locked and slashable.
+    /// Must be at least `stake_epochs`.
+    pub unbonding_epochs: u64,
     /// The number of blocks per epoch.
     pub micro_blocks_in_epoch: u32,
     /// Difficulty in bits, of service awards.
@@ -57,6 +62,157 @@ pub struct ChainConfig {
     pub service_award_per_epoch: i64,
     /// Maximal delta between block's timestamp and local timestamp.
     pub vetted_timestamp_delta: Duration,
+    /// Maintain secondary indexes of transactions, inputs, outputs and recipients.
+    pub explorer_indexes: bool,
+    /// How many consecutive macro blocks must vote for a parameter change to activate it,
+    /// 0 to disable on-chain governance.
+    pub governance_epochs: u64,
+    /// How many consecutive epochs a validator can miss before being jailed,
+    /// 0 to disable jailing.
+    pub jail_missed_epochs: u64,
+    /// How many elections a jailed validator is excluded from.
+    pub jail_epochs: u64,
+    /// Percent of the stake confiscated from a jailed validator, 0 to disable the penalty.
+    pub jail_penalty: i64,
+    /// Hard-fork schedule, sorted by activation epoch.
+    pub forks: Vec<Fork>,
+}
+
+/// A hard fork - changes of consensus rules activated at some epoch.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct Fork {
+    /// The first epoch of new rules.
+    pub epoch: u64,
+    /// Block version required since `epoch`.
+    pub version: u64,
+    /// New `ChainConfig::max_slot_count`, if changed.
+    pub max_slot_count: Option<i64>,
+    /// New `ChainConfig::min_stake_amount`, if changed.
+    pub min_stake_amount: Option<i64>,
+    /// New `ChainConfig::stake_epochs`, if changed.
+    pub stake_epochs: Option<u64>,
+    /// New `ChainConfig::unbonding_epochs`, if changed.
+    pub unbonding_epochs: Option<u64>,
+    /// New `ChainConfig::micro_blocks_in_epoch`, if changed.
+    pub micro_blocks_in_epoch: Option<u32>,
+    /// New `ChainConfig::awards_difficulty`, if changed.
+    pub awards_difficulty: Option<usize>,
+    /// New `ChainConfig::block_reward`, if changed.
+    pub block_reward: Option<i64>,
+    /// New `ChainConfig::service_award_per_epoch`, if changed.
+    pub service_award_per_epoch: Option<i64>,
+    /// New `ChainConfig::vetted_timestamp_delta`, if changed.
+    pub vetted_timestamp_delta: Option<Duration>,
+    /// New `ChainConfig::jail_missed_epochs`, if changed.
+    pub jail_missed_epochs: Option<u64>,
+    /// New `ChainConfig::jail_epochs`, if changed.
+    pub jail_epochs: Option<u64>,
+    /// New `ChainConfig::jail_penalty`, if changed.
+    pub jail_penalty: Option<i64>,
+}
+
+impl Default for Fork {
+    fn default() -> Self {
+        Fork {
+            epoch: 0,
+            version: VERSION,
+            max_slot_count: None,
+            min_stake_amount: None,
+            stake_epochs: None,
+            unbonding_epochs: None,
+            micro_blocks_in_epoch: None,
+            awards_difficulty: None,
+            block_reward: None,
+            service_award_per_epoch: None,
+            vetted_timestamp_delta: None,
+            jail_missed_epochs: None,
+            jail_epochs: None,
+            jail_penalty: None,
+        }
+    }
+}
+
+impl ChainConfig {
+    /// Returns consensus rules active at the specified epoch.
+    pub fn at_epoch(&self, epoch: u64) -> ChainConfig {
+        let mut cfg = self.clone();
+        for fork in self.forks.iter().take_while(|fork| fork.epoch <= epoch) {
+            macro_rules! apply {
+                ($field: ident) => {
+                    if let Some(value) = &fork.$field {
+                        cfg.$field = value.clone();
+                    }
+                };
+            }
+            apply!(max_slot_count);
+            apply!(min_stake_amount);
+            apply!(stake_epochs);
+            apply!(unbonding_epochs);
+            apply!(micro_blocks_in_epoch);
+            apply!(awards_difficulty);
+            apply!(block_reward);
+            apply!(service_award_per_epoch);
+            apply!(vetted_timestamp_delta);
+            apply!(jail_missed_epochs);
+            apply!(jail_epochs);
+            apply!(jail_penalty);
+        }
+        cfg
+    }
+
+    /// Checks the hard-fork schedule and the consensus rules active at every fork.
+    pub fn check(&self) -> Result<(), BlockchainError> {
+        self.check_forks()?;
+        let epochs = std::iter::once(0).chain(self.forks.iter().map(|fork| fork.epoch));
+        for epoch in epochs {
+            // Unbonding outputs must keep the lock of the stakes they replace.
+            let cfg = self.at_epoch(epoch);
+            if cfg.unbonding_epochs < cfg.stake_epochs {
+                return Err(BlockchainError::InvalidUnbondingEpochs(
+                    epoch,
+                    cfg.unbonding_epochs,
+                    cfg.stake_epochs,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the hard-fork schedule is sorted and supported by this software.
+    pub fn check_forks(&self) -> Result<(), BlockchainError> {
+        let mut last_epoch = 0;
+        let mut last_version = VERSION;
+        for fork in &self.forks {
+            if fork.epoch <= last_epoch {
+                let e = format!(
+                    "epochs must be increasing and greater than zero: epoch={}, previous={}",
+                    fork.epoch, last_epoch
+                );
+                return Err(BlockchainError::InvalidForkSchedule(e));
+            }
+            if fork.version < last_version || fork.version > MAX_VERSION {
+                let e = format!(
+                    "unsupported block version: epoch={}, version={}, previous={}, max={}",
+                    fork.epoch, fork.version, last_version, MAX_VERSION
+                );
+                return Err(BlockchainError::InvalidForkSchedule(e));
+            }
+            last_epoch = fork.epoch;
+            last_version = fork.version;
+        }
+        Ok(())
+    }
+
+    /// Returns the block version required at the specified epoch.
+    pub fn version_at_epoch(&self, epoch: u64) -> u64 {
+        self.forks
+            .iter()
+            .take_while(|fork| fork.epoch <= epoch)
+            .last()
+            .map(|fork| fork.version)
+            .unwrap_or(VERSION)
+    }
 }
 
 const STG: i64 = 1_000_000;
@@ -69,11 +225,18 @@ impl Default for ChainConfig {
             min_stake_amount: 50_000 * STG,
             micro_blocks_in_epoch,
             stake_epochs: 10,
+            unbonding_epochs: 10,
             awards_difficulty: 10, // 10 bits = mean(2^10 epochs) ~ 5 days.
             block_reward: 24 * STG,
             service_award_per_epoch: 12 * STG * (micro_blocks_in_epoch as i64 + 1), // 12 STG per block
             // Sic: synchronize this value with NodeConfig::{micro, macro}_block_timeout.
             vetted_timestamp_delta: Duration::from_secs(30),
+            explorer_indexes: false,
+            governance_epochs: 10,
+            jail_missed_epochs: 0,
+            jail_epochs: 10,
+            jail_penalty: 0,
+            forks: Vec::new(),
         }
     }
 }
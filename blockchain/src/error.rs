@@ -67,6 +67,25 @@ pub enum BlockchainError {
         _0, _1, _2
     )]
     StakeIsLocked(pbc::PublicKey, i64, i64),
+    #[fail(
+        display = "Stake is delegated to a validator without active stake: validator={}, utxo={}",
+        _0, _1
+    )]
+    DelegationToInactiveValidator(pbc::PublicKey, Hash),
+    #[fail(display = "Secondary indexes are disabled, please enable explorer_indexes")]
+    IndexesDisabled,
+    #[fail(
+        display = "Pruning must keep more than stake_epochs: pruning_epochs={}, stake_epochs={}",
+        _0, _1
+    )]
+    InvalidPruningEpochs(u64, u64),
+    #[fail(
+        display = "Unbonding must last at least stake_epochs: epoch={}, unbonding_epochs={}, stake_epochs={}",
+        _0, _1, _2
+    )]
+    InvalidUnbondingEpochs(u64, u64, u64),
+    #[fail(display = "Invalid hard-fork schedule: {}", _0)]
+    InvalidForkSchedule(String),
     #[fail(display = "Storage I/O error={}", _0)]
     StorageError(StorageError),
     #[fail(display = "Transaction error={}", _0)]
@@ -77,6 +96,10 @@ pub enum BlockchainError {
     OutputError(OutputError),
     #[fail(display = "Crypto error={}", _0)]
     CryptoError(CryptoError),
+    #[fail(display = "Snapshot error={}", _0)]
+    SnapshotError(SnapshotError),
+    #[fail(display = "Light blockchain error={}", _0)]
+    LightError(LightError),
     #[fail(display = "Cannot convert utf8 string = {}", _0)]
     UtfError(Utf8Error),
     #[fail(display = "Other error={}", _0)]
@@ -143,6 +166,33 @@ pub enum TransactionError {
     #[fail(display = "TXIN amount .ne. TXOUT amount: tx={}", _0)]
     ImbalancedRestaking(Hash),
 
+    #[fail(
+        display = "Invalid multi-signature: tx={}, utxo={}, pkey={}",
+        _0, _1, _2
+    )]
+    InvalidMultisigSignature(Hash, Hash, PublicKey),
+
+    #[fail(
+        display = "Not enough multi-signatures: tx={}, utxo={}, got={}, threshold={}",
+        _0, _1, _2, _3
+    )]
+    NotEnoughMultisigSignatures(Hash, Hash, u32, u32),
+
+    #[fail(display = "Invalid HTLC preimage: tx={}, utxo={}", _0, _1)]
+    InvalidHtlcPreimage(Hash, Hash),
+
+    #[fail(
+        display = "HTLC can't be claimed after deadline: tx={}, utxo={}, deadline={}, last_macro_block_time={}",
+        _0, _1, _2, _3
+    )]
+    HtlcExpired(Hash, Hash, Timestamp, Timestamp),
+
+    #[fail(
+        display = "HTLC can't be refunded before deadline: tx={}, utxo={}, deadline={}, last_macro_block_time={}",
+        _0, _1, _2, _3
+    )]
+    HtlcNotExpired(Hash, Hash, Timestamp, Timestamp),
+
     #[fail(display = "Slashing error ={}", _0)]
     SlashingError(SlashingError),
 }
@@ -248,6 +298,16 @@ pub enum BlockError {
         _0, _1, _2, _3
     )]
     InvalidMacroBlockCanariesHash(u64, Hash, Hash, Hash),
+    #[fail(
+        display = "Invalid utxo_range_hash in a macro block: epoch={}, block={}, expected={}, got={}",
+        _0, _1, _2, _3
+    )]
+    InvalidMacroBlockUtxoHash(u64, Hash, Hash, Hash),
+    #[fail(
+        display = "Invalid state_hash in a macro block: epoch={}, block={}, expected={}, got={}",
+        _0, _1, _2, _3
+    )]
+    InvalidMacroBlockStateHash(u64, Hash, Hash, Hash),
     #[fail(
         display = "Invalid inputs_len in a macro block: epoch={}, block={}, expected={}, got={}",
         _0, _1, _2, _3
@@ -342,6 +402,11 @@ pub enum BlockError {
         _0, _1, _2, _3
     )]
     InvalidBlockVersion(u64, Hash, u64, u64),
+    #[fail(
+        display = "Invalid parameter change in macro block: epoch={}, block={}, error={}",
+        _0, _1, _2
+    )]
+    InvalidParameterChange(u64, Hash, String),
     #[fail(
         display = "Received block with invalid random: epoch={}, block={}",
         _0, _1
@@ -393,6 +458,11 @@ pub enum BlockError {
         _0, _1, _2, _3, _4
     )]
     InvalidMicroBlockReward(u64, u32, Hash, i64, i64),
+    #[fail(
+        display = "Invalid rewards of delegators in coinbase: epoch={}, block={}",
+        _0, _1
+    )]
+    InvalidDelegationPayouts(u64, Hash),
     #[fail(
         display = "Activity bitmap too big: len={}, validators_len={} ",
         _0, _1
@@ -478,6 +548,40 @@ pub enum SlashingError {
         _0, _1
     )]
     DifferentOffset(u32, u32),
+    #[fail(
+        display = "Different signers was found for messages in proofs: \
+                   signer1 = {}, signer2 = {}",
+        _0, _1
+    )]
+    DifferentSigners(pbc::PublicKey, pbc::PublicKey),
+    #[fail(
+        display = "Different rounds was found for votes in proofs: \
+                   round1 = {}, round2 = {}",
+        _0, _1
+    )]
+    DifferentRound(u32, u32),
+    #[fail(
+        display = "Pre-vote and pre-commit was found in proofs: epoch = {}, round = {}",
+        _0, _1
+    )]
+    DifferentVoteKind(u64, u32),
+    #[fail(
+        display = "Found same messages without conflicts: epoch = {}, signer = {}",
+        _0, _1
+    )]
+    MessagesWithoutConflicts(u64, pbc::PublicKey),
+    #[fail(
+        display = "Header doesn't match the last block in proofs: last_block = {}, header = {}",
+        _0, _1
+    )]
+    InvalidLastBlockHeader(Hash, Hash),
+    #[fail(
+        display = "Validator was not jailed by the last macro block: validator = {}, epoch = {}",
+        _0, _1
+    )]
+    NotJailed(pbc::PublicKey, u64),
+    #[fail(display = "Jail penalty is disabled")]
+    JailPenaltyDisabled,
     #[fail(
         display = "Found slashing transaction, with incorrect inputs, tx_hash = {}",
         _0
@@ -490,6 +594,83 @@ pub enum SlashingError {
     IncorrectTxouts(Hash),
 }
 
+/// State snapshot errors.
+#[derive(Debug, Fail)]
+pub enum SnapshotError {
+    #[fail(display = "State snapshot is not available: epoch={}", _0)]
+    NotAvailable(u64),
+    #[fail(
+        display = "Blockchain must be empty to apply a snapshot: epoch={}, offset={}",
+        _0, _1
+    )]
+    NotEmpty(u64, u32),
+    #[fail(
+        display = "Out of order macro block in snapshot: block={}, epoch={}, expected_epoch={}",
+        _0, _1, _2
+    )]
+    OutOfOrderMacroBlock(Hash, u64, u64),
+    #[fail(
+        display = "Invalid previous hash in snapshot: epoch={}, block={}, block_previous={}, expected_previous={}",
+        _0, _1, _2, _3
+    )]
+    InvalidPreviousHash(u64, Hash, Hash, Hash),
+    #[fail(
+        display = "Invalid validators in snapshot: epoch={}, block={}, got={}, expected={}",
+        _0, _1, _2, _3
+    )]
+    InvalidValidators(u64, Hash, Hash, Hash),
+    #[fail(
+        display = "Invalid macro block in snapshot: epoch={}, block={}",
+        _0, _1
+    )]
+    InvalidBlockBody(u64, Hash),
+    #[fail(
+        display = "Macro block doesn't have a state commitment: epoch={}, block={}",
+        _0, _1
+    )]
+    MissingStateHash(u64, Hash),
+    #[fail(
+        display = "Invalid state commitment: epoch={}, block={}, expected={}, got={}",
+        _0, _1, _2, _3
+    )]
+    InvalidStateHash(u64, Hash, Hash, Hash),
+    #[fail(display = "Duplicate output in snapshot: utxo={}", _0)]
+    DuplicateOutput(Hash),
+    #[fail(
+        display = "Invalid monetary balance in snapshot: epoch={}, block={}",
+        _0, _1
+    )]
+    InvalidBalance(u64, Hash),
+    #[fail(
+        display = "Blockchain state is missing and history before the snapshot is not available: snapshot_epoch={}",
+        _0
+    )]
+    MissingHistory(u64),
+}
+
+/// Light blockchain errors.
+#[derive(Debug, Fail)]
+pub enum LightError {
+    #[fail(display = "Invalid light block body: block={}", _0)]
+    InvalidBlockBody(Hash),
+    #[fail(
+        display = "Invalid validators in light block: epoch={}, block={}, got={}, expected={}",
+        _0, _1, _2, _3
+    )]
+    InvalidValidators(u64, Hash, Hash, Hash),
+    #[fail(
+        display = "Light block is not signed by a validator: epoch={}, offset={}, block={}, leader={}",
+        _0, _1, _2, _3
+    )]
+    UnknownLeader(u64, u32, Hash, pbc::PublicKey),
+    #[fail(display = "Block not found: block={}", _0)]
+    BlockNotFound(Hash),
+    #[fail(display = "Output not found: block={}, output_id={}", _0, _1)]
+    OutputNotFound(Hash, u32),
+    #[fail(display = "Invalid output proof: block={}, output_id={}", _0, _1)]
+    InvalidOutputProof(Hash, u32),
+}
+
 impl From<rocksdb::Error> for BlockchainError {
     fn from(error: rocksdb::Error) -> BlockchainError {
         BlockchainError::StorageError(error)
@@ -531,6 +712,18 @@ impl From<SlashingError> for BlockchainError {
     }
 }
 
+impl From<SnapshotError> for BlockchainError {
+    fn from(error: SnapshotError) -> BlockchainError {
+        BlockchainError::SnapshotError(error)
+    }
+}
+
+impl From<LightError> for BlockchainError {
+    fn from(error: LightError) -> BlockchainError {
+        BlockchainError::LightError(error)
+    }
+}
+
 impl From<Utf8Error> for BlockchainError {
     fn from(error: Utf8Error) -> BlockchainError {
         BlockchainError::UtfError(error)
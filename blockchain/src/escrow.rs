@@ -25,12 +25,12 @@
 
 use crate::error::BlockchainError;
 use crate::mvcc::MultiVersionedMap;
-use crate::output::Output;
+use crate::output::{Output, StakeOutput};
 use log::*;
 use serde_derive::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::collections::HashMap;
-use stegos_crypto::hash::Hash;
+use stegos_crypto::hash::{Hash, Hashable, Hasher};
 use stegos_crypto::pbc;
 use stegos_crypto::scc;
 
@@ -45,6 +45,35 @@ pub(crate) struct EscrowValue {
     pub(crate) account_pkey: scc::PublicKey,
     pub(crate) active_until_epoch: u64,
     pub(crate) amount: i64,
+    pub(crate) commission: u32,
+    pub(crate) delegated: bool,
+    pub(crate) unbonding: bool,
+}
+
+impl Hashable for EscrowKey {
+    fn hash(&self, state: &mut Hasher) {
+        self.validator_pkey.hash(state);
+        self.output_hash.hash(state);
+    }
+}
+
+impl Hashable for EscrowValue {
+    fn hash(&self, state: &mut Hasher) {
+        self.account_pkey.hash(state);
+        self.active_until_epoch.hash(state);
+        self.amount.hash(state);
+        // Keep the state hash of ordinary stakes unchanged.
+        if self.commission != 0 {
+            "commission".hash(state);
+            (self.commission as u64).hash(state);
+        }
+        if self.delegated {
+            "delegated".hash(state);
+        }
+        if self.unbonding {
+            "unbonding".hash(state);
+        }
+    }
 }
 
 use crate::LSN;
@@ -66,9 +95,27 @@ pub struct ValidatorInfo {
     pub network_pkey: pbc::PublicKey,
     pub active_stake: i64,
     pub expired_stake: i64,
+    /// Commission in percent of the rewards of delegators.
+    #[serde(default)]
+    pub commission: u32,
+    /// Active stakes delegated by other accounts, included into `active_stake`.
+    #[serde(default)]
+    pub delegated_stake: i64,
+    /// Stakes in the unbonding state, locked but not included into `active_stake`.
+    #[serde(default)]
+    pub unbonding_stake: i64,
+    /// Active stakes of delegators.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub delegators: Vec<DelegatorInfo>,
     pub stakes: Vec<StakeInfo>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DelegatorInfo {
+    pub account_pkey: scc::PublicKey,
+    pub amount: i64,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct StakeInfo {
     pub output_hash: Hash,
@@ -78,6 +125,12 @@ pub struct StakeInfo {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub is_active: Option<bool>,
     pub amount: i64,
+    /// Stake is delegated to a third-party validator.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub delegated: bool,
+    /// Stake is unstaked and waits for the end of the unbonding period.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub unbonding: bool,
 }
 
 impl Escrow {
@@ -94,23 +147,20 @@ impl Escrow {
     pub(crate) fn stake(
         &mut self,
         lsn: LSN,
-        validator_pkey: pbc::PublicKey,
-        account_pkey: scc::PublicKey,
         output_hash: Hash,
+        output: &StakeOutput,
         epoch: u64,
         stakes_epoch: u64,
-        amount: i64,
+        unbonding_epochs: u64,
     ) {
-        let active_until_epoch = epoch + stakes_epoch;
+        let validator_pkey = output.validator;
+        let amount = output.amount;
         let key = EscrowKey {
             validator_pkey,
             output_hash,
         };
-        let value = EscrowValue {
-            account_pkey,
-            active_until_epoch,
-            amount,
-        };
+        let value = Self::stake_value(output, epoch, stakes_epoch, unbonding_epochs);
+        let active_until_epoch = value.active_until_epoch;
 
         if let Some(v) = self.escrow.insert(lsn, key, value) {
             panic!(
@@ -126,6 +176,69 @@ impl Escrow {
         );
     }
 
+    fn stake_value(
+        output: &StakeOutput,
+        epoch: u64,
+        stakes_epoch: u64,
+        unbonding_epochs: u64,
+    ) -> EscrowValue {
+        let active_until_epoch = if output.unbonding {
+            epoch + unbonding_epochs
+        } else {
+            epoch + stakes_epoch
+        };
+        EscrowValue {
+            account_pkey: output.recipient,
+            active_until_epoch,
+            amount: output.amount,
+            commission: output.commission,
+            delegated: output.delegated,
+            unbonding: output.unbonding,
+        }
+    }
+
+    ///
+    /// Returns stakes after unstaking `inputs` and staking `outputs`, without changing the escrow.
+    ///
+    pub(crate) fn stakes_after<'a, I, O>(
+        &self,
+        inputs: I,
+        outputs: O,
+        epoch: u64,
+        stakes_epoch: u64,
+        unbonding_epochs: u64,
+    ) -> BTreeMap<EscrowKey, EscrowValue>
+    where
+        I: Iterator<Item = (&'a Hash, &'a Output)>,
+        O: Iterator<Item = (&'a Hash, &'a Output)>,
+    {
+        let mut stakes: BTreeMap<EscrowKey, EscrowValue> = self
+            .escrow
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        for (output_hash, input) in inputs {
+            if let Output::StakeOutput(o) = input {
+                let key = EscrowKey {
+                    validator_pkey: o.validator,
+                    output_hash: *output_hash,
+                };
+                stakes.remove(&key);
+            }
+        }
+        for (output_hash, output) in outputs {
+            if let Output::StakeOutput(o) = output {
+                let key = EscrowKey {
+                    validator_pkey: o.validator,
+                    output_hash: *output_hash,
+                };
+                let value = Self::stake_value(o, epoch, stakes_epoch, unbonding_epochs);
+                stakes.insert(key, value);
+            }
+        }
+        stakes
+    }
+
     ///
     /// Unstake money from the escrow.
     ///
@@ -150,12 +263,12 @@ impl Escrow {
     }
 
     ///
-    /// Iterate over stakes of specified validator.
+    /// Iterate over all stakes of specified validator, including delegations.
     ///
-    pub fn iter_validator_stakes(
+    fn iter_stakes(
         &self,
         validator_pkey: &pbc::PublicKey,
-    ) -> impl Iterator<Item = (&Hash, i64, &scc::PublicKey, u64)> {
+    ) -> impl Iterator<Item = (&EscrowKey, &EscrowValue)> {
         let (hash_min, hash_max) = Hash::bounds();
         let key_min = EscrowKey {
             validator_pkey: validator_pkey.clone(),
@@ -165,15 +278,64 @@ impl Escrow {
             validator_pkey: validator_pkey.clone(),
             output_hash: hash_max,
         };
+        self.escrow.range(&key_min..=&key_max)
+    }
 
-        self.escrow.range(&key_min..=&key_max).map(|(key, value)| {
-            (
-                &key.output_hash,
-                value.amount,
-                &value.account_pkey,
-                value.active_until_epoch,
-            )
-        })
+    ///
+    /// Iterate over own bonded stakes of specified validator.
+    ///
+    pub fn iter_validator_stakes(
+        &self,
+        validator_pkey: &pbc::PublicKey,
+    ) -> impl Iterator<Item = (&Hash, i64, &scc::PublicKey, u64)> {
+        self.iter_stakes(validator_pkey)
+            .filter(|(_key, value)| !value.delegated && !value.unbonding)
+            .map(|(key, value)| {
+                (
+                    &key.output_hash,
+                    value.amount,
+                    &value.account_pkey,
+                    value.active_until_epoch,
+                )
+            })
+    }
+
+    ///
+    /// Iterate over bonded stakes delegated to specified validator.
+    ///
+    pub fn iter_delegated_stakes(
+        &self,
+        validator_pkey: &pbc::PublicKey,
+    ) -> impl Iterator<Item = (&Hash, i64, &scc::PublicKey, u64)> {
+        self.iter_stakes(validator_pkey)
+            .filter(|(_key, value)| value.delegated && !value.unbonding)
+            .map(|(key, value)| {
+                (
+                    &key.output_hash,
+                    value.amount,
+                    &value.account_pkey,
+                    value.active_until_epoch,
+                )
+            })
+    }
+
+    ///
+    /// Iterate over unbonding stakes of specified validator, including delegations.
+    ///
+    pub fn iter_unbonding_stakes(
+        &self,
+        validator_pkey: &pbc::PublicKey,
+    ) -> impl Iterator<Item = (&Hash, i64, &scc::PublicKey, u64)> {
+        self.iter_stakes(validator_pkey)
+            .filter(|(_key, value)| value.unbonding)
+            .map(|(key, value)| {
+                (
+                    &key.output_hash,
+                    value.amount,
+                    &value.account_pkey,
+                    value.active_until_epoch,
+                )
+            })
     }
 
     ///
@@ -194,6 +356,136 @@ impl Escrow {
         )
     }
 
+    ///
+    /// Get value staked by the account to the validator, including delegations.
+    ///
+    /// Returns (active_balance, expired_balance) stake.
+    ///
+    fn account_balance(
+        &self,
+        validator_pkey: &pbc::PublicKey,
+        account_pkey: &scc::PublicKey,
+        epoch: u64,
+    ) -> (i64, i64) {
+        self.iter_stakes(validator_pkey)
+            .filter(|(_key, value)| &value.account_pkey == account_pkey)
+            .fold(
+                (0i64, 0i64),
+                |(active_balance, expired_balance), (_key, value)| {
+                    if value.active_until_epoch >= epoch {
+                        (active_balance + value.amount, expired_balance)
+                    } else {
+                        (active_balance, expired_balance + value.amount)
+                    }
+                },
+            )
+    }
+
+    ///
+    /// Returns true if validator has an active own stake.
+    /// Only such validators can be elected and accept delegations.
+    ///
+    pub fn is_self_staked(&self, validator_pkey: &pbc::PublicKey, epoch: u64) -> bool {
+        self.iter_validator_stakes(validator_pkey)
+            .any(|(_, _, _, active_until_epoch)| active_until_epoch >= epoch)
+    }
+
+    ///
+    /// Returns commission of the validator for delegators.
+    /// The highest commission of active own stakes is used.
+    ///
+    pub fn commission(&self, validator_pkey: &pbc::PublicKey, epoch: u64) -> u32 {
+        self.iter_stakes(validator_pkey)
+            .filter(|(_key, value)| !value.delegated && !value.unbonding)
+            .filter(|(_key, value)| value.active_until_epoch >= epoch)
+            .map(|(_key, value)| value.commission)
+            .max()
+            .unwrap_or(0)
+    }
+
+    ///
+    /// Split a reward of the validator between the validator and delegators,
+    /// proportionally to active stakes and minus the commission of the validator.
+    ///
+    /// Returns shares of delegators, the rest of the reward belongs to the validator.
+    ///
+    pub fn delegation_payouts(
+        &self,
+        validator_pkey: &pbc::PublicKey,
+        reward: i64,
+        epoch: u64,
+    ) -> Vec<(scc::PublicKey, i64)> {
+        let mut total: i64 = 0;
+        let mut delegated: BTreeMap<scc::PublicKey, i64> = BTreeMap::new();
+        for (_key, value) in self.iter_stakes(validator_pkey) {
+            if value.active_until_epoch < epoch || value.unbonding {
+                continue;
+            }
+            total += value.amount;
+            if value.delegated {
+                *delegated.entry(value.account_pkey).or_insert(0) += value.amount;
+            }
+        }
+        if delegated.is_empty() || reward <= 0 {
+            return Vec::new();
+        }
+        let commission = self.commission(validator_pkey, epoch) as i128;
+        delegated
+            .into_iter()
+            .filter_map(|(account_pkey, amount)| {
+                let share = reward as i128 * amount as i128 / total as i128;
+                let payout = share - share * commission / 100;
+                if payout > 0 {
+                    Some((account_pkey, payout as i64))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    ///
+    /// Split a service award of the account between validators of this account and their delegators.
+    ///
+    /// Returns payouts, the winner goes first.
+    ///
+    pub fn award_payouts(
+        &self,
+        account_pkey: &scc::PublicKey,
+        reward: i64,
+        epoch: u64,
+    ) -> Vec<(scc::PublicKey, i64)> {
+        // Multiple validators can have a single wallet.
+        let mut validators: BTreeMap<pbc::PublicKey, i64> = BTreeMap::new();
+        for (key, value) in self.escrow.iter() {
+            if value.delegated || value.unbonding || &value.account_pkey != account_pkey {
+                continue;
+            }
+            if !validators.contains_key(&key.validator_pkey) {
+                let stake = self
+                    .iter_stakes(&key.validator_pkey)
+                    .filter(|(_key, value)| value.active_until_epoch >= epoch && !value.unbonding)
+                    .map(|(_key, value)| value.amount)
+                    .sum();
+                validators.insert(key.validator_pkey, stake);
+            }
+        }
+        let total: i64 = validators.values().sum();
+        let mut winner_reward = reward;
+        let mut payouts = Vec::new();
+        if total > 0 {
+            for (validator_pkey, stake) in validators {
+                let piece = (reward as i128 * stake as i128 / total as i128) as i64;
+                for (delegator, payout) in self.delegation_payouts(&validator_pkey, piece, epoch) {
+                    winner_reward -= payout;
+                    payouts.push((delegator, payout));
+                }
+            }
+        }
+        payouts.insert(0, (*account_pkey, winner_reward));
+        payouts
+    }
+
     ///
     /// Return an account key by network key.
     ///
@@ -201,9 +493,10 @@ impl Escrow {
         &self,
         validator_pkey: &pbc::PublicKey,
     ) -> Option<scc::PublicKey> {
-        self.iter_validator_stakes(&validator_pkey)
-            .next()
-            .map(|(_hash, _amount, account_pkey, _active_until_epoch)| account_pkey.clone())
+        // Unbonding validators are still slashable and can be rewarded for the current epoch.
+        self.iter_stakes(&validator_pkey)
+            .find(|(_key, value)| !value.delegated)
+            .map(|(_key, value)| value.account_pkey.clone())
     }
 
     ///
@@ -212,13 +505,15 @@ impl Escrow {
     pub(crate) fn get_stakers(&self, epoch: u64) -> BTreeMap<pbc::PublicKey, i64> {
         let mut stakes: BTreeMap<pbc::PublicKey, i64> = BTreeMap::new();
         for (k, v) in self.escrow.iter() {
-            if v.active_until_epoch < epoch {
-                // Skip expired stakes.
+            if v.active_until_epoch < epoch || v.unbonding {
+                // Skip expired and unbonding stakes.
                 continue;
             }
             let entry = stakes.entry(k.validator_pkey).or_insert(0);
             *entry += v.amount;
         }
+        // Delegations count only for validators with own stake.
+        stakes.retain(|validator_pkey, _| self.is_self_staked(validator_pkey, epoch));
         stakes
     }
 
@@ -231,18 +526,8 @@ impl Escrow {
         epoch: u64,
         min_stake_amount: i64,
     ) -> Vec<(pbc::PublicKey, i64)> {
-        let mut stakes: BTreeMap<pbc::PublicKey, i64> = BTreeMap::new();
-        for (k, v) in self.escrow.iter() {
-            if v.active_until_epoch < epoch {
-                // Skip expired stakes.
-                continue;
-            }
-            let entry = stakes.entry(k.validator_pkey).or_insert(0);
-            *entry += v.amount;
-        }
-
         // filter out validators with low stake.
-        stakes
+        self.get_stakers(epoch)
             .into_iter()
             .filter(|(_, amount)| *amount >= min_stake_amount)
             .collect()
@@ -259,26 +544,77 @@ impl Escrow {
         inputs: OutputIter,
         outputs: OutputIter,
         epoch: u64,
+        stake_epochs: u64,
+        unbonding_epochs: u64,
     ) -> Result<(), BlockchainError>
     where
         OutputIter: Iterator<Item = &'a Output>,
     {
-        let mut staking_balance: HashMap<pbc::PublicKey, i64> = HashMap::new();
+        // Stakes of delegators are locked independently of the validator.
+        let mut staking_balance: HashMap<(pbc::PublicKey, scc::PublicKey), i64> = HashMap::new();
+        // The latest epoch until which the spent stakes are locked.
+        let mut locked_until: HashMap<(pbc::PublicKey, scc::PublicKey), u64> = HashMap::new();
         for input in inputs {
             match input {
                 Output::PaymentOutput(_o) => {}
                 Output::PublicPaymentOutput(_o) => {}
+                Output::MultisigOutput(_o) => {}
+                Output::HtlcOutput(_o) => {}
                 Output::StakeOutput(o) => {
                     // Update staking balance.
-                    let stake = staking_balance.entry(o.validator).or_insert(0);
+                    let stake = staking_balance
+                        .entry((o.validator, o.recipient))
+                        .or_insert(0);
                     *stake -= o.amount;
+                    let key = EscrowKey {
+                        validator_pkey: o.validator,
+                        output_hash: Hash::digest(input),
+                    };
+                    if let Some(value) = self.escrow.get(&key) {
+                        if value.active_until_epoch >= epoch {
+                            let lock = locked_until.entry((o.validator, o.recipient)).or_insert(0);
+                            *lock = std::cmp::max(*lock, value.active_until_epoch);
+                        }
+                    }
                 }
             }
         }
+
+        // A locked stake can only be moved into an output, which is locked at least as long.
+        // E.g. an unbonding output can't release an active stake before the end of its lock.
+        let keeps_lock = |o: &StakeOutput| {
+            let active_until_epoch = if o.unbonding {
+                epoch + unbonding_epochs
+            } else {
+                epoch + stake_epochs
+            };
+            match locked_until.get(&(o.validator, o.recipient)) {
+                Some(lock) => active_until_epoch >= *lock,
+                None => true,
+            }
+        };
         for output in outputs {
             match output {
                 Output::PaymentOutput(_o) => {}
                 Output::PublicPaymentOutput(_o) => {}
+                Output::MultisigOutput(_o) => {}
+                Output::HtlcOutput(_o) => {}
+                Output::StakeOutput(o) if o.delegated => {
+                    if !self.is_self_staked(&o.validator, epoch) {
+                        let utxo_hash = Hash::digest(output);
+                        return Err(BlockchainError::DelegationToInactiveValidator(
+                            o.validator,
+                            utxo_hash,
+                        ));
+                    }
+                    // Update staking balance.
+                    let stake = staking_balance
+                        .entry((o.validator, o.recipient))
+                        .or_insert(0);
+                    if keeps_lock(o) {
+                        *stake += o.amount;
+                    }
+                }
                 Output::StakeOutput(o) => {
                     if let Some(account_pkey) = self.account_by_network_key(&o.validator) {
                         if account_pkey != o.recipient {
@@ -292,14 +628,19 @@ impl Escrow {
                         }
                     }
                     // Update staking balance.
-                    let stake = staking_balance.entry(o.validator).or_insert(0);
-                    *stake += o.amount;
+                    let stake = staking_balance
+                        .entry((o.validator, o.recipient))
+                        .or_insert(0);
+                    if keeps_lock(o) {
+                        *stake += o.amount;
+                    }
                 }
             };
         }
 
-        for (validator_pkey, balance) in &staking_balance {
-            let (active_balance, expired_balance) = self.validator_balance(validator_pkey, epoch);
+        for ((validator_pkey, account_pkey), balance) in &staking_balance {
+            let (active_balance, expired_balance) =
+                self.account_balance(validator_pkey, account_pkey, epoch);
             let expected_balance = active_balance + expired_balance + balance;
             if expected_balance < active_balance {
                 return Err(BlockchainError::StakeIsLocked(
@@ -324,6 +665,10 @@ impl Escrow {
                     stakes: Default::default(),
                     active_stake: Default::default(),
                     expired_stake: Default::default(),
+                    commission: self.commission(&k.validator_pkey, epoch),
+                    delegated_stake: Default::default(),
+                    unbonding_stake: Default::default(),
+                    delegators: Default::default(),
                 });
             let is_active = v.active_until_epoch >= epoch;
             let stake = StakeInfo {
@@ -332,10 +677,28 @@ impl Escrow {
                 active_until_epoch: v.active_until_epoch.into(),
                 is_active: is_active.into(),
                 amount: v.amount,
+                delegated: v.delegated,
+                unbonding: v.unbonding,
             };
             (*entry).stakes.push(stake);
-            if is_active {
+            if is_active && v.unbonding {
+                (*entry).unbonding_stake += v.amount;
+            } else if is_active {
                 (*entry).active_stake += v.amount;
+                if v.delegated {
+                    (*entry).delegated_stake += v.amount;
+                    match (*entry)
+                        .delegators
+                        .iter_mut()
+                        .find(|d| d.account_pkey == v.account_pkey)
+                    {
+                        Some(delegator) => delegator.amount += v.amount,
+                        None => (*entry).delegators.push(DelegatorInfo {
+                            account_pkey: v.account_pkey,
+                            amount: v.amount,
+                        }),
+                    }
+                }
             } else {
                 (*entry).expired_stake += v.amount;
             }
@@ -360,3 +723,191 @@ impl Escrow {
         self.escrow.rollback_to_lsn(to_lsn);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delegation() {
+        let (validator_skey, validator_pkey) = pbc::make_random_keys();
+        let (_, account1) = scc::make_random_keys();
+        let (_, account2) = scc::make_random_keys();
+        let (_, account3) = scc::make_random_keys();
+        let epoch = 10;
+        let mut escrow = Escrow::new();
+        let stake = |escrow: &mut Escrow, lsn: u32, output: &StakeOutput| {
+            escrow.stake(LSN(epoch, lsn), Hash::digest(output), output, epoch, 5, 5);
+        };
+
+        // Delegations without own stake are not counted.
+        let delegation = StakeOutput::new_delegated(&account2, &validator_pkey, 300).unwrap();
+        stake(&mut escrow, 0, &delegation);
+        assert!(escrow.get_stakers(epoch).is_empty());
+        assert_eq!(escrow.account_by_network_key(&validator_pkey), None);
+        assert!(escrow
+            .delegation_payouts(&validator_pkey, 100, epoch)
+            .is_empty());
+
+        let own =
+            StakeOutput::with_commission(&account1, &validator_skey, &validator_pkey, 500, 10)
+                .unwrap();
+        stake(&mut escrow, 1, &own);
+        let delegation2 = StakeOutput::new_delegated(&account3, &validator_pkey, 200).unwrap();
+        stake(&mut escrow, 2, &delegation2);
+        assert_eq!(escrow.get_stakers(epoch).get(&validator_pkey), Some(&1000));
+        assert_eq!(
+            escrow.account_by_network_key(&validator_pkey),
+            Some(account1)
+        );
+        assert_eq!(escrow.validator_balance(&validator_pkey, epoch), (500, 0));
+        assert_eq!(escrow.commission(&validator_pkey, epoch), 10);
+
+        // 30% and 20% of the reward minus 10% commission.
+        let payouts = escrow.delegation_payouts(&validator_pkey, 1000, epoch);
+        let mut expected = vec![(account2, 270), (account3, 180)];
+        expected.sort();
+        assert_eq!(payouts, expected);
+
+        // The winner goes first.
+        let payouts = escrow.award_payouts(&account1, 1000, epoch);
+        assert_eq!(payouts[0], (account1, 550));
+        assert_eq!(payouts.iter().map(|(_, a)| a).sum::<i64>(), 1000);
+
+        let info = escrow.info(epoch);
+        let validator = &info.validators[0];
+        assert_eq!(validator.active_stake, 1000);
+        assert_eq!(validator.delegated_stake, 500);
+        assert_eq!(validator.delegators.len(), 2);
+        assert_eq!(validator.stakes.iter().filter(|s| s.delegated).count(), 2);
+
+        // Delegations are locked until expired.
+        let inputs = vec![Output::StakeOutput(delegation)];
+        let outputs: Vec<Output> = Vec::new();
+        match escrow.validate_stakes(inputs.iter(), outputs.iter(), epoch, 5, 5) {
+            Err(BlockchainError::StakeIsLocked(..)) => {}
+            e => panic!("{:?}", e),
+        }
+        escrow
+            .validate_stakes(inputs.iter(), outputs.iter(), epoch + 6, 5, 5)
+            .expect("stake is expired");
+
+        // Delegation to an inactive validator.
+        let (_, unknown_pkey) = pbc::make_random_keys();
+        let outputs = vec![Output::StakeOutput(
+            StakeOutput::new_delegated(&account2, &unknown_pkey, 100).unwrap(),
+        )];
+        let inputs: Vec<Output> = Vec::new();
+        match escrow.validate_stakes(inputs.iter(), outputs.iter(), epoch, 5, 5) {
+            Err(BlockchainError::DelegationToInactiveValidator(..)) => {}
+            e => panic!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn unbonding() {
+        let (validator_skey, validator_pkey) = pbc::make_random_keys();
+        let (_, account_pkey) = scc::make_random_keys();
+        let epoch = 10;
+        let mut escrow = Escrow::new();
+
+        let own = StakeOutput::new(&account_pkey, &validator_skey, &validator_pkey, 500).unwrap();
+        escrow.stake(LSN(epoch, 0), Hash::digest(&own), &own, epoch, 2, 5);
+        let unbonding =
+            StakeOutput::new_unbonding(&account_pkey, &validator_skey, &validator_pkey, 200)
+                .unwrap();
+        escrow.stake(
+            LSN(epoch, 1),
+            Hash::digest(&unbonding),
+            &unbonding,
+            epoch,
+            2,
+            5,
+        );
+
+        // Unbonding stakes don't count in elections.
+        assert_eq!(escrow.get_stakers(epoch).get(&validator_pkey), Some(&500));
+        assert_eq!(escrow.iter_validator_stakes(&validator_pkey).count(), 1);
+        assert_eq!(escrow.iter_unbonding_stakes(&validator_pkey).count(), 1);
+        assert_eq!(
+            escrow.account_by_network_key(&validator_pkey),
+            Some(account_pkey)
+        );
+        let info = escrow.info(epoch);
+        assert_eq!(info.validators[0].active_stake, 500);
+        assert_eq!(info.validators[0].unbonding_stake, 200);
+        assert_eq!(
+            info.validators[0]
+                .stakes
+                .iter()
+                .filter(|s| s.unbonding)
+                .count(),
+            1
+        );
+
+        // Locked until the end of the unbonding period.
+        let inputs = vec![Output::StakeOutput(unbonding)];
+        let outputs: Vec<Output> = Vec::new();
+        match escrow.validate_stakes(inputs.iter(), outputs.iter(), epoch + 5, 2, 5) {
+            Err(BlockchainError::StakeIsLocked(..)) => {}
+            e => panic!("{:?}", e),
+        }
+        escrow
+            .validate_stakes(inputs.iter(), outputs.iter(), epoch + 6, 2, 5)
+            .expect("unbonding is finished");
+        let info = escrow.info(epoch + 6);
+        assert_eq!(info.validators[0].unbonding_stake, 0);
+    }
+
+    #[test]
+    fn unbonding_active_stake() {
+        let (validator_skey, validator_pkey) = pbc::make_random_keys();
+        let (_, account_pkey) = scc::make_random_keys();
+        let epoch = 10;
+        let stake_epochs = 5;
+        let mut escrow = Escrow::new();
+
+        let own = StakeOutput::new(&account_pkey, &validator_skey, &validator_pkey, 500).unwrap();
+        escrow.stake(
+            LSN(epoch, 0),
+            Hash::digest(&own),
+            &own,
+            epoch,
+            stake_epochs,
+            stake_epochs,
+        );
+        let inputs = vec![Output::StakeOutput(own)];
+        let unbonding =
+            StakeOutput::new_unbonding(&account_pkey, &validator_skey, &validator_pkey, 500)
+                .unwrap();
+        let outputs = vec![Output::StakeOutput(unbonding)];
+
+        // A shorter unbonding period can't release the active stake earlier.
+        match escrow.validate_stakes(inputs.iter(), outputs.iter(), epoch + 2, stake_epochs, 2) {
+            Err(BlockchainError::StakeIsLocked(..)) => {}
+            e => panic!("{:?}", e),
+        }
+
+        // The unbonding output outlives the lock of the active stake.
+        escrow
+            .validate_stakes(
+                inputs.iter(),
+                outputs.iter(),
+                epoch + 2,
+                stake_epochs,
+                stake_epochs,
+            )
+            .expect("the lock is kept");
+
+        // An expired stake can be moved into any unbonding output.
+        escrow
+            .validate_stakes(
+                inputs.iter(),
+                outputs.iter(),
+                epoch + stake_epochs + 1,
+                stake_epochs,
+                2,
+            )
+            .expect("stake is expired");
+    }
+}
@@ -0,0 +1,239 @@
+//
+// Copyright (c) 2019 Stegos AG
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+// TODO: Choose difficulty.
+
+//! On-chain governance of consensus parameters.
+
+use crate::config::ChainConfig;
+use log::info;
+use serde_derive::{Deserialize, Serialize};
+use stegos_crypto::hash::{Hashable, Hasher};
+
+/// A change of consensus parameters proposed by validators.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ParameterChange {
+    /// New `ChainConfig::block_reward`, if changed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_reward: Option<i64>,
+    /// New `ChainConfig::min_stake_amount`, if changed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_stake_amount: Option<i64>,
+    /// New `ChainConfig::service_award_per_epoch`, if changed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_award_per_epoch: Option<i64>,
+    /// New `ChainConfig::max_slot_count`, if changed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_slot_count: Option<i64>,
+}
+
+impl ParameterChange {
+    /// Returns true if no parameters are changed.
+    pub fn is_empty(&self) -> bool {
+        self.block_reward.is_none()
+            && self.min_stake_amount.is_none()
+            && self.service_award_per_epoch.is_none()
+            && self.max_slot_count.is_none()
+    }
+
+    /// Checks that the change is not empty and all new values are positive.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.is_empty() {
+            return Err("empty parameter change".to_string());
+        }
+        macro_rules! check_positive {
+            ($field: ident) => {
+                if let Some(value) = self.$field {
+                    if value <= 0 {
+                        return Err(format!(
+                            "{} must be positive: got={}",
+                            stringify!($field),
+                            value
+                        ));
+                    }
+                }
+            };
+        }
+        check_positive!(block_reward);
+        check_positive!(min_stake_amount);
+        check_positive!(service_award_per_epoch);
+        check_positive!(max_slot_count);
+        Ok(())
+    }
+
+    /// Overrides changed parameters in `cfg`.
+    pub fn apply(&self, cfg: &mut ChainConfig) {
+        macro_rules! apply {
+            ($field: ident) => {
+                if let Some(value) = self.$field {
+                    cfg.$field = value;
+                }
+            };
+        }
+        apply!(block_reward);
+        apply!(min_stake_amount);
+        apply!(service_award_per_epoch);
+        apply!(max_slot_count);
+    }
+
+    /// Merges `other` on top of this change.
+    pub fn merge(&mut self, other: &ParameterChange) {
+        macro_rules! merge {
+            ($field: ident) => {
+                if other.$field.is_some() {
+                    self.$field = other.$field;
+                }
+            };
+        }
+        merge!(block_reward);
+        merge!(min_stake_amount);
+        merge!(service_award_per_epoch);
+        merge!(max_slot_count);
+    }
+}
+
+impl Hashable for ParameterChange {
+    fn hash(&self, state: &mut Hasher) {
+        "ParameterChange".hash(state);
+        macro_rules! hash {
+            ($field: ident) => {
+                if let Some(value) = self.$field {
+                    stringify!($field).hash(state);
+                    value.hash(state);
+                }
+            };
+        }
+        hash!(block_reward);
+        hash!(min_stake_amount);
+        hash!(service_award_per_epoch);
+        hash!(max_slot_count);
+    }
+}
+
+/// Voting state of parameter changes.
+///
+/// Every macro block is signed by a supermajority of validators, so a proposal
+/// included into a macro block header counts as one vote. A proposal is activated
+/// after it was included into `ChainConfig::governance_epochs` consecutive macro blocks.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Governance {
+    /// Activated parameter changes.
+    pub active: ParameterChange,
+    /// The proposal being voted.
+    pub pending: Option<ParameterChange>,
+    /// The number of consecutive epochs `pending` was voted for.
+    pub votes: u64,
+}
+
+impl Governance {
+    /// Counts the vote of a macro block.
+    /// Returns the parameter change if it was activated by this block.
+    pub fn on_macro_block(
+        &mut self,
+        epoch: u64,
+        proposal: &Option<ParameterChange>,
+        governance_epochs: u64,
+    ) -> Option<ParameterChange> {
+        let proposal = match proposal {
+            Some(proposal) => proposal,
+            None => {
+                self.pending = None;
+                self.votes = 0;
+                return None;
+            }
+        };
+        if self.pending.as_ref() != Some(proposal) {
+            self.pending = Some(proposal.clone());
+            self.votes = 0;
+        }
+        self.votes += 1;
+        info!(
+            "Voted for parameter change: epoch={}, proposal={:?}, votes={}, required={}",
+            epoch, proposal, self.votes, governance_epochs
+        );
+        if self.votes < governance_epochs {
+            return None;
+        }
+        let proposal = self.pending.take().unwrap();
+        self.votes = 0;
+        self.active.merge(&proposal);
+        info!(
+            "Activated parameter change: epoch={}, change={:?}, active={:?}",
+            epoch, proposal, self.active
+        );
+        Some(proposal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn voting() {
+        let change = ParameterChange {
+            block_reward: Some(10),
+            ..Default::default()
+        };
+        let other = ParameterChange {
+            max_slot_count: Some(20),
+            ..Default::default()
+        };
+        let mut governance = Governance::default();
+        assert_eq!(governance.on_macro_block(1, &Some(change.clone()), 3), None);
+        assert_eq!(governance.on_macro_block(2, &Some(change.clone()), 3), None);
+        // Missing vote resets the counter.
+        assert_eq!(governance.on_macro_block(3, &None, 3), None);
+        assert_eq!(governance.votes, 0);
+        assert_eq!(governance.on_macro_block(4, &Some(change.clone()), 3), None);
+        // Other proposal resets the counter.
+        assert_eq!(governance.on_macro_block(5, &Some(other.clone()), 3), None);
+        assert_eq!(governance.votes, 1);
+        assert_eq!(governance.on_macro_block(6, &Some(other.clone()), 3), None);
+        assert_eq!(
+            governance.on_macro_block(7, &Some(other.clone()), 3),
+            Some(other.clone())
+        );
+        assert_eq!(governance.active, other);
+        assert_eq!(governance.pending, None);
+
+        let mut cfg = ChainConfig::default();
+        governance.active.apply(&mut cfg);
+        assert_eq!(cfg.max_slot_count, 20);
+        assert_eq!(cfg.block_reward, ChainConfig::default().block_reward);
+    }
+
+    #[test]
+    fn validate() {
+        assert!(ParameterChange::default().validate().is_err());
+        let change = ParameterChange {
+            min_stake_amount: Some(0),
+            ..Default::default()
+        };
+        assert!(change.validate().is_err());
+        let change = ParameterChange {
+            min_stake_amount: Some(1),
+            ..Default::default()
+        };
+        assert!(change.validate().is_ok());
+    }
+}
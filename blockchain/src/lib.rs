@@ -31,6 +31,9 @@ mod config;
 pub mod election;
 mod error;
 mod escrow;
+mod governance;
+mod light;
+mod liveness;
 mod merkle;
 mod metrics;
 mod multisignature;
@@ -38,36 +41,71 @@ pub mod mvcc;
 mod output;
 pub mod protos;
 mod slashing;
+mod snapshot;
 pub mod test;
 mod timestamp;
 mod transaction;
+mod utxo_tree;
 mod validation;
 pub mod view_changes;
 
-pub use crate::awards::ValidatorAwardState;
+pub use crate::awards::{AwardDraw, ValidatorAwardState};
 pub use crate::block::*;
 pub use crate::blockchain::*;
 pub use crate::config::*;
 pub use crate::election::{mix, ElectionInfo, ElectionResult};
 pub use crate::error::*;
 pub use crate::escrow::*;
+pub use crate::governance::{Governance, ParameterChange};
+pub use crate::light::*;
+pub use crate::liveness::{JailedValidator, Liveness};
 pub use crate::merkle::*;
 pub use crate::multisignature::*;
 pub use crate::output::*;
 pub use crate::slashing::*;
+pub use crate::snapshot::{MacroBlockCertificate, StateSnapshot};
 pub use crate::timestamp::Timestamp;
 pub use crate::transaction::*;
 
 use failure::{format_err, Error};
+use std::fs;
+use std::path::Path;
 use stegos_serialization::traits::ProtoConvert;
 
-pub fn chain_to_prefix(network: &str) -> &'static str {
+/// Returns the address prefix of a built-in chain.
+pub fn chain_to_prefix(network: &str) -> Result<&'static str, Error> {
     match network {
-        "mainnet" => "stg",
-        "testnet" => "stt",
-        "devnet" => "str",
-        "dev" => "dev",
-        e => panic!("Unexpected prefix name = {}", e),
+        "mainnet" => Ok("stg"),
+        "testnet" => Ok("stt"),
+        "devnet" => Ok("str"),
+        "dev" => Ok("dev"),
+        e => Err(format_err!("Unknown chain: {}", e)),
+    }
+}
+
+/// Checks that the address prefix can be used as a bech32 human-readable part.
+pub fn validate_prefix(prefix: &str) -> Result<(), Error> {
+    if prefix.is_empty()
+        || prefix.len() > 16
+        || !prefix
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+    {
+        return Err(format_err!(
+            "Invalid address prefix '{}': expected 1-16 lowercase letters or digits",
+            prefix
+        ));
+    }
+    Ok(())
+}
+
+/// Loads a genesis block from the binary file.
+pub fn load_genesis(path: &Path) -> Result<MacroBlock, Error> {
+    let buffer =
+        fs::read(path).map_err(|e| format_err!("Failed to read genesis {:?}: {}", path, e))?;
+    match Block::from_buffer(&buffer)? {
+        Block::MacroBlock(genesis) => Ok(genesis),
+        Block::MicroBlock(_) => Err(format_err!("Genesis {:?} is not a macro block", path)),
     }
 }
 
@@ -0,0 +1,654 @@
+//! Light Blockchain.
+
+//
+// Copyright (c) 2019 Stegos AG
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use crate::block::{MacroBlock, MacroBlockHeader, MicroBlock, MicroBlockHeader, StakersGroup};
+use crate::blockchain::LSN;
+use crate::config::ChainConfig;
+use crate::election::{self, mix};
+use crate::error::{BlockError, BlockchainError, LightError};
+use crate::escrow::Escrow;
+use crate::merkle::{Merkle, MerkleProof};
+use crate::multisignature::check_multi_signature;
+use crate::output::{Canary, Output};
+use crate::timestamp::Timestamp;
+use bit_vec::BitVec;
+use byteorder::{BigEndian, ByteOrder};
+use log::*;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use stegos_crypto::hash::{Hash, Hashable, Hasher};
+use stegos_crypto::pbc;
+use stegos_serialization::traits::ProtoConvert;
+
+//--------------------------------------------------------------------------------------------------
+// Light Blocks.
+//--------------------------------------------------------------------------------------------------
+
+/// A macro block without outputs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LightMacroBlock {
+    /// Header of the macro block.
+    pub header: MacroBlockHeader,
+    /// BLS (multi-)signature.
+    pub multisig: pbc::Signature,
+    /// Bitmap of signers in the multi-signature.
+    #[serde(deserialize_with = "stegos_crypto::utils::deserialize_bitvec")]
+    #[serde(serialize_with = "stegos_crypto::utils::serialize_bitvec")]
+    pub multisigmap: BitVec,
+    /// Validators elected by this block.
+    pub validators: StakersGroup,
+    /// Hashes of spent outputs.
+    pub input_hashes: Vec<Hash>,
+    /// Canaries of created outputs, in the same order as outputs.
+    pub canaries: Vec<Canary>,
+}
+
+/// A micro block without transactions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LightMicroBlock {
+    /// Header of the micro block.
+    pub header: MicroBlockHeader,
+    /// BLS signature by leader.
+    pub sig: pbc::Signature,
+    /// Hashes of spent outputs.
+    pub input_hashes: Vec<Hash>,
+    /// Canaries of created outputs, in the same order as outputs.
+    pub canaries: Vec<Canary>,
+}
+
+/// A block without outputs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+pub enum LightBlock {
+    LightMacroBlock(LightMacroBlock),
+    LightMicroBlock(LightMicroBlock),
+}
+
+/// An output together with a proof of inclusion into a block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputProof {
+    /// The output.
+    pub output: Output,
+    /// Proof of membership in outputs_range_hash of the block.
+    pub proof: MerkleProof,
+}
+
+impl LightMacroBlock {
+    /// Create a light block from a macro block.
+    /// `validators` must be the validators elected by this block.
+    pub fn new(block: MacroBlock, validators: StakersGroup) -> LightMacroBlock {
+        let canaries = block.outputs.iter().map(Output::canary).collect();
+        LightMacroBlock {
+            header: block.header,
+            multisig: block.multisig,
+            multisigmap: block.multisigmap,
+            validators,
+            input_hashes: block.inputs,
+            canaries,
+        }
+    }
+
+    /// Check the body of the block against the header.
+    pub fn validate_body(&self) -> Result<(), BlockchainError> {
+        let block_hash = Hash::digest(self);
+        let inputs_range_hash = Merkle::root_hash_from_array(&self.input_hashes);
+        let canary_hashes: Vec<Hash> = self.canaries.iter().map(Hash::digest).collect();
+        let canaries_range_hash = Merkle::root_hash_from_array(&canary_hashes);
+        if self.header.inputs_len != self.input_hashes.len() as u32
+            || self.header.inputs_range_hash != inputs_range_hash
+            || self.header.outputs_len != self.canaries.len() as u32
+            || self.header.canaries_range_hash != canaries_range_hash
+        {
+            return Err(LightError::InvalidBlockBody(block_hash).into());
+        }
+        let validators_range_hash = Merkle::root_hash_from_array(&self.validators);
+        if self.header.validators_len != self.validators.len() as u32
+            || self.header.validators_range_hash != validators_range_hash
+        {
+            return Err(LightError::InvalidValidators(
+                self.header.epoch,
+                block_hash,
+                validators_range_hash,
+                self.header.validators_range_hash,
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+impl From<MicroBlock> for LightMicroBlock {
+    fn from(block: MicroBlock) -> LightMicroBlock {
+        let input_hashes = block.inputs().cloned().collect();
+        let canaries = block.outputs().map(Output::canary).collect();
+        LightMicroBlock {
+            header: block.header,
+            sig: block.sig,
+            input_hashes,
+            canaries,
+        }
+    }
+}
+
+impl LightMicroBlock {
+    /// Check the body of the block against the header.
+    pub fn validate_body(&self) -> Result<(), BlockchainError> {
+        let inputs_range_hash = Merkle::root_hash_from_array(&self.input_hashes);
+        let canary_hashes: Vec<Hash> = self.canaries.iter().map(Hash::digest).collect();
+        let canaries_range_hash = Merkle::root_hash_from_array(&canary_hashes);
+        if self.header.inputs_len != self.input_hashes.len() as u32
+            || self.header.inputs_range_hash != inputs_range_hash
+            || self.header.outputs_len != self.canaries.len() as u32
+            || self.header.canaries_range_hash != canaries_range_hash
+        {
+            let block_hash = Hash::digest(self);
+            return Err(LightError::InvalidBlockBody(block_hash).into());
+        }
+        Ok(())
+    }
+}
+
+impl Hashable for LightMacroBlock {
+    fn hash(&self, state: &mut Hasher) {
+        self.header.hash(state)
+    }
+}
+
+impl Hashable for LightMicroBlock {
+    fn hash(&self, state: &mut Hasher) {
+        self.header.hash(state)
+    }
+}
+
+impl Hashable for LightBlock {
+    fn hash(&self, state: &mut Hasher) {
+        match self {
+            LightBlock::LightMacroBlock(block) => block.hash(state),
+            LightBlock::LightMicroBlock(block) => block.hash(state),
+        }
+    }
+}
+
+impl Hashable for OutputProof {
+    fn hash(&self, state: &mut Hasher) {
+        self.output.hash(state);
+        self.proof.path.0.hash(state);
+        for sibling in &self.proof.siblings {
+            match sibling {
+                Some(sibling) => sibling.hash(state),
+                None => "None".hash(state),
+            }
+        }
+    }
+}
+
+/// Returns membership proofs for outputs of a block.
+pub(crate) fn output_proofs(
+    block_hash: &Hash,
+    outputs: &[Output],
+    output_ids: &[u32],
+) -> Result<Vec<OutputProof>, LightError> {
+    let output_hashes: Vec<Hash> = outputs.iter().map(Hash::digest).collect();
+    let tree = Merkle::from_array(&output_hashes);
+    let paths = tree.leafs();
+    let mut proofs = Vec::with_capacity(output_ids.len());
+    for output_id in output_ids {
+        let output = match outputs.get(*output_id as usize) {
+            Some(output) => output.clone(),
+            None => return Err(LightError::OutputNotFound(*block_hash, *output_id)),
+        };
+        let (_hash, path) = paths[*output_id as usize];
+        let proof = tree.proof(&path).expect("leaf exists");
+        proofs.push(OutputProof { output, proof });
+    }
+    Ok(proofs)
+}
+
+//--------------------------------------------------------------------------------------------------
+// Light Blockchain.
+//--------------------------------------------------------------------------------------------------
+
+/// The blockchain database for light nodes.
+///
+/// Stores only headers, multi-signatures, input hashes and output canaries.
+/// Macro blocks are fully verified using the chain of multi-signatures.
+/// Micro blocks are not final and kept in memory until the next macro block,
+/// which contains all inputs and outputs of the epoch.
+pub struct LightBlockchain {
+    /// Configuration of the genesis, including the hard-fork schedule.
+    genesis_cfg: ChainConfig,
+    /// Consensus rules of the current epoch.
+    cfg: ChainConfig,
+    /// Persistent storage for macro blocks.
+    database: rocksdb::DB,
+    /// In-memory index to lookup macro blocks by hash.
+    epoch_by_hash: HashMap<Hash, u64>,
+    /// Micro blocks of the current epoch.
+    micro_blocks: Vec<LightMicroBlock>,
+    /// Validators of the current epoch.
+    validators: StakersGroup,
+    /// The number of macro blocks in the blockchain.
+    epoch: u64,
+    /// A timestamp from the last macro block.
+    last_macro_block_timestamp: Timestamp,
+    /// Copy of the last macro block hash.
+    last_macro_block_hash: Hash,
+    /// Copy of the last macro block random.
+    last_macro_block_random: Hash,
+}
+
+impl LightBlockchain {
+    pub fn new(
+        genesis_cfg: ChainConfig,
+        chain_dir: &Path,
+        genesis: MacroBlock,
+    ) -> Result<LightBlockchain, BlockchainError> {
+        genesis_cfg.check()?;
+        let cfg = genesis_cfg.at_epoch(0);
+        let mut opts = rocksdb::Options::default();
+        opts.create_if_missing(true);
+        let database = rocksdb::DB::open(&opts, chain_dir)?;
+        let mut chain = LightBlockchain {
+            genesis_cfg,
+            cfg,
+            database,
+            epoch_by_hash: HashMap::new(),
+            micro_blocks: Vec::new(),
+            validators: Vec::new(),
+            epoch: 0,
+            last_macro_block_timestamp: Timestamp::UNIX_EPOCH,
+            last_macro_block_hash: Hash::digest("genesis"),
+            last_macro_block_random: Hash::digest("genesis"),
+        };
+
+        //
+        // Recovery.
+        //
+        let genesis_hash = Hash::digest(&genesis);
+        let blocks: Vec<LightMacroBlock> = chain
+            .database
+            .iterator(rocksdb::IteratorMode::Start)
+            .map(|(_, v)| LightMacroBlock::from_buffer(&*v))
+            .collect::<Result<_, _>>()?;
+        if let Some(block) = blocks.first() {
+            let hash = Hash::digest(block);
+            if hash != genesis_hash {
+                return Err(BlockchainError::IncompatibleGenesis(genesis_hash, hash));
+            }
+            for block in blocks {
+                chain.register_macro_block(block);
+            }
+            info!(
+                "Recovered light blockchain from the disk: epoch={}, last_block={}",
+                chain.epoch, chain.last_macro_block_hash
+            );
+            return Ok(chain);
+        }
+
+        //
+        // Genesis.
+        //
+        let validators = Self::genesis_validators(&chain.cfg, &genesis);
+        let genesis = LightMacroBlock::new(genesis, validators);
+        genesis.validate_body()?;
+        chain.write_macro_block(&genesis)?;
+        chain.register_macro_block(genesis);
+        info!(
+            "Initialized light blockchain: epoch={}, last_block={}",
+            chain.epoch, chain.last_macro_block_hash
+        );
+        Ok(chain)
+    }
+
+    /// Elect validators from stakes in the genesis block.
+    fn genesis_validators(cfg: &ChainConfig, genesis: &MacroBlock) -> StakersGroup {
+        let mut escrow = Escrow::new();
+        let lsn = LSN(genesis.header.epoch, 0);
+        for output in &genesis.outputs {
+            if let Output::StakeOutput(o) = output {
+                escrow.stake(
+                    lsn,
+                    Hash::digest(output),
+                    o,
+                    genesis.header.epoch,
+                    cfg.stake_epochs,
+                    cfg.unbonding_epochs,
+                );
+            }
+        }
+        election::select_validators_slots(
+            escrow.get_stakers_majority(genesis.header.epoch + 1, cfg.min_stake_amount),
+            genesis.header.random,
+            cfg.max_slot_count,
+        )
+        .validators
+    }
+
+    fn block_key(epoch: u64) -> [u8; 8] {
+        let mut bytes = [0u8; 8];
+        BigEndian::write_u64(&mut bytes, epoch);
+        bytes
+    }
+
+    /// Write macro block to the disk.
+    fn write_macro_block(&self, block: &LightMacroBlock) -> Result<(), BlockchainError> {
+        let data = block.into_buffer()?;
+        self.database
+            .put(&Self::block_key(block.header.epoch), &data)?;
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Accessors.
+    //----------------------------------------------------------------------------------------------
+
+    /// Returns consensus rules of the current epoch.
+    pub fn cfg(&self) -> &ChainConfig {
+        &self.cfg
+    }
+
+    /// Return the current epoch.
+    #[inline]
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Return the current offset.
+    #[inline]
+    pub fn offset(&self) -> u32 {
+        self.micro_blocks.len() as u32
+    }
+
+    /// Returns validators of the current epoch.
+    pub fn validators(&self) -> &StakersGroup {
+        &self.validators
+    }
+
+    /// Return the last block hash.
+    pub fn last_block_hash(&self) -> Hash {
+        match self.micro_blocks.last() {
+            Some(block) => Hash::digest(block),
+            None => self.last_macro_block_hash,
+        }
+    }
+
+    /// Return the last block timestamp.
+    pub fn last_block_timestamp(&self) -> Timestamp {
+        match self.micro_blocks.last() {
+            Some(block) => block.header.timestamp,
+            None => self.last_macro_block_timestamp,
+        }
+    }
+
+    /// Return the last macro block hash.
+    pub fn last_macro_block_hash(&self) -> Hash {
+        self.last_macro_block_hash
+    }
+
+    /// Return the last macro block timestamp.
+    pub fn last_macro_block_timestamp(&self) -> Timestamp {
+        self.last_macro_block_timestamp
+    }
+
+    /// Return the last random value.
+    fn last_random(&self) -> Hash {
+        match self.micro_blocks.last() {
+            Some(block) => block.header.random.rand,
+            None => self.last_macro_block_random,
+        }
+    }
+
+    /// Get a macro block by epoch.
+    pub fn light_macro_block(
+        &self,
+        epoch: u64,
+    ) -> Result<Option<LightMacroBlock>, BlockchainError> {
+        match self.database.get(&Self::block_key(epoch))? {
+            Some(buffer) => Ok(Some(LightMacroBlock::from_buffer(&buffer)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get a micro block of the current epoch by offset.
+    pub fn light_micro_block(&self, epoch: u64, offset: u32) -> Option<&LightMicroBlock> {
+        if epoch != self.epoch {
+            return None;
+        }
+        self.micro_blocks.get(offset as usize)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Blocks.
+    //----------------------------------------------------------------------------------------------
+
+    /// Add a macro block to the blockchain.
+    /// Drops all micro blocks of the epoch.
+    pub fn push_macro_block(&mut self, block: LightMacroBlock) -> Result<(), BlockchainError> {
+        let epoch = block.header.epoch;
+        let block_hash = Hash::digest(&block);
+
+        // Check block version.
+        let version = self.genesis_cfg.version_at_epoch(epoch);
+        if block.header.version != version {
+            return Err(BlockError::InvalidBlockVersion(
+                epoch,
+                block_hash,
+                block.header.version,
+                version,
+            )
+            .into());
+        }
+
+        // Check epoch.
+        if epoch != self.epoch {
+            return Err(BlockError::OutOfOrderMacroBlock(block_hash, epoch, self.epoch).into());
+        }
+
+        // Check previous hash.
+        if block.header.previous != self.last_macro_block_hash {
+            return Err(BlockError::InvalidMacroBlockPreviousHash(
+                epoch,
+                block_hash,
+                block.header.previous,
+                self.last_macro_block_hash,
+            )
+            .into());
+        }
+
+        // Check VRF.
+        let seed = mix(self.last_macro_block_random, block.header.view_change);
+        if !pbc::validate_VRF_source(&block.header.random, &block.header.pkey, &seed).is_ok() {
+            return Err(BlockError::IncorrectRandom(epoch, block_hash).into());
+        }
+
+        // Check multi-signature.
+        let total_slots = self.validators.iter().map(|(_, slots)| *slots).sum();
+        check_multi_signature(
+            &block_hash,
+            &block.multisig,
+            &block.multisigmap,
+            &self.validators,
+            total_slots,
+        )
+        .map_err(|e| BlockError::InvalidBlockSignature(e, epoch, block_hash))?;
+
+        // Check body.
+        block.validate_body()?;
+
+        self.write_macro_block(&block)?;
+        self.register_macro_block(block);
+        info!(
+            "Registered a light macro block: epoch={}, block={}",
+            epoch, block_hash
+        );
+        Ok(())
+    }
+
+    fn register_macro_block(&mut self, block: LightMacroBlock) {
+        let block_hash = Hash::digest(&block);
+        assert_eq!(block.header.epoch, self.epoch);
+        self.epoch_by_hash.insert(block_hash, block.header.epoch);
+        self.micro_blocks.clear();
+        self.validators = block.validators;
+        self.epoch = block.header.epoch + 1;
+        self.last_macro_block_timestamp = block.header.timestamp;
+        self.last_macro_block_hash = block_hash;
+        self.last_macro_block_random = block.header.random.rand;
+        self.cfg = self.genesis_cfg.at_epoch(self.epoch);
+    }
+
+    /// Add a micro block to the blockchain.
+    ///
+    /// Micro blocks are not final - only the signature of a validator
+    /// of the current epoch is checked.
+    pub fn push_micro_block(&mut self, block: LightMicroBlock) -> Result<(), BlockchainError> {
+        let epoch = block.header.epoch;
+        let offset = block.header.offset;
+        let block_hash = Hash::digest(&block);
+
+        // Check block version.
+        let version = self.genesis_cfg.version_at_epoch(epoch);
+        if block.header.version != version {
+            return Err(BlockError::InvalidBlockVersion(
+                epoch,
+                block_hash,
+                block.header.version,
+                version,
+            )
+            .into());
+        }
+
+        // Check the block order.
+        if self.offset() >= self.cfg.micro_blocks_in_epoch {
+            return Err(BlockchainError::ExpectedMacroBlock(
+                self.epoch,
+                self.offset(),
+                block_hash,
+            ));
+        }
+
+        // Check epoch and offset.
+        if epoch != self.epoch || offset != self.offset() {
+            return Err(BlockError::OutOfOrderMicroBlock(
+                block_hash,
+                epoch,
+                offset,
+                self.epoch,
+                self.offset(),
+            )
+            .into());
+        }
+
+        // Check previous hash.
+        let previous_hash = self.last_block_hash();
+        if block.header.previous != previous_hash {
+            return Err(BlockError::InvalidMicroBlockPreviousHash(
+                epoch,
+                offset,
+                block_hash,
+                block.header.previous,
+                previous_hash,
+            )
+            .into());
+        }
+
+        // Check signature.
+        let leader = block.header.pkey;
+        if !self.validators.iter().any(|(pkey, _slots)| pkey == &leader) {
+            return Err(LightError::UnknownLeader(epoch, offset, block_hash, leader).into());
+        }
+        if let Err(_e) = pbc::check_hash(&block_hash, &block.sig, &leader) {
+            return Err(BlockError::InvalidLeaderSignature(epoch, block_hash).into());
+        }
+
+        // Check random.
+        let seed = mix(self.last_random(), block.header.view_change);
+        if !pbc::validate_VRF_source(&block.header.random, &leader, &seed).is_ok() {
+            return Err(BlockError::IncorrectRandom(epoch, block_hash).into());
+        }
+
+        // Check body.
+        block.validate_body()?;
+
+        self.micro_blocks.push(block);
+        debug!(
+            "Registered a light micro block: epoch={}, offset={}, block={}",
+            epoch, offset, block_hash
+        );
+        Ok(())
+    }
+
+    /// Remove the last micro block from the blockchain.
+    pub fn pop_micro_block(&mut self) -> Option<LightMicroBlock> {
+        self.micro_blocks.pop()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Outputs.
+    //----------------------------------------------------------------------------------------------
+
+    /// Returns the canary of an output and outputs_range_hash of the block.
+    fn canary(&self, block_hash: &Hash, output_id: u32) -> Result<(Canary, Hash), BlockchainError> {
+        let (canaries, outputs_range_hash) = if let Some(epoch) = self.epoch_by_hash.get(block_hash)
+        {
+            let block = self
+                .light_macro_block(*epoch)?
+                .expect("Corrupted epoch_by_hash");
+            (block.canaries, block.header.outputs_range_hash)
+        } else if let Some(block) = self
+            .micro_blocks
+            .iter()
+            .find(|b| &Hash::digest(*b) == block_hash)
+        {
+            (block.canaries.clone(), block.header.outputs_range_hash)
+        } else {
+            return Err(LightError::BlockNotFound(*block_hash).into());
+        };
+        match canaries.into_iter().nth(output_id as usize) {
+            Some(canary) => Ok((canary, outputs_range_hash)),
+            None => Err(LightError::OutputNotFound(*block_hash, output_id).into()),
+        }
+    }
+
+    /// Check an output received from a full node.
+    pub fn validate_output_proof(
+        &self,
+        block_hash: &Hash,
+        output_id: u32,
+        proof: &OutputProof,
+    ) -> Result<(), BlockchainError> {
+        let (canary, outputs_range_hash) = self.canary(block_hash, output_id)?;
+        let output_hash = Hash::digest(&proof.output);
+        if Hash::digest(&proof.output.canary()) != Hash::digest(&canary)
+            || proof
+                .proof
+                .validate(&output_hash, &outputs_range_hash)
+                .is_err()
+        {
+            return Err(LightError::InvalidOutputProof(*block_hash, output_id).into());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,329 @@
+//
+// Copyright (c) 2019 Stegos AG
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Liveness tracking and downtime jailing of validators.
+
+use crate::block::StakersGroup;
+use bit_vec::BitVec;
+use log::{info, warn};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use stegos_crypto::pbc;
+
+/// A validator excluded from elections for downtime.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JailedValidator {
+    /// The epoch of the macro block which jailed the validator.
+    pub epoch: u64,
+    /// The first epoch the validator can be elected again.
+    pub release_epoch: u64,
+}
+
+/// Liveness state of validators.
+///
+/// A validator misses an epoch if it failed its leader slot or didn't sign the macro block.
+/// A validator which missed `ChainConfig::jail_missed_epochs` consecutive epochs is
+/// excluded from the next `ChainConfig::jail_epochs` elections. Misses of a jailed validator
+/// are still counted, but don't extend the jail.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Liveness {
+    /// The number of consecutive epochs missed by validators.
+    pub missed: BTreeMap<pbc::PublicKey, u64>,
+    /// Jailed validators.
+    pub jailed: BTreeMap<pbc::PublicKey, JailedValidator>,
+}
+
+impl Liveness {
+    /// Returns true if the validator cannot be elected for the specified epoch.
+    pub fn is_jailed(&self, validator: &pbc::PublicKey, epoch: u64) -> bool {
+        match self.jailed.get(validator) {
+            // The epoch after the jail was elected before it.
+            Some(jailed) => jailed.epoch + 1 < epoch && epoch < jailed.release_epoch,
+            None => false,
+        }
+    }
+
+    /// Removes jailed validators from the list of election candidates for the specified epoch.
+    /// Keeps the original list if all candidates are jailed.
+    pub fn filter_stakers(&self, stakers: StakersGroup, epoch: u64) -> StakersGroup {
+        let free: StakersGroup = stakers
+            .iter()
+            .filter(|(validator, _)| !self.is_jailed(validator, epoch))
+            .cloned()
+            .collect();
+        if free.is_empty() && !stakers.is_empty() {
+            warn!("All stakers are jailed, ignoring the jail: epoch={}", epoch);
+            return stakers;
+        }
+        free
+    }
+
+    /// Counts the activity of validators of the epoch finalized by a macro block.
+    /// `activity_map` and `multisigmap` are indexed by `validators`.
+    /// Returns validators jailed by this block.
+    ///
+    /// Must be called after the election of the block, because the signers of
+    /// the block are unknown during the election.
+    pub fn on_macro_block(
+        &mut self,
+        epoch: u64,
+        validators: &StakersGroup,
+        activity_map: &BitVec,
+        multisigmap: &BitVec,
+        jail_missed_epochs: u64,
+        jail_epochs: u64,
+    ) -> Vec<pbc::PublicKey> {
+        // Validators can be elected again starting from the next election.
+        self.jailed
+            .retain(|_validator, jailed| jailed.release_epoch > epoch + 2);
+        if jail_missed_epochs == 0 {
+            self.missed.clear();
+            return Vec::new();
+        }
+
+        let mut missed = BTreeMap::new();
+        let mut jailed = Vec::new();
+        for (id, (validator, _slots)) in validators.iter().enumerate() {
+            let active =
+                activity_map.get(id).unwrap_or(false) && multisigmap.get(id).unwrap_or(false);
+            if active {
+                continue;
+            }
+            let count = self.missed.get(validator).cloned().unwrap_or(0) + 1;
+            missed.insert(*validator, count);
+            if count < jail_missed_epochs {
+                continue;
+            }
+            if let Some(jailed) = self.jailed.get(validator) {
+                // Jailed validator is still in the elected group of the next epoch.
+                info!(
+                    "Jailed validator missed an epoch: validator={}, epoch={}, missed_epochs={}, release_epoch={}",
+                    validator, epoch, count, jailed.release_epoch
+                );
+                continue;
+            }
+            // Jailed validator is still in the elected group of the next epoch,
+            // jail starts from the next election.
+            let release_epoch = epoch + 2 + jail_epochs;
+            info!(
+                "Jailed validator for downtime: validator={}, epoch={}, missed_epochs={}, release_epoch={}",
+                validator, epoch, count, release_epoch
+            );
+            self.jailed.insert(
+                *validator,
+                JailedValidator {
+                    epoch,
+                    release_epoch,
+                },
+            );
+            jailed.push(*validator);
+        }
+        // Only consecutive epochs in the validator group are counted.
+        self.missed = missed;
+        jailed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JAIL_MISSED_EPOCHS: u64 = 2;
+    const JAIL_EPOCHS: u64 = 3;
+
+    struct Validators {
+        pkey1: pbc::PublicKey,
+        pkey2: pbc::PublicKey,
+        group: StakersGroup,
+        all: BitVec,
+        first_only: BitVec,
+    }
+
+    fn validators() -> Validators {
+        let (_, pkey1) = pbc::make_random_keys();
+        let (_, pkey2) = pbc::make_random_keys();
+        let group: StakersGroup = vec![(pkey1, 1), (pkey2, 1)];
+        let all = BitVec::from_elem(2, true);
+        let mut first_only = BitVec::from_elem(2, false);
+        first_only.set(0, true);
+        Validators {
+            pkey1,
+            pkey2,
+            group,
+            all,
+            first_only,
+        }
+    }
+
+    /// Jails the second validator by the macro block of epoch 2.
+    fn jail_second(v: &Validators) -> Liveness {
+        let mut liveness = Liveness::default();
+        assert!(liveness
+            .on_macro_block(
+                1,
+                &v.group,
+                &v.all,
+                &v.first_only,
+                JAIL_MISSED_EPOCHS,
+                JAIL_EPOCHS
+            )
+            .is_empty());
+        assert_eq!(
+            liveness.on_macro_block(
+                2,
+                &v.group,
+                &v.first_only,
+                &v.all,
+                JAIL_MISSED_EPOCHS,
+                JAIL_EPOCHS
+            ),
+            vec![v.pkey2]
+        );
+        liveness
+    }
+
+    #[test]
+    fn reset_on_activity() {
+        let v = validators();
+        let mut liveness = Liveness::default();
+        assert!(liveness
+            .on_macro_block(1, &v.group, &v.all, &v.first_only, 3, JAIL_EPOCHS)
+            .is_empty());
+        assert_eq!(liveness.missed.get(&v.pkey2), Some(&1));
+        assert_eq!(liveness.missed.get(&v.pkey1), None);
+
+        // Both the leader slot and the signature are required.
+        assert!(liveness
+            .on_macro_block(2, &v.group, &v.first_only, &v.all, 3, JAIL_EPOCHS)
+            .is_empty());
+        assert_eq!(liveness.missed.get(&v.pkey2), Some(&2));
+
+        // Activity resets the counter.
+        assert!(liveness
+            .on_macro_block(3, &v.group, &v.all, &v.all, 3, JAIL_EPOCHS)
+            .is_empty());
+        assert!(liveness.missed.is_empty());
+
+        // Epochs out of the validator group reset the counter.
+        assert!(liveness
+            .on_macro_block(4, &v.group, &v.all, &v.first_only, 3, JAIL_EPOCHS)
+            .is_empty());
+        assert!(liveness
+            .on_macro_block(5, &vec![(v.pkey1, 1)], &v.all, &v.all, 3, JAIL_EPOCHS)
+            .is_empty());
+        assert!(liveness.missed.is_empty());
+
+        // Jailing is disabled.
+        assert!(liveness
+            .on_macro_block(6, &v.group, &v.all, &v.first_only, 0, JAIL_EPOCHS)
+            .is_empty());
+        assert!(liveness.missed.is_empty());
+    }
+
+    #[test]
+    fn jail_threshold() {
+        let v = validators();
+        let liveness = jail_second(&v);
+        assert_eq!(liveness.missed.get(&v.pkey2), Some(&2));
+        assert_eq!(
+            liveness.jailed.get(&v.pkey2),
+            Some(&JailedValidator {
+                epoch: 2,
+                release_epoch: 2 + 2 + JAIL_EPOCHS,
+            })
+        );
+        assert!(!liveness.jailed.contains_key(&v.pkey1));
+
+        // The next epoch was elected before jailing.
+        assert!(!liveness.is_jailed(&v.pkey2, 3));
+        for epoch in 4..7 {
+            assert!(liveness.is_jailed(&v.pkey2, epoch));
+        }
+        assert!(!liveness.is_jailed(&v.pkey2, 7));
+        assert!(!liveness.is_jailed(&v.pkey1, 4));
+    }
+
+    #[test]
+    fn jailed_keeps_missed() {
+        let v = validators();
+        let mut liveness = jail_second(&v);
+        let jailed = liveness.jailed[&v.pkey2];
+
+        // Misses in the last elected epoch are counted, but don't extend the jail.
+        assert!(liveness
+            .on_macro_block(
+                3,
+                &v.group,
+                &v.all,
+                &v.first_only,
+                JAIL_MISSED_EPOCHS,
+                JAIL_EPOCHS
+            )
+            .is_empty());
+        assert_eq!(liveness.missed.get(&v.pkey2), Some(&3));
+        assert_eq!(liveness.jailed.get(&v.pkey2), Some(&jailed));
+    }
+
+    #[test]
+    fn release() {
+        let v = validators();
+        let mut liveness = jail_second(&v);
+
+        // The record is removed after the last election of the jail.
+        for epoch in 3..5 {
+            liveness.on_macro_block(
+                epoch,
+                &vec![(v.pkey1, 1)],
+                &v.all,
+                &v.all,
+                JAIL_MISSED_EPOCHS,
+                JAIL_EPOCHS,
+            );
+            assert!(liveness.jailed.contains_key(&v.pkey2));
+        }
+        liveness.on_macro_block(
+            5,
+            &vec![(v.pkey1, 1)],
+            &v.all,
+            &v.all,
+            JAIL_MISSED_EPOCHS,
+            JAIL_EPOCHS,
+        );
+        assert!(!liveness.jailed.contains_key(&v.pkey2));
+        assert!(liveness.missed.is_empty());
+        let stakers = liveness.filter_stakers(v.group.clone(), 6);
+        assert_eq!(stakers, v.group);
+    }
+
+    #[test]
+    fn all_jailed_fallback() {
+        let v = validators();
+        let liveness = jail_second(&v);
+        let stakers = liveness.filter_stakers(v.group.clone(), 4);
+        assert_eq!(stakers, vec![(v.pkey1, 1)]);
+
+        // The last validator is never jailed out.
+        let stakers = liveness.filter_stakers(vec![(v.pkey2, 1)], 4);
+        assert_eq!(stakers, vec![(v.pkey2, 1)]);
+        let stakers = liveness.filter_stakers(Vec::new(), 4);
+        assert!(stakers.is_empty());
+    }
+}
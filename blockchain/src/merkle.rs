@@ -22,6 +22,7 @@
 // SOFTWARE.
 
 use failure::Fail;
+use serde_derive::{Deserialize, Serialize};
 use std::fmt;
 use std::vec::Vec;
 use stegos_crypto::hash::{Hash, Hashable, Hasher};
@@ -105,8 +106,83 @@ type Path = u32;
 /// 0 bit - go to the left subtree
 /// 1 bit - go to the right subtree
 /// Stored in inverted order - from leaf to root
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
-pub struct MerklePath(Path);
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct MerklePath(pub(crate) Path);
+
+/// Proof of membership of an element in Merkle Tree.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct MerkleProof {
+    /// Path from the root to the leaf.
+    pub path: MerklePath,
+    /// Hashes of sibling nodes, from the root to the leaf.
+    /// None if the node doesn't have the right sibling.
+    pub siblings: Vec<Option<Hash>>,
+}
+
+impl MerkleProof {
+    /// Calculate the root hash of a tree containing the value.
+    pub fn root_hash<T: Hashable>(&self, value: &T) -> Hash {
+        let mut hasher = Hasher::new();
+        LEAF_PREFIX.hash(&mut hasher);
+        value.hash(&mut hasher);
+        let mut hash = hasher.result();
+
+        // Traverse from the leaf to the root.
+        for (h, sibling) in self.siblings.iter().enumerate().rev() {
+            // true - left subtree, false - right subtree
+            let left_direction = (self.path.0 >> h) & 1 == 0;
+            let mut hasher = Hasher::new();
+            INNER_PREFIX.hash(&mut hasher);
+            match sibling {
+                Some(sibling) if left_direction => {
+                    hash.hash(&mut hasher);
+                    sibling.hash(&mut hasher);
+                }
+                Some(sibling) => {
+                    sibling.hash(&mut hasher);
+                    hash.hash(&mut hasher);
+                }
+                None => {
+                    // Node is paired with itself if it doesn't have the right sibling.
+                    hash.hash(&mut hasher);
+                    hash.hash(&mut hasher);
+                }
+            }
+            hash = hasher.result();
+        }
+        hash
+    }
+
+    /// Extend the proof to a parent tree, where the root of this proof is
+    /// an inner node at the depth `siblings.len()` on `path`.
+    pub(crate) fn with_parent(self, path: Path, siblings: Vec<Option<Hash>>) -> MerkleProof {
+        let path = MerklePath(path | (self.path.0 << siblings.len()));
+        let mut siblings = siblings;
+        siblings.extend(self.siblings);
+        MerkleProof { path, siblings }
+    }
+
+    /// Check that the value is a member of a tree with the specified root hash.
+    pub fn validate<T: Hashable>(&self, value: &T, root_hash: &Hash) -> Result<(), MerkleError> {
+        if self.siblings.len() > Height::max_value() as usize {
+            return Err(MerkleError::InvalidStructure);
+        }
+        let check_hash = self.root_hash(value);
+        if *root_hash != check_hash {
+            return Err(MerkleError::ValidationError(*root_hash, check_hash));
+        }
+        Ok(())
+    }
+}
+
+/// Calculate the hash of an inner node.
+pub(crate) fn inner_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Hasher::new();
+    INNER_PREFIX.hash(&mut hasher);
+    left.hash(&mut hasher);
+    right.hash(&mut hasher);
+    hasher.result()
+}
 
 // -------------------------------------
 
@@ -307,6 +383,58 @@ impl<T: Hashable + fmt::Debug> Merkle<T> {
         }
     }
 
+    /// Create a membership proof for an element by path.
+    pub fn proof(&self, path: &MerklePath) -> Option<MerkleProof> {
+        let mut node = &self.root;
+        let mut siblings: Vec<Option<Hash>> = Vec::new();
+        let mut p = path.0;
+
+        // Traverse via inner nodes
+        loop {
+            // true - go left, false - go right
+            let left_direction = (p & 1) == 0;
+            p >>= 1;
+
+            node = match **node {
+                Node {
+                    left: Some(ref left),
+                    ref right,
+                    value: None, // node is not a leaf
+                    ..
+                } if left_direction => {
+                    siblings.push(right.as_ref().map(|right| right.hash));
+                    left
+                }
+                Node {
+                    left: Some(ref left),
+                    right: Some(ref right),
+                    value: None, // node is not a leaf
+                    ..
+                } if !left_direction => {
+                    siblings.push(Some(left.hash));
+                    right
+                }
+                Node {
+                    left: None,
+                    right: None,
+                    value: Some(_),
+                    ..
+                } => {
+                    let proof = MerkleProof {
+                        path: *path,
+                        siblings,
+                    };
+                    return Some(proof);
+                }
+                Node {
+                    value: None, // node is not a leaf
+                    ..
+                } => return None, // missing subtree
+                _ => unreachable!(), // a leaf, doesn't happen in this algorithm
+            };
+        }
+    }
+
     // A recursive helper for prune_r().
     // Although the pruning algorithm is straightforward and doesn't require recursion
     // for implementation, we had to use it here in order to deal with Rust's borrow checker.
@@ -1297,4 +1425,37 @@ pub mod tests {
         assert_eq!(val3, data[3]);
         check_serialize_rt(&tree);
     }
+
+    #[test]
+    fn proofs() {
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        for n in 1..18 {
+            let data: Vec<u32> = (0..n).collect();
+            let tree = Merkle::from_array(&data);
+            let root_hash = tree.roothash().clone();
+            for (value, path) in tree.leafs() {
+                let proof = tree.proof(&path).expect("leaf exists");
+                proof.validate(value, &root_hash).expect("proof is valid");
+                let other = value + 1;
+                proof
+                    .validate(&other, &root_hash)
+                    .expect_err("proof is invalid");
+            }
+        }
+
+        // Missing elements.
+        let data: [u32; 5] = [1, 2, 3, 4, 5];
+        let mut tree = Merkle::from_array(&data);
+        let paths = tree
+            .leafs()
+            .iter()
+            .map(|(_elem, path)| *path)
+            .collect::<Vec<MerklePath>>();
+        let root_hash = tree.roothash().clone();
+        tree.prune(&paths[1]).unwrap();
+        assert!(tree.proof(&paths[1]).is_none());
+        let proof = tree.proof(&paths[2]).unwrap();
+        proof.validate(&data[2], &root_hash).unwrap();
+    }
 }
@@ -26,6 +26,7 @@ use crate::BlockchainError;
 use failure::{Error, Fail};
 use rand::random;
 use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::mem::transmute;
 use stegos_crypto::bulletproofs::{fee_a, make_range_proof, validate_range_proof, BulletProof};
 use stegos_crypto::hash::{Hash, Hashable, Hasher, HASH_SIZE};
@@ -37,15 +38,31 @@ use stegos_crypto::scc::{
 use stegos_crypto::CryptoError;
 
 /// Size of the canary (hint) string stored in encrypted payload.
-const PAYMENT_PAYLOAD_CANARY_LEN: usize = 4;
+pub(crate) const PAYMENT_PAYLOAD_CANARY_LEN: usize = 4;
 
 /// Exact size of encrypted payload of PaymentOutput.
 const PAYMENT_PAYLOAD_LEN: usize = 1024;
 
 /// Maximum length of data field of encrypted payload of PaymentOutput.
-/// Equals to PAYMENT_PAYLOAD_LEN - canary.len() - delta.len() - gamma.len() - amount.len() - spenderSignature.len().
+/// Equals to PAYMENT_PAYLOAD_LEN - canary.len() - delta.len() - gamma.len() - amount.len() - spenderSignature.len() - lockedTimestamp.len().
 pub const PAYMENT_DATA_LEN: usize =
-    PAYMENT_PAYLOAD_LEN - PAYMENT_PAYLOAD_CANARY_LEN - 32 - 32 - 8 - 64;
+    PAYMENT_PAYLOAD_LEN - PAYMENT_PAYLOAD_CANARY_LEN - 32 - 32 - 8 - 64 - 8;
+
+/// Maximal number of co-signers of MultisigOutput.
+pub const MAX_MULTISIG_RECIPIENTS: usize = 16;
+
+/// Maximal length of the secret preimage of HtlcOutput.
+pub const MAX_HTLC_PREIMAGE_LEN: usize = 256;
+
+/// Returns the hash lock of HtlcOutput for the secret `preimage`.
+///
+/// SHA-256 is used instead of the native hash function to be compatible
+/// with HTLCs on Bitcoin and Ethereum, so both legs of an atomic swap
+/// can be locked by the same secret.
+pub fn htlc_hash_lock(preimage: &[u8]) -> Hash {
+    let digest = Sha256::digest(preimage);
+    Hash::try_from_bytes(&digest).expect("SHA-256 digest is 32 bytes")
+}
 
 /// UTXO errors.
 #[derive(Debug, Fail)]
@@ -70,7 +87,12 @@ pub enum OutputError {
     #[fail(display = "Invalid signature on validator pkey: utxo={}", _0)]
     InvalidStakeSignature(Hash),
     #[fail(
-        display = "Input is locked: hash={}, tx_time={}, last_macro_block_time={}",
+        display = "Invalid validator commission: utxo={}, commission={}",
+        _0, _1
+    )]
+    InvalidCommission(Hash, u32),
+    #[fail(
+        display = "Input is locked: hash={}, locked_until={}, last_macro_block_time={}",
         _0, _1, _2
     )]
     UtxoLocked(Hash, Timestamp, Timestamp),
@@ -80,6 +102,21 @@ pub enum OutputError {
     UtfError(std::str::Utf8Error),
     #[fail(display = "Invalid payment certificate")]
     InvalidCertificate,
+    #[fail(
+        display = "Invalid multi-signature threshold: utxo={}, threshold={}, recipients={}",
+        _0, _1, _2
+    )]
+    InvalidMultisigThreshold(Hash, u32, usize),
+    #[fail(
+        display = "Too many multi-signature recipients: utxo={}, max={}, got={}",
+        _0, _1, _2
+    )]
+    TooManyMultisigRecipients(Hash, usize, usize),
+    #[fail(
+        display = "Duplicate multi-signature recipient: utxo={}, recipient={}",
+        _0, _1
+    )]
+    DuplicateMultisigRecipient(Hash, PublicKey),
 }
 
 impl From<CryptoError> for OutputError {
@@ -123,6 +160,11 @@ pub struct PublicPaymentOutput {
 
     /// Uncloaked amount
     pub amount: i64,
+
+    /// UTXO can't be spent until this time.
+    /// Checked against the last macro block, see Output::validate_lock().
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locked_timestamp: Option<Timestamp>,
 }
 
 /// Stake UTXO.
@@ -141,7 +183,75 @@ pub struct StakeOutput {
     pub serno: i64,
 
     /// BLS signature of recipient, validator and payload.
+    /// Delegated stakes are not signed by the validator.
     pub signature: pbc::Signature,
+
+    /// Commission of the validator in percent of the rewards of delegators.
+    /// Only valid for stakes of the validator itself.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub commission: u32,
+
+    /// Stake is delegated by the recipient to a third-party validator.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub delegated: bool,
+
+    /// Stake is being withdrawn: it is locked and slashable during the unbonding period,
+    /// but doesn't count in elections and rewards.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub unbonding: bool,
+}
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// Multi-signature (m-of-n) UTXO.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct MultisigOutput {
+    /// Uncloaked public keys of co-signers.
+    pub recipients: Vec<PublicKey>,
+
+    /// The number of co-signers required to spend this UTXO.
+    pub threshold: u32,
+
+    /// Randomize for hash collision avoidance
+    pub serno: i64,
+
+    /// Uncloaked amount
+    pub amount: i64,
+}
+
+/// Hash-time-locked UTXO for atomic swaps.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct HtlcOutput {
+    /// Uncloaked public key of recipient, who can claim this UTXO
+    /// by revealing the preimage of `hash_lock` before `deadline`.
+    pub recipient: PublicKey,
+
+    /// Uncloaked public key of refunder, who can spend this UTXO
+    /// after `deadline`.
+    pub refunder: PublicKey,
+
+    /// SHA-256 hash of the secret preimage, see `htlc_hash_lock()`.
+    pub hash_lock: Hash,
+
+    /// The time until which this UTXO can be claimed by recipient.
+    ///
+    /// Validators compare it with the timestamp of the last macro block, which
+    /// can lag behind the wall clock up to an epoch. Claims are accepted and
+    /// refunds are rejected until the first macro block past the deadline,
+    /// so the other leg of a swap must leave at least an epoch of margin.
+    pub deadline: Timestamp,
+
+    /// Randomize for hash collision avoidance
+    pub serno: i64,
+
+    /// Uncloaked amount
+    pub amount: i64,
 }
 
 /// Blockchain UTXO.
@@ -152,6 +262,8 @@ pub enum Output {
     PaymentOutput(PaymentOutput),
     PublicPaymentOutput(PublicPaymentOutput),
     StakeOutput(StakeOutput),
+    MultisigOutput(MultisigOutput),
+    HtlcOutput(HtlcOutput),
 }
 
 /// PaymentOutput canary for the light nodes.
@@ -173,12 +285,27 @@ pub struct StakeCanary {
     pub recipient: PublicKey,
 }
 
+/// MultisigOutput canary for the light nodes.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct MultisigCanary {
+    pub recipients: Vec<PublicKey>,
+}
+
+/// HtlcOutput canary for the light nodes.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct HtlcCanary {
+    pub recipient: PublicKey,
+    pub refunder: PublicKey,
+}
+
 /// Output canary for light nodes.
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Canary {
     PaymentCanary(PaymentCanary),
     PublicPaymentCanary(PublicPaymentCanary),
     StakeCanary(StakeCanary),
+    MultisigCanary(MultisigCanary),
+    HtlcCanary(HtlcCanary),
 }
 
 /// Cloak recipient's public key.
@@ -262,10 +389,22 @@ pub struct PaymentPayload {
     /// Signature for rest of data, produced by sender.
     /// This signature used on validating Payment certificate.
     pub signature: SchnorrSig,
+    /// UTXO can't be spent until this time.
+    ///
+    /// The lock is private and is honored only by the recipient's wallet,
+    /// because validators can't decrypt the payload.
+    /// Use PublicPaymentOutput for locks which must be enforced by validators.
+    pub locked_timestamp: Option<Timestamp>,
 }
 
 impl PaymentPayload {
-    fn new(delta: Fr, gamma: Fr, amount: i64, data: PaymentPayloadData) -> PaymentPayload {
+    fn new(
+        delta: Fr,
+        gamma: Fr,
+        amount: i64,
+        data: PaymentPayloadData,
+        locked_timestamp: Option<Timestamp>,
+    ) -> PaymentPayload {
         let signature = SchnorrSig::new();
         PaymentPayload {
             delta,
@@ -273,6 +412,7 @@ impl PaymentPayload {
             amount,
             data,
             signature,
+            locked_timestamp,
         }
     }
 
@@ -282,8 +422,9 @@ impl PaymentPayload {
         gamma: Fr,
         amount: i64,
         data: PaymentPayloadData,
+        locked_timestamp: Option<Timestamp>,
     ) -> PaymentPayload {
-        let mut payment_payload = PaymentPayload::new(delta, gamma, amount, data);
+        let mut payment_payload = PaymentPayload::new(delta, gamma, amount, data, locked_timestamp);
         let hash = Hash::digest(&payment_payload);
         let signature = sign_hash(&hash, &sender_key);
         payment_payload.signature = signature;
@@ -324,6 +465,12 @@ impl PaymentPayload {
         payload[pos..pos + signature_k.len()].copy_from_slice(&signature_k);
         pos += signature_k.len();
 
+        // Time-lock, zero if not locked.
+        let locked_timestamp: u64 = self.locked_timestamp.map(Into::into).unwrap_or(0);
+        let locked_timestamp_bytes: [u8; 8] = locked_timestamp.to_le_bytes();
+        payload[pos..pos + locked_timestamp_bytes.len()].copy_from_slice(&locked_timestamp_bytes);
+        pos += locked_timestamp_bytes.len();
+
         // Data.
         payload[pos] = self.data.discriminant();
         pos += 1;
@@ -451,6 +598,15 @@ impl PaymentPayload {
             K: signature_k,
         };
 
+        // Time-lock.
+        let mut locked_timestamp_bytes: [u8; 8] = [0u8; 8];
+        locked_timestamp_bytes.copy_from_slice(&payload[pos..pos + 8]);
+        pos += locked_timestamp_bytes.len();
+        let locked_timestamp = match u64::from_le_bytes(locked_timestamp_bytes) {
+            0 => None,
+            t => Some(t.into()),
+        };
+
         // Data.
         let code: u8 = payload[pos];
         pos += 1;
@@ -486,6 +642,7 @@ impl PaymentPayload {
             amount,
             signature,
             data,
+            locked_timestamp,
         };
         Ok(payload)
     }
@@ -497,6 +654,9 @@ impl Hashable for PaymentPayload {
         self.gamma.hash(hasher);
         self.amount.hash(hasher);
         self.data.hash(hasher);
+        if let Some(locked_timestamp) = &self.locked_timestamp {
+            locked_timestamp.hash(hasher);
+        }
     }
 }
 
@@ -507,6 +667,18 @@ impl PaymentOutput {
         recipient_pkey: &PublicKey,
         amount: i64,
         data: PaymentPayloadData,
+    ) -> Result<(Self, Fr, Fr), BlockchainError> {
+        Self::with_payload_locked(sender_key, recipient_pkey, amount, data, None)
+    }
+
+    /// Create a new PaymentOutput with generic payload and optional time-lock.
+    /// The time-lock is stored in the encrypted payload, see PaymentPayload::locked_timestamp.
+    pub fn with_payload_locked(
+        sender_key: Option<&SecretKey>,
+        recipient_pkey: &PublicKey,
+        amount: i64,
+        data: PaymentPayloadData,
+        locked_timestamp: Option<Timestamp>,
     ) -> Result<(Self, Fr, Fr), BlockchainError> {
         // Create range proofs.
         let (proof, gamma) = make_range_proof(amount);
@@ -521,9 +693,10 @@ impl PaymentOutput {
                 gamma.clone(),
                 amount,
                 data,
+                locked_timestamp,
             )
         } else {
-            PaymentPayload::new(delta.clone(), gamma.clone(), amount, data)
+            PaymentPayload::new(delta.clone(), gamma.clone(), amount, data, locked_timestamp)
         };
         // NOTE: real public key should be used to encrypt payload
         let (ag, payload, rvalue) = payload.encrypt(recipient_pkey)?;
@@ -545,13 +718,15 @@ impl PaymentOutput {
         Ok((output, gamma))
     }
 
-    /// Create a new PaymentOutput with lock.
+    /// Create a new PaymentOutput with a private lock, honored only by the recipient's wallet.
     pub fn new_locked(
         recipient_pkey: &PublicKey,
         amount: i64,
+        locked_timestamp: Timestamp,
     ) -> Result<(Self, Fr), BlockchainError> {
         let data = PaymentPayloadData::Comment(String::new());
-        let (output, gamma, _) = Self::with_payload(None, recipient_pkey, amount, data)?;
+        let (output, gamma, _) =
+            Self::with_payload_locked(None, recipient_pkey, amount, data, Some(locked_timestamp))?;
         Ok((output, gamma))
     }
 
@@ -629,15 +804,21 @@ impl PublicPaymentOutput {
             recipient: recipient_pkey.clone(),
             serno,
             amount,
+            locked_timestamp: None,
         }
     }
 
-    pub fn new_locked(recipient_pkey: &PublicKey, amount: i64) -> Self {
+    pub fn new_locked(
+        recipient_pkey: &PublicKey,
+        amount: i64,
+        locked_timestamp: Timestamp,
+    ) -> Self {
         let serno = random::<i64>();
         PublicPaymentOutput {
             recipient: recipient_pkey.clone(),
             serno,
             amount,
+            locked_timestamp: Some(locked_timestamp),
         }
     }
 
@@ -681,6 +862,9 @@ impl StakeOutput {
             amount,
             serno,
             signature: pbc::Signature::zero(),
+            commission: 0,
+            delegated: false,
+            unbonding: false,
         };
 
         // Form BLS signature on the Stake UTXO
@@ -690,6 +874,55 @@ impl StakeOutput {
         Ok(output)
     }
 
+    /// Create a new StakeOutput of the validator, which accepts delegations.
+    pub fn with_commission(
+        recipient_pkey: &PublicKey,
+        validator_skey: &pbc::SecretKey,
+        validator_pkey: &pbc::PublicKey,
+        amount: i64,
+        commission: u32,
+    ) -> Result<Self, Error> {
+        let mut output = Self::new(recipient_pkey, validator_skey, validator_pkey, amount)?;
+        output.commission = commission;
+        let h = Hash::digest(&output);
+        output.signature = pbc::sign_hash(&h, validator_skey);
+        Ok(output)
+    }
+
+    /// Create a new StakeOutput of the validator in the unbonding state.
+    pub fn new_unbonding(
+        recipient_pkey: &PublicKey,
+        validator_skey: &pbc::SecretKey,
+        validator_pkey: &pbc::PublicKey,
+        amount: i64,
+    ) -> Result<Self, Error> {
+        let mut output = Self::new(recipient_pkey, validator_skey, validator_pkey, amount)?;
+        output.unbonding = true;
+        let h = Hash::digest(&output);
+        output.signature = pbc::sign_hash(&h, validator_skey);
+        Ok(output)
+    }
+
+    /// Create a new StakeOutput delegated to a third-party validator.
+    pub fn new_delegated(
+        recipient_pkey: &PublicKey,
+        validator_pkey: &pbc::PublicKey,
+        amount: i64,
+    ) -> Result<Self, Error> {
+        assert!(amount > 0);
+        let serno = random::<i64>();
+        Ok(StakeOutput {
+            recipient: recipient_pkey.clone(),
+            validator: validator_pkey.clone(),
+            amount,
+            serno,
+            signature: pbc::Signature::zero(),
+            commission: 0,
+            delegated: true,
+            unbonding: false,
+        })
+    }
+
     /// Validates UTXO structure and keying.
     pub fn validate(&self) -> Result<(), BlockchainError> {
         let output_hash = Hash::digest(self);
@@ -697,6 +930,17 @@ impl StakeOutput {
             return Err(OutputError::InvalidAmount(output_hash, self.amount).into());
         }
 
+        if self.delegated {
+            // Delegations are checked against the escrow.
+            if self.commission != 0 {
+                return Err(OutputError::InvalidCommission(output_hash, self.commission).into());
+            }
+            return Ok(());
+        }
+        if self.commission > 100 {
+            return Err(OutputError::InvalidCommission(output_hash, self.commission).into());
+        }
+
         // Validate BLS signature of validator_pkey
         if let Err(_e) = pbc::check_hash(&output_hash, &self.signature, &self.validator) {
             return Err(OutputError::InvalidStakeSignature(output_hash).into());
@@ -717,6 +961,111 @@ impl StakeOutput {
     }
 }
 
+impl MultisigOutput {
+    /// Create a new MultisigOutput.
+    pub fn new(recipients: &[PublicKey], threshold: u32, amount: i64) -> Self {
+        let serno = random::<i64>();
+        MultisigOutput {
+            recipients: recipients.to_vec(),
+            threshold,
+            serno,
+            amount,
+        }
+    }
+
+    /// Validates UTXO structure and keying.
+    pub fn validate(&self) -> Result<(), BlockchainError> {
+        let output_hash = Hash::digest(self);
+        if self.amount <= 0 {
+            return Err(OutputError::InvalidAmount(output_hash, self.amount).into());
+        }
+        if self.recipients.len() > MAX_MULTISIG_RECIPIENTS {
+            return Err(OutputError::TooManyMultisigRecipients(
+                output_hash,
+                MAX_MULTISIG_RECIPIENTS,
+                self.recipients.len(),
+            )
+            .into());
+        }
+        if self.threshold == 0 || self.threshold as usize > self.recipients.len() {
+            return Err(OutputError::InvalidMultisigThreshold(
+                output_hash,
+                self.threshold,
+                self.recipients.len(),
+            )
+            .into());
+        }
+        for (i, recipient) in self.recipients.iter().enumerate() {
+            if self.recipients[..i].contains(recipient) {
+                return Err(
+                    OutputError::DuplicateMultisigRecipient(output_hash, *recipient).into(),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns Pedersen commitment.
+    pub fn pedersen_commitment(&self) -> Result<Pt, CryptoError> {
+        Ok(fee_a(self.amount))
+    }
+
+    /// Returns canary for the light nodes.
+    pub fn canary(&self) -> MultisigCanary {
+        MultisigCanary {
+            recipients: self.recipients.clone(),
+        }
+    }
+}
+
+impl HtlcOutput {
+    /// Create a new HtlcOutput.
+    pub fn new(
+        recipient_pkey: &PublicKey,
+        refunder_pkey: &PublicKey,
+        hash_lock: Hash,
+        deadline: Timestamp,
+        amount: i64,
+    ) -> Self {
+        let serno = random::<i64>();
+        HtlcOutput {
+            recipient: recipient_pkey.clone(),
+            refunder: refunder_pkey.clone(),
+            hash_lock,
+            deadline,
+            serno,
+            amount,
+        }
+    }
+
+    /// Validates UTXO structure and keying.
+    pub fn validate(&self) -> Result<(), BlockchainError> {
+        if self.amount <= 0 {
+            let h = Hash::digest(self);
+            return Err(OutputError::InvalidAmount(h, self.amount).into());
+        }
+        Ok(())
+    }
+
+    /// Returns true if the preimage matches `hash_lock`.
+    pub fn check_preimage(&self, preimage: &[u8]) -> bool {
+        preimage.len() <= MAX_HTLC_PREIMAGE_LEN && htlc_hash_lock(preimage) == self.hash_lock
+    }
+
+    /// Returns Pedersen commitment.
+    pub fn pedersen_commitment(&self) -> Result<Pt, CryptoError> {
+        Ok(fee_a(self.amount))
+    }
+
+    /// Returns canary for the light nodes.
+    pub fn canary(&self) -> HtlcCanary {
+        HtlcCanary {
+            recipient: self.recipient,
+            refunder: self.refunder,
+        }
+    }
+}
+
 impl Output {
     /// Create a new payment UTXO.
     pub fn new_payment(recipient_pkey: &PublicKey, amount: i64) -> Result<(Self, Fr), Error> {
@@ -741,16 +1090,25 @@ impl Output {
             Output::PaymentOutput(o) => o.validate(),
             Output::PublicPaymentOutput(o) => o.validate(),
             Output::StakeOutput(o) => o.validate(),
+            Output::MultisigOutput(o) => o.validate(),
+            Output::HtlcOutput(o) => o.validate(),
         }
     }
 
     /// Returns decompressed public key.
+    ///
+    /// MultisigOutput has no single recipient and is spent by signatures
+    /// of co-signers, therefore it doesn't contribute to the transaction key.
+    /// HtlcOutput returns the key of recipient, the key of refunder is
+    /// used instead when the UTXO is spent without a preimage.
     pub fn recipient_pkey(&self) -> Result<Pt, CryptoError> {
-        Ok(Pt::from(match self {
-            Output::PaymentOutput(o) => o.recipient,
-            Output::PublicPaymentOutput(o) => o.recipient,
-            Output::StakeOutput(o) => o.recipient,
-        }))
+        Ok(match self {
+            Output::PaymentOutput(o) => Pt::from(o.recipient),
+            Output::PublicPaymentOutput(o) => Pt::from(o.recipient),
+            Output::StakeOutput(o) => Pt::from(o.recipient),
+            Output::MultisigOutput(_o) => Pt::inf(),
+            Output::HtlcOutput(o) => Pt::from(o.recipient),
+        })
     }
 
     /// Returns Pedersen commitment.
@@ -759,7 +1117,36 @@ impl Output {
             Output::PaymentOutput(o) => o.pedersen_commitment(),
             Output::PublicPaymentOutput(o) => o.pedersen_commitment(),
             Output::StakeOutput(o) => o.pedersen_commitment(),
+            Output::MultisigOutput(o) => o.pedersen_commitment(),
+            Output::HtlcOutput(o) => o.pedersen_commitment(),
+        }
+    }
+
+    /// Returns the time until which this UTXO can't be spent, if visible to validators.
+    /// Locks of PaymentOutput are encrypted, see PaymentPayload::locked_timestamp.
+    pub fn locked_timestamp(&self) -> Option<Timestamp> {
+        match self {
+            Output::PaymentOutput(_o) => None,
+            Output::PublicPaymentOutput(o) => o.locked_timestamp,
+            Output::StakeOutput(_o) => None,
+            Output::MultisigOutput(_o) => None,
+            Output::HtlcOutput(_o) => None,
+        }
+    }
+
+    /// Checks that UTXO is not time-locked at the specified time.
+    ///
+    /// Validators pass the timestamp of the last macro block, so all nodes get
+    /// the same result. It can lag behind the wall clock up to an epoch, so UTXO
+    /// becomes spendable only after the first macro block past `locked_timestamp`.
+    pub fn validate_lock(&self, timestamp: Timestamp) -> Result<(), OutputError> {
+        if let Some(locked_timestamp) = self.locked_timestamp() {
+            if timestamp < locked_timestamp {
+                let h = Hash::digest(self);
+                return Err(OutputError::UtxoLocked(h, locked_timestamp, timestamp));
+            }
         }
+        Ok(())
     }
 
     /// Returns canary for the light nodes.
@@ -768,6 +1155,8 @@ impl Output {
             Output::PaymentOutput(o) => o.canary().into(),
             Output::PublicPaymentOutput(o) => o.canary().into(),
             Output::StakeOutput(o) => o.canary().into(),
+            Output::MultisigOutput(o) => o.canary().into(),
+            Output::HtlcOutput(o) => o.canary().into(),
         }
     }
 }
@@ -790,6 +1179,18 @@ impl From<PublicPaymentOutput> for Output {
     }
 }
 
+impl From<MultisigOutput> for Output {
+    fn from(output: MultisigOutput) -> Output {
+        Output::MultisigOutput(output)
+    }
+}
+
+impl From<HtlcOutput> for Output {
+    fn from(output: HtlcOutput) -> Output {
+        Output::HtlcOutput(output)
+    }
+}
+
 impl Hashable for PaymentOutput {
     fn hash(&self, state: &mut Hasher) {
         "Payment".hash(state);
@@ -806,6 +1207,10 @@ impl Hashable for PublicPaymentOutput {
         self.recipient.hash(state);
         self.serno.hash(state);
         self.amount.hash(state);
+        // Keep hashes of unlocked outputs compatible.
+        if let Some(locked_timestamp) = &self.locked_timestamp {
+            locked_timestamp.hash(state);
+        }
     }
 }
 
@@ -816,6 +1221,42 @@ impl Hashable for StakeOutput {
         self.validator.hash(state);
         self.amount.hash(state);
         self.serno.hash(state);
+        // Keep hashes of ordinary stakes unchanged.
+        if self.commission != 0 {
+            "commission".hash(state);
+            (self.commission as u64).hash(state);
+        }
+        if self.delegated {
+            "delegated".hash(state);
+        }
+        if self.unbonding {
+            "unbonding".hash(state);
+        }
+    }
+}
+
+impl Hashable for MultisigOutput {
+    fn hash(&self, state: &mut Hasher) {
+        "Multisig".hash(state);
+        (self.recipients.len() as u64).hash(state);
+        for recipient in &self.recipients {
+            recipient.hash(state);
+        }
+        self.threshold.hash(state);
+        self.serno.hash(state);
+        self.amount.hash(state);
+    }
+}
+
+impl Hashable for HtlcOutput {
+    fn hash(&self, state: &mut Hasher) {
+        "Htlc".hash(state);
+        self.recipient.hash(state);
+        self.refunder.hash(state);
+        self.hash_lock.hash(state);
+        self.deadline.hash(state);
+        self.serno.hash(state);
+        self.amount.hash(state);
     }
 }
 
@@ -825,6 +1266,8 @@ impl Hashable for Output {
             Output::PaymentOutput(payment) => payment.hash(state),
             Output::PublicPaymentOutput(payment) => payment.hash(state),
             Output::StakeOutput(stake) => stake.hash(state),
+            Output::MultisigOutput(multisig) => multisig.hash(state),
+            Output::HtlcOutput(htlc) => htlc.hash(state),
         }
     }
 }
@@ -861,6 +1304,31 @@ impl StakeCanary {
     }
 }
 
+impl MultisigCanary {
+    pub fn is_my(&self, pkey: &PublicKey) -> bool {
+        self.recipients.contains(pkey)
+    }
+}
+
+impl HtlcCanary {
+    pub fn is_my(&self, pkey: &PublicKey) -> bool {
+        &self.recipient == pkey || &self.refunder == pkey
+    }
+}
+
+impl Canary {
+    /// Returns true if the output of this canary belongs to the account.
+    pub fn is_my(&self, pkey: &PublicKey, skey: &SecretKey) -> bool {
+        match self {
+            Canary::PaymentCanary(c) => c.is_my(pkey, skey),
+            Canary::PublicPaymentCanary(c) => c.is_my(pkey),
+            Canary::StakeCanary(c) => c.is_my(pkey),
+            Canary::MultisigCanary(c) => c.is_my(pkey),
+            Canary::HtlcCanary(c) => c.is_my(pkey),
+        }
+    }
+}
+
 impl From<PaymentCanary> for Canary {
     fn from(canary: PaymentCanary) -> Canary {
         Canary::PaymentCanary(canary)
@@ -879,6 +1347,18 @@ impl From<PublicPaymentCanary> for Canary {
     }
 }
 
+impl From<MultisigCanary> for Canary {
+    fn from(canary: MultisigCanary) -> Canary {
+        Canary::MultisigCanary(canary)
+    }
+}
+
+impl From<HtlcCanary> for Canary {
+    fn from(canary: HtlcCanary) -> Canary {
+        Canary::HtlcCanary(canary)
+    }
+}
+
 impl Hashable for PaymentCanary {
     fn hash(&self, state: &mut Hasher) {
         "Payment".hash(state);
@@ -901,12 +1381,32 @@ impl Hashable for StakeCanary {
     }
 }
 
+impl Hashable for MultisigCanary {
+    fn hash(&self, state: &mut Hasher) {
+        "Multisig".hash(state);
+        (self.recipients.len() as u64).hash(state);
+        for recipient in &self.recipients {
+            recipient.hash(state);
+        }
+    }
+}
+
+impl Hashable for HtlcCanary {
+    fn hash(&self, state: &mut Hasher) {
+        "Htlc".hash(state);
+        self.recipient.hash(state);
+        self.refunder.hash(state);
+    }
+}
+
 impl Hashable for Canary {
     fn hash(&self, state: &mut Hasher) {
         match self {
             Canary::PaymentCanary(payment) => payment.hash(state),
             Canary::PublicPaymentCanary(payment) => payment.hash(state),
             Canary::StakeCanary(stake) => stake.hash(state),
+            Canary::MultisigCanary(multisig) => multisig.hash(state),
+            Canary::HtlcCanary(htlc) => htlc.hash(state),
         }
     }
 }
@@ -949,7 +1449,7 @@ pub mod tests {
         let delta: Fr = Fr::random();
         let amount: i64 = 100500;
         let data = PaymentPayloadData::Comment(String::new());
-        let payload = PaymentPayload::new(delta, gamma, amount, data);
+        let payload = PaymentPayload::new(delta, gamma, amount, data, None);
         rt(&payload, &skey, &pkey);
 
         // With non-empty comment.
@@ -957,7 +1457,7 @@ pub mod tests {
         let delta: Fr = Fr::random();
         let amount: i64 = 100500;
         let data = PaymentPayloadData::ContentHash(Hash::digest(&100500u64));
-        let payload = PaymentPayload::new(delta, gamma, amount, data);
+        let payload = PaymentPayload::new(delta, gamma, amount, data, None);
         rt(&payload, &skey, &pkey);
 
         // With long comment.
@@ -965,7 +1465,7 @@ pub mod tests {
         let delta: Fr = Fr::random();
         let amount: i64 = 100500;
         let data = PaymentPayloadData::Comment(random_string(PAYMENT_DATA_LEN - 2));
-        let payload = PaymentPayload::new(delta, gamma, amount, data);
+        let payload = PaymentPayload::new(delta, gamma, amount, data, None);
         rt(&payload, &skey, &pkey);
 
         // Overflow.
@@ -984,7 +1484,7 @@ pub mod tests {
         let delta: Fr = Fr::random();
         let amount: i64 = 100500;
         let data = PaymentPayloadData::ContentHash(Hash::digest(&100500u64));
-        let payload = PaymentPayload::new(delta, gamma, amount, data);
+        let payload = PaymentPayload::new(delta, gamma, amount, data, None);
         rt(&payload, &skey, &pkey);
 
         //
@@ -994,7 +1494,7 @@ pub mod tests {
         let delta: Fr = Fr::random();
         let amount: i64 = 100500;
         let data = PaymentPayloadData::ContentHash(Hash::digest(&100500u64));
-        let payload = PaymentPayload::new(delta, gamma, amount, data);
+        let payload = PaymentPayload::new(delta, gamma, amount, data, None);
         let (ag, encrypted, _rvalue) = payload.encrypt(&pkey).expect("keys are valid");
         let raw = aes_decrypt(ag, &encrypted, &skey).expect("keys are valid");
 
@@ -1121,4 +1621,26 @@ pub mod tests {
             .unwrap_err();
         assert_matches!(e, OutputError::InvalidCertificate);
     }
+
+    ///
+    /// Tests that the hash lock of HtlcOutput is SHA-256.
+    #[test]
+    fn htlc_hash_lock_sha256() {
+        let hash_lock = htlc_hash_lock(b"");
+        assert_eq!(
+            hash_lock.to_hex(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        let hash_lock = htlc_hash_lock(b"abc");
+        assert_eq!(
+            hash_lock.to_hex(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        let (_skey, pkey) = make_random_keys();
+        let output = HtlcOutput::new(&pkey, &pkey, hash_lock, Timestamp::now(), 1);
+        assert!(output.check_preimage(b"abc"));
+        assert!(!output.check_preimage(b"abd"));
+        assert_ne!(Hash::digest(&b"abc"[..]), hash_lock);
+    }
 }
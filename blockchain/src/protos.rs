@@ -37,10 +37,16 @@ pub enum ProtoError {
     MissingField(String, String),
     #[fail(display = "Duplicate value in field '{}'.", _0)]
     DuplicateValue(String),
+    #[fail(
+        display = "Invalid length of field '{}': got={}, expected={}.",
+        _0, _1, _2
+    )]
+    InvalidFieldLength(String, usize, usize),
 }
 
 // link protobuf dependencies
-use crate::awards::Awards;
+use crate::awards::{AwardDraw, Awards};
+use crate::output::PAYMENT_PAYLOAD_CANARY_LEN;
 use bit_vec::BitVec;
 use std::collections::BTreeMap;
 use std::iter::FromIterator;
@@ -89,6 +95,11 @@ impl ProtoConvert for OutputKey {
                 sub.set_txout_id(*txout_id);
                 msg.set_micro_block(sub);
             }
+            OutputKey::Snapshot { epoch } => {
+                let mut sub = blockchain::SnapshotOutputKey::new();
+                sub.set_epoch(*epoch);
+                msg.set_snapshot(sub);
+            }
         }
         msg
     }
@@ -113,6 +124,10 @@ impl ProtoConvert for OutputKey {
                     txout_id,
                 }
             }
+            Some(blockchain::OutputKey_oneof_key::snapshot(ref msg)) => {
+                let epoch = msg.get_epoch();
+                OutputKey::Snapshot { epoch }
+            }
             None => {
                 return Err(ProtoError::MissingField("key".to_string(), "key".to_string()).into());
             }
@@ -121,6 +136,82 @@ impl ProtoConvert for OutputKey {
     }
 }
 
+impl ProtoConvert for TransactionKey {
+    type Proto = blockchain::TransactionKey;
+
+    fn into_proto(&self) -> Self::Proto {
+        let mut msg = Self::Proto::new();
+        msg.set_epoch(self.epoch);
+        msg.set_offset(self.offset);
+        msg.set_tx_id(self.tx_id);
+        msg
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let epoch = proto.get_epoch();
+        let offset = proto.get_offset();
+        let tx_id = proto.get_tx_id();
+        Ok(TransactionKey {
+            epoch,
+            offset,
+            tx_id,
+        })
+    }
+}
+
+impl ProtoConvert for IndexedTransaction {
+    type Proto = blockchain::IndexedTransaction;
+
+    fn into_proto(&self) -> Self::Proto {
+        let mut msg = Self::Proto::new();
+        msg.set_key(self.key.into_proto());
+        msg.set_block_hash(self.block_hash.into_proto());
+        msg.set_transaction(self.transaction.into_proto());
+        msg
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let key = TransactionKey::from_proto(proto.get_key())?;
+        let block_hash = Hash::from_proto(proto.get_block_hash())?;
+        let transaction = Transaction::from_proto(proto.get_transaction())?;
+        Ok(IndexedTransaction {
+            key,
+            block_hash,
+            transaction,
+        })
+    }
+}
+
+impl ProtoConvert for UndoLogEntry {
+    type Proto = blockchain::UndoLogEntry;
+
+    fn into_proto(&self) -> Self::Proto {
+        let mut msg = Self::Proto::new();
+        match self {
+            UndoLogEntry::Push(block_hash) => msg.set_push(block_hash.into_proto()),
+            UndoLogEntry::Pop(block_hash) => msg.set_pop(block_hash.into_proto()),
+        }
+        msg
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let data = match proto.entry {
+            Some(blockchain::UndoLogEntry_oneof_entry::push(ref msg)) => {
+                UndoLogEntry::Push(Hash::from_proto(msg)?)
+            }
+            Some(blockchain::UndoLogEntry_oneof_entry::pop(ref msg)) => {
+                UndoLogEntry::Pop(Hash::from_proto(msg)?)
+            }
+            None => {
+                return Err(
+                    ProtoError::MissingField("entry".to_string(), "entry".to_string()).into(),
+                );
+            }
+        };
+        Ok(data)
+    }
+}
+
 impl ProtoConvert for EscrowKey {
     type Proto = blockchain::EscrowKey;
 
@@ -150,6 +241,9 @@ impl ProtoConvert for EscrowValue {
         msg.set_account_pkey(self.account_pkey.into_proto());
         msg.set_active_until_epoch(self.active_until_epoch);
         msg.set_amount(self.amount);
+        msg.set_commission(self.commission);
+        msg.set_delegated(self.delegated);
+        msg.set_unbonding(self.unbonding);
         msg
     }
 
@@ -157,11 +251,17 @@ impl ProtoConvert for EscrowValue {
         let account_pkey = ProtoConvert::from_proto(proto.get_account_pkey())?;
         let active_until_epoch = proto.get_active_until_epoch();
         let amount = proto.get_amount();
+        let commission = proto.get_commission();
+        let delegated = proto.get_delegated();
+        let unbonding = proto.get_unbonding();
 
         Ok(Self {
             account_pkey,
             active_until_epoch,
             amount,
+            commission,
+            delegated,
+            unbonding,
         })
     }
 }
@@ -369,6 +469,50 @@ impl ProtoConvert for AwardsInfo {
     }
 }
 
+impl ProtoConvert for AwardDraw {
+    type Proto = blockchain::AwardDraw;
+
+    fn into_proto(&self) -> Self::Proto {
+        let mut msg = Self::Proto::new();
+        msg.set_epoch(self.epoch);
+        msg.set_random(self.random.into_proto());
+        msg.set_difficulty(self.difficulty as u64);
+        for validator in &self.eligible {
+            msg.eligible.push(validator.into_proto());
+        }
+        if let Some(winner) = &self.winner {
+            msg.set_winner(winner.into_proto());
+        }
+        msg.set_payout(self.payout);
+        msg
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let epoch = proto.get_epoch();
+        let random = ProtoConvert::from_proto(proto.get_random())?;
+        let difficulty = proto.get_difficulty() as usize;
+        let mut eligible = Vec::with_capacity(proto.eligible.len());
+        for validator in proto.get_eligible() {
+            eligible.push(ProtoConvert::from_proto(validator)?);
+        }
+        let winner = if proto.has_winner() {
+            Some(ProtoConvert::from_proto(proto.get_winner())?)
+        } else {
+            None
+        };
+        let payout = proto.get_payout();
+
+        Ok(Self {
+            epoch,
+            random,
+            difficulty,
+            eligible,
+            winner,
+            payout,
+        })
+    }
+}
+
 impl ProtoConvert for EpochInfo {
     type Proto = blockchain::EpochInfo;
 
@@ -467,6 +611,9 @@ impl ProtoConvert for PublicPaymentOutput {
         proto.set_recipient(self.recipient.into_proto());
         proto.set_amount(self.amount);
         proto.set_serno(self.serno);
+        if let Some(locked_timestamp) = self.locked_timestamp {
+            proto.set_locked_timestamp(locked_timestamp.into());
+        }
         proto
     }
 
@@ -474,10 +621,15 @@ impl ProtoConvert for PublicPaymentOutput {
         let recipient = PublicKey::from_proto(proto.get_recipient())?;
         let amount = proto.get_amount();
         let serno = proto.get_serno();
+        let locked_timestamp = match proto.get_locked_timestamp() {
+            0 => None,
+            t => Some(t.into()),
+        };
         Ok(PublicPaymentOutput {
             recipient,
             amount,
             serno,
+            locked_timestamp,
         })
     }
 }
@@ -491,6 +643,9 @@ impl ProtoConvert for StakeOutput {
         proto.set_amount(self.amount);
         proto.set_serno(self.serno);
         proto.set_signature(self.signature.into_proto());
+        proto.set_commission(self.commission);
+        proto.set_delegated(self.delegated);
+        proto.set_unbonding(self.unbonding);
         proto
     }
 
@@ -500,12 +655,79 @@ impl ProtoConvert for StakeOutput {
         let amount = proto.get_amount();
         let serno = proto.get_serno();
         let signature = pbc::Signature::from_proto(proto.get_signature())?;
+        let commission = proto.get_commission();
+        let delegated = proto.get_delegated();
+        let unbonding = proto.get_unbonding();
         Ok(StakeOutput {
             recipient,
             validator,
             amount,
             serno,
             signature,
+            commission,
+            delegated,
+            unbonding,
+        })
+    }
+}
+
+impl ProtoConvert for MultisigOutput {
+    type Proto = blockchain::MultisigOutput;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = blockchain::MultisigOutput::new();
+        for recipient in &self.recipients {
+            proto.recipients.push(recipient.into_proto());
+        }
+        proto.set_threshold(self.threshold);
+        proto.set_serno(self.serno);
+        proto.set_amount(self.amount);
+        proto
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let mut recipients = Vec::<PublicKey>::with_capacity(proto.recipients.len());
+        for recipient in proto.recipients.iter() {
+            recipients.push(PublicKey::from_proto(recipient)?);
+        }
+        let threshold = proto.get_threshold();
+        let serno = proto.get_serno();
+        let amount = proto.get_amount();
+        Ok(MultisigOutput {
+            recipients,
+            threshold,
+            serno,
+            amount,
+        })
+    }
+}
+
+impl ProtoConvert for HtlcOutput {
+    type Proto = blockchain::HtlcOutput;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = blockchain::HtlcOutput::new();
+        proto.set_recipient(self.recipient.into_proto());
+        proto.set_refunder(self.refunder.into_proto());
+        proto.set_hash_lock(self.hash_lock.into_proto());
+        proto.set_deadline(self.deadline.into());
+        proto.set_serno(self.serno);
+        proto.set_amount(self.amount);
+        proto
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let recipient = PublicKey::from_proto(proto.get_recipient())?;
+        let refunder = PublicKey::from_proto(proto.get_refunder())?;
+        let hash_lock = Hash::from_proto(proto.get_hash_lock())?;
+        let deadline = proto.get_deadline().into();
+        let serno = proto.get_serno();
+        let amount = proto.get_amount();
+        Ok(HtlcOutput {
+            recipient,
+            refunder,
+            hash_lock,
+            deadline,
+            serno,
+            amount,
         })
     }
 }
@@ -520,6 +742,8 @@ impl ProtoConvert for Output {
                 proto.set_public_payment_output(output.into_proto())
             }
             Output::StakeOutput(output) => proto.set_stake_output(output.into_proto()),
+            Output::MultisigOutput(output) => proto.set_multisig_output(output.into_proto()),
+            Output::HtlcOutput(output) => proto.set_htlc_output(output.into_proto()),
         }
         proto
     }
@@ -538,6 +762,14 @@ impl ProtoConvert for Output {
                 let output = StakeOutput::from_proto(output)?;
                 Ok(Output::StakeOutput(output))
             }
+            Some(blockchain::Output_oneof_output::multisig_output(ref output)) => {
+                let output = MultisigOutput::from_proto(output)?;
+                Ok(Output::MultisigOutput(output))
+            }
+            Some(blockchain::Output_oneof_output::htlc_output(ref output)) => {
+                let output = HtlcOutput::from_proto(output)?;
+                Ok(Output::HtlcOutput(output))
+            }
             None => {
                 Err(ProtoError::MissingField("output".to_string(), "output".to_string()).into())
             }
@@ -575,6 +807,40 @@ impl ProtoConvert for CoinbaseTransaction {
     }
 }
 
+impl ProtoConvert for MultisigSignature {
+    type Proto = blockchain::MultisigSignature;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = blockchain::MultisigSignature::new();
+        proto.set_txin(self.txin.into_proto());
+        proto.set_pkey(self.pkey.into_proto());
+        proto.set_sig(self.sig.into_proto());
+        proto
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let txin = Hash::from_proto(proto.get_txin())?;
+        let pkey = PublicKey::from_proto(proto.get_pkey())?;
+        let sig = SchnorrSig::from_proto(proto.get_sig())?;
+        Ok(MultisigSignature { txin, pkey, sig })
+    }
+}
+
+impl ProtoConvert for HtlcPreimage {
+    type Proto = blockchain::HtlcPreimage;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = blockchain::HtlcPreimage::new();
+        proto.set_txin(self.txin.into_proto());
+        proto.set_preimage(self.preimage.clone());
+        proto
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let txin = Hash::from_proto(proto.get_txin())?;
+        let preimage = proto.get_preimage().to_vec();
+        Ok(HtlcPreimage { txin, preimage })
+    }
+}
+
 impl ProtoConvert for PaymentTransaction {
     type Proto = blockchain::PaymentTransaction;
     fn into_proto(&self) -> Self::Proto {
@@ -589,6 +855,12 @@ impl ProtoConvert for PaymentTransaction {
         proto.set_gamma(self.gamma.into_proto());
         proto.set_fee(self.fee);
         proto.set_sig(self.sig.into_proto());
+        for multisig in &self.multisigs {
+            proto.multisigs.push(multisig.into_proto());
+        }
+        for htlc_preimage in &self.htlc_preimages {
+            proto.htlc_preimages.push(htlc_preimage.into_proto());
+        }
         proto
     }
 
@@ -604,6 +876,14 @@ impl ProtoConvert for PaymentTransaction {
         let gamma = Fr::from_proto(proto.get_gamma())?;
         let fee = proto.get_fee();
         let sig = SchnorrSig::from_proto(proto.get_sig())?;
+        let mut multisigs = Vec::<MultisigSignature>::with_capacity(proto.multisigs.len());
+        for multisig in proto.multisigs.iter() {
+            multisigs.push(MultisigSignature::from_proto(multisig)?);
+        }
+        let mut htlc_preimages = Vec::<HtlcPreimage>::with_capacity(proto.htlc_preimages.len());
+        for htlc_preimage in proto.htlc_preimages.iter() {
+            htlc_preimages.push(HtlcPreimage::from_proto(htlc_preimage)?);
+        }
 
         Ok(PaymentTransaction {
             txins,
@@ -611,6 +891,8 @@ impl ProtoConvert for PaymentTransaction {
             gamma,
             fee,
             sig,
+            multisigs,
+            htlc_preimages,
         })
     }
 }
@@ -867,6 +1149,95 @@ impl ProtoConvert for MicroBlock {
     }
 }
 
+impl ProtoConvert for ParameterChange {
+    type Proto = blockchain::ParameterChange;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = blockchain::ParameterChange::new();
+        proto.set_block_reward(self.block_reward.unwrap_or(0));
+        proto.set_min_stake_amount(self.min_stake_amount.unwrap_or(0));
+        proto.set_service_award_per_epoch(self.service_award_per_epoch.unwrap_or(0));
+        proto.set_max_slot_count(self.max_slot_count.unwrap_or(0));
+        proto
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let non_zero = |value: i64| if value != 0 { Some(value) } else { None };
+        Ok(ParameterChange {
+            block_reward: non_zero(proto.get_block_reward()),
+            min_stake_amount: non_zero(proto.get_min_stake_amount()),
+            service_award_per_epoch: non_zero(proto.get_service_award_per_epoch()),
+            max_slot_count: non_zero(proto.get_max_slot_count()),
+        })
+    }
+}
+
+impl ProtoConvert for Governance {
+    type Proto = blockchain::Governance;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = blockchain::Governance::new();
+        proto.set_active(self.active.into_proto());
+        if let Some(pending) = &self.pending {
+            proto.set_pending(pending.into_proto());
+        }
+        proto.set_votes(self.votes);
+        proto
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let active = ParameterChange::from_proto(proto.get_active())?;
+        let pending = if proto.has_pending() {
+            Some(ParameterChange::from_proto(proto.get_pending())?)
+        } else {
+            None
+        };
+        let votes = proto.get_votes();
+        Ok(Governance {
+            active,
+            pending,
+            votes,
+        })
+    }
+}
+
+impl ProtoConvert for Liveness {
+    type Proto = blockchain::Liveness;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = blockchain::Liveness::new();
+        for (validator, count) in &self.missed {
+            let mut missed = blockchain::MissedEpochs::new();
+            missed.set_validator(validator.into_proto());
+            missed.set_count(*count);
+            proto.missed.push(missed);
+        }
+        for (validator, jailed) in &self.jailed {
+            let mut msg = blockchain::JailedValidator::new();
+            msg.set_validator(validator.into_proto());
+            msg.set_epoch(jailed.epoch);
+            msg.set_release_epoch(jailed.release_epoch);
+            proto.jailed.push(msg);
+        }
+        proto
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let mut missed = BTreeMap::new();
+        for msg in proto.get_missed() {
+            let validator = pbc::PublicKey::from_proto(msg.get_validator())?;
+            missed.insert(validator, msg.get_count());
+        }
+        let mut jailed = BTreeMap::new();
+        for msg in proto.get_jailed() {
+            let validator = pbc::PublicKey::from_proto(msg.get_validator())?;
+            let value = JailedValidator {
+                epoch: msg.get_epoch(),
+                release_epoch: msg.get_release_epoch(),
+            };
+            jailed.insert(validator, value);
+        }
+        Ok(Liveness { missed, jailed })
+    }
+}
+
 impl ProtoConvert for MacroBlockHeader {
     type Proto = blockchain::MacroBlockHeader;
     fn into_proto(&self) -> Self::Proto {
@@ -889,6 +1260,15 @@ impl ProtoConvert for MacroBlockHeader {
         proto.set_outputs_len(self.outputs_len);
         proto.set_outputs_range_hash(self.outputs_range_hash.into_proto());
         proto.set_canaries_range_hash(self.canaries_range_hash.into_proto());
+        if let Some(state_hash) = &self.state_hash {
+            proto.set_state_hash(state_hash.into_proto());
+        }
+        if let Some(utxo_range_hash) = &self.utxo_range_hash {
+            proto.set_utxo_range_hash(utxo_range_hash.into_proto());
+        }
+        if let Some(proposal) = &self.proposal {
+            proto.set_proposal(proposal.into_proto());
+        }
         proto
     }
 
@@ -911,6 +1291,21 @@ impl ProtoConvert for MacroBlockHeader {
         let outputs_len = proto.get_outputs_len();
         let outputs_range_hash = Hash::from_proto(proto.get_outputs_range_hash())?;
         let canaries_range_hash = Hash::from_proto(proto.get_canaries_range_hash())?;
+        let state_hash = if proto.has_state_hash() {
+            Some(Hash::from_proto(proto.get_state_hash())?)
+        } else {
+            None
+        };
+        let utxo_range_hash = if proto.has_utxo_range_hash() {
+            Some(Hash::from_proto(proto.get_utxo_range_hash())?)
+        } else {
+            None
+        };
+        let proposal = if proto.has_proposal() {
+            Some(ParameterChange::from_proto(proto.get_proposal())?)
+        } else {
+            None
+        };
         Ok(MacroBlockHeader {
             version,
             previous,
@@ -930,6 +1325,9 @@ impl ProtoConvert for MacroBlockHeader {
             outputs_len,
             outputs_range_hash,
             canaries_range_hash,
+            state_hash,
+            utxo_range_hash,
+            proposal,
         })
     }
 }
@@ -979,6 +1377,110 @@ impl ProtoConvert for MacroBlock {
     }
 }
 
+impl ProtoConvert for MacroBlockCertificate {
+    type Proto = blockchain::MacroBlockCertificate;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = blockchain::MacroBlockCertificate::new();
+        proto.set_header(self.header.into_proto());
+        proto.set_multisig(self.multisig.into_proto());
+        proto.multisigmap.extend(self.multisigmap.iter());
+        for validator in &self.validators {
+            let mut staker = blockchain::Staker::new();
+            staker.set_network_pkey(validator.0.into_proto());
+            staker.set_amount(validator.1);
+            proto.validators.push(staker);
+        }
+        proto
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let header = MacroBlockHeader::from_proto(proto.get_header())?;
+        let multisig = pbc::Signature::from_proto(proto.get_multisig())?;
+        let multisigmap = BitVec::from_iter(proto.multisigmap.iter().map(|x| *x));
+        let mut validators = Vec::with_capacity(proto.validators.len());
+        for staker in proto.validators.iter() {
+            validators.push((
+                ProtoConvert::from_proto(staker.get_network_pkey())?,
+                staker.get_amount(),
+            ));
+        }
+        Ok(MacroBlockCertificate {
+            header,
+            multisig,
+            multisigmap,
+            validators,
+        })
+    }
+}
+
+impl ProtoConvert for StateSnapshot {
+    type Proto = blockchain::StateSnapshot;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = blockchain::StateSnapshot::new();
+        for certificate in &self.certificates {
+            proto.certificates.push(certificate.into_proto());
+        }
+        proto.set_block(self.block.into_proto());
+        for validator in &self.validators {
+            let mut staker = blockchain::Staker::new();
+            staker.set_network_pkey(validator.0.into_proto());
+            staker.set_amount(validator.1);
+            proto.validators.push(staker);
+        }
+        for output in &self.outputs {
+            proto.outputs.push(output.into_proto());
+        }
+        for (key, value) in &self.stakes {
+            let mut stake = blockchain::SnapshotStake::new();
+            stake.set_key(key.into_proto());
+            stake.set_value(value.into_proto());
+            proto.stakes.push(stake);
+        }
+        proto.set_awards(self.awards.into_proto());
+        proto.set_gamma(self.gamma.into_proto());
+        proto.set_block_reward(self.block_reward);
+        proto
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let mut certificates = Vec::with_capacity(proto.certificates.len());
+        for certificate in proto.certificates.iter() {
+            certificates.push(MacroBlockCertificate::from_proto(certificate)?);
+        }
+        let block = MacroBlock::from_proto(proto.get_block())?;
+        let mut validators = Vec::with_capacity(proto.validators.len());
+        for staker in proto.validators.iter() {
+            validators.push((
+                ProtoConvert::from_proto(staker.get_network_pkey())?,
+                staker.get_amount(),
+            ));
+        }
+        let mut outputs = Vec::with_capacity(proto.outputs.len());
+        for output in proto.outputs.iter() {
+            outputs.push(Output::from_proto(output)?);
+        }
+        let mut stakes = Vec::with_capacity(proto.stakes.len());
+        for stake in proto.stakes.iter() {
+            let key = EscrowKey::from_proto(stake.get_key())?;
+            let value = EscrowValue::from_proto(stake.get_value())?;
+            stakes.push((key, value));
+        }
+        let awards = Awards::from_proto(proto.get_awards())?;
+        let gamma = Fr::from_proto(proto.get_gamma())?;
+        let block_reward = proto.get_block_reward();
+        Ok(StateSnapshot {
+            certificates,
+            block,
+            validators,
+            outputs,
+            stakes,
+            awards,
+            gamma,
+            block_reward,
+        })
+    }
+}
+
 impl ProtoConvert for Block {
     type Proto = blockchain::Block;
     fn into_proto(&self) -> Self::Proto {
@@ -1055,18 +1557,414 @@ impl ProtoConvert for ViewChangeProof {
     }
 }
 
-impl ProtoConvert for SlashingProof {
-    type Proto = blockchain::SlashingProof;
+impl ProtoConvert for ConsensusVote {
+    type Proto = blockchain::ConsensusVote;
     fn into_proto(&self) -> Self::Proto {
-        let mut proto = blockchain::SlashingProof::new();
-        proto.set_block1(self.block1.into_proto());
-        proto.set_block2(self.block2.into_proto());
-        proto
+        let mut proto = blockchain::ConsensusVote::new();
+        proto.set_epoch(self.epoch);
+        proto.set_round(self.round);
+        proto.set_block_hash(self.block_hash.into_proto());
+        if let Some(block_hash_sig) = &self.block_hash_sig {
+            proto.set_block_hash_sig(block_hash_sig.into_proto());
+        }
+        proto.set_pkey(self.pkey.into_proto());
+        proto.set_sig(self.sig.into_proto());
+        proto
+    }
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let epoch = proto.get_epoch();
+        let round = proto.get_round();
+        let block_hash = Hash::from_proto(proto.get_block_hash())?;
+        let block_hash_sig = if proto.has_block_hash_sig() {
+            Some(pbc::Signature::from_proto(proto.get_block_hash_sig())?)
+        } else {
+            None
+        };
+        let pkey = pbc::PublicKey::from_proto(proto.get_pkey())?;
+        let sig = pbc::Signature::from_proto(proto.get_sig())?;
+        Ok(ConsensusVote {
+            epoch,
+            round,
+            block_hash,
+            block_hash_sig,
+            pkey,
+            sig,
+        })
+    }
+}
+
+impl ProtoConvert for ViewChangeVote {
+    type Proto = blockchain::ViewChangeVote;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = blockchain::ViewChangeVote::new();
+        proto.set_chain(self.chain.into_proto());
+        proto.set_pkey(self.pkey.into_proto());
+        proto.set_sig(self.sig.into_proto());
+        proto
+    }
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let chain = ChainInfo::from_proto(proto.get_chain())?;
+        let pkey = pbc::PublicKey::from_proto(proto.get_pkey())?;
+        let sig = pbc::Signature::from_proto(proto.get_sig())?;
+        Ok(ViewChangeVote { chain, pkey, sig })
+    }
+}
+
+impl ProtoConvert for SlashingProof {
+    type Proto = blockchain::SlashingProof;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = blockchain::SlashingProof::new();
+        match self {
+            SlashingProof::MicroBlocks { block1, block2 } => {
+                proto.set_block1(block1.into_proto());
+                proto.set_block2(block2.into_proto());
+            }
+            SlashingProof::ConsensusVotes { vote1, vote2 } => {
+                proto.set_vote1(vote1.into_proto());
+                proto.set_vote2(vote2.into_proto());
+            }
+            SlashingProof::ViewChanges {
+                view_change1,
+                view_change2,
+                header1,
+                header2,
+            } => {
+                proto.set_view_change1(view_change1.into_proto());
+                proto.set_view_change2(view_change2.into_proto());
+                proto.set_header1(header1.into_proto());
+                proto.set_header2(header2.into_proto());
+            }
+            SlashingProof::Downtime { validator, epoch } => {
+                proto.set_downtime_validator(validator.into_proto());
+                proto.set_downtime_epoch(*epoch);
+            }
+        }
+        proto
+    }
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        if proto.has_vote1() {
+            let vote1 = ConsensusVote::from_proto(proto.get_vote1())?;
+            let vote2 = ConsensusVote::from_proto(proto.get_vote2())?;
+            Ok(SlashingProof::ConsensusVotes { vote1, vote2 })
+        } else if proto.has_view_change1() {
+            let view_change1 = ViewChangeVote::from_proto(proto.get_view_change1())?;
+            let view_change2 = ViewChangeVote::from_proto(proto.get_view_change2())?;
+            let header1 = MicroBlockHeader::from_proto(proto.get_header1())?;
+            let header2 = MicroBlockHeader::from_proto(proto.get_header2())?;
+            Ok(SlashingProof::ViewChanges {
+                view_change1,
+                view_change2,
+                header1,
+                header2,
+            })
+        } else if proto.has_downtime_validator() {
+            let validator = pbc::PublicKey::from_proto(proto.get_downtime_validator())?;
+            let epoch = proto.get_downtime_epoch();
+            Ok(SlashingProof::Downtime { validator, epoch })
+        } else {
+            let block1 = MicroBlock::from_proto(proto.get_block1())?;
+            let block2 = MicroBlock::from_proto(proto.get_block2())?;
+            Ok(SlashingProof::MicroBlocks { block1, block2 })
+        }
+    }
+}
+
+impl ProtoConvert for PaymentCanary {
+    type Proto = blockchain::PaymentCanary;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = blockchain::PaymentCanary::new();
+        proto.set_ag(self.ag.into_proto());
+        proto.set_canary(self.canary.to_vec());
+        proto
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let ag = Pt::from_proto(proto.get_ag())?;
+        let bytes = proto.get_canary();
+        if bytes.len() != PAYMENT_PAYLOAD_CANARY_LEN {
+            return Err(ProtoError::InvalidFieldLength(
+                "canary".to_string(),
+                bytes.len(),
+                PAYMENT_PAYLOAD_CANARY_LEN,
+            )
+            .into());
+        }
+        let mut canary = [0u8; PAYMENT_PAYLOAD_CANARY_LEN];
+        canary.copy_from_slice(bytes);
+        Ok(PaymentCanary { ag, canary })
+    }
+}
+
+impl ProtoConvert for PublicPaymentCanary {
+    type Proto = blockchain::PublicPaymentCanary;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = blockchain::PublicPaymentCanary::new();
+        proto.set_recipient(self.recipient.into_proto());
+        proto
     }
+
     fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
-        let block1 = MicroBlock::from_proto(proto.get_block1())?;
-        let block2 = MicroBlock::from_proto(proto.get_block2())?;
-        Ok(SlashingProof { block1, block2 })
+        let recipient = PublicKey::from_proto(proto.get_recipient())?;
+        Ok(PublicPaymentCanary { recipient })
+    }
+}
+
+impl ProtoConvert for StakeCanary {
+    type Proto = blockchain::StakeCanary;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = blockchain::StakeCanary::new();
+        proto.set_recipient(self.recipient.into_proto());
+        proto
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let recipient = PublicKey::from_proto(proto.get_recipient())?;
+        Ok(StakeCanary { recipient })
+    }
+}
+
+impl ProtoConvert for MultisigCanary {
+    type Proto = blockchain::MultisigCanary;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = blockchain::MultisigCanary::new();
+        for recipient in &self.recipients {
+            proto.recipients.push(recipient.into_proto());
+        }
+        proto
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let mut recipients = Vec::with_capacity(proto.recipients.len());
+        for recipient in proto.recipients.iter() {
+            recipients.push(PublicKey::from_proto(recipient)?);
+        }
+        Ok(MultisigCanary { recipients })
+    }
+}
+
+impl ProtoConvert for HtlcCanary {
+    type Proto = blockchain::HtlcCanary;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = blockchain::HtlcCanary::new();
+        proto.set_recipient(self.recipient.into_proto());
+        proto.set_refunder(self.refunder.into_proto());
+        proto
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let recipient = PublicKey::from_proto(proto.get_recipient())?;
+        let refunder = PublicKey::from_proto(proto.get_refunder())?;
+        Ok(HtlcCanary {
+            recipient,
+            refunder,
+        })
+    }
+}
+
+impl ProtoConvert for Canary {
+    type Proto = blockchain::Canary;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = blockchain::Canary::new();
+        match self {
+            Canary::PaymentCanary(canary) => proto.set_payment_canary(canary.into_proto()),
+            Canary::PublicPaymentCanary(canary) => {
+                proto.set_public_payment_canary(canary.into_proto())
+            }
+            Canary::StakeCanary(canary) => proto.set_stake_canary(canary.into_proto()),
+            Canary::MultisigCanary(canary) => proto.set_multisig_canary(canary.into_proto()),
+            Canary::HtlcCanary(canary) => proto.set_htlc_canary(canary.into_proto()),
+        }
+        proto
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        match proto.canary {
+            Some(blockchain::Canary_oneof_canary::payment_canary(ref canary)) => {
+                let canary = PaymentCanary::from_proto(canary)?;
+                Ok(Canary::PaymentCanary(canary))
+            }
+            Some(blockchain::Canary_oneof_canary::public_payment_canary(ref canary)) => {
+                let canary = PublicPaymentCanary::from_proto(canary)?;
+                Ok(Canary::PublicPaymentCanary(canary))
+            }
+            Some(blockchain::Canary_oneof_canary::stake_canary(ref canary)) => {
+                let canary = StakeCanary::from_proto(canary)?;
+                Ok(Canary::StakeCanary(canary))
+            }
+            Some(blockchain::Canary_oneof_canary::multisig_canary(ref canary)) => {
+                let canary = MultisigCanary::from_proto(canary)?;
+                Ok(Canary::MultisigCanary(canary))
+            }
+            Some(blockchain::Canary_oneof_canary::htlc_canary(ref canary)) => {
+                let canary = HtlcCanary::from_proto(canary)?;
+                Ok(Canary::HtlcCanary(canary))
+            }
+            None => {
+                Err(ProtoError::MissingField("canary".to_string(), "canary".to_string()).into())
+            }
+        }
+    }
+}
+
+impl ProtoConvert for LightMacroBlock {
+    type Proto = blockchain::LightMacroBlock;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = blockchain::LightMacroBlock::new();
+        proto.set_header(self.header.into_proto());
+        proto.set_multisig(self.multisig.into_proto());
+        proto.multisigmap.extend(self.multisigmap.iter());
+        for validator in &self.validators {
+            let mut staker = blockchain::Staker::new();
+            staker.set_network_pkey(validator.0.into_proto());
+            staker.set_amount(validator.1);
+            proto.validators.push(staker);
+        }
+        for input_hash in &self.input_hashes {
+            proto.input_hashes.push(input_hash.into_proto());
+        }
+        for canary in &self.canaries {
+            proto.canaries.push(canary.into_proto());
+        }
+        proto
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let header = MacroBlockHeader::from_proto(proto.get_header())?;
+        let multisig = pbc::Signature::from_proto(proto.get_multisig())?;
+        let multisigmap = BitVec::from_iter(proto.multisigmap.iter().map(|x| *x));
+        let mut validators = Vec::with_capacity(proto.validators.len());
+        for staker in proto.validators.iter() {
+            validators.push((
+                ProtoConvert::from_proto(staker.get_network_pkey())?,
+                staker.get_amount(),
+            ));
+        }
+        let mut input_hashes = Vec::with_capacity(proto.input_hashes.len());
+        for input_hash in proto.input_hashes.iter() {
+            input_hashes.push(Hash::from_proto(input_hash)?);
+        }
+        let mut canaries = Vec::with_capacity(proto.canaries.len());
+        for canary in proto.canaries.iter() {
+            canaries.push(Canary::from_proto(canary)?);
+        }
+        Ok(LightMacroBlock {
+            header,
+            multisig,
+            multisigmap,
+            validators,
+            input_hashes,
+            canaries,
+        })
+    }
+}
+
+impl ProtoConvert for LightMicroBlock {
+    type Proto = blockchain::LightMicroBlock;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = blockchain::LightMicroBlock::new();
+        proto.set_header(self.header.into_proto());
+        proto.set_sig(self.sig.into_proto());
+        for input_hash in &self.input_hashes {
+            proto.input_hashes.push(input_hash.into_proto());
+        }
+        for canary in &self.canaries {
+            proto.canaries.push(canary.into_proto());
+        }
+        proto
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let header = MicroBlockHeader::from_proto(proto.get_header())?;
+        let sig = pbc::Signature::from_proto(proto.get_sig())?;
+        let mut input_hashes = Vec::with_capacity(proto.input_hashes.len());
+        for input_hash in proto.input_hashes.iter() {
+            input_hashes.push(Hash::from_proto(input_hash)?);
+        }
+        let mut canaries = Vec::with_capacity(proto.canaries.len());
+        for canary in proto.canaries.iter() {
+            canaries.push(Canary::from_proto(canary)?);
+        }
+        Ok(LightMicroBlock {
+            header,
+            sig,
+            input_hashes,
+            canaries,
+        })
+    }
+}
+
+impl ProtoConvert for LightBlock {
+    type Proto = blockchain::LightBlock;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = blockchain::LightBlock::new();
+        match self {
+            LightBlock::LightMacroBlock(block) => proto.set_macro_block(block.into_proto()),
+            LightBlock::LightMicroBlock(block) => proto.set_micro_block(block.into_proto()),
+        }
+        proto
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let block = match proto.block {
+            Some(blockchain::LightBlock_oneof_block::macro_block(ref block)) => {
+                LightBlock::LightMacroBlock(LightMacroBlock::from_proto(block)?)
+            }
+            Some(blockchain::LightBlock_oneof_block::micro_block(ref block)) => {
+                LightBlock::LightMicroBlock(LightMicroBlock::from_proto(block)?)
+            }
+            None => {
+                return Err(
+                    ProtoError::MissingField("block".to_string(), "block".to_string()).into(),
+                );
+            }
+        };
+        Ok(block)
+    }
+}
+
+impl ProtoConvert for MerkleProof {
+    type Proto = blockchain::MerkleProof;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = blockchain::MerkleProof::new();
+        proto.set_path(self.path.0);
+        for sibling in &self.siblings {
+            let mut msg = blockchain::MerkleSibling::new();
+            if let Some(hash) = sibling {
+                msg.set_hash(hash.into_proto());
+            }
+            proto.siblings.push(msg);
+        }
+        proto
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let path = MerklePath(proto.get_path());
+        let mut siblings = Vec::with_capacity(proto.siblings.len());
+        for sibling in proto.siblings.iter() {
+            let sibling = if sibling.has_hash() {
+                Some(Hash::from_proto(sibling.get_hash())?)
+            } else {
+                None
+            };
+            siblings.push(sibling);
+        }
+        Ok(MerkleProof { path, siblings })
+    }
+}
+
+impl ProtoConvert for OutputProof {
+    type Proto = blockchain::OutputProof;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = blockchain::OutputProof::new();
+        proto.set_output(self.output.into_proto());
+        proto.set_proof(self.proof.into_proto());
+        proto
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let output = Output::from_proto(proto.get_output())?;
+        let proof = MerkleProof::from_proto(proto.get_proof())?;
+        Ok(OutputProof { output, proof })
     }
 }
 
@@ -1074,6 +1972,7 @@ impl ProtoConvert for SlashingProof {
 mod tests {
     use super::*;
     use crate::timestamp::Timestamp;
+    use std::time::Duration;
     use stegos_crypto::hash::{Hash, Hashable};
     use stegos_crypto::pbc;
     use stegos_crypto::scc;
@@ -1106,6 +2005,23 @@ mod tests {
         let (output, _gamma) = Output::new_payment(&pkey1, amount).expect("keys are valid");
         roundtrip(&output);
 
+        let (output, _gamma) =
+            PaymentOutput::new_locked(&pkey1, amount, Timestamp::now()).expect("keys are valid");
+        roundtrip_eq(&output);
+
+        let output =
+            StakeOutput::with_commission(&pkey1, &network_skey1, &network_pkey1, amount, 10)
+                .expect("keys are valid");
+        roundtrip_eq(&output);
+
+        let output =
+            StakeOutput::new_delegated(&pkey1, &network_pkey1, amount).expect("keys are valid");
+        roundtrip_eq(&output);
+
+        let output = StakeOutput::new_unbonding(&pkey1, &network_skey1, &network_pkey1, amount)
+            .expect("keys are valid");
+        roundtrip_eq(&output);
+
         let output = Output::new_stake(&pkey1, &network_skey1, &network_pkey1, amount)
             .expect("keys are valid");
         roundtrip(&output);
@@ -1136,10 +2052,10 @@ mod tests {
 
         let tx = PaymentTransaction::new(&skey1, &inputs1, &[output11], &outputs_gamma, fee)
             .expect("keys are valid");
-        tx.validate(&inputs1).unwrap();
+        tx.validate(&inputs1, Timestamp::now()).unwrap();
 
         let tx2 = roundtrip(&tx);
-        tx2.validate(&inputs1).unwrap();
+        tx2.validate(&inputs1, Timestamp::now()).unwrap();
 
         tx
     }
@@ -1167,13 +2083,66 @@ mod tests {
         let output: Output = output.into();
         roundtrip(&output);
 
-        let output = PublicPaymentOutput::new_locked(&pkey, 100);
+        let output = PublicPaymentOutput::new_locked(&pkey, 100, Timestamp::now());
 
-        roundtrip(&output);
+        roundtrip_eq(&output);
         let output: Output = output.into();
         roundtrip(&output);
     }
 
+    #[test]
+    fn multisig_utxo() {
+        let (skey1, pkey1) = scc::make_random_keys();
+        let (_skey2, pkey2) = scc::make_random_keys();
+        let output = MultisigOutput::new(&[pkey1, pkey2], 2, 100);
+
+        roundtrip_eq(&output);
+        let output_hash = Hash::digest(&output);
+        let inputs: [Output; 1] = [output.into()];
+        roundtrip(&inputs[0]);
+
+        let (output, gamma) = Output::new_payment(&pkey1, 100).expect("keys are valid");
+        let mut tx =
+            PaymentTransaction::new(&skey1, &inputs, &[output], &gamma, 0).expect("keys are valid");
+        tx.sign_multisig(&output_hash, &skey1);
+        roundtrip_eq(&tx);
+    }
+
+    #[test]
+    fn htlc_utxo() {
+        let (_skey1, pkey1) = scc::make_random_keys();
+        let (skey2, pkey2) = scc::make_random_keys();
+        let preimage = b"secret".to_vec();
+        let output = HtlcOutput::new(
+            &pkey1,
+            &pkey2,
+            htlc_hash_lock(&preimage),
+            Timestamp::now(),
+            100,
+        );
+
+        roundtrip_eq(&output);
+        let output_hash = Hash::digest(&output);
+        let inputs: [Output; 1] = [output.into()];
+        roundtrip(&inputs[0]);
+
+        let (output, gamma) = Output::new_payment(&pkey2, 100).expect("keys are valid");
+        let htlc_preimage = HtlcPreimage {
+            txin: output_hash,
+            preimage,
+        };
+        let tx = PaymentTransaction::with_htlc_preimages(
+            &skey2,
+            &inputs,
+            &[output],
+            &gamma,
+            0,
+            vec![htlc_preimage],
+        )
+        .expect("keys are valid");
+        roundtrip_eq(&tx);
+    }
+
     #[test]
     fn payment_transaction() {
         let tx = mktransaction();
@@ -1231,6 +2200,15 @@ mod tests {
         for (tx1, tx2) in block2.transactions.iter().zip(block.transactions.iter()) {
             assert_eq!(Hash::digest(&tx1), Hash::digest(&tx2));
         }
+
+        // Light block.
+        let light_block = LightMicroBlock::from(block);
+        let light_block2 = roundtrip(&light_block);
+        assert_eq!(light_block2.sig, light_block.sig);
+        assert_eq!(light_block2.input_hashes, light_block.input_hashes);
+        assert_eq!(light_block2.canaries, light_block.canaries);
+        light_block2.validate_body().expect("valid body");
+        roundtrip(&LightBlock::LightMicroBlock(light_block2));
     }
 
     #[test]
@@ -1272,12 +2250,19 @@ mod tests {
             block_reward,
             gamma,
             activity_map,
-            validators,
+            validators.clone(),
             inputs1,
             outputs1,
         );
         roundtrip(&block.header);
         roundtrip(&block);
+        let mut header = block.header.clone();
+        header.proposal = Some(ParameterChange {
+            block_reward: Some(1),
+            max_slot_count: Some(2),
+            ..Default::default()
+        });
+        roundtrip(&header);
         let block2 = roundtrip(&block);
         assert_eq!(block2.multisig, block.multisig);
         assert_eq!(block2.multisigmap, block.multisigmap);
@@ -1285,12 +2270,123 @@ mod tests {
         for (input1, input2) in block.inputs.iter().zip(block2.inputs.iter()) {
             assert_eq!(Hash::digest(&input1), Hash::digest(&input2));
         }
-        let outputs1 = block.outputs;
+        let outputs1 = block.outputs.clone();
         let outputs2 = block2.outputs;
         assert_eq!(outputs1.len(), outputs2.len());
         for (input1, input2) in outputs1.iter().zip(outputs2.iter()) {
             assert_eq!(Hash::digest(&input1), Hash::digest(&input2));
         }
+
+        // Light block.
+        let light_block = LightMacroBlock::new(block, validators);
+        let light_block2 = roundtrip(&light_block);
+        assert_eq!(light_block2.multisig, light_block.multisig);
+        assert_eq!(light_block2.multisigmap, light_block.multisigmap);
+        assert_eq!(light_block2.validators, light_block.validators);
+        assert_eq!(light_block2.input_hashes, light_block.input_hashes);
+        assert_eq!(light_block2.canaries, light_block.canaries);
+        light_block2.validate_body().expect("valid body");
+        roundtrip(&LightBlock::LightMacroBlock(light_block2));
+    }
+
+    #[test]
+    fn canaries() {
+        let (_skey, pkey) = scc::make_random_keys();
+        let (_skey2, pkey2) = scc::make_random_keys();
+        let (output, _gamma) = Output::new_payment(&pkey, 100).unwrap();
+        roundtrip_eq(&output.canary());
+        let output = PublicPaymentOutput::new(&pkey, 100);
+        roundtrip_eq(&Output::PublicPaymentOutput(output).canary());
+        roundtrip_eq(&Canary::StakeCanary(StakeCanary { recipient: pkey }));
+        roundtrip_eq(&Canary::MultisigCanary(MultisigCanary {
+            recipients: vec![pkey, pkey2],
+        }));
+        roundtrip_eq(&Canary::HtlcCanary(HtlcCanary {
+            recipient: pkey,
+            refunder: pkey2,
+        }));
+    }
+
+    #[test]
+    fn output_proofs() {
+        let (_skey, pkey) = scc::make_random_keys();
+        let outputs: Vec<Output> = (0..5)
+            .map(|i| Output::new_payment(&pkey, 100 + i).unwrap().0)
+            .collect();
+        let block_hash = Hash::digest("block");
+        let proofs = crate::light::output_proofs(&block_hash, &outputs, &[0, 3, 4]).unwrap();
+        for proof in proofs {
+            let proof2 = roundtrip(&proof);
+            assert_eq!(proof2.proof.path, proof.proof.path);
+            assert_eq!(proof2.proof.siblings, proof.proof.siblings);
+        }
+    }
+
+    #[test]
+    fn slashing_proofs() {
+        let (skey, pkey) = pbc::make_random_keys();
+        let vote = |block_hash: Hash, block_hash_sig: Option<pbc::Signature>| {
+            let mut vote = ConsensusVote {
+                epoch: 10,
+                round: 2,
+                block_hash,
+                block_hash_sig,
+                pkey,
+                sig: pbc::Signature::zero(),
+            };
+            vote.sig = pbc::sign_hash(&vote.message_hash(), &skey);
+            vote
+        };
+        let vote1 = vote(Hash::digest("block1"), None);
+        let vote2 = vote(
+            Hash::digest("block2"),
+            Some(pbc::sign_hash(&Hash::digest("block2"), &skey)),
+        );
+        roundtrip_eq(&vote1);
+        roundtrip_eq(&vote2);
+        roundtrip(&SlashingProof::ConsensusVotes { vote1, vote2 });
+
+        let header = |timestamp: Timestamp| {
+            let random = pbc::make_VRF(&skey, &Hash::digest("random"));
+            MicroBlock::empty(
+                Hash::digest("previous"),
+                10,
+                4,
+                0,
+                None,
+                pkey,
+                random,
+                vec![1u8, 2, 3, 4],
+                timestamp,
+            )
+            .header
+        };
+        let view_change = |last_block: Hash| {
+            let chain = ChainInfo {
+                epoch: 10,
+                offset: 5,
+                view_change: 1,
+                last_block,
+            };
+            let sig = pbc::sign_hash(&Hash::digest(&chain), &skey);
+            ViewChangeVote { chain, pkey, sig }
+        };
+        let timestamp = Timestamp::now();
+        let header1 = header(timestamp);
+        let header2 = header(timestamp + Duration::from_millis(1));
+        let view_change1 = view_change(Hash::digest(&header1));
+        let view_change2 = view_change(Hash::digest(&header2));
+        roundtrip_eq(&view_change1);
+        roundtrip(&SlashingProof::ViewChanges {
+            view_change1,
+            view_change2,
+            header1,
+            header2,
+        });
+        roundtrip(&SlashingProof::Downtime {
+            validator: pkey,
+            epoch: 10,
+        });
     }
 
     #[test]
@@ -1306,6 +2402,52 @@ mod tests {
             output_id: 43,
         };
         roundtrip_eq(&key);
+
+        let key = OutputKey::Snapshot { epoch: 7 };
+        roundtrip_eq(&key);
+    }
+
+    #[test]
+    fn roundtrip_transaction_key() {
+        let key = TransactionKey {
+            epoch: 12,
+            offset: 3,
+            tx_id: 43,
+        };
+        roundtrip_eq(&key);
+    }
+
+    #[test]
+    fn roundtrip_indexed_transaction() {
+        let (skey, pkey) = scc::make_random_keys();
+        let (tx, _inputs, _outputs) =
+            PaymentTransaction::new_test(&skey, &pkey, 300, 2, 100, 1, 100)
+                .expect("Invalid transaction");
+        let indexed = IndexedTransaction {
+            key: TransactionKey {
+                epoch: 12,
+                offset: 3,
+                tx_id: 43,
+            },
+            block_hash: Hash::digest("test"),
+            transaction: tx.into(),
+        };
+        let indexed2 = IndexedTransaction::from_proto(&indexed.into_proto()).unwrap();
+        assert_eq!(indexed2.key, indexed.key);
+        assert_eq!(indexed2.block_hash, indexed.block_hash);
+        assert_eq!(
+            Hash::digest(&indexed2.transaction),
+            Hash::digest(&indexed.transaction)
+        );
+    }
+
+    #[test]
+    fn roundtrip_undo_log_entry() {
+        let entry = UndoLogEntry::Push(Hash::digest("test"));
+        roundtrip_eq(&entry);
+
+        let entry = UndoLogEntry::Pop(Hash::digest("test"));
+        roundtrip_eq(&entry);
     }
 
     #[test]
@@ -1323,6 +2465,29 @@ mod tests {
             account_pkey: scc::PublicKey::from(scc::Pt::random()),
             active_until_epoch: 324,
             amount: 55,
+            commission: 0,
+            delegated: false,
+            unbonding: false,
+        };
+        roundtrip_eq(&value);
+
+        let value = EscrowValue {
+            account_pkey: scc::PublicKey::from(scc::Pt::random()),
+            active_until_epoch: 324,
+            amount: 55,
+            commission: 10,
+            delegated: true,
+            unbonding: false,
+        };
+        roundtrip_eq(&value);
+
+        let value = EscrowValue {
+            account_pkey: scc::PublicKey::from(scc::Pt::random()),
+            active_until_epoch: 324,
+            amount: 55,
+            commission: 0,
+            delegated: false,
+            unbonding: true,
         };
         roundtrip_eq(&value);
     }
@@ -1351,6 +2516,46 @@ mod tests {
         roundtrip_eq(&balance);
     }
 
+    #[test]
+    fn roundtrip_governance() {
+        let change = ParameterChange {
+            block_reward: Some(10),
+            min_stake_amount: Some(20),
+            service_award_per_epoch: Some(30),
+            max_slot_count: Some(40),
+        };
+        roundtrip_eq(&change);
+        let governance = Governance {
+            active: change.clone(),
+            pending: None,
+            votes: 0,
+        };
+        roundtrip_eq(&governance);
+        let governance = Governance {
+            active: ParameterChange::default(),
+            pending: Some(change),
+            votes: 3,
+        };
+        roundtrip_eq(&governance);
+    }
+
+    #[test]
+    fn roundtrip_liveness() {
+        let (_skey, pkey1) = pbc::make_random_keys();
+        let (_skey, pkey2) = pbc::make_random_keys();
+        let mut liveness = Liveness::default();
+        roundtrip_eq(&liveness);
+        liveness.missed.insert(pkey1, 2);
+        liveness.jailed.insert(
+            pkey2,
+            JailedValidator {
+                epoch: 5,
+                release_epoch: 17,
+            },
+        );
+        roundtrip_eq(&liveness);
+    }
+
     #[test]
     fn roundtrip_epoch_info() {
         let key = ValidatorKeyInfo {
@@ -1403,5 +2608,22 @@ mod tests {
             awards: awards_info,
         };
         roundtrip_eq(&epoch_info);
+
+        let winner = scc::PublicKey::from(scc::Pt::random());
+        let draw = AwardDraw {
+            epoch: 12,
+            random: Hash::digest("random"),
+            difficulty: 8,
+            eligible: vec![winner, scc::PublicKey::from(scc::Pt::random())],
+            winner: Some(winner),
+            payout: 656,
+        };
+        roundtrip_eq(&draw);
+        let draw = AwardDraw {
+            winner: None,
+            payout: 0,
+            ..draw
+        };
+        roundtrip_eq(&draw);
     }
 }
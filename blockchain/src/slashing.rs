@@ -21,79 +21,360 @@
 
 use crate::error::SlashingError;
 use crate::transaction::SlashingTransaction;
-use crate::{Blockchain, BlockchainError, MicroBlock, PublicPaymentOutput};
+use crate::{
+    Blockchain, BlockchainError, ChainInfo, MicroBlock, MicroBlockHeader, PublicPaymentOutput,
+};
 use log::debug;
 use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use stegos_crypto::hash::{Hash, Hashable, Hasher};
-use stegos_crypto::pbc;
+use stegos_crypto::{pbc, scc};
 
+/// A signed pre-vote or pre-commit of a validator in the macro block consensus.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsensusVote {
+    /// Consensus epoch.
+    pub epoch: u64,
+    /// Consensus round.
+    pub round: u32,
+    /// Hash of the proposed macro block.
+    pub block_hash: Hash,
+    /// Signature of `block_hash`, present only in pre-commits.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_hash_sig: Option<pbc::Signature>,
+    /// Sender of the vote.
+    pub pkey: pbc::PublicKey,
+    /// Signature of the consensus message.
+    pub sig: pbc::Signature,
+}
+
+/// Returns the hash signed by a consensus message with `body`.
+/// The consensus crate signs its messages with this hash.
+pub fn consensus_message_hash<B: Hashable + ?Sized>(
+    epoch: u64,
+    round: u32,
+    block_hash: &Hash,
+    body: &B,
+) -> Hash {
+    let mut hasher = Hasher::new();
+    epoch.hash(&mut hasher);
+    round.hash(&mut hasher);
+    block_hash.hash(&mut hasher);
+    body.hash(&mut hasher);
+    hasher.result()
+}
+
+/// Hashes the body of a pre-vote (`None`) or a pre-commit (`Some(block_hash_sig)`).
+pub fn hash_vote_body(block_hash_sig: Option<&pbc::Signature>, state: &mut Hasher) {
+    match block_hash_sig {
+        None => "Prevote".hash(state),
+        Some(block_hash_sig) => {
+            "Precommit".hash(state);
+            block_hash_sig.hash(state);
+        }
+    }
+}
+
+/// The body of a vote, as signed in the consensus message.
+struct VoteBody<'a>(Option<&'a pbc::Signature>);
+
+impl<'a> Hashable for VoteBody<'a> {
+    fn hash(&self, state: &mut Hasher) {
+        hash_vote_body(self.0, state)
+    }
+}
+
+impl ConsensusVote {
+    /// Returns the hash signed by the consensus message.
+    pub fn message_hash(&self) -> Hash {
+        let body = VoteBody(self.block_hash_sig.as_ref());
+        consensus_message_hash(self.epoch, self.round, &self.block_hash, &body)
+    }
+}
+
+impl Hashable for ConsensusVote {
+    fn hash(&self, state: &mut Hasher) {
+        self.message_hash().hash(state);
+        self.pkey.hash(state);
+        self.sig.hash(state);
+    }
+}
+
+/// A signed view change message of a validator.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ViewChangeVote {
+    /// The state of the chain signed by the validator.
+    pub chain: ChainInfo,
+    /// Sender of the message.
+    pub pkey: pbc::PublicKey,
+    /// Signature of `chain`.
+    pub sig: pbc::Signature,
+}
+
+impl Hashable for ViewChangeVote {
+    fn hash(&self, state: &mut Hasher) {
+        self.chain.hash(state);
+        self.pkey.hash(state);
+        self.sig.hash(state);
+    }
+}
+
+/// A proof that a validator has signed two conflicting messages.
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct SlashingProof {
-    pub block1: MicroBlock,
-    pub block2: MicroBlock,
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+pub enum SlashingProof {
+    /// Two different micro blocks of the same leader at the same (epoch, offset, view_change).
+    MicroBlocks {
+        block1: MicroBlock,
+        block2: MicroBlock,
+    },
+    /// Two votes of the same kind for different macro blocks at the same (epoch, round).
+    ConsensusVotes {
+        vote1: ConsensusVote,
+        vote2: ConsensusVote,
+    },
+    /// Two view change messages at the same (epoch, offset, view_change) for different
+    /// last blocks, which have the same parent and view_change.
+    ViewChanges {
+        view_change1: ViewChangeVote,
+        view_change2: ViewChangeVote,
+        header1: MicroBlockHeader,
+        header2: MicroBlockHeader,
+    },
+    /// A validator jailed for downtime by the macro block of `epoch`.
+    Downtime {
+        validator: pbc::PublicKey,
+        epoch: u64,
+    },
 }
 
 impl SlashingProof {
     pub fn new_unchecked(block1: MicroBlock, block2: MicroBlock) -> SlashingProof {
-        let proof = SlashingProof { block1, block2 };
+        let proof = SlashingProof::MicroBlocks { block1, block2 };
         proof
     }
 
-    pub fn validate(&self, blockchain: &Blockchain) -> Result<(), BlockchainError> {
-        let epoch = self.block1.header.epoch;
-        let offset = self.block1.header.offset;
-
-        if self.block1.header.epoch != self.block2.header.epoch {
-            return Err(SlashingError::DifferentEpoch(
-                self.block1.header.epoch,
-                self.block2.header.epoch,
-            )
-            .into());
+    /// Returns the validator who has signed conflicting messages.
+    pub fn cheater(&self) -> pbc::PublicKey {
+        match self {
+            SlashingProof::MicroBlocks { block1, .. } => block1.header.pkey,
+            SlashingProof::ConsensusVotes { vote1, .. } => vote1.pkey,
+            SlashingProof::ViewChanges { view_change1, .. } => view_change1.pkey,
+            SlashingProof::Downtime { validator, .. } => *validator,
         }
+    }
 
-        if self.block1.header.offset != self.block2.header.offset {
-            return Err(SlashingError::DifferentOffset(
-                self.block1.header.offset,
-                self.block2.header.offset,
-            )
-            .into());
+    /// Returns the epoch of conflicting messages.
+    pub fn epoch(&self) -> u64 {
+        match self {
+            SlashingProof::MicroBlocks { block1, .. } => block1.header.epoch,
+            SlashingProof::ConsensusVotes { vote1, .. } => vote1.epoch,
+            SlashingProof::ViewChanges { view_change1, .. } => view_change1.chain.epoch,
+            SlashingProof::Downtime { epoch, .. } => *epoch,
         }
+    }
 
-        if epoch != blockchain.epoch() {
-            return Err(SlashingError::InvalidProofEpoch(epoch, blockchain.epoch()).into());
+    pub fn validate(&self, blockchain: &Blockchain) -> Result<(), BlockchainError> {
+        match self {
+            SlashingProof::MicroBlocks { block1, block2 } => {
+                validate_micro_blocks(blockchain, block1, block2)
+            }
+            SlashingProof::ConsensusVotes { vote1, vote2 } => {
+                validate_consensus_votes(blockchain, vote1, vote2)
+            }
+            SlashingProof::ViewChanges {
+                view_change1,
+                view_change2,
+                header1,
+                header2,
+            } => validate_view_changes(blockchain, view_change1, view_change2, header1, header2),
+            SlashingProof::Downtime { validator, epoch } => {
+                validate_downtime(blockchain, validator, *epoch)
+            }
         }
+    }
+}
 
-        if self.block1.header.previous != self.block2.header.previous {
-            return Err(SlashingError::DifferentHistory(
-                self.block1.header.previous,
-                self.block2.header.previous,
-            )
-            .into());
-        }
+fn validate_micro_blocks(
+    blockchain: &Blockchain,
+    block1: &MicroBlock,
+    block2: &MicroBlock,
+) -> Result<(), BlockchainError> {
+    let epoch = block1.header.epoch;
+    let offset = block1.header.offset;
 
-        if self.block1.header.view_change != self.block2.header.view_change {
-            return Err(SlashingError::DifferentLeader(
-                self.block1.header.view_change,
-                self.block2.header.view_change,
-            )
-            .into());
-        }
+    if block1.header.epoch != block2.header.epoch {
+        return Err(SlashingError::DifferentEpoch(block1.header.epoch, block2.header.epoch).into());
+    }
 
-        let block1_hash = Hash::digest(&self.block1);
+    if block1.header.offset != block2.header.offset {
+        return Err(
+            SlashingError::DifferentOffset(block1.header.offset, block2.header.offset).into(),
+        );
+    }
 
-        let block2_hash = Hash::digest(&self.block2);
-        if block1_hash == block2_hash {
-            return Err(SlashingError::BlockWithoutConflicts(epoch, offset, block1_hash).into());
-        }
+    if epoch != blockchain.epoch() {
+        return Err(SlashingError::InvalidProofEpoch(epoch, blockchain.epoch()).into());
+    }
+
+    if block1.header.previous != block2.header.previous {
+        return Err(SlashingError::DifferentHistory(
+            block1.header.previous,
+            block2.header.previous,
+        )
+        .into());
+    }
+
+    if block1.header.view_change != block2.header.view_change {
+        return Err(SlashingError::DifferentLeader(
+            block1.header.view_change,
+            block2.header.view_change,
+        )
+        .into());
+    }
+
+    let block1_hash = Hash::digest(block1);
+
+    let block2_hash = Hash::digest(block2);
+    if block1_hash == block2_hash {
+        return Err(SlashingError::BlockWithoutConflicts(epoch, offset, block1_hash).into());
+    }
+
+    let election_result = blockchain.election_result_by_offset(offset)?;
+
+    let ref leader_pk = election_result.select_leader(block1.header.view_change);
+
+    pbc::check_hash(&block1_hash, &block1.sig, leader_pk)?;
+    pbc::check_hash(&block2_hash, &block2.sig, leader_pk)?;
+    Ok(())
+}
+
+fn validate_consensus_votes(
+    blockchain: &Blockchain,
+    vote1: &ConsensusVote,
+    vote2: &ConsensusVote,
+) -> Result<(), BlockchainError> {
+    if vote1.pkey != vote2.pkey {
+        return Err(SlashingError::DifferentSigners(vote1.pkey, vote2.pkey).into());
+    }
+    if vote1.epoch != vote2.epoch {
+        return Err(SlashingError::DifferentEpoch(vote1.epoch, vote2.epoch).into());
+    }
+    if vote1.round != vote2.round {
+        return Err(SlashingError::DifferentRound(vote1.round, vote2.round).into());
+    }
+    if vote1.block_hash_sig.is_some() != vote2.block_hash_sig.is_some() {
+        return Err(SlashingError::DifferentVoteKind(vote1.epoch, vote1.round).into());
+    }
+    if vote1.block_hash == vote2.block_hash {
+        return Err(SlashingError::MessagesWithoutConflicts(vote1.epoch, vote1.pkey).into());
+    }
+    check_validator(blockchain, vote1.epoch, &vote1.pkey)?;
+    pbc::check_hash(&vote1.message_hash(), &vote1.sig, &vote1.pkey)?;
+    pbc::check_hash(&vote2.message_hash(), &vote2.sig, &vote2.pkey)?;
+    Ok(())
+}
 
-        let election_result = blockchain.election_result_by_offset(offset)?;
+/// An honest validator votes for another last block at the same (epoch, offset, view_change)
+/// only after a fork switch, which replaces a block with a block of greater view_change.
+/// Only votes for two last blocks with the same parent and view_change are punished.
+fn validate_view_changes(
+    blockchain: &Blockchain,
+    view_change1: &ViewChangeVote,
+    view_change2: &ViewChangeVote,
+    header1: &MicroBlockHeader,
+    header2: &MicroBlockHeader,
+) -> Result<(), BlockchainError> {
+    let (chain1, chain2) = (&view_change1.chain, &view_change2.chain);
+    if view_change1.pkey != view_change2.pkey {
+        return Err(SlashingError::DifferentSigners(view_change1.pkey, view_change2.pkey).into());
+    }
+    if chain1.epoch != chain2.epoch {
+        return Err(SlashingError::DifferentEpoch(chain1.epoch, chain2.epoch).into());
+    }
+    if chain1.offset != chain2.offset {
+        return Err(SlashingError::DifferentOffset(chain1.offset, chain2.offset).into());
+    }
+    if chain1.view_change != chain2.view_change {
+        return Err(SlashingError::DifferentLeader(chain1.view_change, chain2.view_change).into());
+    }
+    if chain1.last_block == chain2.last_block {
+        return Err(
+            SlashingError::MessagesWithoutConflicts(chain1.epoch, view_change1.pkey).into(),
+        );
+    }
+    let header1_hash = Hash::digest(header1);
+    if header1_hash != chain1.last_block {
+        return Err(SlashingError::InvalidLastBlockHeader(chain1.last_block, header1_hash).into());
+    }
+    let header2_hash = Hash::digest(header2);
+    if header2_hash != chain2.last_block {
+        return Err(SlashingError::InvalidLastBlockHeader(chain2.last_block, header2_hash).into());
+    }
+    if header1.previous != header2.previous {
+        return Err(SlashingError::DifferentHistory(header1.previous, header2.previous).into());
+    }
+    if header1.view_change != header2.view_change {
+        return Err(
+            SlashingError::DifferentLeader(header1.view_change, header2.view_change).into(),
+        );
+    }
+    check_validator(blockchain, chain1.epoch, &view_change1.pkey)?;
+    pbc::check_hash(&Hash::digest(chain1), &view_change1.sig, &view_change1.pkey)?;
+    pbc::check_hash(&Hash::digest(chain2), &view_change2.sig, &view_change2.pkey)?;
+    Ok(())
+}
 
-        let ref leader_pk = election_result.select_leader(self.block1.header.view_change);
+fn validate_downtime(
+    blockchain: &Blockchain,
+    validator: &pbc::PublicKey,
+    epoch: u64,
+) -> Result<(), BlockchainError> {
+    // Only the jail of the last macro block can be punished, and only once.
+    if epoch + 1 != blockchain.epoch() {
+        return Err(SlashingError::InvalidProofEpoch(epoch, blockchain.epoch()).into());
+    }
+    if blockchain.cfg().jail_penalty <= 0 {
+        return Err(SlashingError::JailPenaltyDisabled.into());
+    }
+    match blockchain.liveness().jailed.get(validator) {
+        Some(jailed) if jailed.epoch == epoch => Ok(()),
+        _ => Err(SlashingError::NotJailed(*validator, epoch).into()),
+    }
+}
 
-        pbc::check_hash(&block1_hash, &self.block1.sig, leader_pk)?;
-        pbc::check_hash(&block2_hash, &self.block2.sig, leader_pk)?;
-        Ok(())
+/// Checks that `pkey` was a validator at `epoch`.
+/// Only messages of the current and the previous epoch can be punished,
+/// because the macro block consensus finishes the epoch.
+fn check_validator(
+    blockchain: &Blockchain,
+    epoch: u64,
+    pkey: &pbc::PublicKey,
+) -> Result<(), BlockchainError> {
+    let is_validator = if epoch == blockchain.epoch() {
+        blockchain
+            .validators_at_epoch_start()
+            .iter()
+            .any(|(k, _)| k == pkey)
+    } else if epoch > 0 && epoch + 1 == blockchain.epoch() {
+        // Validators of the epoch are stored in the info of the previous epoch.
+        match blockchain.epoch_info(epoch - 1)? {
+            Some(epoch_info) => epoch_info
+                .validators
+                .iter()
+                .any(|v| &v.network_pkey == pkey),
+            None => {
+                return Err(SlashingError::InvalidProofEpoch(epoch, blockchain.epoch()).into());
+            }
+        }
+    } else {
+        return Err(SlashingError::InvalidProofEpoch(epoch, blockchain.epoch()).into());
+    };
+    if !is_validator {
+        return Err(SlashingError::NotValidator(*pkey).into());
     }
+    Ok(())
 }
 
 pub fn confiscate_tx(
@@ -101,19 +382,23 @@ pub fn confiscate_tx(
     our_key: &pbc::PublicKey, // our key, used to add change to payment utxo.
     proof: SlashingProof,
 ) -> Result<SlashingTransaction, BlockchainError> {
-    assert_eq!(proof.block1.header.pkey, proof.block2.header.pkey);
-    let ref cheater = proof.block1.header.pkey;
+    let ref cheater = proof.cheater();
     let epoch = chain.epoch();
-    let (inputs, stake) = chain.iter_validator_stakes(cheater).fold(
-        (Vec::<Hash>::new(), 0i64),
-        |(mut result, mut stake), (hash, amount, _, active_until_epoch)| {
-            if active_until_epoch >= epoch {
-                stake += amount;
-                result.push(hash.clone());
-            }
-            (result, stake)
-        },
-    );
+    // Delegators and unbonding stakes share the risk of the validator.
+    let mut inputs = Vec::<Hash>::new();
+    let mut stake = 0i64;
+    let mut owners: BTreeMap<scc::PublicKey, i64> = BTreeMap::new();
+    for (hash, amount, account_pkey, active_until_epoch) in chain
+        .iter_validator_stakes(cheater)
+        .chain(chain.iter_delegated_stakes(cheater))
+        .chain(chain.iter_unbonding_stakes(cheater))
+    {
+        if active_until_epoch >= epoch {
+            stake += amount;
+            inputs.push(hash.clone());
+            *owners.entry(*account_pkey).or_insert(0) += amount;
+        }
+    }
     let validators: Vec<_> = chain
         .validators()
         .iter()
@@ -131,8 +416,22 @@ pub fn confiscate_tx(
 
     proof.validate(&chain)?;
     assert!(stake > 0);
-    let piece = stake / validators.len() as i64;
-    let change = stake % validators.len() as i64;
+    // Jailed validators lose only a part of the stake, the rest is unlocked.
+    let refunds: Vec<(scc::PublicKey, i64)> = match &proof {
+        SlashingProof::Downtime { .. } => owners
+            .into_iter()
+            .map(|(account_pkey, amount)| {
+                let penalty = amount * chain.cfg().jail_penalty.min(100) / 100;
+                (account_pkey, amount - penalty)
+            })
+            .filter(|(_, refund)| *refund > 0)
+            .collect(),
+        _ => Vec::new(),
+    };
+    let refund: i64 = refunds.iter().map(|(_, amount)| amount).sum();
+    let penalty = stake - refund;
+    let piece = penalty / validators.len() as i64;
+    let change = penalty % validators.len() as i64;
 
     let mut outputs = Vec::new();
     for validator in &validators {
@@ -145,7 +444,10 @@ pub fn confiscate_tx(
         }
         outputs.push(output.into());
     }
-    debug!("Creating confiscate transaction: cheater = {}, piece = {}, change = {}, num_validators = {}", cheater, piece, change, outputs.len());
+    for (key, amount) in refunds {
+        outputs.push(PublicPaymentOutput::new(&key, amount).into());
+    }
+    debug!("Creating confiscate transaction: cheater = {}, piece = {}, change = {}, refund = {}, num_validators = {}", cheater, piece, change, refund, validators.len());
 
     Ok(SlashingTransaction {
         proof,
@@ -156,7 +458,181 @@ pub fn confiscate_tx(
 
 impl Hashable for SlashingProof {
     fn hash(&self, state: &mut Hasher) {
-        self.block1.hash(state);
-        self.block2.hash(state);
+        match self {
+            SlashingProof::MicroBlocks { block1, block2 } => {
+                block1.hash(state);
+                block2.hash(state);
+            }
+            SlashingProof::ConsensusVotes { vote1, vote2 } => {
+                "ConsensusVotes".hash(state);
+                vote1.hash(state);
+                vote2.hash(state);
+            }
+            SlashingProof::ViewChanges {
+                view_change1,
+                view_change2,
+                header1,
+                header2,
+            } => {
+                "ViewChanges".hash(state);
+                view_change1.hash(state);
+                view_change2.hash(state);
+                header1.hash(state);
+                header2.hash(state);
+            }
+            SlashingProof::Downtime { validator, epoch } => {
+                "Downtime".hash(state);
+                validator.hash(state);
+                epoch.hash(state);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::TransactionError;
+    use crate::test::{self, KeyChain};
+    use crate::{ChainConfig, ConsistencyCheck, Timestamp};
+    use std::time::Duration;
+    use tempdir::TempDir;
+
+    fn header(
+        keychain: &KeyChain,
+        previous: Hash,
+        epoch: u64,
+        view_change: u32,
+        timestamp: Timestamp,
+    ) -> MicroBlockHeader {
+        let random = pbc::make_VRF(&keychain.network_skey, &Hash::digest("random"));
+        MicroBlock::empty(
+            previous,
+            epoch,
+            4,
+            view_change,
+            None,
+            keychain.network_pkey,
+            random,
+            vec![1u8, 2, 3, 4],
+            timestamp,
+        )
+        .header
+    }
+
+    /// A view change at offset 5 on top of the block with `header`.
+    fn view_change(keychain: &KeyChain, header: &MicroBlockHeader) -> ViewChangeVote {
+        let chain = ChainInfo {
+            epoch: header.epoch,
+            offset: header.offset + 1,
+            view_change: 0,
+            last_block: Hash::digest(header),
+        };
+        let sig = pbc::sign_hash(&Hash::digest(&chain), &keychain.network_skey);
+        ViewChangeVote {
+            chain,
+            pkey: keychain.network_pkey,
+            sig,
+        }
+    }
+
+    fn proof(
+        keychain: &KeyChain,
+        header1: MicroBlockHeader,
+        header2: MicroBlockHeader,
+    ) -> SlashingProof {
+        SlashingProof::ViewChanges {
+            view_change1: view_change(keychain, &header1),
+            view_change2: view_change(keychain, &header2),
+            header1,
+            header2,
+        }
+    }
+
+    fn check_slashing_error(result: Result<(), BlockchainError>) -> SlashingError {
+        match result {
+            Err(BlockchainError::TransactionError(TransactionError::SlashingError(e))) => e,
+            e => panic!("Unexpected result: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn view_changes() {
+        let mut timestamp = Timestamp::now();
+        let cfg: ChainConfig = Default::default();
+        let (keychains, genesis) = test::fake_genesis(
+            cfg.min_stake_amount,
+            10 * cfg.min_stake_amount,
+            cfg.max_slot_count,
+            3,
+            timestamp,
+            None,
+        );
+        let chain_dir = TempDir::new("test").unwrap();
+        timestamp += Duration::from_millis(1);
+        let chain = Blockchain::new(
+            cfg,
+            chain_dir.path(),
+            ConsistencyCheck::None,
+            genesis,
+            timestamp,
+        )
+        .expect("Failed to create blockchain");
+        let epoch = chain.epoch();
+        let (leader, validator) = (&keychains[0], &keychains[1]);
+        let previous = Hash::digest("previous");
+        let block1 = header(leader, previous, epoch, 0, timestamp);
+
+        // Two blocks of the leader at the same view_change.
+        let block2 = header(
+            leader,
+            previous,
+            epoch,
+            0,
+            timestamp + Duration::from_millis(1),
+        );
+        proof(validator, block1.clone(), block2)
+            .validate(&chain)
+            .expect("equivocation");
+
+        // Fork switch replaces the block with a block of greater view_change.
+        let block2 = header(leader, previous, epoch, 1, timestamp);
+        match check_slashing_error(proof(validator, block1.clone(), block2).validate(&chain)) {
+            SlashingError::DifferentLeader(0, 1) => {}
+            e => panic!("Unexpected error: {:?}", e),
+        }
+
+        // Fork switch at an earlier offset.
+        let block2 = header(leader, Hash::digest("other"), epoch, 0, timestamp);
+        match check_slashing_error(proof(validator, block1.clone(), block2).validate(&chain)) {
+            SlashingError::DifferentHistory(..) => {}
+            e => panic!("Unexpected error: {:?}", e),
+        }
+
+        // Header doesn't match the view change.
+        let block2 = header(
+            leader,
+            previous,
+            epoch,
+            0,
+            timestamp + Duration::from_millis(1),
+        );
+        let block3 = header(
+            leader,
+            previous,
+            epoch,
+            0,
+            timestamp + Duration::from_millis(2),
+        );
+        let proof = SlashingProof::ViewChanges {
+            view_change1: view_change(validator, &block1),
+            view_change2: view_change(validator, &block2),
+            header1: block1,
+            header2: block3,
+        };
+        match check_slashing_error(proof.validate(&chain)) {
+            SlashingError::InvalidLastBlockHeader(..) => {}
+            e => panic!("Unexpected error: {:?}", e),
+        }
     }
 }
@@ -0,0 +1,113 @@
+//! Blockchain state snapshots.
+
+//
+// Copyright (c) 2018 Stegos AG
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use crate::awards::Awards;
+use crate::block::{MacroBlock, MacroBlockHeader, StakersGroup};
+use crate::escrow::{EscrowKey, EscrowValue};
+use crate::output::Output;
+use bit_vec::BitVec;
+use serde_derive::{Deserialize, Serialize};
+use stegos_crypto::hash::{Hash, Hashable, Hasher};
+use stegos_crypto::pbc;
+use stegos_crypto::scc::Fr;
+
+/// A macro block header together with the signature of validators.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MacroBlockCertificate {
+    /// Header of the macro block.
+    pub(crate) header: MacroBlockHeader,
+    /// BLS (multi-)signature.
+    pub(crate) multisig: pbc::Signature,
+    /// Bitmap of signers in the multi-signature.
+    #[serde(deserialize_with = "stegos_crypto::utils::deserialize_bitvec")]
+    #[serde(serialize_with = "stegos_crypto::utils::serialize_bitvec")]
+    pub(crate) multisigmap: BitVec,
+    /// Validators which signed this block.
+    pub(crate) validators: StakersGroup,
+}
+
+/// A snapshot of the blockchain state as of some macro block.
+///
+/// The snapshot is verified by the state commitment in the macro block header,
+/// which is signed by the validators. The validators themselves are verified
+/// by a chain of macro block certificates starting from the genesis.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    /// Certificates of macro blocks from the epoch 1 up to the snapshot block (exclusive).
+    pub(crate) certificates: Vec<MacroBlockCertificate>,
+    /// The macro block of the snapshot.
+    pub(crate) block: MacroBlock,
+    /// Validators which signed the macro block of the snapshot.
+    pub(crate) validators: StakersGroup,
+    /// Unspent outputs.
+    pub(crate) outputs: Vec<Output>,
+    /// Active stakes.
+    pub(crate) stakes: Vec<(EscrowKey, EscrowValue)>,
+    /// Service awards.
+    pub(crate) awards: Awards,
+    /// Cumulative gamma of the monetary balance.
+    pub(crate) gamma: Fr,
+    /// Cumulative block reward of the monetary balance.
+    pub(crate) block_reward: i64,
+}
+
+impl StateSnapshot {
+    /// Returns the epoch of the snapshot.
+    pub fn epoch(&self) -> u64 {
+        self.block.header.epoch
+    }
+
+    /// Returns the hash of the macro block of the snapshot.
+    pub fn block_hash(&self) -> Hash {
+        Hash::digest(&self.block)
+    }
+
+    /// Returns the number of unspent outputs in the snapshot.
+    pub fn utxo_count(&self) -> usize {
+        self.outputs.len()
+    }
+}
+
+///
+/// Calculate the commitment to the blockchain state.
+/// UTXO and stakes must be sorted.
+///
+pub(crate) fn state_hash<'a, U, S>(utxos: U, stakes: S, awards: &Awards) -> Hash
+where
+    U: ExactSizeIterator<Item = &'a Hash>,
+    S: ExactSizeIterator<Item = (&'a EscrowKey, &'a EscrowValue)>,
+{
+    let mut hasher = Hasher::new();
+    "State".hash(&mut hasher);
+    (utxos.len() as u64).hash(&mut hasher);
+    for output_hash in utxos {
+        output_hash.hash(&mut hasher);
+    }
+    (stakes.len() as u64).hash(&mut hasher);
+    for (key, value) in stakes {
+        key.hash(&mut hasher);
+        value.hash(&mut hasher);
+    }
+    awards.hash(&mut hasher);
+    hasher.result()
+}
@@ -28,7 +28,7 @@ use super::blockchain::{Blockchain, OutputRecovery};
 use super::election::mix;
 use super::error::BlockchainError;
 use super::multisignature::create_multi_signature;
-use super::output::{Output, PaymentOutput, PaymentPayloadData, StakeOutput};
+use super::output::{Output, PaymentOutput, PaymentPayloadData, PublicPaymentOutput, StakeOutput};
 use super::timestamp::Timestamp;
 use super::transaction::{
     CoinbaseTransaction, PaymentTransaction, RestakeTransaction, Transaction,
@@ -102,6 +102,8 @@ fn recover_account(
             Output::PaymentOutput(o) => o.decrypt_payload(&account_pkey, &account_skey).is_ok(),
             Output::PublicPaymentOutput(o) => &o.recipient == account_pkey,
             Output::StakeOutput(o) => &o.recipient == account_pkey,
+            Output::MultisigOutput(o) => o.recipients.contains(account_pkey),
+            Output::HtlcOutput(o) => &o.recipient == account_pkey || &o.refunder == account_pkey,
         };
         if is_my_utxo {
             let output = OutputRecovery {
@@ -110,6 +112,7 @@ fn recover_account(
                 block_hash: block_hash.clone(),
                 timestamp,
                 is_final,
+                utxo_proof: None,
             };
 
             if is_final {
@@ -133,6 +136,7 @@ fn recover_account(
                 block_hash: block_hash.clone(),
                 timestamp,
                 is_final,
+                utxo_proof: None,
             };
 
             account_state.removed.insert(*input, output);
@@ -295,6 +299,7 @@ pub fn create_fake_macro_block(
         &keys.network_skey,
         keys.network_pkey,
         timestamp,
+        None,
     );
     sign_fake_macro_block(&mut block, chain, keychains);
     (block, extra_transactions)
@@ -323,15 +328,21 @@ pub fn create_fake_micro_block(
     // Create coinbase transaction.
     //
     let coinbase_tx = {
+        let payouts = chain.delegation_payouts(&leader.network_pkey, block_reward);
+        let delegated: i64 = payouts.iter().map(|(_, amount)| amount).sum();
         let data = PaymentPayloadData::Comment(format!("Block reward"));
         let (output, gamma, _rvalue) =
-            PaymentOutput::with_payload(None, &leader.account_pkey, block_reward, data)
+            PaymentOutput::with_payload(None, &leader.account_pkey, block_reward - delegated, data)
                 .expect("invalid keys");
+        let mut txouts = vec![Output::PaymentOutput(output)];
+        for (delegator_pkey, amount) in payouts {
+            txouts.push(PublicPaymentOutput::new(&delegator_pkey, amount).into());
+        }
         CoinbaseTransaction {
             block_reward,
             block_fee,
             gamma: -gamma,
-            txouts: vec![Output::PaymentOutput(output)],
+            txouts,
         }
     };
     coinbase_tx.validate().expect("Invalid transaction");
@@ -380,6 +391,10 @@ pub fn create_fake_micro_block(
                     staking_balance += o.amount;
                     stakes.push(output);
                 }
+                // Requires signatures of co-signers.
+                Output::MultisigOutput(ref _o) => continue,
+                // Requires a preimage or an expired deadline.
+                Output::HtlcOutput(ref _o) => continue,
             }
         }
 
@@ -402,7 +417,8 @@ pub fn create_fake_micro_block(
                 block_fee,
             )
             .expect("Invalid keys");
-            tx.validate(&inputs).expect("Invalid transaction");
+            tx.validate(&inputs, chain.last_macro_block_timestamp())
+                .expect("Invalid transaction");
             transactions.push(tx.into());
         }
 
@@ -450,6 +466,7 @@ pub fn create_fake_micro_block(
         timestamp,
         transactions,
     );
+    block.header.version = chain.version();
     block.sign(&leader.network_skey, &leader.network_pkey);
     (block, input_hashes, output_hashes)
 }
@@ -474,8 +491,10 @@ pub fn create_micro_block_with_coinbase(
 
     let block_fee = 0;
     let block_reward = chain.cfg().block_reward;
+    let payouts = chain.delegation_payouts(&keys.network_pkey, block_reward);
+    let delegated: i64 = payouts.iter().map(|(_, amount)| amount).sum();
     // Create outputs for fee and rewards.
-    for (amount, comment) in vec![(block_fee, "fee"), (block_reward, "reward")] {
+    for (amount, comment) in vec![(block_fee, "fee"), (block_reward - delegated, "reward")] {
         if amount <= 0 {
             continue;
         }
@@ -495,6 +514,9 @@ pub fn create_micro_block_with_coinbase(
         );
         txouts.push(Output::PaymentOutput(output_fee));
     }
+    for (delegator_pkey, amount) in payouts {
+        txouts.push(PublicPaymentOutput::new(&delegator_pkey, amount).into());
+    }
 
     let coinbase = CoinbaseTransaction {
         block_reward,
@@ -515,6 +537,7 @@ pub fn create_micro_block_with_coinbase(
         timestamp,
         txs,
     );
+    block.header.version = chain.version();
     block.sign(&keys.network_skey, &keys.network_pkey);
     block
 }
@@ -130,8 +130,7 @@ impl Hashable for CoinbaseTransaction {
 
 impl SlashingTransaction {
     pub fn cheater(&self) -> pbc::PublicKey {
-        assert_eq!(self.proof.block1.header.pkey, self.proof.block2.header.pkey);
-        self.proof.block1.header.pkey
+        self.proof.cheater()
     }
 }
 
@@ -139,8 +138,46 @@ impl SlashingTransaction {
 // Payment Transaction.
 //--------------------------------------------------------------------------------------------------
 
+/// Signature of a co-signer of MultisigOutput.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultisigSignature {
+    /// Hash of MultisigOutput being spent.
+    pub txin: Hash,
+    /// Public key of co-signer.
+    pub pkey: PublicKey,
+    /// Signature of the transaction hash.
+    pub sig: SchnorrSig,
+}
+
+impl Hashable for MultisigSignature {
+    fn hash(&self, state: &mut Hasher) {
+        self.txin.hash(state);
+        self.pkey.hash(state);
+        self.sig.hash(state);
+    }
+}
+
+/// Secret preimage which unlocks HtlcOutput.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HtlcPreimage {
+    /// Hash of HtlcOutput being claimed.
+    pub txin: Hash,
+    /// Preimage of HtlcOutput's hash_lock.
+    #[serde(deserialize_with = "stegos_crypto::utils::vec_deserialize_from_hex")]
+    #[serde(serialize_with = "stegos_crypto::utils::vec_serialize_to_hex")]
+    pub preimage: Vec<u8>,
+}
+
+impl Hashable for HtlcPreimage {
+    fn hash(&self, state: &mut Hasher) {
+        self.txin.hash(state);
+        (self.preimage.len() as u64).hash(state);
+        self.preimage.hash(state);
+    }
+}
+
 /// PaymentTransaction.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PaymentTransaction {
     /// List of inputs.
     pub txins: Vec<Hash>,
@@ -152,6 +189,12 @@ pub struct PaymentTransaction {
     pub fee: i64,
     /// Transaction signature.
     pub sig: SchnorrSig,
+    /// Signatures of co-signers for MultisigOutput inputs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub multisigs: Vec<MultisigSignature>,
+    /// Preimages to claim HtlcOutput inputs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub htlc_preimages: Vec<HtlcPreimage>,
 }
 
 impl Hashable for PaymentTransaction {
@@ -175,6 +218,15 @@ impl Hashable for PaymentTransaction {
 
         // Sign fee.
         (self.fee as u64).hash(state);
+
+        // Sign preimages, keep hashes of other transactions compatible.
+        if !self.htlc_preimages.is_empty() {
+            let htlc_preimages_count: u64 = self.htlc_preimages.len() as u64;
+            htlc_preimages_count.hash(state);
+            for htlc_preimage in &self.htlc_preimages {
+                htlc_preimage.hash(state);
+            }
+        }
     }
 }
 
@@ -186,6 +238,8 @@ impl PaymentTransaction {
             gamma: Fr::zero(),
             fee: 0,
             sig: SchnorrSig::new(),
+            multisigs: Vec::new(),
+            htlc_preimages: Vec::new(),
         }
     }
 
@@ -211,6 +265,37 @@ impl PaymentTransaction {
         Ok(tx)
     }
 
+    /// Create a new transaction which claims HtlcOutput inputs.
+    ///
+    /// # Arguments
+    ///
+    /// * `skey` - Sender's secret key
+    /// * `inputs` - UXTO to spent
+    /// * `outputs` - UXTO to create
+    /// * `outputs_gamma` - gamma adjustment for outputs
+    /// * `fee` - Total Fee
+    /// * `htlc_preimages` - Preimages for HtlcOutput inputs
+    ///
+    pub fn with_htlc_preimages(
+        skey: &SecretKey,
+        inputs: &[Output],
+        outputs: &[Output],
+        outputs_gamma: &Fr, // = sum(outputs.gamma)
+        fee: i64,
+        htlc_preimages: Vec<HtlcPreimage>,
+    ) -> Result<Self, Error> {
+        assert!(fee >= 0);
+        let tx = Self::unchecked_with_htlc_preimages(
+            skey,
+            inputs,
+            outputs,
+            outputs_gamma,
+            fee,
+            htlc_preimages,
+        )?;
+        Ok(tx)
+    }
+
     /// Same as new(), but without checks and assertions.
     pub fn unchecked(
         skey: &SecretKey,
@@ -218,6 +303,17 @@ impl PaymentTransaction {
         outputs: &[Output],
         outputs_gamma: &Fr, // = sum(outputs.gamma)
         fee: i64,
+    ) -> Result<Self, Error> {
+        Self::unchecked_with_htlc_preimages(skey, inputs, outputs, outputs_gamma, fee, Vec::new())
+    }
+
+    fn unchecked_with_htlc_preimages(
+        skey: &SecretKey,
+        inputs: &[Output],
+        outputs: &[Output],
+        outputs_gamma: &Fr, // = sum(outputs.gamma)
+        fee: i64,
+        htlc_preimages: Vec<HtlcPreimage>,
     ) -> Result<Self, Error> {
         let pkey: PublicKey = skey.clone().into();
 
@@ -231,15 +327,25 @@ impl PaymentTransaction {
         let mut txins: Vec<Hash> = Vec::with_capacity(inputs.len());
 
         for txin in inputs {
-            eff_skey += Fr::from(*skey);
             match txin {
                 Output::PaymentOutput(o) => {
+                    eff_skey += Fr::from(*skey);
                     let payload = o.decrypt_payload(&pkey, skey)?;
                     gamma_adj += payload.gamma;
                     eff_skey += payload.delta * payload.gamma;
                 }
-                Output::PublicPaymentOutput(_) => {}
-                Output::StakeOutput(_o) => {}
+                Output::PublicPaymentOutput(_) => {
+                    eff_skey += Fr::from(*skey);
+                }
+                Output::StakeOutput(_o) => {
+                    eff_skey += Fr::from(*skey);
+                }
+                // Signed by co-signers, see sign_multisig().
+                Output::MultisigOutput(_o) => {}
+                // Signed by recipient or refunder.
+                Output::HtlcOutput(_o) => {
+                    eff_skey += Fr::from(*skey);
+                }
             }
             let hash = Hasher::digest(txin);
             txins.push(hash);
@@ -255,6 +361,8 @@ impl PaymentTransaction {
             gamma: gamma_adj,
             fee,
             sig: SchnorrSig::new(),
+            multisigs: Vec::new(),
+            htlc_preimages,
         };
 
         // Create an effective private key and sign transaction.
@@ -317,6 +425,8 @@ impl PaymentTransaction {
             gamma: gamma_adj.clone(),
             fee: total_fee,
             sig: SchnorrSig::new(),
+            multisigs: Vec::new(),
+            htlc_preimages: Vec::new(),
         };
 
         // Create an effective private key and sign transaction.
@@ -326,6 +436,26 @@ impl PaymentTransaction {
         Ok(tx)
     }
 
+    /// Add a signature of co-signer for MultisigOutput input.
+    ///
+    /// # Arguments
+    ///
+    /// * `txin` - Hash of MultisigOutput to spend
+    /// * `skey` - Co-signer's secret key
+    ///
+    pub fn sign_multisig(&mut self, txin: &Hash, skey: &SecretKey) {
+        let pkey: PublicKey = skey.clone().into();
+        let tx_hash = Hasher::digest(&*self);
+        let sig = sign_hash(&tx_hash, skey);
+        self.multisigs
+            .retain(|multisig| multisig.txin != *txin || multisig.pkey != pkey);
+        self.multisigs.push(MultisigSignature {
+            txin: *txin,
+            pkey,
+            sig,
+        });
+    }
+
     /// Used only for tests.
     //#[cfg(test)]
     #[doc(hidden)]
@@ -428,7 +558,10 @@ impl RestakeTransaction {
         for txin in inputs {
             let h = Hash::digest(&txin);
             match txin {
-                Output::PaymentOutput(_) | Output::PublicPaymentOutput(_) => {
+                Output::PaymentOutput(_)
+                | Output::PublicPaymentOutput(_)
+                | Output::MultisigOutput(_)
+                | Output::HtlcOutput(_) => {
                     return Err(TransactionError::InvalidRestakingInput(htx, h).into());
                 }
                 Output::StakeOutput(o) => {
@@ -462,7 +595,10 @@ impl RestakeTransaction {
             txout.validate()?;
             let h = Hash::digest(txout);
             match txout {
-                Output::PaymentOutput(_) | Output::PublicPaymentOutput(_) => {
+                Output::PaymentOutput(_)
+                | Output::PublicPaymentOutput(_)
+                | Output::MultisigOutput(_)
+                | Output::HtlcOutput(_) => {
                     return Err(TransactionError::InvalidRestakingOutput(htx, h).into())
                 }
                 Output::StakeOutput(o) => {
@@ -604,7 +740,12 @@ impl Transaction {
         self.hash(state);
         match self {
             Transaction::CoinbaseTransaction(_tx) => {}
-            Transaction::PaymentTransaction(tx) => tx.sig.hash(state),
+            Transaction::PaymentTransaction(tx) => {
+                tx.sig.hash(state);
+                for multisig in &tx.multisigs {
+                    multisig.hash(state);
+                }
+            }
             Transaction::RestakeTransaction(tx) => tx.sig.hash(state),
             Transaction::SlashingTransaction(_tx) => (),
             Transaction::ServiceAwardTransaction(_tx) => (),
@@ -0,0 +1,234 @@
+//! Merkle Tree of UTXO.
+
+//
+// Copyright (c) 2019 Stegos AG
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use crate::merkle::{inner_hash, Merkle, MerkleProof};
+use std::collections::{BTreeMap, BTreeSet};
+use stegos_crypto::hash::Hash;
+
+/// The number of leading bits of UTXO hash which select a bucket.
+const BUCKET_BITS: usize = 12;
+/// The number of buckets.
+const BUCKETS: usize = 1 << BUCKET_BITS;
+
+///
+/// Merkle Tree of UTXO hashes which can be updated incrementally.
+///
+/// UTXO hashes are split into buckets by their leading bits.
+/// Each bucket is a regular Merkle Tree of sorted hashes, and the roots
+/// of buckets are the leafs of a complete binary tree. Updating the tree
+/// rehashes only the affected buckets and their paths to the root.
+/// The root hash depends only on the set of UTXO, not on the order of updates.
+///
+/// ```text
+///                 root
+///              /        \
+///           h01          h23
+///          /   \        /   \
+///        b0     b1    b2     b3
+///       /  \    |    /  \   (empty)
+///     u1   u2   u3  u4   u5
+/// ```
+///
+#[derive(Clone, Debug)]
+pub(crate) struct UtxoTree {
+    /// Sorted UTXO hashes by buckets.
+    buckets: Vec<BTreeSet<Hash>>,
+    /// Nodes of the binary tree over buckets: `nodes[1]` is the root,
+    /// `nodes[BUCKETS + i]` is the root hash of i-th bucket.
+    nodes: Vec<Hash>,
+}
+
+/// Returns the bucket of UTXO.
+fn bucket_of(utxo_hash: &Hash) -> usize {
+    let bits = utxo_hash.base_vector();
+    let prefix = ((bits[0] as usize) << 8) | (bits[1] as usize);
+    prefix >> (16 - BUCKET_BITS)
+}
+
+/// Returns the root hash of a bucket.
+fn bucket_hash(bucket: &BTreeSet<Hash>) -> Hash {
+    let utxos: Vec<Hash> = bucket.iter().cloned().collect();
+    Merkle::root_hash_from_array(&utxos)
+}
+
+impl UtxoTree {
+    /// Create a tree from the set of UTXO.
+    pub fn new<'a, I: IntoIterator<Item = &'a Hash>>(utxos: I) -> Self {
+        let mut buckets = vec![BTreeSet::new(); BUCKETS];
+        for utxo_hash in utxos {
+            buckets[bucket_of(utxo_hash)].insert(*utxo_hash);
+        }
+        let mut nodes = vec![Hash::zero(); 2 * BUCKETS];
+        for (i, bucket) in buckets.iter().enumerate() {
+            nodes[BUCKETS + i] = bucket_hash(bucket);
+        }
+        for i in (1..BUCKETS).rev() {
+            nodes[i] = inner_hash(&nodes[2 * i], &nodes[2 * i + 1]);
+        }
+        UtxoTree { buckets, nodes }
+    }
+
+    /// Returns the root hash.
+    pub fn root_hash(&self) -> Hash {
+        self.nodes[1]
+    }
+
+    /// Returns the buckets changed by removing `inputs` and adding `outputs`.
+    fn changed_buckets<'a, I, O>(&self, inputs: I, outputs: O) -> BTreeMap<usize, BTreeSet<Hash>>
+    where
+        I: IntoIterator<Item = &'a Hash>,
+        O: IntoIterator<Item = &'a Hash>,
+    {
+        let mut buckets: BTreeMap<usize, BTreeSet<Hash>> = BTreeMap::new();
+        for input_hash in inputs {
+            let i = bucket_of(input_hash);
+            buckets
+                .entry(i)
+                .or_insert_with(|| self.buckets[i].clone())
+                .remove(input_hash);
+        }
+        for output_hash in outputs {
+            let i = bucket_of(output_hash);
+            buckets
+                .entry(i)
+                .or_insert_with(|| self.buckets[i].clone())
+                .insert(*output_hash);
+        }
+        buckets
+    }
+
+    /// Returns the nodes changed by the new content of buckets.
+    fn changed_nodes(&self, buckets: &BTreeMap<usize, BTreeSet<Hash>>) -> BTreeMap<usize, Hash> {
+        let mut nodes: BTreeMap<usize, Hash> = BTreeMap::new();
+        for (i, bucket) in buckets {
+            nodes.insert(BUCKETS + i, bucket_hash(bucket));
+        }
+        // Rehash parents level by level, from the buckets to the root.
+        let mut level: BTreeSet<usize> = nodes.keys().map(|i| i / 2).collect();
+        while !level.is_empty() {
+            let mut parents = BTreeSet::new();
+            for i in level {
+                let left = nodes.get(&(2 * i)).unwrap_or(&self.nodes[2 * i]);
+                let right = nodes.get(&(2 * i + 1)).unwrap_or(&self.nodes[2 * i + 1]);
+                let hash = inner_hash(left, right);
+                nodes.insert(i, hash);
+                if i > 1 {
+                    parents.insert(i / 2);
+                }
+            }
+            level = parents;
+        }
+        nodes
+    }
+
+    /// Returns the root hash after removing `inputs` and adding `outputs`,
+    /// without changing the tree.
+    pub fn root_hash_after<'a, I, O>(&self, inputs: I, outputs: O) -> Hash
+    where
+        I: IntoIterator<Item = &'a Hash>,
+        O: IntoIterator<Item = &'a Hash>,
+    {
+        let buckets = self.changed_buckets(inputs, outputs);
+        let nodes = self.changed_nodes(&buckets);
+        nodes.get(&1).cloned().unwrap_or(self.nodes[1])
+    }
+
+    /// Remove `inputs` and add `outputs`.
+    pub fn update<'a, I, O>(&mut self, inputs: I, outputs: O)
+    where
+        I: IntoIterator<Item = &'a Hash>,
+        O: IntoIterator<Item = &'a Hash>,
+    {
+        let buckets = self.changed_buckets(inputs, outputs);
+        let nodes = self.changed_nodes(&buckets);
+        for (i, bucket) in buckets {
+            self.buckets[i] = bucket;
+        }
+        for (i, hash) in nodes {
+            self.nodes[i] = hash;
+        }
+    }
+
+    /// Returns a proof of membership of UTXO.
+    pub fn proof(&self, utxo_hash: &Hash) -> Option<MerkleProof> {
+        let i = bucket_of(utxo_hash);
+        let bucket = &self.buckets[i];
+        if !bucket.contains(utxo_hash) {
+            return None;
+        }
+        let utxos: Vec<Hash> = bucket.iter().cloned().collect();
+        let tree = Merkle::from_array(&utxos);
+        let (_, path) = tree
+            .leafs()
+            .into_iter()
+            .find(|(hash, _path)| *hash == utxo_hash)?;
+        let proof = tree.proof(&path)?;
+
+        // Path and siblings from the root to the bucket.
+        let mut path: u32 = 0;
+        let mut siblings: Vec<Option<Hash>> = Vec::with_capacity(BUCKET_BITS);
+        for h in 0..BUCKET_BITS {
+            let direction = (i >> (BUCKET_BITS - h - 1)) & 1;
+            path |= (direction as u32) << h;
+            let child = (1 << (h + 1)) | (i >> (BUCKET_BITS - h - 1));
+            siblings.push(Some(self.nodes[child ^ 1]));
+        }
+        Some(proof.with_parent(path, siblings))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn incremental_updates() {
+        let utxos: Vec<Hash> = (0..1000u64).map(|i| Hash::digest(&i)).collect();
+        let mut tree = UtxoTree::new(&utxos[0..500]);
+        assert_eq!(tree.root_hash(), UtxoTree::new(&utxos[0..500]).root_hash());
+        assert_ne!(tree.root_hash(), UtxoTree::new(&utxos[0..499]).root_hash());
+
+        // Remove 100 and add 500.
+        let inputs = &utxos[0..100];
+        let outputs = &utxos[500..1000];
+        let root_hash = tree.root_hash_after(inputs, outputs);
+        assert_eq!(root_hash, UtxoTree::new(&utxos[100..1000]).root_hash());
+        tree.update(inputs, outputs);
+        assert_eq!(tree.root_hash(), root_hash);
+
+        // Proofs.
+        for utxo_hash in &utxos[100..1000] {
+            let proof = tree.proof(utxo_hash).expect("utxo exists");
+            proof
+                .validate(utxo_hash, &root_hash)
+                .expect("proof is valid");
+        }
+        for utxo_hash in &utxos[0..100] {
+            assert!(tree.proof(utxo_hash).is_none());
+        }
+
+        // Remove everything.
+        tree.update(&utxos[100..1000], &[]);
+        assert_eq!(tree.root_hash(), UtxoTree::new(&[]).root_hash());
+    }
+}
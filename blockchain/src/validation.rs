@@ -21,7 +21,7 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use crate::block::{Block, MacroBlock, MacroBlockHeader, MicroBlock, VERSION};
+use crate::block::{Block, MacroBlock, MacroBlockHeader, MicroBlock};
 use crate::blockchain::{Blockchain, ChainInfo};
 use crate::election::mix;
 use crate::error::{BlockError, BlockchainError, SlashingError, TransactionError};
@@ -66,6 +66,11 @@ impl CoinbaseTransaction {
                     output.validate()?;
                     mined += output.pedersen_commitment()?;
                 }
+                // Shares of delegators, checked by upper levels.
+                Output::PublicPaymentOutput(_o) => {
+                    output.validate()?;
+                    mined += output.pedersen_commitment()?;
+                }
                 _ => {
                     return Err(
                         TransactionError::NonPaymentOutputInCoinbase(tx_hash, output_hash).into(),
@@ -90,8 +95,10 @@ impl PaymentTransaction {
     /// # Arguments
     ///
     /// * - `inputs` - UTXOs referred by self.txins, in the same order as in self.txins.
+    /// * - `timestamp` - the time used to check time-locks and HTLC deadlines of inputs.
+    ///   Validators use the timestamp of the last macro block, which can lag up to an epoch.
     ///
-    pub fn validate(&self, inputs: &[Output]) -> Result<(), BlockchainError> {
+    pub fn validate(&self, inputs: &[Output], timestamp: Timestamp) -> Result<(), BlockchainError> {
         //
         // Validation checklist:
         //
@@ -99,11 +106,14 @@ impl PaymentTransaction {
         // - Inputs can be resolved.
         // - Inputs have not been spent by blocks.
         // - Inputs are unique.
+        // - Inputs are not time-locked.
         // - Outputs are unique.
         // - Bulletpoofs/amounts are valid.
         // - UTXO-specific checks.
         // - Monetary balance is valid.
         // - Signature is valid.
+        // - Multi-signatures are valid.
+        // - HTLC preimages and deadlines are valid.
         //
 
         let tx_hash = Hash::digest(&self);
@@ -134,6 +144,9 @@ impl PaymentTransaction {
         let mut txin_sum = Pt::inf();
         let mut txout_sum = Pt::inf();
 
+        // Check preimages for HtlcOutput inputs.
+        let htlc_claims = self.validate_htlc_preimages(inputs, &tx_hash)?;
+
         // +\sum{C_i} for i in txins
         let mut txins_set: HashSet<Hash> = HashSet::new();
         for (txin_hash, txin) in self.txins.iter().zip(inputs) {
@@ -144,9 +157,33 @@ impl PaymentTransaction {
             if cfg!(debug_assertions) {
                 txin.validate()?;
             }
+            txin.validate_lock(timestamp)?;
             let cmt = txin.pedersen_commitment()?;
             txin_sum += cmt;
-            eff_pkey += txin.recipient_pkey()? + cmt;
+            let recipient_pkey = match txin {
+                // Claimed by recipient before deadline or refunded after it.
+                Output::HtlcOutput(o) => {
+                    if htlc_claims.contains(txin_hash) {
+                        if timestamp >= o.deadline {
+                            return Err(TransactionError::HtlcExpired(
+                                tx_hash, *txin_hash, o.deadline, timestamp,
+                            )
+                            .into());
+                        }
+                        Pt::from(o.recipient)
+                    } else {
+                        if timestamp < o.deadline {
+                            return Err(TransactionError::HtlcNotExpired(
+                                tx_hash, *txin_hash, o.deadline, timestamp,
+                            )
+                            .into());
+                        }
+                        Pt::from(o.refunder)
+                    }
+                }
+                _ => txin.recipient_pkey()?,
+            };
+            eff_pkey += recipient_pkey + cmt;
         }
         drop(txins_set);
 
@@ -180,9 +217,95 @@ impl PaymentTransaction {
         scc::validate_sig(&tx_hash, &self.sig, &eff_pkey)
             .map_err(|_e| TransactionError::InvalidSignature(tx_hash))?;
 
+        // Check signatures of co-signers for MultisigOutput inputs.
+        self.validate_multisigs(inputs, &tx_hash)?;
+
         // Transaction is valid.
         Ok(())
     }
+
+    ///
+    /// Checks that every preimage unlocks a HtlcOutput input.
+    /// Returns hashes of claimed inputs.
+    ///
+    fn validate_htlc_preimages(
+        &self,
+        inputs: &[Output],
+        tx_hash: &Hash,
+    ) -> Result<HashSet<Hash>, BlockchainError> {
+        let mut claims: HashSet<Hash> = HashSet::new();
+        for htlc_preimage in &self.htlc_preimages {
+            let invalid_preimage =
+                || TransactionError::InvalidHtlcPreimage(*tx_hash, htlc_preimage.txin);
+            let txin = self
+                .txins
+                .iter()
+                .zip(inputs)
+                .find(|(txin_hash, _txin)| **txin_hash == htlc_preimage.txin);
+            match txin {
+                Some((_txin_hash, Output::HtlcOutput(o))) => {
+                    if !o.check_preimage(&htlc_preimage.preimage) {
+                        return Err(invalid_preimage().into());
+                    }
+                }
+                _ => return Err(invalid_preimage().into()),
+            }
+            if !claims.insert(htlc_preimage.txin) {
+                return Err(invalid_preimage().into());
+            }
+        }
+        Ok(claims)
+    }
+
+    ///
+    /// Checks that every MultisigOutput input is signed by enough co-signers.
+    ///
+    fn validate_multisigs(&self, inputs: &[Output], tx_hash: &Hash) -> Result<(), BlockchainError> {
+        let mut signers: HashMap<Hash, HashSet<scc::PublicKey>> = HashMap::new();
+        for multisig in &self.multisigs {
+            let invalid_sig = || {
+                TransactionError::InvalidMultisigSignature(*tx_hash, multisig.txin, multisig.pkey)
+            };
+            let txin = self
+                .txins
+                .iter()
+                .zip(inputs)
+                .find(|(txin_hash, _txin)| **txin_hash == multisig.txin);
+            match txin {
+                Some((_txin_hash, Output::MultisigOutput(o))) => {
+                    if !o.recipients.contains(&multisig.pkey) {
+                        return Err(invalid_sig().into());
+                    }
+                }
+                _ => return Err(invalid_sig().into()),
+            }
+            scc::validate_sig(tx_hash, &multisig.sig, &multisig.pkey)
+                .map_err(|_e| invalid_sig())?;
+            if !signers
+                .entry(multisig.txin)
+                .or_insert_with(HashSet::new)
+                .insert(multisig.pkey)
+            {
+                return Err(invalid_sig().into());
+            }
+        }
+
+        for (txin_hash, txin) in self.txins.iter().zip(inputs) {
+            if let Output::MultisigOutput(o) = txin {
+                let signed = signers.get(txin_hash).map_or(0, |s| s.len()) as u32;
+                if signed < o.threshold {
+                    return Err(TransactionError::NotEnoughMultisigSignatures(
+                        *tx_hash,
+                        *txin_hash,
+                        signed,
+                        o.threshold,
+                    )
+                    .into());
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 impl RestakeTransaction {
@@ -240,7 +363,14 @@ impl RestakeTransaction {
                 txin.validate()?;
             }
             match txin {
-                Output::PaymentOutput(_) | Output::PublicPaymentOutput(_) => {
+                Output::PaymentOutput(_)
+                | Output::PublicPaymentOutput(_)
+                | Output::MultisigOutput(_)
+                | Output::HtlcOutput(_) => {
+                    return Err(TransactionError::InvalidRestakingInput(tx_hash, *txin_hash).into());
+                }
+                // Delegations are not restaked, they are withdrawn by delegators.
+                Output::StakeOutput(o) if o.delegated => {
                     return Err(TransactionError::InvalidRestakingInput(tx_hash, *txin_hash).into());
                 }
                 Output::StakeOutput(o) => {
@@ -282,7 +412,15 @@ impl RestakeTransaction {
             }
             txout.validate()?;
             match txout {
-                Output::PaymentOutput(_) | Output::PublicPaymentOutput(_) => {
+                Output::PaymentOutput(_)
+                | Output::PublicPaymentOutput(_)
+                | Output::MultisigOutput(_)
+                | Output::HtlcOutput(_) => {
+                    return Err(
+                        TransactionError::InvalidRestakingOutput(tx_hash, txout_hash).into(),
+                    );
+                }
+                Output::StakeOutput(o) if o.delegated || o.unbonding => {
                     return Err(
                         TransactionError::InvalidRestakingOutput(tx_hash, txout_hash).into(),
                     );
@@ -339,6 +477,9 @@ impl SlashingTransaction {
         if tx.txins != self.txins {
             return Err(SlashingError::IncorrectTxins(tx_hash).into());
         }
+        if tx.txouts.len() != self.txouts.len() {
+            return Err(SlashingError::IncorrectTxouts(tx_hash).into());
+        }
         // Try to find unhonest devided stake.
         // Txouts is ordered by recipient validator id.
         for txs in tx.txouts.iter().zip(self.txouts.iter()) {
@@ -350,14 +491,19 @@ impl SlashingTransaction {
                         recipient: recipient1,
                         amount: amount1,
                         serno: _,
+                        locked_timestamp: locked_timestamp1,
                     }),
                     Output::PublicPaymentOutput(PublicPaymentOutput {
                         recipient: recipient2,
                         amount: amount2,
                         serno: _,
+                        locked_timestamp: locked_timestamp2,
                     }),
                 ) => {
-                    if recipient1 != recipient2 || amount1 != amount2 {
+                    if recipient1 != recipient2
+                        || amount1 != amount2
+                        || locked_timestamp1 != locked_timestamp2
+                    {
                         return Err(SlashingError::IncorrectTxins(tx_hash).into());
                     }
                 }
@@ -445,6 +591,30 @@ impl Blockchain {
         Ok(())
     }
 
+    ///
+    /// Check that coinbase pays shares of delegators of the leader.
+    ///
+    fn validate_delegation_payouts(
+        &self,
+        epoch: u64,
+        block_hash: &Hash,
+        leader: &pbc::PublicKey,
+        tx: &CoinbaseTransaction,
+    ) -> Result<(), BlockError> {
+        let payouts: Vec<(scc::PublicKey, i64)> = tx
+            .txouts
+            .iter()
+            .filter_map(|output| match output {
+                Output::PublicPaymentOutput(o) => Some((o.recipient, o.amount)),
+                _ => None,
+            })
+            .collect();
+        if payouts != self.delegation_payouts(leader, tx.block_reward) {
+            return Err(BlockError::InvalidDelegationPayouts(epoch, *block_hash));
+        }
+        Ok(())
+    }
+
     ///
     /// A common part of validate_macro_block() and validate_proposed_macro_block().
     ///
@@ -457,12 +627,13 @@ impl Blockchain {
         let epoch = header.epoch;
 
         // Check block version.
-        if header.version != VERSION {
+        let version = self.version_at_epoch(epoch);
+        if header.version != version {
             return Err(BlockError::InvalidBlockVersion(
                 epoch,
                 *block_hash,
                 header.version,
-                VERSION,
+                version,
             )
             .into());
         }
@@ -472,6 +643,17 @@ impl Blockchain {
             return Err(BlockError::OutOfOrderMacroBlock(*block_hash, epoch, self.epoch()).into());
         }
 
+        // Check parameter change.
+        if let Some(proposal) = &header.proposal {
+            if self.cfg().governance_epochs == 0 {
+                let e = "on-chain governance is disabled".to_string();
+                return Err(BlockError::InvalidParameterChange(epoch, *block_hash, e).into());
+            }
+            proposal
+                .validate()
+                .map_err(|e| BlockError::InvalidParameterChange(epoch, *block_hash, e))?;
+        }
+
         // Check new hash.
         if self.contains_block(&block_hash) {
             return Err(BlockError::MacroBlockHashCollision(epoch, *block_hash).into());
@@ -668,11 +850,28 @@ impl Blockchain {
         //
         block.validate_balance(&inputs)?;
 
+        //
+        // Validate UTXO.
+        //
+        if let Some(block_utxo_range_hash) = block.header.utxo_range_hash {
+            let utxo_range_hash = self.utxo_range_hash_after(&block.inputs, &output_hashes);
+            if block_utxo_range_hash != utxo_range_hash {
+                return Err(BlockError::InvalidMacroBlockUtxoHash(
+                    epoch,
+                    block_hash,
+                    utxo_range_hash,
+                    block_utxo_range_hash,
+                )
+                .into());
+            }
+        }
+
         //
         // Sic: the following fields can't be validated properly
         // without processing the block itself:
         // - validators_len
         // - validators_range_hash
+        // - state_hash
         // We blindly rely on consensus here.
         //
 
@@ -743,6 +942,7 @@ impl Blockchain {
                 )
                 .into());
             }
+            self.validate_delegation_payouts(epoch, block_hash, &header.pkey, tx)?;
 
             if tx.block_fee != 0 {
                 return Err(BlockError::InvalidMacroBlockFee(
@@ -765,34 +965,51 @@ impl Blockchain {
             tx_len += 1;
             full_reward += reward;
             if let Some(Transaction::ServiceAwardTransaction(tx)) = transactions.get(1) {
-                if tx.winner_reward.len() != 1 {
-                    return Err(BlockError::AwardMoreThanOneWinner(
-                        block_hash.clone(),
-                        tx.winner_reward.len(),
-                    )
-                    .into());
-                }
-                let ref output = tx.winner_reward[0];
-
-                if let Output::PublicPaymentOutput(out) = output {
-                    if out.recipient != k {
-                        return Err(BlockError::AwardDifferentWinner(
+                // The winner goes first, then shares of delegators.
+                let payouts = self.award_payouts(&k, reward);
+                if tx.winner_reward.len() != payouts.len() {
+                    if payouts.len() == 1 {
+                        return Err(BlockError::AwardMoreThanOneWinner(
                             block_hash.clone(),
-                            out.recipient,
-                            k,
+                            tx.winner_reward.len(),
                         )
                         .into());
                     }
-                    if out.amount != reward {
-                        return Err(BlockError::AwardDifferentReward(
-                            block_hash.clone(),
-                            out.amount,
-                            reward,
-                        )
-                        .into());
+                    return Err(
+                        BlockError::InvalidDelegationPayouts(epoch, block_hash.clone()).into(),
+                    );
+                }
+
+                for (i, (output, (recipient, amount))) in
+                    tx.winner_reward.iter().zip(payouts).enumerate()
+                {
+                    if let Output::PublicPaymentOutput(out) = output {
+                        if out.recipient != recipient {
+                            if i > 0 {
+                                return Err(BlockError::InvalidDelegationPayouts(
+                                    epoch,
+                                    block_hash.clone(),
+                                )
+                                .into());
+                            }
+                            return Err(BlockError::AwardDifferentWinner(
+                                block_hash.clone(),
+                                out.recipient,
+                                recipient,
+                            )
+                            .into());
+                        }
+                        if out.amount != amount {
+                            return Err(BlockError::AwardDifferentReward(
+                                block_hash.clone(),
+                                out.amount,
+                                amount,
+                            )
+                            .into());
+                        }
+                    } else {
+                        return Err(BlockError::AwardDifferentOutputType(block_hash.clone()).into());
                     }
-                } else {
-                    return Err(BlockError::AwardDifferentOutputType(block_hash.clone()).into());
                 }
             } else {
                 return Err(BlockError::NoServiceAwardTx(block_hash.clone()).into());
@@ -804,6 +1021,7 @@ impl Blockchain {
         }
 
         // Collect transactions from epoch.
+        let mut epoch_transactions: Vec<Transaction> = Vec::new();
         let count = self.cfg().micro_blocks_in_epoch as usize;
         let blocks: Vec<Block> = self.blocks_starting(epoch, 0).take(count).collect();
         for (offset, block) in blocks.into_iter().enumerate() {
@@ -813,14 +1031,23 @@ impl Blockchain {
                 panic!("Expected micro block: epoch={}, offset={}", epoch, offset);
             };
 
-            transactions.extend(block.transactions);
+            epoch_transactions.extend(block.transactions);
         }
 
+        // Calculate the state commitments.
+        let (state_hash, utxo_range_hash) = self.macro_block_commitments(
+            &activity_map,
+            &header.random,
+            &transactions,
+            &epoch_transactions,
+        )?;
+        transactions.extend(epoch_transactions);
+
         // Select validators.
         let validators = self.next_election_result(header.random).validators;
 
         // Re-create original block.
-        let block = MacroBlock::from_transactions(
+        let mut block = MacroBlock::from_transactions(
             header.previous,
             epoch,
             header.view_change,
@@ -831,8 +1058,12 @@ impl Blockchain {
             full_reward,
             activity_map,
             validators,
+            state_hash,
+            utxo_range_hash,
             &transactions,
         )?;
+        block.header.version = header.version;
+        block.header.proposal = header.proposal.clone();
 
         // Check that block has the same hash.
         let expected_block_hash = Hash::digest(&block);
@@ -901,7 +1132,11 @@ impl Blockchain {
                 assert_eq!(inputs.len(), 0);
                 tx.validate()?;
             }
-            Transaction::PaymentTransaction(tx) => tx.validate(&inputs)?,
+            Transaction::PaymentTransaction(tx) => {
+                // Time-locks are checked against the last macro block
+                // to get the same result on all nodes.
+                tx.validate(&inputs, self.last_macro_block_timestamp())?
+            }
             Transaction::RestakeTransaction(tx) => tx.validate(&inputs)?,
             Transaction::SlashingTransaction(tx) => tx.validate(self, leader)?,
             Transaction::ServiceAwardTransaction(_) => {
@@ -934,12 +1169,13 @@ impl Blockchain {
         let block_hash = Hash::digest(&block);
 
         // Check block version.
-        if block.header.version != VERSION {
+        let version = self.version_at_epoch(epoch);
+        if block.header.version != version {
             return Err(BlockError::InvalidBlockVersion(
                 epoch,
                 block_hash,
                 block.header.version,
-                VERSION,
+                version,
             )
             .into());
         }
@@ -1035,6 +1271,7 @@ impl Blockchain {
                 )
                 .into());
             }
+            self.validate_delegation_payouts(epoch, &block_hash, &block.header.pkey, tx)?;
         } else {
             // Force coinbase if reward is not zero.
             return Err(BlockError::CoinbaseMustBeFirst(block_hash).into());
@@ -1198,11 +1435,15 @@ impl Blockchain {
 pub mod tests {
     use super::*;
     use crate::block::MacroBlock;
+    use crate::output::htlc_hash_lock;
+    use crate::output::HtlcOutput;
+    use crate::output::MultisigOutput;
     use crate::output::OutputError;
     use crate::output::PaymentOutput;
     use crate::output::StakeOutput;
     use crate::timestamp::Timestamp;
     use bit_vec::BitVec;
+    use std::time::Duration;
     use stegos_crypto::pbc;
 
     ///
@@ -1218,7 +1459,8 @@ pub mod tests {
         let mut tx =
             PaymentTransaction::new(&skey, &inputs, &[], &Fr::zero(), fee).expect("keys are valid");
         tx.txins.clear(); // remove all inputs
-        tx.validate(&[]).expect_err("tx is invalid");
+        tx.validate(&[], Timestamp::now())
+            .expect_err("tx is invalid");
     }
 
     ///
@@ -1230,7 +1472,8 @@ pub mod tests {
         let (skey, pkey) = scc::make_random_keys();
         let (tx, inputs, _outputs) = PaymentTransaction::new_test(&skey, &pkey, 100, 1, 0, 0, 100)
             .expect("transaction is valid");
-        tx.validate(&inputs).expect("transaction is valid");
+        tx.validate(&inputs, Timestamp::now())
+            .expect("transaction is valid");
     }
 
     ///
@@ -1264,7 +1507,8 @@ pub mod tests {
             let (tx, inputs, _outputs) =
                 PaymentTransaction::new_test(&skey0, &pkey0, 0, 2, 0, 1, 0)
                     .expect("transaction is valid");
-            tx.validate(&inputs).expect("transaction is valid");
+            tx.validate(&inputs, Timestamp::now())
+                .expect("transaction is valid");
         }
 
         //
@@ -1274,7 +1518,8 @@ pub mod tests {
             let (tx, inputs, _outputs) =
                 PaymentTransaction::new_test(&skey0, &pkey0, 100, 2, 200, 1, 0)
                     .expect("transaction is valid");
-            tx.validate(&inputs).expect("transaction is valid");
+            tx.validate(&inputs, Timestamp::now())
+                .expect("transaction is valid");
         }
 
         //
@@ -1305,7 +1550,9 @@ pub mod tests {
                 }
                 _ => panic!(),
             };
-            let e = tx.validate(&inputs).expect_err("transaction is invalid");
+            let e = tx
+                .validate(&inputs, Timestamp::now())
+                .expect_err("transaction is invalid");
             match e {
                 BlockchainError::TransactionError(TransactionError::InvalidSignature(tx_hash)) => {
                     // the hash of a transaction excludes its signature
@@ -1328,14 +1575,15 @@ pub mod tests {
             .expect("keys are valid");
 
         // Validation
-        tx.validate(&inputs1).expect("keys are valid");
+        tx.validate(&inputs1, Timestamp::now())
+            .expect("keys are valid");
 
         //
         // Invalid fee
         //
         let fee = tx.fee;
         tx.fee = -1i64;
-        match tx.validate(&inputs1).unwrap_err() {
+        match tx.validate(&inputs1, Timestamp::now()).unwrap_err() {
             BlockchainError::TransactionError(TransactionError::NegativeFee(_)) => {}
             _ => panic!(),
         };
@@ -1346,7 +1594,7 @@ pub mod tests {
         //
         tx.txins.push(tx.txins.last().unwrap().clone());
         let inputs11 = &[output0.clone(), output0.clone()];
-        match tx.validate(inputs11).unwrap_err() {
+        match tx.validate(inputs11, Timestamp::now()).unwrap_err() {
             BlockchainError::TransactionError(TransactionError::DuplicateInput(
                 _tx_hash,
                 txin_hash,
@@ -1361,7 +1609,7 @@ pub mod tests {
         // Duplicate output
         //
         tx.txouts.push(tx.txouts.last().unwrap().clone());
-        match tx.validate(&inputs1).unwrap_err() {
+        match tx.validate(&inputs1, Timestamp::now()).unwrap_err() {
             BlockchainError::TransactionError(TransactionError::DuplicateOutput(
                 _tx_hash,
                 txout_hash,
@@ -1376,7 +1624,7 @@ pub mod tests {
         // Invalid signature
         //
         tx.sig.u = Fr::zero();
-        match tx.validate(&inputs1).unwrap_err() {
+        match tx.validate(&inputs1, Timestamp::now()).unwrap_err() {
             BlockchainError::TransactionError(TransactionError::InvalidSignature(_tx_hash)) => {}
             _ => panic!(),
         };
@@ -1388,7 +1636,7 @@ pub mod tests {
             PaymentTransaction::new_test(&skey0, &pkey0, 100, 2, 200, 1, 0)
                 .expect("transaction is valid");
         tx.gamma = Fr::random();
-        match tx.validate(&inputs).unwrap_err() {
+        match tx.validate(&inputs, Timestamp::now()).unwrap_err() {
             BlockchainError::TransactionError(TransactionError::InvalidMonetaryBalance(
                 _tx_hash,
             )) => {}
@@ -1404,7 +1652,7 @@ pub mod tests {
         let outputs_gamma = gamma_invalid1;
         let tx = PaymentTransaction::new(&skey1, &inputs1, &outputs, &outputs_gamma, fee)
             .expect("keys are valid");
-        match tx.validate(&inputs1).unwrap_err() {
+        match tx.validate(&inputs1, Timestamp::now()).unwrap_err() {
             BlockchainError::TransactionError(TransactionError::InvalidMonetaryBalance(
                 _tx_hash,
             )) => {}
@@ -1460,7 +1708,7 @@ pub mod tests {
             Output::new_payment(&pkey1, amount - fee).expect("keys are valid");
         let tx = PaymentTransaction::new(&skey1, &inputs, &[output], &outputs_gamma, fee)
             .expect("keys are valid");
-        tx.validate(&inputs).expect("tx is valid");
+        tx.validate(&inputs, Timestamp::now()).expect("tx is valid");
 
         //
         // StakeUTXO as an output.
@@ -1472,7 +1720,7 @@ pub mod tests {
         let outputs_gamma = Fr::zero();
         let tx = PaymentTransaction::new(&skey1, &inputs, &[output], &outputs_gamma, fee)
             .expect("keys are valid");
-        tx.validate(&inputs).expect("tx is valid");
+        tx.validate(&inputs, Timestamp::now()).expect("tx is valid");
 
         //
         // Invalid monetary balance.
@@ -1487,7 +1735,7 @@ pub mod tests {
         let outputs_gamma = Fr::zero();
         let tx = PaymentTransaction::new(&skey1, &inputs, &outputs, &outputs_gamma, fee)
             .expect("Invalid keys");
-        match tx.validate(&inputs).unwrap_err() {
+        match tx.validate(&inputs, Timestamp::now()).unwrap_err() {
             BlockchainError::TransactionError(TransactionError::InvalidMonetaryBalance(
                 _tx_hash,
             )) => {}
@@ -1504,7 +1752,7 @@ pub mod tests {
         let outputs_gamma = Fr::zero();
         let mut tx = PaymentTransaction::new(&skey1, &inputs, &[output], &outputs_gamma, fee)
             .expect("keys are valid");
-        tx.validate(&inputs).expect("tx is valid");
+        tx.validate(&inputs, Timestamp::now()).expect("tx is valid");
         let output = &mut tx.txouts[0];
         match output {
             Output::StakeOutput(ref mut o) => {
@@ -1513,7 +1761,10 @@ pub mod tests {
             }
             _ => panic!(),
         };
-        match tx.validate(&inputs).expect_err("transaction is invalid") {
+        match tx
+            .validate(&inputs, Timestamp::now())
+            .expect_err("transaction is invalid")
+        {
             BlockchainError::TransactionError(TransactionError::InvalidSignature(tx_hash)) => {
                 assert_eq!(tx_hash, Hash::digest(&tx));
             }
@@ -1547,6 +1798,230 @@ pub mod tests {
         };
     }
 
+    ///
+    /// Tests that time-locked UTXO can't be spent before unlock.
+    ///
+    #[test]
+    fn locked_utxo() {
+        let (skey, pkey) = scc::make_random_keys();
+        let amount: i64 = 100;
+        let fee: i64 = 1;
+        let locked_timestamp = Timestamp::now() + Duration::from_secs(60);
+
+        let input: Output = PublicPaymentOutput::new_locked(&pkey, amount, locked_timestamp).into();
+        let input_hash = Hash::digest(&input);
+        let inputs = [input];
+        let (output, gamma) = Output::new_payment(&pkey, amount - fee).expect("keys are valid");
+        let tx = PaymentTransaction::new(&skey, &inputs, &[output], &gamma, fee)
+            .expect("keys are valid");
+
+        // Locked.
+        let timestamp = locked_timestamp - Duration::from_millis(1);
+        match tx.validate(&inputs, timestamp).unwrap_err() {
+            BlockchainError::OutputError(OutputError::UtxoLocked(hash, l, t)) => {
+                assert_eq!(hash, input_hash);
+                assert_eq!(l, locked_timestamp);
+                assert_eq!(t, timestamp);
+            }
+            e => panic!("{:?}", e),
+        }
+
+        // Unlocked.
+        tx.validate(&inputs, locked_timestamp)
+            .expect("transaction is valid");
+
+        // Locks of PaymentOutput are private and can't be checked by validators.
+        let (input, _gamma) =
+            PaymentOutput::new_locked(&pkey, amount, locked_timestamp).expect("keys are valid");
+        let payload = input.decrypt_payload(&pkey, &skey).expect("keys are valid");
+        assert_eq!(payload.locked_timestamp, Some(locked_timestamp));
+        let input: Output = input.into();
+        assert_eq!(input.locked_timestamp(), None);
+    }
+
+    #[test]
+    fn multisig_utxo() {
+        let (skey1, pkey1) = scc::make_random_keys();
+        let (skey2, pkey2) = scc::make_random_keys();
+        let (skey3, pkey3) = scc::make_random_keys();
+        let (skey4, pkey4) = scc::make_random_keys();
+        let amount: i64 = 100;
+        let fee: i64 = 1;
+
+        // Invalid threshold.
+        MultisigOutput::new(&[pkey1, pkey2], 0, amount)
+            .validate()
+            .unwrap_err();
+        MultisigOutput::new(&[pkey1, pkey2], 3, amount)
+            .validate()
+            .unwrap_err();
+        MultisigOutput::new(&[pkey1, pkey1], 1, amount)
+            .validate()
+            .unwrap_err();
+
+        let input = MultisigOutput::new(&[pkey1, pkey2, pkey3], 2, amount);
+        input.validate().expect("output is valid");
+        let input_hash = Hash::digest(&input);
+        let inputs = [Output::MultisigOutput(input)];
+        let (output, gamma) = Output::new_payment(&pkey4, amount - fee).expect("keys are valid");
+        let mut tx = PaymentTransaction::new(&skey4, &inputs, &[output], &gamma, fee)
+            .expect("keys are valid");
+        let tx_hash = Hash::digest(&tx);
+
+        // No signatures.
+        match tx.validate(&inputs, Timestamp::now()).unwrap_err() {
+            BlockchainError::TransactionError(TransactionError::NotEnoughMultisigSignatures(
+                h,
+                txin,
+                signed,
+                threshold,
+            )) => {
+                assert_eq!(h, tx_hash);
+                assert_eq!(txin, input_hash);
+                assert_eq!(signed, 0);
+                assert_eq!(threshold, 2);
+            }
+            e => panic!("{:?}", e),
+        }
+
+        // Not a co-signer.
+        let mut tx2 = tx.clone();
+        tx2.sign_multisig(&input_hash, &skey4);
+        match tx2.validate(&inputs, Timestamp::now()).unwrap_err() {
+            BlockchainError::TransactionError(TransactionError::InvalidMultisigSignature(
+                h,
+                txin,
+                pkey,
+            )) => {
+                assert_eq!(h, tx_hash);
+                assert_eq!(txin, input_hash);
+                assert_eq!(pkey, pkey4);
+            }
+            e => panic!("{:?}", e),
+        }
+
+        // 1-of-3.
+        tx.sign_multisig(&input_hash, &skey1);
+        match tx.validate(&inputs, Timestamp::now()).unwrap_err() {
+            BlockchainError::TransactionError(TransactionError::NotEnoughMultisigSignatures(
+                _,
+                _,
+                signed,
+                _,
+            )) => {
+                assert_eq!(signed, 1);
+            }
+            e => panic!("{:?}", e),
+        }
+
+        // 2-of-3.
+        tx.sign_multisig(&input_hash, &skey3);
+        tx.validate(&inputs, Timestamp::now())
+            .expect("transaction is valid");
+        assert_eq!(Hash::digest(&tx), tx_hash);
+
+        // Forged signature.
+        let mut tx2 = tx.clone();
+        tx2.multisigs[0].sig = scc::sign_hash(&tx_hash, &skey2);
+        tx2.validate(&inputs, Timestamp::now()).unwrap_err();
+
+        // Modified transaction.
+        let mut tx2 = tx.clone();
+        tx2.fee += 1;
+        tx2.validate(&inputs, Timestamp::now()).unwrap_err();
+    }
+
+    #[test]
+    fn htlc_utxo() {
+        let (recipient_skey, recipient_pkey) = scc::make_random_keys();
+        let (refunder_skey, refunder_pkey) = scc::make_random_keys();
+        let amount: i64 = 100;
+        let fee: i64 = 1;
+        let preimage = b"secret".to_vec();
+        let deadline = Timestamp::now() + Duration::from_secs(60);
+        let before = deadline - Duration::from_millis(1);
+
+        let input = HtlcOutput::new(
+            &recipient_pkey,
+            &refunder_pkey,
+            htlc_hash_lock(&preimage),
+            deadline,
+            amount,
+        );
+        let input_hash = Hash::digest(&input);
+        let inputs = [Output::HtlcOutput(input)];
+        let claim = |skey: &scc::SecretKey, preimage: &Vec<u8>| {
+            let (output, gamma) = Output::new_payment(&recipient_pkey, amount - fee).unwrap();
+            let htlc_preimage = HtlcPreimage {
+                txin: input_hash,
+                preimage: preimage.clone(),
+            };
+            PaymentTransaction::with_htlc_preimages(
+                skey,
+                &inputs,
+                &[output],
+                &gamma,
+                fee,
+                vec![htlc_preimage],
+            )
+            .expect("keys are valid")
+        };
+
+        // Claim before deadline.
+        let tx = claim(&recipient_skey, &preimage);
+        tx.validate(&inputs, before).expect("transaction is valid");
+
+        // Claim after deadline.
+        match tx.validate(&inputs, deadline).unwrap_err() {
+            BlockchainError::TransactionError(TransactionError::HtlcExpired(_, h, d, t)) => {
+                assert_eq!(h, input_hash);
+                assert_eq!(d, deadline);
+                assert_eq!(t, deadline);
+            }
+            e => panic!("{:?}", e),
+        }
+
+        // Claim with invalid preimage.
+        let tx = claim(&recipient_skey, &b"guess".to_vec());
+        match tx.validate(&inputs, before).unwrap_err() {
+            BlockchainError::TransactionError(TransactionError::InvalidHtlcPreimage(_, h)) => {
+                assert_eq!(h, input_hash);
+            }
+            e => panic!("{:?}", e),
+        }
+
+        // Claim by refunder.
+        let tx = claim(&refunder_skey, &preimage);
+        match tx.validate(&inputs, before).unwrap_err() {
+            BlockchainError::TransactionError(TransactionError::InvalidSignature(_)) => {}
+            e => panic!("{:?}", e),
+        }
+
+        // Refund.
+        let (output, gamma) = Output::new_payment(&refunder_pkey, amount - fee).unwrap();
+        let tx = PaymentTransaction::new(&refunder_skey, &inputs, &[output], &gamma, fee)
+            .expect("keys are valid");
+        match tx.validate(&inputs, before).unwrap_err() {
+            BlockchainError::TransactionError(TransactionError::HtlcNotExpired(_, h, d, t)) => {
+                assert_eq!(h, input_hash);
+                assert_eq!(d, deadline);
+                assert_eq!(t, before);
+            }
+            e => panic!("{:?}", e),
+        }
+        tx.validate(&inputs, deadline)
+            .expect("transaction is valid");
+
+        // Refund by recipient.
+        let (output, gamma) = Output::new_payment(&recipient_pkey, amount - fee).unwrap();
+        let tx = PaymentTransaction::new(&recipient_skey, &inputs, &[output], &gamma, fee)
+            .expect("keys are valid");
+        match tx.validate(&inputs, deadline).unwrap_err() {
+            BlockchainError::TransactionError(TransactionError::InvalidSignature(_)) => {}
+            e => panic!("{:?}", e),
+        }
+    }
+
     #[test]
     fn test_supertransaction() {
         let (skey1, pkey1) = scc::make_random_keys();
@@ -1634,7 +2109,7 @@ pub mod tests {
         let sig3 = stx3.sig;
         let final_sig = sig1 + sig2 + sig3;
         stx1.sig = final_sig;
-        dbg!(&stx1.validate(&inputs));
+        dbg!(&stx1.validate(&inputs, Timestamp::now()));
     }
 
     #[test]
@@ -22,6 +22,7 @@
 // SOFTWARE.
 
 use failure::Fail;
+use stegos_blockchain::SlashingProof;
 use stegos_crypto::hash::Hash;
 use stegos_crypto::pbc;
 
@@ -81,6 +82,12 @@ pub enum ConsensusError {
         _0, _1
     )]
     InvalidLastBlockHash(Hash, Hash),
+    #[fail(
+        display = "Received ViewChangeMessage, with invalid last block header: \
+                   message_block_hash={}, header_hash={}",
+        _0, _1
+    )]
+    InvalidLastBlockHeader(Hash, Hash),
     #[fail(
         display = "Malicious view change message found, validator_id greater than \
                    size of validators: validator_id={}",
@@ -89,6 +96,11 @@ pub enum ConsensusError {
     InvalidValidatorId(u32),
     #[fail(display = "Failed to check view change message signature.")]
     InvalidViewChangeSignature,
+    #[fail(
+        display = "Validator has signed conflicting messages: validator={}",
+        _0
+    )]
+    Equivocation(pbc::PublicKey, Box<SlashingProof>),
 }
 
 impl ConsensusError {
@@ -22,7 +22,9 @@
 // SOFTWARE.
 
 use crate::error::*;
-use stegos_blockchain::{MacroBlockHeader, Transaction};
+use stegos_blockchain::{
+    consensus_message_hash, hash_vote_body, ConsensusVote, MacroBlockHeader, Transaction,
+};
 use stegos_crypto::hash::{Hash, Hashable, Hasher};
 use stegos_crypto::pbc;
 
@@ -62,11 +64,10 @@ impl Hashable for ConsensusMessageBody {
                 proposal.hash(state);
             }
             ConsensusMessageBody::Prevote => {
-                "Prevote".hash(state);
+                hash_vote_body(None, state);
             }
             ConsensusMessageBody::Precommit(block_sig) => {
-                "Precommit".hash(state);
-                block_sig.hash(state);
+                hash_vote_body(Some(block_sig), state);
             }
         }
     }
@@ -90,6 +91,23 @@ pub struct ConsensusMessage {
 }
 
 impl ConsensusMessage {
+    /// Returns the signed vote, if this message is a pre-vote or a pre-commit.
+    pub fn to_vote(&self) -> Option<ConsensusVote> {
+        let block_hash_sig = match &self.body {
+            ConsensusMessageBody::Proposal(_) => return None,
+            ConsensusMessageBody::Prevote => None,
+            ConsensusMessageBody::Precommit(block_hash_sig) => Some(*block_hash_sig),
+        };
+        Some(ConsensusVote {
+            epoch: self.epoch,
+            round: self.round,
+            block_hash: self.block_hash,
+            block_hash_sig,
+            pkey: self.pkey,
+            sig: self.sig,
+        })
+    }
+
     pub fn name(&self) -> &'static str {
         match self.body {
             ConsensusMessageBody::Proposal { .. } => "Proposal",
@@ -111,12 +129,7 @@ impl ConsensusMessage {
         pkey: &pbc::PublicKey,
         body: ConsensusMessageBody,
     ) -> ConsensusMessage {
-        let mut hasher = Hasher::new();
-        epoch.hash(&mut hasher);
-        round.hash(&mut hasher);
-        block_hash.hash(&mut hasher);
-        body.hash(&mut hasher);
-        let hash = hasher.result();
+        let hash = consensus_message_hash(epoch, round, &block_hash, &body);
         let sig = pbc::sign_hash(&hash, skey);
         ConsensusMessage {
             epoch,
@@ -132,12 +145,7 @@ impl ConsensusMessage {
     /// Validate signature of the message.
     ///
     pub fn validate(&self) -> Result<(), ConsensusError> {
-        let mut hasher = Hasher::new();
-        self.epoch.hash(&mut hasher);
-        self.round.hash(&mut hasher);
-        self.block_hash.hash(&mut hasher);
-        self.body.hash(&mut hasher);
-        let hash = hasher.result();
+        let hash = consensus_message_hash(self.epoch, self.round, &self.block_hash, &self.body);
         if let Err(_e) = pbc::check_hash(&hash, &self.sig, &self.pkey) {
             return Err(ConsensusError::InvalidMessageSignature);
         }
@@ -27,13 +27,19 @@ use crate::error::ConsensusError;
 use log::*;
 use std::collections::HashMap;
 use stegos_blockchain::view_changes::*;
-use stegos_blockchain::{check_supermajority, Blockchain, ChainInfo, ValidatorId};
+use stegos_blockchain::{
+    check_supermajority, Blockchain, ChainInfo, MicroBlockHeader, SlashingProof, ValidatorId,
+    ViewChangeVote,
+};
 use stegos_crypto::hash::{Hash, Hashable, Hasher};
 use stegos_crypto::pbc;
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug)]
 pub struct ViewChangeMessage {
     pub chain: ChainInfo,
+    /// Header of the last micro block, used in slashing proofs.
+    /// None if the last block is a macro block.
+    pub last_block_header: Option<MicroBlockHeader>,
     pub validator_id: ValidatorId,
     pub signature: pbc::Signature,
 }
@@ -53,21 +59,39 @@ pub struct AddressedViewChangeProof {
 impl Hashable for ViewChangeMessage {
     fn hash(&self, state: &mut Hasher) {
         self.chain.hash(state);
+        if let Some(last_block_header) = &self.last_block_header {
+            last_block_header.hash(state);
+        }
         self.validator_id.hash(state);
         self.signature.hash(state);
     }
 }
 
 impl ViewChangeMessage {
-    pub fn new(chain: ChainInfo, validator_id: ValidatorId, skey: &pbc::SecretKey) -> Self {
+    pub fn new(
+        chain: ChainInfo,
+        last_block_header: Option<MicroBlockHeader>,
+        validator_id: ValidatorId,
+        skey: &pbc::SecretKey,
+    ) -> Self {
         let hash = Hash::digest(&chain);
         let signature = pbc::sign_hash(&hash, skey);
         ViewChangeMessage {
             chain,
+            last_block_header,
             validator_id,
             signature,
         }
     }
+
+    /// Returns the signed vote of the validator with `pkey`.
+    pub fn to_vote(&self, pkey: pbc::PublicKey) -> ViewChangeVote {
+        ViewChangeVote {
+            chain: self.chain,
+            pkey,
+            sig: self.signature,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -132,6 +156,41 @@ impl ViewChangeCollector {
             return Err(ConsensusError::InvalidViewChangeSignature);
         }
 
+        // Check the header of the last block.
+        if let Some(last_block_header) = &message.last_block_header {
+            let header_hash = Hash::digest(last_block_header);
+            if header_hash != message.chain.last_block {
+                return Err(ConsensusError::InvalidLastBlockHeader(
+                    message.chain.last_block,
+                    header_hash,
+                ));
+            }
+        }
+
+        // Check for conflicting messages from the same validator.
+        // Votes after a fork switch are not conflicting, see SlashingProof::validate().
+        if let Some(prev) = self.actual_view_changes.get(&validator_id) {
+            match (&prev.last_block_header, &message.last_block_header) {
+                (Some(header1), Some(header2))
+                    if prev.chain.last_block != message.chain.last_block =>
+                {
+                    let proof = SlashingProof::ViewChanges {
+                        view_change1: prev.to_vote(validator_pkey),
+                        view_change2: message.to_vote(validator_pkey),
+                        header1: header1.clone(),
+                        header2: header2.clone(),
+                    };
+                    if proof.validate(blockchain).is_ok() {
+                        return Err(ConsensusError::Equivocation(
+                            validator_pkey,
+                            Box::new(proof),
+                        ));
+                    }
+                }
+                _ => {}
+            }
+        }
+
         // Check offset.
         if message.chain.offset < blockchain.offset() {
             return Err(ConsensusError::ViewChangeOffsetFromThePast(
@@ -195,8 +254,12 @@ impl ViewChangeCollector {
     }
 
     /// Handle block timeout, starting mooving to the next view change.
-    pub fn handle_timeout(&self, chain_info: ChainInfo) -> ViewChangeMessage {
+    pub fn handle_timeout(
+        &self,
+        chain_info: ChainInfo,
+        last_block_header: Option<MicroBlockHeader>,
+    ) -> ViewChangeMessage {
         // on timeout, create view change message.
-        ViewChangeMessage::new(chain_info, self.validator_id, &self.skey)
+        ViewChangeMessage::new(chain_info, last_block_header, self.validator_id, &self.skey)
     }
 }
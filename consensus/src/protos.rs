@@ -126,17 +126,26 @@ impl ProtoConvert for ViewChangeMessage {
     fn into_proto(&self) -> Self::Proto {
         let mut proto = consensus::ViewChangeMessage::new();
         proto.set_chain(self.chain.into_proto());
+        if let Some(last_block_header) = &self.last_block_header {
+            proto.set_last_block_header(last_block_header.into_proto());
+        }
         proto.set_validator_id(self.validator_id);
         proto.set_signature(self.signature.into_proto());
         proto
     }
     fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
         let chain = ChainInfo::from_proto(proto.get_chain())?;
+        let last_block_header = if proto.has_last_block_header() {
+            Some(MicroBlockHeader::from_proto(proto.get_last_block_header())?)
+        } else {
+            None
+        };
         let validator_id = proto.get_validator_id();
         let signature = pbc::Signature::from_proto(proto.get_signature())?;
 
         Ok(ViewChangeMessage {
             chain,
+            last_block_header,
             validator_id,
             signature,
         })
@@ -279,7 +288,7 @@ mod tests {
 
     #[test]
     fn view_change() {
-        let (skey0, _pkey0) = pbc::make_random_keys();
+        let (skey0, pkey0) = pbc::make_random_keys();
 
         let chain = ChainInfo {
             epoch: 41,
@@ -287,7 +296,27 @@ mod tests {
             view_change: 12,
             last_block: Hash::digest("test"),
         };
-        let view_change_vote = ViewChangeMessage::new(chain, 1, &skey0);
+        let view_change_vote = ViewChangeMessage::new(chain, None, 1, &skey0);
         roundtrip(&view_change_vote);
+
+        let random = pbc::make_VRF(&skey0, &Hash::digest("random"));
+        let block = MicroBlock::empty(
+            Hash::digest("previous"),
+            41,
+            47,
+            0,
+            None,
+            pkey0,
+            random,
+            vec![1u8, 2, 3, 4],
+            Timestamp::now(),
+        );
+        let chain = ChainInfo {
+            last_block: Hash::digest(&block),
+            ..chain
+        };
+        let view_change_vote = ViewChangeMessage::new(chain, Some(block.header), 1, &skey0);
+        let view_change_vote2 = roundtrip(&view_change_vote);
+        assert!(view_change_vote2.last_block_header.is_some());
     }
 }
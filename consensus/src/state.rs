@@ -28,7 +28,8 @@ use log::*;
 use std::collections::BTreeMap;
 use std::mem;
 use stegos_blockchain::{
-    check_supermajority, create_multi_signature, ElectionResult, MacroBlock, Timestamp,
+    check_supermajority, create_multi_signature, ConsensusVote, ElectionResult, MacroBlock,
+    SlashingProof, Timestamp,
 };
 use stegos_crypto::hash::Hash;
 use stegos_crypto::pbc;
@@ -117,6 +118,9 @@ pub struct Consensus {
     prevotes: BTreeMap<pbc::PublicKey, pbc::Signature>,
     /// Collected Precommits.
     precommits: BTreeMap<pbc::PublicKey, pbc::Signature>,
+    /// The first pre-vote and pre-commit of each validator in this round,
+    /// used to detect conflicting votes.
+    votes: BTreeMap<(pbc::PublicKey, bool), ConsensusVote>,
 
     /// Consensus start time (used for metrics).
     start_time: Timestamp,
@@ -155,6 +159,7 @@ impl Consensus {
         debug!("New => {}({}:{})", state.name(), epoch, 0);
         let prevotes: BTreeMap<pbc::PublicKey, pbc::Signature> = BTreeMap::new();
         let precommits: BTreeMap<pbc::PublicKey, pbc::Signature> = BTreeMap::new();
+        let votes = BTreeMap::new();
         let total_slots = validators.iter().map(|v| v.1).sum();
         let block = None;
         let block_hash = None;
@@ -181,6 +186,7 @@ impl Consensus {
             locked_round,
             prevotes,
             precommits,
+            votes,
             inbox,
             outbox,
             start_time,
@@ -244,6 +250,7 @@ impl Consensus {
             self.round
         );
         self.round += 1;
+        self.votes.clear();
         // if our last state was Precommit, keep lock in the state.
         if self.state == ConsensusState::Precommit {
             self.lock()
@@ -429,6 +436,33 @@ impl Consensus {
         }
         assert_eq!(msg.round, self.round);
 
+        // Check for conflicting votes from the same validator.
+        if let Some(vote) = msg.to_vote() {
+            let key = (msg.pkey, vote.block_hash_sig.is_some());
+            match self.votes.get(&key) {
+                Some(prev) if prev.block_hash != vote.block_hash => {
+                    warn!(
+                        "{}({}:{}): conflicting votes: from={:?}, block1={:?}, block2={:?}",
+                        self.state.name(),
+                        self.epoch,
+                        self.round,
+                        &msg.pkey,
+                        &prev.block_hash,
+                        &vote.block_hash
+                    );
+                    let proof = SlashingProof::ConsensusVotes {
+                        vote1: prev.clone(),
+                        vote2: vote,
+                    };
+                    return Err(ConsensusError::Equivocation(msg.pkey, Box::new(proof)));
+                }
+                Some(_) => {}
+                None => {
+                    self.votes.insert(key, vote);
+                }
+            }
+        }
+
         // Check block_hash.
         if self.state != ConsensusState::Propose {
             let expected_block_hash = self.block_hash.as_ref().unwrap();
@@ -25,8 +25,8 @@ use futures::sync::mpsc;
 use serde_derive::{Deserialize, Serialize};
 use std::collections::HashMap;
 use stegos_blockchain::{
-    ElectionInfo, EpochInfo, EscrowInfo, MacroBlock, MicroBlock, Output, Timestamp, Transaction,
-    ValidatorKeyInfo,
+    AwardDraw, ElectionInfo, EpochInfo, EscrowInfo, LightMacroBlock, LightMicroBlock, MacroBlock,
+    MicroBlock, Output, Timestamp, Transaction, ValidatorKeyInfo,
 };
 use stegos_crypto::hash::{Hash, Hashable, Hasher};
 use stegos_crypto::scc;
@@ -45,6 +45,16 @@ pub enum NodeRequest {
     ChainName {},
     #[serde(skip)]
     AddTransaction(Transaction),
+    /// Validate and broadcast a transaction signed elsewhere.
+    BroadcastTransaction {
+        tx: Transaction,
+    },
+    /// The same as BroadcastTransaction, but with a hex-encoded protobuf transaction.
+    BroadcastRawTransaction {
+        #[serde(deserialize_with = "stegos_crypto::utils::vec_deserialize_from_hex")]
+        #[serde(serialize_with = "stegos_crypto::utils::vec_serialize_to_hex")]
+        data: Vec<u8>,
+    },
     ValidateCertificate {
         output_hash: Hash,
         spender: scc::PublicKey,
@@ -68,6 +78,47 @@ pub enum NodeRequest {
         epoch: u64,
         offset: u32,
     },
+    LightMacroBlockInfo {
+        epoch: u64,
+    },
+    LightMicroBlockInfo {
+        epoch: u64,
+        offset: u32,
+    },
+    LightOutputs {
+        block_hash: Hash,
+        output_ids: Vec<u32>,
+    },
+    TransactionInfo {
+        tx_hash: Hash,
+    },
+    /// What happened to the transaction: mempool, micro blocks or macro blocks.
+    TransactionStatus {
+        tx_hash: Hash,
+    },
+    SpentBy {
+        output_hash: Hash,
+    },
+    CreatedBy {
+        output_hash: Hash,
+    },
+    OutputsByRecipient {
+        recipient: scc::PublicKey,
+    },
+    EstimateFee {},
+    AwardsHistory {
+        starting_epoch: u64,
+        limit: u64,
+    },
+    MempoolInfo {},
+    /// Transactions in mempool, ordered by fee rate from the highest.
+    MempoolTransactions {
+        offset: u64,
+        limit: u64,
+    },
+    RemoveTransaction {
+        tx_hash: Hash,
+    },
 }
 
 ///
@@ -83,12 +134,18 @@ pub enum NodeResponse {
     MicroBlockPopped,
     ChainName {
         name: String,
+        /// Address prefix.
+        prefix: String,
     },
     #[serde(skip)]
     AddTransaction {
         hash: Hash,
         status: TransactionStatus,
     },
+    TransactionBroadcasted {
+        tx_hash: Hash,
+        status: TransactionStatus,
+    },
     CertificateValid {
         epoch: u64,
         block_hash: Hash,
@@ -120,11 +177,87 @@ pub enum NodeResponse {
         #[serde(skip)]
         rx: Option<mpsc::Receiver<ChainNotification>>, // Option is needed for serde.
     },
+    LightMacroBlockInfo(LightMacroBlock),
+    LightMicroBlockInfo(LightMicroBlock),
+    LightOutputs {
+        block_hash: Hash,
+        outputs: Vec<Output>,
+    },
+    TransactionInfo(ExtendedTransaction),
+    TransactionStatus {
+        tx_hash: Hash,
+        status: TransactionStatus,
+    },
+    OutputsByRecipient {
+        recipient: scc::PublicKey,
+        outputs: Vec<Hash>,
+    },
+    /// Suggested fees based on recent micro blocks.
+    FeeEstimate {
+        /// The median fee per FEE_RATE_UTXOS (1000) inputs/outputs required by
+        /// recent congested micro blocks.
+        fee_rate: i64,
+        /// The suggested fee per output of a payment with the same number of inputs and outputs.
+        payment_fee: i64,
+        /// The suggested fee of a stake transaction.
+        stake_fee: i64,
+    },
+    /// Service award draws, which can be re-verified with `AwardDraw::verify()`.
+    AwardsHistory {
+        draws: Vec<AwardDraw>,
+    },
+    MempoolInfo(MempoolInfo),
+    MempoolTransactions {
+        /// The total number of transactions in mempool.
+        total: u64,
+        transactions: Vec<MempoolTransactionInfo>,
+    },
+    TransactionRemoved {
+        tx_hash: Hash,
+    },
     Error {
         error: String,
     },
 }
 
+/// Mempool statistics.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MempoolInfo {
+    pub transactions: u64,
+    pub inputs: u64,
+    pub outputs: u64,
+    /// The total size of serialized transactions in bytes.
+    pub size: u64,
+    /// Transactions grouped by fee rate, from the lowest.
+    pub fee_histogram: Vec<FeeRateBucket>,
+}
+
+/// Transactions with a fee rate in [min_fee_rate, 2 * min_fee_rate).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FeeRateBucket {
+    pub min_fee_rate: i64,
+    pub transactions: u64,
+    /// The total size of serialized transactions in bytes.
+    pub size: u64,
+}
+
+/// A pending transaction in mempool.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MempoolTransactionInfo {
+    pub tx_hash: Hash,
+    pub fee: i64,
+    /// Fee per FEE_RATE_UTXOS (1000) inputs/outputs.
+    pub fee_rate: i64,
+    pub inputs: u64,
+    pub outputs: u64,
+    /// The size of serialized transaction in bytes.
+    pub size: u64,
+    /// The local time when the transaction was added to mempool.
+    pub received: Timestamp,
+    /// Seconds since the transaction was added to mempool.
+    pub age: u64,
+}
+
 /// Notification about synchronization status.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct StatusInfo {
@@ -182,6 +315,16 @@ impl ExtendedMacroBlock {
     }
 }
 
+/// A transaction with its location in the blockchain.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExtendedTransaction {
+    pub tx_hash: Hash,
+    pub epoch: u64,
+    pub offset: u32,
+    pub block_hash: Hash,
+    pub transaction: Transaction,
+}
+
 /// Information about reverted micro block.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RevertedMicroBlock {
@@ -255,6 +398,10 @@ pub enum TransactionStatus {
         epoch: u64,
         offset: Option<u32>,
     },
+    /// Transaction was replaced by other transaction with a higher fee.
+    Replaced {
+        replaced_by: Hash,
+    },
 }
 
 impl Hashable for TransactionStatus {
@@ -291,6 +438,10 @@ impl Hashable for TransactionStatus {
                     "none".hash(hasher);
                 }
             }
+            TransactionStatus::Replaced { replaced_by } => {
+                "Replaced".hash(hasher);
+                replaced_by.hash(hasher);
+            }
         }
     }
 }
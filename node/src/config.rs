@@ -23,6 +23,7 @@
 
 use serde_derive::{Deserialize, Serialize};
 use std::time::Duration;
+use stegos_blockchain::ParameterChange;
 
 /// Node configuration.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -48,10 +49,21 @@ pub struct NodeConfig {
     pub max_inputs_in_mempool: usize,
     /// The maximal number of outputs in mempool.
     pub max_outputs_in_mempool: usize,
+    /// How often save mempool to the disk.
+    pub mempool_save_interval: Duration,
     /// Minimal fee for payment transactions.
     pub min_payment_fee: i64,
     /// Minimal fee for the stake transactions.
     pub min_stake_fee: i64,
+    /// Initialize the empty blockchain from a state snapshot downloaded from the network.
+    pub fast_sync: bool,
+    /// Keep only block headers and output canaries, download outputs on demand.
+    pub light: bool,
+    /// Keep only the last N epochs of full blocks, 0 to keep the full history.
+    /// Must be greater than `ChainConfig::stake_epochs`.
+    pub pruning_epochs: u64,
+    /// A change of consensus parameters to propose and vote for as a validator.
+    pub proposal: Option<ParameterChange>,
 }
 
 impl Default for NodeConfig {
@@ -68,8 +80,13 @@ impl Default for NodeConfig {
             max_outputs_in_block: 1000,
             max_inputs_in_mempool: 10000,
             max_outputs_in_mempool: 10000,
+            mempool_save_interval: Duration::from_secs(60),
             min_payment_fee: 1_000, // 0.001 STG
             min_stake_fee: 0,       // free
+            fast_sync: false,
+            light: false,
+            pruning_epochs: 0,
+            proposal: None,
         }
     }
 }
@@ -50,6 +50,10 @@ pub enum NodeTransactionError {
     TooManyOutputs(Hash, usize, usize),
     #[fail(display = "Can't process transaction - mempool is full: tx={}", _0)]
     MempoolIsFull(Hash),
+    #[fail(display = "Transaction not found in mempool: tx={}", _0)]
+    NotFound(Hash),
+    #[fail(display = "Node is not synchronized: tx={}", _0)]
+    NotSynchronized(Hash),
 }
 
 #[derive(Debug, Fail)]
@@ -26,6 +26,7 @@
 pub mod api;
 mod config;
 mod error;
+mod light;
 mod loader;
 mod mempool;
 pub mod metrics;
@@ -38,9 +39,10 @@ mod validation;
 pub use crate::api::*;
 pub use crate::config::NodeConfig;
 use crate::error::*;
+use crate::light::LightMessage;
 use crate::loader::ChainLoaderMessage;
-use crate::mempool::Mempool;
-use crate::replication::Replication;
+use crate::mempool::{fee_rate, fee_rate_bucket, Mempool, FEE_RATE_UTXOS};
+use crate::replication::{Replication, ReplicationRow};
 use crate::txpool::TransactionPoolService;
 pub use crate::txpool::MAX_PARTICIPANTS;
 use crate::validation::*;
@@ -48,10 +50,12 @@ use failure::{format_err, Error};
 use futures::sync::{mpsc, oneshot};
 use futures::{task, Async, AsyncSink, Future, Poll, Sink, Stream};
 use futures_stream_select_all_send::select_all;
+pub use light::{LightNodeService, LIGHT_TOPIC};
+use linked_hash_map::LinkedHashMap;
 pub use loader::CHAIN_LOADER_TOPIC;
 use rand::{self, Rng};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::thread;
 use std::time::{Duration, Instant};
 use stegos_blockchain::Timestamp;
@@ -59,7 +63,9 @@ use stegos_blockchain::*;
 use stegos_consensus::optimistic::{
     AddressedViewChangeProof, SealedViewChangeProof, ViewChangeCollector, ViewChangeMessage,
 };
-use stegos_consensus::{self as consensus, Consensus, ConsensusMessage, MacroBlockProposal};
+use stegos_consensus::{
+    self as consensus, Consensus, ConsensusError, ConsensusMessage, MacroBlockProposal,
+};
 use stegos_crypto::hash::Hash;
 use stegos_crypto::pbc;
 use stegos_network::{Network, ReplicationEvent};
@@ -114,6 +120,8 @@ pub const VIEW_CHANGE_PROOFS_TOPIC: &'static str = "view_changes_proofs";
 pub const VIEW_CHANGE_DIRECT: &'static str = "view_changes_direct";
 /// Topic used for sending sealed blocks.
 const SEALED_BLOCK_TOPIC: &'static str = "block";
+/// The maximal number of remembered statuses of transactions which left mempool.
+const MAX_RECENT_TX_STATUSES: usize = 10_000;
 
 //
 // Logging utils.
@@ -157,6 +165,7 @@ pub enum NodeMessage {
     ViewChangeProof(Vec<u8>),
     ViewChangeProofMessage(UnicastMessage),
     ChainLoaderMessage(UnicastMessage),
+    LightMessage(UnicastMessage),
 }
 
 enum MicroBlockTimer {
@@ -288,6 +297,12 @@ pub struct NodeService {
     /// Memory pool of pending transactions.
     mempool: Mempool,
 
+    /// Transactions saved before the restart, waiting for synchronization.
+    saved_txs: Vec<(Hash, Transaction)>,
+
+    /// Statuses of transactions which were reverted or conflicted by blocks.
+    recent_tx_statuses: LinkedHashMap<Hash, TransactionStatus>,
+
     /// Consensus state.
     validation: Validation,
 
@@ -306,6 +321,9 @@ pub struct NodeService {
     /// Timer to check sync status
     check_sync: Interval,
 
+    /// Timer to save mempool to the disk.
+    mempool_save: Interval,
+
     //
     // Communication with environment.
     //
@@ -334,7 +352,7 @@ impl NodeService {
     /// Constructor.
     pub fn new(
         cfg: NodeConfig,
-        chain: Blockchain,
+        mut chain: Blockchain,
         network_skey: pbc::SecretKey,
         network_pkey: pbc::PublicKey,
         network: Network,
@@ -342,8 +360,11 @@ impl NodeService {
         peer_id: PeerId,
         replication_rx: mpsc::UnboundedReceiver<ReplicationEvent>,
     ) -> Result<(Self, Node), Error> {
+        chain.set_pruning_epochs(cfg.pruning_epochs)?;
         let (outbox, inbox) = mpsc::unbounded();
         let mempool = Mempool::new();
+        let saved_txs = Vec::new();
+        let recent_tx_statuses = LinkedHashMap::new();
 
         let last_block_clock = clock::now();
         let validation = if chain.is_epoch_full() {
@@ -402,9 +423,16 @@ impl NodeService {
             .map(NodeMessage::ChainLoaderMessage);
         streams.push(Box::new(requests_rx));
 
+        // Light node requests.
+        let light_rx = network
+            .subscribe_unicast(light::LIGHT_TOPIC)?
+            .map(NodeMessage::LightMessage);
+        streams.push(Box::new(light_rx));
+
         let events = select_all(streams);
 
         let check_sync = Interval::new_interval(cfg.sync_change_timeout);
+        let mempool_save = Interval::new_interval(cfg.mempool_save_interval);
         let chain_readers = Vec::new();
         let chain_subscribers = Vec::new();
         let node = Node {
@@ -415,18 +443,21 @@ impl NodeService {
         let replication = Replication::new(
             chain.epoch(),
             chain.offset(),
+            cfg.fast_sync,
             peer_id,
             network.clone(),
             replication_rx,
         );
 
-        let service = NodeService {
+        let mut service = NodeService {
             cfg,
             chain_name,
             chain,
             network_skey,
             network_pkey,
             mempool,
+            saved_txs,
+            recent_tx_statuses,
             validation,
             last_block_clock,
             cheating_proofs,
@@ -437,30 +468,97 @@ impl NodeService {
             node: node.clone(),
             network: network.clone(),
             check_sync,
+            mempool_save,
             events,
             txpool_service,
             replication,
             status_subscribers,
         };
         service.update_stake_balance();
+        if let Err(e) = service.load_mempool() {
+            serror!(service, "Failed to load mempool: {}", e);
+        }
 
         Ok((service, node))
     }
 
+    /// Load transactions saved before the restart.
+    fn load_mempool(&mut self) -> Result<(), Error> {
+        let txs = self.chain.load_mempool()?;
+        self.saved_txs = txs.into_iter().map(|tx| (Hash::digest(&tx), tx)).collect();
+        sinfo!(
+            self,
+            "Loaded saved mempool: transactions={}",
+            self.saved_txs.len()
+        );
+        Ok(())
+    }
+
+    /// Put transactions saved before the restart back to mempool.
+    /// Transactions are validated, limited and announced to the network
+    /// like new ones, so this waits until the node is synchronized.
+    fn restore_mempool(&mut self) {
+        if self.saved_txs.is_empty() || !self.is_synchronized() {
+            return;
+        }
+        let saved_txs = std::mem::replace(&mut self.saved_txs, Vec::new());
+        for (tx_hash, tx) in saved_txs {
+            if let TransactionStatus::Rejected { error } = self.handle_add_tx(tx) {
+                sdebug!(
+                    self,
+                    "Dropped saved transaction: tx={}, error={}",
+                    &tx_hash,
+                    error
+                );
+            }
+        }
+        sinfo!(
+            self,
+            "Restored mempool: transactions={}",
+            self.mempool.len()
+        );
+    }
+
+    /// Save mempool to the disk.
+    fn save_mempool(&self) {
+        let saved_txs = self.saved_txs.iter().map(|(tx_hash, tx)| (tx_hash, tx));
+        if let Err(e) = self
+            .chain
+            .save_mempool(self.mempool.iter().chain(saved_txs))
+        {
+            serror!(self, "Failed to save mempool: {}", e);
+            return;
+        }
+        strace!(self, "Saved mempool: transactions={}", self.mempool.len());
+    }
+
     /// Invoked when network is ready.
     pub fn init(&mut self) -> Result<(), Error> {
         self.update_validation_status();
         self.on_facilitator_changed();
         self.on_status_changed();
         self.restake_expiring_stakes()?;
+        self.restore_mempool();
         Ok(())
     }
 
     /// Send transaction to node and to the network.
+    /// The transaction is relayed to the network only if it has been accepted to mempool.
     fn send_transaction(&mut self, tx: Transaction) -> Result<(), Error> {
         let data = tx.into_buffer()?;
         let tx_hash = Hash::digest(&tx);
-        self.network.publish(&TX_TOPIC, data.clone())?;
+        if let Err(e) = self.add_transaction(tx.clone()) {
+            match e.downcast::<NodeTransactionError>() {
+                // Already validated, relay it again.
+                Ok(NodeTransactionError::AlreadyExists(tx_hash)) => {
+                    self.network.publish(&TX_TOPIC, data)?;
+                    return Err(NodeTransactionError::AlreadyExists(tx_hash).into());
+                }
+                Ok(e) => return Err(e.into()),
+                Err(e) => return Err(e),
+            }
+        }
+        self.network.publish(&TX_TOPIC, data)?;
         sinfo!(
             self,
             "Sent transaction to the network: tx={}, inputs={:?}, outputs={:?}, fee={}",
@@ -475,23 +573,12 @@ impl NodeService {
                 .collect::<Vec<String>>(),
             tx.fee()
         );
-        self.handle_transaction(tx)?;
         Ok(())
     }
 
     /// Handle incoming transactions received from network.
     fn handle_transaction(&mut self, tx: Transaction) -> Result<(), Error> {
         let tx_hash = Hash::digest(&tx);
-        if !tx.is_restaking() && !self.is_synchronized() {
-            sdebug!(self,
-                "Node is not synchronized - ignore transaction from the network: tx={}, inputs={:?}, outputs={:?}, fee={}",
-                &tx_hash,
-                tx.txins(),
-                tx.txouts().iter().map(Hash::digest),
-                tx.fee()
-            );
-            return Ok(());
-        }
         sinfo!(
             self,
             "Received transaction from the network: tx={}, inputs={:?}, outputs={:?}, fee={}",
@@ -506,6 +593,29 @@ impl NodeService {
                 .collect::<Vec<String>>(),
             tx.fee()
         );
+        match self.add_transaction(tx) {
+            Ok(()) => Ok(()),
+            Err(e) => match e.downcast::<NodeTransactionError>() {
+                Ok(NodeTransactionError::NotSynchronized(_tx_hash)) => {
+                    sdebug!(
+                        self,
+                        "Node is not synchronized - ignore transaction from the network: tx={}",
+                        &tx_hash
+                    );
+                    Ok(())
+                }
+                Ok(e) => Err(e.into()),
+                Err(e) => Err(e),
+            },
+        }
+    }
+
+    /// Validate transaction and add it to mempool.
+    fn add_transaction(&mut self, tx: Transaction) -> Result<(), Error> {
+        let tx_hash = Hash::digest(&tx);
+        if !tx.is_restaking() && !self.is_synchronized() {
+            return Err(NodeTransactionError::NotSynchronized(tx_hash).into());
+        }
 
         // Check that transaction has proper type.
         let check_limits = match &tx {
@@ -533,13 +643,6 @@ impl NodeService {
                 )
                 .into());
             }
-
-            // Limit the maximum size of mempool.
-            if self.mempool.inputs_len() > self.cfg.max_inputs_in_mempool
-                || self.mempool.outputs_len() > self.cfg.max_outputs_in_mempool
-            {
-                return Err(NodeTransactionError::MempoolIsFull(tx_hash).into());
-            }
         }
 
         // Validate transaction.
@@ -560,12 +663,63 @@ impl NodeService {
                     "Error during transaction validating when not synchronized: {}",
                     e
                 );
-                return Ok(());
+                return Err(NodeTransactionError::NotSynchronized(tx_hash).into());
             }
             Err(e) => return Err(e),
             Ok(()) => {}
         };
 
+        // Replace conflicting transactions with a lower fee.
+        let replaced = self.mempool.replace(&tx);
+
+        // Limit the maximum size of mempool.
+        if check_limits {
+            let evicted = self.mempool.evict(
+                &tx,
+                self.cfg.max_inputs_in_mempool,
+                self.cfg.max_outputs_in_mempool,
+            );
+            match evicted {
+                Some(evicted) => {
+                    for evicted_tx_hash in evicted.keys() {
+                        sinfo!(
+                            self,
+                            "Evicted transaction with a lower fee rate from mempool: tx={}, new_tx={}",
+                            evicted_tx_hash,
+                            &tx_hash
+                        );
+                        let status = TransactionStatus::Rejected {
+                            error: format!(
+                                "Evicted from mempool by a transaction with a higher fee rate: tx={}",
+                                &tx_hash
+                            ),
+                        };
+                        self.remember_tx_status(*evicted_tx_hash, status);
+                    }
+                }
+                None => {
+                    // Put replaced transactions back.
+                    for (replaced_tx_hash, replaced_tx) in replaced {
+                        self.mempool.push_tx(replaced_tx_hash, replaced_tx);
+                    }
+                    return Err(NodeTransactionError::MempoolIsFull(tx_hash).into());
+                }
+            }
+        }
+
+        for replaced_tx_hash in replaced.keys() {
+            sinfo!(
+                self,
+                "Replaced transaction with a higher fee in mempool: tx={}, new_tx={}",
+                replaced_tx_hash,
+                &tx_hash
+            );
+            let status = TransactionStatus::Replaced {
+                replaced_by: tx_hash,
+            };
+            self.remember_tx_status(*replaced_tx_hash, status);
+        }
+
         // Queue to mempool.
         sinfo!(
             self,
@@ -702,7 +856,15 @@ impl NodeService {
             tx.txouts.len()
         );
 
-        self.send_transaction(tx.into())?;
+        match self.send_transaction(tx.into()) {
+            Ok(()) => {}
+            Err(e) => match e.downcast::<NodeTransactionError>() {
+                // Will be re-created on the next attempt.
+                Ok(NodeTransactionError::NotSynchronized(_tx_hash)) => {}
+                Ok(e) => return Err(e.into()),
+                Err(e) => return Err(e),
+            },
+        }
 
         self.restaking_offset = if self.chain.cfg().micro_blocks_in_epoch > 1 {
             // Restake in [0; blocks_in_epoch * 4/5) interval.
@@ -748,8 +910,6 @@ impl NodeService {
                 remote.header.pkey, local.header.pkey,
                 "checked by upper levels"
             );
-            let leader = remote.header.pkey;
-
             if remote_hash == local_hash {
                 sdebug!(
                     self,
@@ -773,10 +933,7 @@ impl NodeService {
             metrics::MICRO_BLOCKS_CHEATS.inc();
 
             let proof = SlashingProof::new_unchecked(remote.clone(), local.into_owned());
-
-            if let Some(_proof) = self.cheating_proofs.insert(leader, proof) {
-                sdebug!(self, "Cheater was already detected: cheater={}", leader);
-            }
+            self.on_cheater_detected(proof);
 
             return Err(ForkError::Canceled);
         } else if remote.header.view_change < local.header.view_change {
@@ -895,8 +1052,9 @@ impl NodeService {
             remote_view_change);
 
         // Truncate the blockchain.
+        self.chain.begin_rollback(offset)?;
         while self.chain.offset() > offset {
-            self.pop_micro_block()?;
+            self.pop_micro_block(false)?;
         }
         assert_eq!(offset, self.chain.offset());
 
@@ -1073,6 +1231,27 @@ impl NodeService {
         }
     }
 
+    /// Initialize the empty blockchain from a state snapshot.
+    fn handle_snapshot(&mut self, snapshot: StateSnapshot) -> Result<(), Error> {
+        let epoch = snapshot.epoch();
+        let block_hash = snapshot.block_hash();
+        let utxo_count = snapshot.utxo_count();
+        self.chain.apply_snapshot(snapshot)?;
+        self.last_block_clock = clock::now();
+        sinfo!(
+            self,
+            "Applied a state snapshot: epoch={}, block={}, utxos={}",
+            epoch,
+            block_hash,
+            utxo_count
+        );
+        self.on_facilitator_changed();
+        self.update_stake_balance();
+        self.update_validation_status();
+        self.on_status_changed();
+        Ok(())
+    }
+
     /// Try to apply a new micro block into the blockchain.
     fn apply_macro_block(&mut self, block: MacroBlock) -> Result<(), Error> {
         let hash = Hash::digest(&block);
@@ -1091,16 +1270,57 @@ impl NodeService {
         )
         .map_err(|e| BlockError::InvalidBlockSignature(e, epoch, hash))?;
 
+        // Transactions of our micro blocks are committed if the macro block is built on top of them.
+        // The block can also be built on top of a prefix of our micro blocks after a view change.
+        let mut committed_txs: HashSet<Hash> = HashSet::new();
+        let mut committed_offset: u32 = 0;
+        let mut micro_block_txs: Vec<Vec<Hash>> = Vec::new();
+        for offset in 0..self.chain.offset() {
+            let micro_block = self.chain.micro_block(epoch, offset)?;
+            if Hash::digest(micro_block.as_ref()) == block.header.previous {
+                committed_offset = offset + 1;
+            }
+            micro_block_txs.push(micro_block.transactions.iter().map(Hash::digest).collect());
+        }
+        for txs in micro_block_txs.into_iter().take(committed_offset as usize) {
+            committed_txs.extend(txs);
+        }
+
         // Remove all micro blocks.
+        self.chain.begin_rollback(0)?;
         while self.chain.offset() > 0 {
-            self.pop_micro_block()?;
+            let is_committed = self.chain.offset() <= committed_offset;
+            self.pop_micro_block(is_committed)?;
         }
         assert_eq!(0, self.chain.offset());
 
         let (inputs, outputs) = self.chain.push_macro_block(block.clone(), timestamp)?;
 
+        // Transactions of another fork are found by outputs of the block.
+        let forked_txs: Vec<Hash> = self
+            .mempool
+            .find_committed(outputs.keys())
+            .into_iter()
+            .filter(|tx_hash| !committed_txs.contains(tx_hash))
+            .collect();
+        self.chain
+            .index_committed_transactions(epoch, &forked_txs)?;
+        committed_txs.extend(forked_txs);
+        for tx_hash in &committed_txs {
+            self.recent_tx_statuses.remove(tx_hash);
+        }
+
         // Remove conflict transactions from the mempool.
-        self.mempool.prune(inputs.iter(), outputs.keys());
+        let pruned = self.mempool.prune(inputs.iter(), outputs.keys());
+        for (tx_hash, (_tx, full)) in pruned {
+            if !full && !committed_txs.contains(&tx_hash) {
+                let status = TransactionStatus::Conflicted {
+                    epoch,
+                    offset: None,
+                };
+                self.remember_tx_status(tx_hash, status);
+            }
+        }
 
         let epoch_info = self
             .chain
@@ -1108,7 +1328,30 @@ impl NodeService {
             .expect("Expect epoch info for last macroblock.")
             .clone();
         let notification = ExtendedMacroBlock { block, epoch_info };
-        self.cheating_proofs.clear();
+        // Keep proofs collected during the macro block consensus of the previous epoch.
+        let chain = &self.chain;
+        self.cheating_proofs
+            .retain(|_cheater, proof| proof.validate(chain).is_ok());
+        // Punish validators jailed for downtime by this block.
+        if chain.cfg().jail_penalty > 0 {
+            let jailed: Vec<pbc::PublicKey> = chain
+                .liveness()
+                .jailed
+                .iter()
+                .filter(|(_validator, jailed)| jailed.epoch == epoch)
+                .map(|(validator, _jailed)| *validator)
+                .collect();
+            for validator in jailed {
+                swarn!(
+                    self,
+                    "Validator was jailed for downtime: validator={}",
+                    validator
+                );
+                // Proofs of conflicting messages confiscate the whole stake.
+                let proof = SlashingProof::Downtime { validator, epoch };
+                self.cheating_proofs.entry(validator).or_insert(proof);
+            }
+        }
         self.on_facilitator_changed();
         self.on_block_added(block_timestamp, notification.into(), was_synchronized);
 
@@ -1196,7 +1439,21 @@ impl NodeService {
             self.chain.push_micro_block(block.clone(), timestamp)?;
 
         // Remove conflict transactions from the mempool.
-        self.mempool.prune(inputs.iter(), outputs.keys());
+        let pruned = self.mempool.prune(inputs.iter(), outputs.keys());
+        for (tx_hash, (_tx, full)) in pruned {
+            if !full {
+                let status = TransactionStatus::Conflicted {
+                    epoch,
+                    offset: Some(offset),
+                };
+                self.remember_tx_status(tx_hash, status);
+            }
+        }
+        self.mempool.push_micro_block(
+            &block,
+            self.cfg.max_inputs_in_block,
+            self.cfg.max_outputs_in_block,
+        );
 
         // Update metrics.
         self.on_block_added(block_timestamp, block.into(), was_synchronized);
@@ -1311,6 +1568,15 @@ impl NodeService {
         if epoch > self.chain.epoch() {
             return Err(format_err!("Invalid epoch requested: epoch={}", epoch));
         }
+        if let Some(snapshot_epoch) = self.chain.snapshot_epoch() {
+            if epoch <= snapshot_epoch {
+                return Err(format_err!(
+                    "Blocks before the state snapshot are not available: epoch={}, snapshot_epoch={}",
+                    epoch,
+                    snapshot_epoch
+                ));
+            }
+        }
         // Set buffer size to fit entire epoch plus some extra blocks.
         let buffer = self.chain.cfg().micro_blocks_in_epoch as usize + 10;
         let (tx, rx) = mpsc::channel(buffer);
@@ -1320,6 +1586,152 @@ impl NodeService {
         Ok(rx)
     }
 
+    /// Handler for NodeRequest::EstimateFee
+    fn handle_estimate_fee(&self) -> NodeResponse {
+        let fee_rate = self.mempool.estimate_fee_rate();
+        // Each output is paired with an input.
+        let fee = (2 * fee_rate + FEE_RATE_UTXOS - 1) / FEE_RATE_UTXOS;
+        let payment_fee = std::cmp::max(self.cfg.min_payment_fee, fee);
+        NodeResponse::FeeEstimate {
+            fee_rate,
+            payment_fee,
+            stake_fee: self.cfg.min_stake_fee,
+        }
+    }
+
+    /// Handler for NodeRequest::MempoolInfo
+    fn handle_mempool_info(&self) -> Result<MempoolInfo, Error> {
+        let mut size: u64 = 0;
+        let mut fee_histogram: BTreeMap<i64, FeeRateBucket> = BTreeMap::new();
+        for (_tx_hash, tx) in self.mempool.iter() {
+            let tx_size = tx.into_buffer()?.len() as u64;
+            size += tx_size;
+            let min_fee_rate = fee_rate_bucket(fee_rate(tx));
+            let bucket = fee_histogram
+                .entry(min_fee_rate)
+                .or_insert_with(|| FeeRateBucket {
+                    min_fee_rate,
+                    transactions: 0,
+                    size: 0,
+                });
+            bucket.transactions += 1;
+            bucket.size += tx_size;
+        }
+        Ok(MempoolInfo {
+            transactions: self.mempool.len() as u64,
+            inputs: self.mempool.inputs_len() as u64,
+            outputs: self.mempool.outputs_len() as u64,
+            size,
+            fee_histogram: fee_histogram.into_iter().map(|(_k, v)| v).collect(),
+        })
+    }
+
+    /// Handler for NodeRequest::MempoolTransactions
+    fn handle_mempool_transactions(&self, offset: u64, limit: u64) -> Result<NodeResponse, Error> {
+        let mut txs: Vec<(&Hash, &Transaction)> = self.mempool.iter().collect();
+        // The same order as in create_block(), the hash makes pages stable.
+        txs.sort_by(|(h1, tx1), (h2, tx2)| fee_rate(tx2).cmp(&fee_rate(tx1)).then(h1.cmp(h2)));
+        let now = Timestamp::now();
+        let mut transactions = Vec::new();
+        for (tx_hash, tx) in txs.iter().skip(offset as usize).take(limit as usize) {
+            let received = self.mempool.get_received(tx_hash).expect("exists");
+            let age = if now > received {
+                now.duration_since(received).as_secs()
+            } else {
+                0
+            };
+            transactions.push(MempoolTransactionInfo {
+                tx_hash: **tx_hash,
+                fee: tx.fee(),
+                fee_rate: fee_rate(tx),
+                inputs: tx.txins().len() as u64,
+                outputs: tx.txouts().len() as u64,
+                size: tx.into_buffer()?.len() as u64,
+                received,
+                age,
+            });
+        }
+        Ok(NodeResponse::MempoolTransactions {
+            total: txs.len() as u64,
+            transactions,
+        })
+    }
+
+    /// Handler for NodeRequest::RemoveTransaction
+    fn handle_remove_tx(&mut self, tx_hash: Hash) -> Result<(), Error> {
+        if self.mempool.remove_tx(&tx_hash).is_none() {
+            return Err(NodeTransactionError::NotFound(tx_hash).into());
+        }
+        sinfo!(self, "Removed transaction from mempool: tx={}", &tx_hash);
+        let status = TransactionStatus::Rejected {
+            error: "Removed from mempool".to_string(),
+        };
+        self.remember_tx_status(tx_hash, status);
+        metrics::MEMPOOL_TRANSACTIONS.set(self.mempool.len() as i64);
+        metrics::MEMPOOL_INPUTS.set(self.mempool.inputs_len() as i64);
+        metrics::MEMPOOL_OUTPUTS.set(self.mempool.outputs_len() as i64);
+        Ok(())
+    }
+
+    /// Remember the status of a transaction which left mempool or was returned back.
+    fn remember_tx_status(&mut self, tx_hash: Hash, status: TransactionStatus) {
+        self.recent_tx_statuses.insert(tx_hash, status);
+        while self.recent_tx_statuses.len() > MAX_RECENT_TX_STATUSES {
+            self.recent_tx_statuses.pop_front();
+        }
+    }
+
+    /// Handler for NodeRequest::TransactionStatus
+    fn handle_transaction_status(&self, tx_hash: Hash) -> Result<TransactionStatus, Error> {
+        // Committed by a macro block.
+        if let Some(epoch) = self.chain.committed_transaction_epoch(&tx_hash)? {
+            return Ok(TransactionStatus::Committed { epoch });
+        }
+
+        // Included into a micro block of the current epoch.
+        let epoch = self.chain.epoch();
+        if self.chain.cfg().explorer_indexes {
+            if let Some(key) = self.chain.transaction_key(&tx_hash)? {
+                return Ok(TransactionStatus::Prepared {
+                    epoch: key.epoch,
+                    offset: key.offset,
+                });
+            }
+        } else {
+            for offset in 0..self.chain.offset() {
+                let block = self.chain.micro_block(epoch, offset)?;
+                if block
+                    .transactions
+                    .iter()
+                    .any(|tx| Hash::digest(tx) == tx_hash)
+                {
+                    return Ok(TransactionStatus::Prepared { epoch, offset });
+                }
+            }
+        }
+
+        // Reverted back to mempool, replaced, evicted or dropped because of a conflict.
+        let in_mempool = self.mempool.contains_tx(&tx_hash);
+        match self.recent_tx_statuses.get(&tx_hash) {
+            Some(status @ TransactionStatus::Rollback { .. }) if in_mempool => {
+                return Ok(status.clone());
+            }
+            Some(status @ TransactionStatus::Conflicted { .. })
+            | Some(status @ TransactionStatus::Replaced { .. })
+            | Some(status @ TransactionStatus::Rejected { .. })
+                if !in_mempool =>
+            {
+                return Ok(status.clone());
+            }
+            _ => {}
+        }
+
+        if in_mempool {
+            return Ok(TransactionStatus::Accepted {});
+        }
+        Err(format_err!("Transaction not found: tx={}", tx_hash))
+    }
+
     /// Handler for NodeRequest::AddTransaction
     fn handle_add_tx(&mut self, tx: Transaction) -> TransactionStatus {
         match self.send_transaction(tx.clone()) {
@@ -1346,11 +1758,22 @@ impl NodeService {
     ///
     /// # Arguments
     ///
-    /// * `notify_tx_statuses` - notify subscribers that transactions has been reverted.
+    /// * `is_committed` - the block is committed by the next macro block, keep it in indexes.
     ///
-    fn pop_micro_block(&mut self) -> Result<(), Error> {
-        let (pruned_outputs, recovered_inputs, txs, block) = self.chain.pop_micro_block()?;
+    fn pop_micro_block(&mut self, is_committed: bool) -> Result<(), Error> {
+        let (pruned_outputs, recovered_inputs, txs, block) = if is_committed {
+            self.chain.pop_committed_micro_block()?
+        } else {
+            self.chain.pop_micro_block()?
+        };
         self.last_block_clock = clock::now();
+        for tx in &txs {
+            let status = TransactionStatus::Rollback {
+                epoch: block.header.epoch,
+                offset: block.header.offset,
+            };
+            self.remember_tx_status(Hash::digest(tx), status);
+        }
         self.mempool.pop_micro_block(txs);
 
         // Update validation status.
@@ -1379,7 +1802,7 @@ impl NodeService {
                 self.chain.epoch()
             ));
         }
-        self.pop_micro_block()?;
+        self.pop_micro_block(false)?;
         Ok(())
     }
 
@@ -1511,6 +1934,30 @@ impl NodeService {
         task::current().notify();
     }
 
+    /// Called when a validator has signed conflicting messages.
+    /// The cheater will be punished by the next micro block of this node.
+    fn on_cheater_detected(&mut self, proof: SlashingProof) {
+        let cheater = proof.cheater();
+        if let Err(e) = proof.validate(&self.chain) {
+            swarn!(
+                self,
+                "Invalid slashing proof: cheater={}, error={}",
+                cheater,
+                e
+            );
+            return;
+        }
+        swarn!(
+            self,
+            "Validator has signed conflicting messages: cheater={}, epoch={}",
+            cheater,
+            proof.epoch()
+        );
+        if let Some(_proof) = self.cheating_proofs.insert(cheater, proof) {
+            sdebug!(self, "Cheater was already detected: cheater={}", cheater);
+        }
+    }
+
     /// Called when facilitator is changed.
     fn on_facilitator_changed(&mut self) {
         let facilitator = self.chain.facilitator();
@@ -1583,8 +2030,14 @@ impl NodeService {
             } = prev
             {
                 for msg in future_consensus_messages {
-                    if let Err(e) = consensus.feed_message(msg) {
-                        sdebug!(self, "Error in future consensus message: {}", e);
+                    match consensus.feed_message(msg) {
+                        Err(ConsensusError::Equivocation(_cheater, proof)) => {
+                            self.on_cheater_detected(*proof);
+                        }
+                        Err(e) => {
+                            sdebug!(self, "Error in future consensus message: {}", e);
+                        }
+                        Ok(()) => {}
                     }
                 }
             }
@@ -1622,7 +2075,13 @@ impl NodeService {
         };
 
         // Feed message into consensus module.
-        consensus.feed_message(msg)?;
+        match consensus.feed_message(msg) {
+            Err(ConsensusError::Equivocation(_cheater, proof)) => {
+                self.on_cheater_detected(*proof);
+                return Ok(());
+            }
+            r => r?,
+        }
         self.handle_consensus_events();
         Ok(())
     }
@@ -1644,12 +2103,25 @@ impl NodeService {
                 &block_hash
             );
             let start_clock = clock::now();
-            let r = self.chain.validate_proposed_macro_block(
-                view_change,
-                block_hash,
-                &block_proposal.header,
-                &block_proposal.transactions,
-            );
+            let our_proposal = self.cfg.proposal.as_ref();
+            let r = self
+                .chain
+                .validate_proposed_macro_block(
+                    view_change,
+                    block_hash,
+                    &block_proposal.header,
+                    &block_proposal.transactions,
+                )
+                .and_then(|block| {
+                    // Vote only for the parameter change supported by this node.
+                    match &block.header.proposal {
+                        Some(proposal) if Some(proposal) != our_proposal => {
+                            let e = "not supported by this validator".to_string();
+                            Err(BlockError::InvalidParameterChange(epoch, *block_hash, e).into())
+                        }
+                        _ => Ok(block),
+                    }
+                });
             let duration = clock::now().duration_since(start_clock);
             let duration = (duration.as_secs() as f64) + (duration.subsec_nanos() as f64) * 1e-9;
             metrics::MACRO_BLOCK_VALIDATE_TIME.set(duration);
@@ -1754,6 +2226,7 @@ impl NodeService {
             &self.network_skey,
             self.network_pkey.clone(),
             timestamp,
+            self.cfg.proposal.clone(),
         );
         let block_hash = Hash::digest(&block);
 
@@ -1891,6 +2364,9 @@ impl NodeService {
                 self.on_micro_block_leader_changed();
             }
             Ok(None) => {}
+            Err(ConsensusError::Equivocation(_cheater, proof)) => {
+                self.on_cheater_detected(*proof);
+            }
             Err(ref e) if e.is_future_viewchange() => {
                 let validator_pkey = self
                     .chain
@@ -1941,7 +2417,15 @@ impl NodeService {
 
         // Send a view_change message.
         let chain_info = ChainInfo::from_blockchain(&self.chain);
-        let msg = view_change_collector.handle_timeout(chain_info);
+        let last_block_header = if self.chain.offset() > 0 {
+            let block = self
+                .chain
+                .micro_block(self.chain.epoch(), self.chain.offset() - 1)?;
+            Some(block.header.clone())
+        } else {
+            None
+        };
+        let msg = view_change_collector.handle_timeout(chain_info, last_block_header);
         self.network
             .publish(VIEW_CHANGE_TOPIC, msg.into_buffer()?)?;
         metrics::MICRO_BLOCK_VIEW_CHANGES.inc();
@@ -2011,11 +2495,14 @@ impl NodeService {
 
         for (cheater, proof) in &self.cheating_proofs {
             // the cheater was already punished, so we keep proofs for rollback case,
-            // but avoid punish them second time.
-            if !self.chain.is_validator(cheater) {
-                continue;
-            }
-            let slash_tx = confiscate_tx(&self.chain, &self.network_pkey, proof.clone())?;
+            // but avoid punish them second time - confiscate_tx() fails without stakes.
+            let slash_tx = match confiscate_tx(&self.chain, &self.network_pkey, proof.clone()) {
+                Ok(slash_tx) => slash_tx,
+                Err(e) => {
+                    sdebug!(self, "Skip slashing: cheater={}, error={}", cheater, e);
+                    continue;
+                }
+            };
             let tx: Transaction = slash_tx.into();
             let tx_hash = Hash::digest(&tx);
             self.mempool.push_tx(tx_hash, tx);
@@ -2026,6 +2513,9 @@ impl NodeService {
             .chain
             .account_by_network_key(&self.network_pkey)
             .expect("Staked");
+        let delegation_payouts = self
+            .chain
+            .delegation_payouts(&self.network_pkey, self.chain.cfg().block_reward);
         let timestamp = self.next_block_timestamp();
         let mut block = self.mempool.create_block(
             previous,
@@ -2036,6 +2526,7 @@ impl NodeService {
             self.chain.last_random(),
             solution,
             self.chain.cfg().block_reward,
+            &delegation_payouts,
             &recipient_pkey,
             &self.network_skey,
             &self.network_pkey,
@@ -2043,6 +2534,7 @@ impl NodeService {
             self.cfg.max_outputs_in_block,
             timestamp,
         );
+        block.header.version = self.chain.version();
 
         let block_hash = Hash::digest(&block);
 
@@ -2095,6 +2587,7 @@ impl NodeService {
         if epoch >= self.chain.epoch() {
             return Err(format_err!("Macro block doesn't exists: epoch={}", epoch));
         }
+        self.check_block_available(epoch)?;
 
         let block = self.chain.macro_block(epoch)?.into_owned();
         let epoch_info = self.chain.epoch_info(epoch)?.unwrap().clone();
@@ -2102,6 +2595,52 @@ impl NodeService {
         Ok(msg)
     }
 
+    /// Checks that the macro block of `epoch` wasn't pruned or skipped by a state snapshot.
+    fn check_block_available(&self, epoch: u64) -> Result<(), Error> {
+        if let Some(snapshot_epoch) = self.chain.snapshot_epoch() {
+            if epoch < snapshot_epoch {
+                return Err(format_err!(
+                    "Macro block is not available: epoch={}, snapshot_epoch={}",
+                    epoch,
+                    snapshot_epoch
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_transaction_info(&self, tx_hash: Hash) -> Result<ExtendedTransaction, Error> {
+        let indexed = match self.chain.indexed_transaction(&tx_hash)? {
+            Some(indexed) => indexed,
+            None => return Err(format_err!("Transaction not found: tx={}", tx_hash)),
+        };
+        assert_eq!(Hash::digest(&indexed.transaction), tx_hash);
+        Ok(ExtendedTransaction {
+            tx_hash,
+            epoch: indexed.key.epoch,
+            offset: indexed.key.offset,
+            block_hash: indexed.block_hash,
+            transaction: indexed.transaction,
+        })
+    }
+
+    fn handle_spent_by(&self, output_hash: Hash) -> Result<ExtendedTransaction, Error> {
+        match self.chain.spent_by(&output_hash)? {
+            Some(tx_hash) => self.handle_transaction_info(tx_hash),
+            None => Err(format_err!("Output is not spent: utxo={}", output_hash)),
+        }
+    }
+
+    fn handle_created_by(&self, output_hash: Hash) -> Result<ExtendedTransaction, Error> {
+        match self.chain.created_by(&output_hash)? {
+            Some(tx_hash) => self.handle_transaction_info(tx_hash),
+            None => Err(format_err!(
+                "Output wasn't created by a transaction: utxo={}",
+                output_hash
+            )),
+        }
+    }
+
     fn handle_micro_block_info(&self, epoch: u64, offset: u32) -> Result<MicroBlock, Error> {
         if epoch != self.chain.epoch() || offset >= self.chain.offset() {
             return Err(format_err!(
@@ -2113,6 +2652,34 @@ impl NodeService {
         let block = self.chain.micro_block(epoch, offset)?.into_owned();
         Ok(block)
     }
+
+    fn handle_light_macro_block_info(&self, epoch: u64) -> Result<LightMacroBlock, Error> {
+        if epoch >= self.chain.epoch() {
+            return Err(format_err!("Macro block doesn't exists: epoch={}", epoch));
+        }
+        self.check_block_available(epoch)?;
+        let block = self.chain.macro_block(epoch)?.into_owned();
+        match self.chain.light_block(Block::MacroBlock(block))? {
+            LightBlock::LightMacroBlock(block) => Ok(block),
+            LightBlock::LightMicroBlock(_) => unreachable!("Expected a macro block"),
+        }
+    }
+
+    fn handle_light_micro_block_info(
+        &self,
+        epoch: u64,
+        offset: u32,
+    ) -> Result<LightMicroBlock, Error> {
+        let block = self.handle_micro_block_info(epoch, offset)?;
+        Ok(block.into())
+    }
+}
+
+impl Drop for NodeService {
+    fn drop(&mut self) {
+        // Keep pending transactions across restarts.
+        self.save_mempool();
+    }
 }
 
 // Event loop.
@@ -2170,6 +2737,8 @@ impl Future for NodeService {
                 Ok(Async::Ready(Some(_))) => {
                     if !self.is_synchronized() {
                         self.on_status_changed();
+                    } else {
+                        self.restore_mempool();
                     }
                 }
                 Ok(Async::Ready(None)) => {
@@ -2186,6 +2755,23 @@ impl Future for NodeService {
             }
         }
 
+        loop {
+            match self.mempool_save.poll() {
+                Ok(Async::Ready(Some(_))) => self.save_mempool(),
+                Ok(Async::Ready(None)) => {
+                    serror!(self, "Error during saving mempool");
+                    return Ok(Async::Ready(()));
+                }
+                Err(e) => {
+                    serror!(self, "Error: {}", e);
+                    return Err(());
+                }
+                Ok(Async::NotReady) => {
+                    break;
+                }
+            }
+        }
+
         // Poll chain readers.
         let mut i = 0;
         while i < self.chain_readers.len() {
@@ -2223,6 +2809,7 @@ impl Future for NodeService {
                                 }
                                 NodeRequest::ChainName {} => NodeResponse::ChainName {
                                     name: self.chain_name.clone(),
+                                    prefix: stegos_crypto::get_network_prefix().to_string(),
                                 },
                                 NodeRequest::EscrowInfo {} => {
                                     NodeResponse::EscrowInfo(self.chain.escrow_info())
@@ -2245,6 +2832,27 @@ impl Future for NodeService {
                                         status: self.handle_add_tx(tx),
                                     }
                                 }
+                                NodeRequest::BroadcastTransaction { tx } => {
+                                    let tx_hash = Hash::digest(&tx);
+                                    NodeResponse::TransactionBroadcasted {
+                                        tx_hash,
+                                        status: self.handle_add_tx(tx),
+                                    }
+                                }
+                                NodeRequest::BroadcastRawTransaction { data } => {
+                                    match Transaction::from_buffer(&data) {
+                                        Ok(tx) => {
+                                            let tx_hash = Hash::digest(&tx);
+                                            NodeResponse::TransactionBroadcasted {
+                                                tx_hash,
+                                                status: self.handle_add_tx(tx),
+                                            }
+                                        }
+                                        Err(e) => NodeResponse::Error {
+                                            error: format!("{}", e),
+                                        },
+                                    }
+                                }
                                 NodeRequest::ValidateCertificate {
                                     output_hash,
                                     spender,
@@ -2266,6 +2874,7 @@ impl Future for NodeService {
                                         block_hash,
                                         is_final,
                                         timestamp,
+                                        ..
                                     })) => {
                                         match output
                                             .validate_certificate(&spender, &recipient, &rvalue)
@@ -2365,6 +2974,110 @@ impl Future for NodeService {
                                         },
                                     }
                                 }
+                                NodeRequest::LightMacroBlockInfo { epoch } => {
+                                    match self.handle_light_macro_block_info(epoch) {
+                                        Ok(block) => NodeResponse::LightMacroBlockInfo(block),
+                                        Err(e) => NodeResponse::Error {
+                                            error: format!("{}", e),
+                                        },
+                                    }
+                                }
+                                NodeRequest::LightMicroBlockInfo { epoch, offset } => {
+                                    match self.handle_light_micro_block_info(epoch, offset) {
+                                        Ok(block) => NodeResponse::LightMicroBlockInfo(block),
+                                        Err(e) => NodeResponse::Error {
+                                            error: format!("{}", e),
+                                        },
+                                    }
+                                }
+                                NodeRequest::LightOutputs {
+                                    block_hash,
+                                    output_ids,
+                                } => match self.chain.output_proofs(&block_hash, &output_ids) {
+                                    Ok(proofs) => NodeResponse::LightOutputs {
+                                        block_hash,
+                                        outputs: proofs.into_iter().map(|p| p.output).collect(),
+                                    },
+                                    Err(e) => NodeResponse::Error {
+                                        error: format!("{}", e),
+                                    },
+                                },
+                                NodeRequest::TransactionInfo { tx_hash } => {
+                                    match self.handle_transaction_info(tx_hash) {
+                                        Ok(tx_info) => NodeResponse::TransactionInfo(tx_info),
+                                        Err(e) => NodeResponse::Error {
+                                            error: format!("{}", e),
+                                        },
+                                    }
+                                }
+                                NodeRequest::SpentBy { output_hash } => {
+                                    match self.handle_spent_by(output_hash) {
+                                        Ok(tx_info) => NodeResponse::TransactionInfo(tx_info),
+                                        Err(e) => NodeResponse::Error {
+                                            error: format!("{}", e),
+                                        },
+                                    }
+                                }
+                                NodeRequest::CreatedBy { output_hash } => {
+                                    match self.handle_created_by(output_hash) {
+                                        Ok(tx_info) => NodeResponse::TransactionInfo(tx_info),
+                                        Err(e) => NodeResponse::Error {
+                                            error: format!("{}", e),
+                                        },
+                                    }
+                                }
+                                NodeRequest::OutputsByRecipient { recipient } => {
+                                    match self.chain.outputs_by_recipient(&recipient) {
+                                        Ok(outputs) => {
+                                            NodeResponse::OutputsByRecipient { recipient, outputs }
+                                        }
+                                        Err(e) => NodeResponse::Error {
+                                            error: format!("{}", e),
+                                        },
+                                    }
+                                }
+                                NodeRequest::EstimateFee {} => self.handle_estimate_fee(),
+                                NodeRequest::TransactionStatus { tx_hash } => {
+                                    match self.handle_transaction_status(tx_hash) {
+                                        Ok(status) => {
+                                            NodeResponse::TransactionStatus { tx_hash, status }
+                                        }
+                                        Err(e) => NodeResponse::Error {
+                                            error: format!("{}", e),
+                                        },
+                                    }
+                                }
+                                NodeRequest::MempoolInfo {} => match self.handle_mempool_info() {
+                                    Ok(info) => NodeResponse::MempoolInfo(info),
+                                    Err(e) => NodeResponse::Error {
+                                        error: format!("{}", e),
+                                    },
+                                },
+                                NodeRequest::MempoolTransactions { offset, limit } => {
+                                    match self.handle_mempool_transactions(offset, limit) {
+                                        Ok(r) => r,
+                                        Err(e) => NodeResponse::Error {
+                                            error: format!("{}", e),
+                                        },
+                                    }
+                                }
+                                NodeRequest::RemoveTransaction { tx_hash } => {
+                                    match self.handle_remove_tx(tx_hash) {
+                                        Ok(()) => NodeResponse::TransactionRemoved { tx_hash },
+                                        Err(e) => NodeResponse::Error {
+                                            error: format!("{}", e),
+                                        },
+                                    }
+                                }
+                                NodeRequest::AwardsHistory {
+                                    starting_epoch,
+                                    limit,
+                                } => match self.chain.award_draws(starting_epoch, limit as usize) {
+                                    Ok(draws) => NodeResponse::AwardsHistory { draws },
+                                    Err(e) => NodeResponse::Error {
+                                        error: format!("{}", e),
+                                    },
+                                },
                                 NodeRequest::SubscribeChain { epoch, offset } => {
                                     match self.handle_subscription_to_chain(epoch, offset) {
                                         Ok(rx) => NodeResponse::SubscribedChain {
@@ -2404,6 +3117,8 @@ impl Future for NodeService {
                             ChainLoaderMessage::from_buffer(&msg.data)
                                 .and_then(|data| self.handle_chain_loader_message(msg.from, data))
                         }
+                        NodeMessage::LightMessage(msg) => LightMessage::from_buffer(&msg.data)
+                            .and_then(|data| self.handle_light_message(msg.from, data)),
                     };
                     if let Err(e) = result {
                         serror!(self, "Error: {}", e);
@@ -2416,13 +3131,19 @@ impl Future for NodeService {
         // Replication
         loop {
             match self.replication.poll(&self.chain) {
-                Async::Ready(Some(blocks)) => {
+                Async::Ready(Some(ReplicationRow::Blocks(blocks))) => {
                     for block in blocks {
                         if let Err(e) = self.handle_block(block) {
                             serror!(self, "Invalid block received from replication: {}", e);
                         }
                     }
                 }
+                Async::Ready(Some(ReplicationRow::Snapshot(snapshot))) => {
+                    if let Err(e) = self.handle_snapshot(snapshot) {
+                        serror!(self, "Invalid snapshot received from replication: {}", e);
+                        self.replication.change_upstream();
+                    }
+                }
                 Async::Ready(None) => return Ok(Async::Ready(())), // Shutdown.
                 Async::NotReady => break,
             }
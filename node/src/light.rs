@@ -0,0 +1,711 @@
+//! Light Node.
+
+//
+// Copyright (c) 2019 Stegos AG
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use crate::api::*;
+use crate::config::NodeConfig;
+use crate::{Node, NodeMessage, NodeService, SEALED_BLOCK_TOPIC, TX_TOPIC};
+use failure::{format_err, Error};
+use futures::sync::{mpsc, oneshot};
+use futures::{Async, Future, Poll, Stream};
+use futures_stream_select_all_send::select_all;
+use log::*;
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+use std::collections::HashMap;
+use std::time::Instant;
+use stegos_blockchain::{
+    Block, LightBlock, LightBlockchain, Output, OutputProof, Timestamp, Transaction,
+};
+use stegos_crypto::hash::{Hash, Hashable, Hasher};
+use stegos_crypto::pbc;
+use stegos_network::Network;
+use stegos_serialization::traits::ProtoConvert;
+use tokio_timer::{clock, Interval};
+
+//--------------------------------------------------------------------------------------------------
+// Messages.
+//--------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RequestLightBlocks {
+    pub epoch: u64,
+}
+
+impl Hashable for RequestLightBlocks {
+    fn hash(&self, state: &mut Hasher) {
+        self.epoch.hash(state);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ResponseLightBlocks {
+    pub blocks: Vec<LightBlock>,
+}
+
+impl Hashable for ResponseLightBlocks {
+    fn hash(&self, state: &mut Hasher) {
+        for block in &self.blocks {
+            block.hash(state);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RequestOutputs {
+    pub block_hash: Hash,
+    pub output_ids: Vec<u32>,
+}
+
+impl Hashable for RequestOutputs {
+    fn hash(&self, state: &mut Hasher) {
+        self.block_hash.hash(state);
+        for output_id in &self.output_ids {
+            output_id.hash(state);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ResponseOutputs {
+    pub block_hash: Hash,
+    pub output_ids: Vec<u32>,
+    pub proofs: Vec<OutputProof>,
+}
+
+impl Hashable for ResponseOutputs {
+    fn hash(&self, state: &mut Hasher) {
+        self.block_hash.hash(state);
+        for output_id in &self.output_ids {
+            output_id.hash(state);
+        }
+        for proof in &self.proofs {
+            proof.hash(state);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum LightMessage {
+    RequestBlocks(RequestLightBlocks),
+    ResponseBlocks(ResponseLightBlocks),
+    RequestOutputs(RequestOutputs),
+    ResponseOutputs(ResponseOutputs),
+}
+
+impl Hashable for LightMessage {
+    fn hash(&self, state: &mut Hasher) {
+        match self {
+            LightMessage::RequestBlocks(r) => {
+                "request_blocks".hash(state);
+                r.hash(state)
+            }
+            LightMessage::ResponseBlocks(r) => {
+                "response_blocks".hash(state);
+                r.hash(state)
+            }
+            LightMessage::RequestOutputs(r) => {
+                "request_outputs".hash(state);
+                r.hash(state)
+            }
+            LightMessage::ResponseOutputs(r) => {
+                "response_outputs".hash(state);
+                r.hash(state)
+            }
+        }
+    }
+}
+
+/// Unicast topic for light nodes.
+pub const LIGHT_TOPIC: &'static str = "light";
+
+//--------------------------------------------------------------------------------------------------
+// Full Node.
+//--------------------------------------------------------------------------------------------------
+
+impl NodeService {
+    fn handle_request_light_blocks(
+        &mut self,
+        pkey: pbc::PublicKey,
+        request: RequestLightBlocks,
+    ) -> Result<(), Error> {
+        if request.epoch > self.chain.epoch() {
+            warn!(
+                "Received a light request with epoch >= our_epoch: remote_epoch={}, our_epoch={}",
+                request.epoch,
+                self.chain.epoch()
+            );
+            return Ok(());
+        }
+        if let Some(snapshot_epoch) = self.chain.snapshot_epoch() {
+            if request.epoch < snapshot_epoch {
+                warn!(
+                    "Received a light request for pruned blocks: remote_epoch={}, snapshot_epoch={}",
+                    request.epoch, snapshot_epoch
+                );
+                return Ok(());
+            }
+        }
+
+        // Feed the whole epoch.
+        let mut blocks: Vec<LightBlock> = Vec::new();
+        for block in self.chain.blocks_starting(request.epoch, 0) {
+            let is_macro_block = match block {
+                Block::MacroBlock(_) => true,
+                Block::MicroBlock(_) => false,
+            };
+            blocks.push(self.chain.light_block(block)?);
+            if is_macro_block {
+                break;
+            }
+        }
+        info!(
+            "Feeding light blocks: to={}, num_blocks={}",
+            pkey,
+            blocks.len()
+        );
+        let msg = LightMessage::ResponseBlocks(ResponseLightBlocks { blocks });
+        self.network.send(pkey, LIGHT_TOPIC, msg.into_buffer()?)?;
+        Ok(())
+    }
+
+    fn handle_request_outputs(
+        &mut self,
+        pkey: pbc::PublicKey,
+        request: RequestOutputs,
+    ) -> Result<(), Error> {
+        let proofs = match self
+            .chain
+            .output_proofs(&request.block_hash, &request.output_ids)
+        {
+            Ok(proofs) => proofs,
+            Err(e) => {
+                warn!("Failed to get outputs: from={}, error={}", pkey, e);
+                Vec::new()
+            }
+        };
+        debug!(
+            "Feeding outputs: to={}, block={}, num_outputs={}",
+            pkey,
+            request.block_hash,
+            proofs.len()
+        );
+        let msg = LightMessage::ResponseOutputs(ResponseOutputs {
+            block_hash: request.block_hash,
+            output_ids: request.output_ids,
+            proofs,
+        });
+        self.network.send(pkey, LIGHT_TOPIC, msg.into_buffer()?)?;
+        Ok(())
+    }
+
+    pub(crate) fn handle_light_message(
+        &mut self,
+        pkey: pbc::PublicKey,
+        msg: LightMessage,
+    ) -> Result<(), Error> {
+        match msg {
+            LightMessage::RequestBlocks(r) => self.handle_request_light_blocks(pkey, r),
+            LightMessage::RequestOutputs(r) => self.handle_request_outputs(pkey, r),
+            LightMessage::ResponseBlocks(_) | LightMessage::ResponseOutputs(_) => {
+                warn!("Unexpected light response: from={}", pkey);
+                Ok(())
+            }
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Light Node.
+//--------------------------------------------------------------------------------------------------
+
+/// A pending NodeRequest::LightOutputs.
+struct PendingOutputs {
+    output_ids: Vec<u32>,
+    since: Instant,
+    tx: oneshot::Sender<NodeResponse>,
+}
+
+/// Blockchain Node which keeps only headers, multi-signatures and canaries.
+///
+/// Outputs are downloaded from validators on demand and are checked
+/// against the Merkle roots of the stored headers.
+pub struct LightNodeService {
+    /// Config.
+    cfg: NodeConfig,
+    chain_name: String,
+    /// Light Blockchain.
+    chain: LightBlockchain,
+    /// Pending requests for outputs.
+    pending_outputs: HashMap<Hash, Vec<PendingOutputs>>,
+    /// Timer to request blocks.
+    check_sync: Interval,
+    /// Network interface.
+    network: Network,
+    /// Aggregated stream of events.
+    events: Box<dyn Stream<Item = NodeMessage, Error = ()> + Send>,
+}
+
+impl LightNodeService {
+    /// Constructor.
+    pub fn new(
+        cfg: NodeConfig,
+        chain: LightBlockchain,
+        network: Network,
+        chain_name: String,
+    ) -> Result<(Self, Node), Error> {
+        let (outbox, inbox) = mpsc::unbounded();
+
+        let mut streams = Vec::<Box<dyn Stream<Item = NodeMessage, Error = ()> + Send>>::new();
+
+        // Control messages
+        streams.push(Box::new(inbox));
+
+        // Sealed blocks broadcast topic.
+        let block_rx = network
+            .subscribe(&SEALED_BLOCK_TOPIC)?
+            .map(|m| NodeMessage::Block(m));
+        streams.push(Box::new(block_rx));
+
+        // Light messages.
+        let light_rx = network
+            .subscribe_unicast(LIGHT_TOPIC)?
+            .map(NodeMessage::LightMessage);
+        streams.push(Box::new(light_rx));
+
+        let events = select_all(streams);
+
+        let check_sync = Interval::new_interval(cfg.sync_change_timeout);
+        let pending_outputs = HashMap::new();
+        let node = Node {
+            outbox,
+            network: network.clone(),
+        };
+
+        let service = LightNodeService {
+            cfg,
+            chain_name,
+            chain,
+            pending_outputs,
+            check_sync,
+            network,
+            events,
+        };
+
+        Ok((service, node))
+    }
+
+    /// Invoked when network is ready.
+    pub fn init(&mut self) -> Result<(), Error> {
+        self.request_blocks("init")
+    }
+
+    /// Returns a random validator of the current epoch.
+    fn random_validator(&self) -> Option<pbc::PublicKey> {
+        let mut rng = thread_rng();
+        self.chain
+            .validators()
+            .as_slice()
+            .choose(&mut rng)
+            .map(|(pkey, _slots)| *pkey)
+    }
+
+    /// Request blocks of the current epoch from a validator.
+    fn request_blocks(&mut self, reason: &str) -> Result<(), Error> {
+        let from = match self.random_validator() {
+            Some(from) => from,
+            None => return Ok(()),
+        };
+        let epoch = self.chain.epoch();
+        debug!(
+            "Downloading light blocks: from={}, epoch={}, reason='{}'",
+            &from, epoch, reason
+        );
+        let msg = LightMessage::RequestBlocks(RequestLightBlocks { epoch });
+        self.network.send(from, LIGHT_TOPIC, msg.into_buffer()?)
+    }
+
+    fn is_synchronized(&self) -> bool {
+        let timestamp = Timestamp::now();
+        let block_timestamp = self.chain.last_block_timestamp();
+        block_timestamp + self.cfg.sync_timeout >= timestamp
+    }
+
+    fn status(&self) -> StatusInfo {
+        let view_change = match self.chain.offset().checked_sub(1) {
+            Some(offset) => {
+                let block = self.chain.light_micro_block(self.chain.epoch(), offset);
+                block.expect("exists").header.view_change
+            }
+            None => 0,
+        };
+        StatusInfo {
+            is_synchronized: self.is_synchronized(),
+            epoch: self.chain.epoch(),
+            offset: self.chain.offset(),
+            view_change,
+            last_block_hash: self.chain.last_block_hash(),
+            last_macro_block_hash: self.chain.last_macro_block_hash(),
+            last_macro_block_timestamp: self.chain.last_macro_block_timestamp(),
+            local_timestamp: Timestamp::now(),
+        }
+    }
+
+    /// Handle a block from the broadcast topic.
+    fn handle_block(&mut self, block: Block) -> Result<(), Error> {
+        match block {
+            Block::MicroBlock(block)
+                if block.header.epoch == self.chain.epoch()
+                    && block.header.offset == self.chain.offset() =>
+            {
+                self.chain.push_micro_block(block.into())?;
+                Ok(())
+            }
+            Block::MicroBlock(block) => {
+                let reason = if block.header.epoch == self.chain.epoch()
+                    && block.header.offset < self.chain.offset()
+                {
+                    "fork"
+                } else {
+                    "gap"
+                };
+                self.request_blocks(reason)
+            }
+            // Validators elected by macro blocks are not broadcast.
+            Block::MacroBlock(_block) => self.request_blocks("macro block"),
+        }
+    }
+
+    /// Handle a response from a full node.
+    fn handle_response_blocks(
+        &mut self,
+        pkey: pbc::PublicKey,
+        response: ResponseLightBlocks,
+    ) -> Result<(), Error> {
+        info!(
+            "Received light blocks: from={}, our_epoch={}, num_blocks={}",
+            pkey,
+            self.chain.epoch(),
+            response.blocks.len()
+        );
+        let mut has_macro_block = false;
+        for block in response.blocks {
+            match block {
+                LightBlock::LightMacroBlock(block) => {
+                    if block.header.epoch < self.chain.epoch() {
+                        continue;
+                    }
+                    self.chain.push_macro_block(block)?;
+                    has_macro_block = true;
+                }
+                LightBlock::LightMicroBlock(block) => {
+                    let epoch = block.header.epoch;
+                    let offset = block.header.offset;
+                    if epoch != self.chain.epoch() {
+                        continue;
+                    }
+                    if let Some(our_block) = self.chain.light_micro_block(epoch, offset) {
+                        if Hash::digest(our_block) == Hash::digest(&block) {
+                            continue;
+                        }
+                        // Fork - revert to the common ancestor.
+                        while self.chain.offset() > offset {
+                            let block = self.chain.pop_micro_block().expect("exists");
+                            warn!(
+                                "Reverted a light micro block: epoch={}, offset={}, block={}",
+                                epoch,
+                                block.header.offset,
+                                Hash::digest(&block)
+                            );
+                        }
+                    }
+                    self.chain.push_micro_block(block)?;
+                }
+            }
+        }
+        if has_macro_block {
+            // Load the next epoch.
+            self.request_blocks("next epoch")?;
+        }
+        Ok(())
+    }
+
+    /// Handle NodeRequest::LightOutputs.
+    fn handle_request_outputs(
+        &mut self,
+        block_hash: Hash,
+        output_ids: Vec<u32>,
+        tx: oneshot::Sender<NodeResponse>,
+    ) -> Result<(), Error> {
+        let from = match self.random_validator() {
+            Some(from) => from,
+            None => return Err(format_err!("No validators to request outputs from")),
+        };
+        debug!(
+            "Downloading outputs: from={}, block={}, num_outputs={}",
+            from,
+            block_hash,
+            output_ids.len()
+        );
+        let msg = LightMessage::RequestOutputs(RequestOutputs {
+            block_hash,
+            output_ids: output_ids.clone(),
+        });
+        self.network.send(from, LIGHT_TOPIC, msg.into_buffer()?)?;
+        let pending = PendingOutputs {
+            output_ids,
+            since: clock::now(),
+            tx,
+        };
+        self.pending_outputs
+            .entry(block_hash)
+            .or_insert_with(Vec::new)
+            .push(pending);
+        Ok(())
+    }
+
+    /// Handle a response with outputs from a full node.
+    fn handle_response_outputs(
+        &mut self,
+        pkey: pbc::PublicKey,
+        response: ResponseOutputs,
+    ) -> Result<(), Error> {
+        let pending = match self.pending_outputs.get_mut(&response.block_hash) {
+            Some(pending) => pending,
+            None => {
+                warn!("Unexpected outputs: from={}", pkey);
+                return Ok(());
+            }
+        };
+        let index = match pending
+            .iter()
+            .position(|p| p.output_ids == response.output_ids)
+        {
+            Some(index) => index,
+            None => {
+                warn!("Unexpected outputs: from={}", pkey);
+                return Ok(());
+            }
+        };
+        let request = pending.swap_remove(index);
+        if pending.is_empty() {
+            self.pending_outputs.remove(&response.block_hash);
+        }
+
+        let result = self.validate_outputs(pkey, &response);
+        let response = match result {
+            Ok(outputs) => NodeResponse::LightOutputs {
+                block_hash: response.block_hash,
+                outputs,
+            },
+            Err(e) => NodeResponse::Error {
+                error: format!("{}", e),
+            },
+        };
+        request.tx.send(response).ok(); // ignore errors.
+        Ok(())
+    }
+
+    /// Check outputs received from a full node.
+    fn validate_outputs(
+        &self,
+        pkey: pbc::PublicKey,
+        response: &ResponseOutputs,
+    ) -> Result<Vec<Output>, Error> {
+        if response.proofs.len() != response.output_ids.len() {
+            return Err(format_err!(
+                "Outputs not found: from={}, block={}",
+                pkey,
+                response.block_hash
+            ));
+        }
+        let mut outputs = Vec::with_capacity(response.proofs.len());
+        for (output_id, proof) in response.output_ids.iter().zip(&response.proofs) {
+            self.chain
+                .validate_output_proof(&response.block_hash, *output_id, proof)?;
+            outputs.push(proof.output.clone());
+        }
+        Ok(outputs)
+    }
+
+    /// Fail requests for outputs which have not been answered in time.
+    fn expire_pending_outputs(&mut self) {
+        let now = clock::now();
+        let timeout = self.cfg.sync_change_timeout;
+        for (block_hash, pending) in self.pending_outputs.iter_mut() {
+            let mut i = 0;
+            while i < pending.len() {
+                if now.duration_since(pending[i].since) < timeout {
+                    i += 1;
+                    continue;
+                }
+                let request = pending.swap_remove(i);
+                let response = NodeResponse::Error {
+                    error: format!("Timed out loading outputs: block={}", block_hash),
+                };
+                request.tx.send(response).ok(); // ignore errors.
+            }
+        }
+        self.pending_outputs
+            .retain(|_, pending| !pending.is_empty());
+    }
+
+    /// Handler for NodeRequest::AddTransaction.
+    fn handle_add_tx(&mut self, tx: Transaction) -> TransactionStatus {
+        let data = match tx.into_buffer() {
+            Ok(data) => data,
+            Err(e) => {
+                return TransactionStatus::Rejected {
+                    error: format!("{}", e),
+                }
+            }
+        };
+        match self.network.publish(&TX_TOPIC, data) {
+            Ok(()) => TransactionStatus::Accepted {},
+            Err(e) => TransactionStatus::Rejected {
+                error: format!("{}", e),
+            },
+        }
+    }
+
+    /// Handle a request from API.
+    fn handle_request(&mut self, request: NodeRequest, tx: oneshot::Sender<NodeResponse>) {
+        trace!("=> {:?}", request);
+        let response = match request {
+            NodeRequest::ChainName {} => NodeResponse::ChainName {
+                name: self.chain_name.clone(),
+                prefix: stegos_crypto::get_network_prefix().to_string(),
+            },
+            NodeRequest::StatusInfo {} => NodeResponse::StatusInfo(self.status()),
+            NodeRequest::AddTransaction(tx) => {
+                let hash = Hash::digest(&tx);
+                NodeResponse::AddTransaction {
+                    hash,
+                    status: self.handle_add_tx(tx),
+                }
+            }
+            NodeRequest::LightMacroBlockInfo { epoch } => {
+                match self.chain.light_macro_block(epoch) {
+                    Ok(Some(block)) => NodeResponse::LightMacroBlockInfo(block),
+                    Ok(None) => NodeResponse::Error {
+                        error: format!("Macro block doesn't exists: epoch={}", epoch),
+                    },
+                    Err(e) => NodeResponse::Error {
+                        error: format!("{}", e),
+                    },
+                }
+            }
+            NodeRequest::LightMicroBlockInfo { epoch, offset } => {
+                match self.chain.light_micro_block(epoch, offset) {
+                    Some(block) => NodeResponse::LightMicroBlockInfo(block.clone()),
+                    None => NodeResponse::Error {
+                        error: format!(
+                            "Micro block doesn't exists: epoch={}, offset={}",
+                            epoch, offset
+                        ),
+                    },
+                }
+            }
+            NodeRequest::LightOutputs {
+                block_hash,
+                output_ids,
+            } => {
+                // The response is sent when outputs are received.
+                match self.handle_request_outputs(block_hash, output_ids, tx) {
+                    Ok(()) => {}
+                    Err(e) => error!("Error: {}", e),
+                }
+                return;
+            }
+            request => NodeResponse::Error {
+                error: format!("Not supported by light nodes: {:?}", request),
+            },
+        };
+        trace!("<= {:?}", response);
+        tx.send(response).ok(); // ignore errors.
+    }
+}
+
+// Event loop.
+impl Future for LightNodeService {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            match self.check_sync.poll() {
+                Ok(Async::Ready(Some(_))) => {
+                    self.expire_pending_outputs();
+                    if !self.is_synchronized() {
+                        if let Err(e) = self.request_blocks("sync") {
+                            error!("Error: {}", e);
+                        }
+                    }
+                }
+                Ok(Async::Ready(None)) => return Ok(Async::Ready(())), // Shutdown.
+                Err(e) => {
+                    error!("Error: {}", e);
+                    return Err(());
+                }
+                Ok(Async::NotReady) => break,
+            }
+        }
+
+        loop {
+            match self.events.poll().expect("all errors are already handled") {
+                Async::Ready(Some(event)) => {
+                    let result: Result<(), Error> = match event {
+                        NodeMessage::Request { request, tx } => {
+                            self.handle_request(request, tx);
+                            Ok(())
+                        }
+                        NodeMessage::Block(msg) => {
+                            Block::from_buffer(&msg).and_then(|msg| self.handle_block(msg))
+                        }
+                        NodeMessage::LightMessage(msg) => LightMessage::from_buffer(&msg.data)
+                            .and_then(|data| match data {
+                                LightMessage::ResponseBlocks(r) => {
+                                    self.handle_response_blocks(msg.from, r)
+                                }
+                                LightMessage::ResponseOutputs(r) => {
+                                    self.handle_response_outputs(msg.from, r)
+                                }
+                                LightMessage::RequestBlocks(_)
+                                | LightMessage::RequestOutputs(_) => {
+                                    debug!("Ignored a light request: from={}", msg.from);
+                                    Ok(())
+                                }
+                            }),
+                        _ => unreachable!("Not subscribed"),
+                    };
+                    if let Err(e) = result {
+                        error!("Error: {}", e);
+                    }
+                }
+                Async::Ready(None) => return Ok(Async::Ready(())), // Shutdown.
+                Async::NotReady => break,
+            }
+        }
+
+        Ok(Async::NotReady)
+    }
+}
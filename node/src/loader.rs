@@ -116,6 +116,15 @@ impl NodeService {
             );
             return Ok(());
         }
+        if let Some(snapshot_epoch) = self.chain.snapshot_epoch() {
+            if request.epoch < snapshot_epoch {
+                warn!(
+                    "Received a loader request for pruned blocks: remote_epoch={}, snapshot_epoch={}",
+                    request.epoch, snapshot_epoch
+                );
+                return Ok(());
+            }
+        }
 
         self.send_blocks(pkey, request.epoch, 0)
     }
@@ -24,6 +24,7 @@
 use log::*;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::collections::VecDeque;
 use stegos_blockchain::view_changes::ViewChangeProof;
 use stegos_blockchain::Timestamp;
 use stegos_blockchain::*;
@@ -31,11 +32,41 @@ use stegos_crypto::hash::Hash;
 use stegos_crypto::pbc;
 use stegos_crypto::scc::{self, Fr};
 
+/// The number of recent micro blocks used for fee estimation.
+const FEE_HISTORY_LEN: usize = 20;
+
+/// Fee rates are measured per this number of inputs and outputs,
+/// so small fees are not rounded down to zero.
+pub const FEE_RATE_UTXOS: i64 = 1_000;
+
+///
+/// Returns the fee per FEE_RATE_UTXOS inputs/outputs of the transaction.
+///
+pub fn fee_rate(tx: &Transaction) -> i64 {
+    let utxos = std::cmp::max(tx.txins().len() + tx.txouts().len(), 1) as i128;
+    let rate = tx.fee() as i128 * FEE_RATE_UTXOS as i128 / utxos;
+    std::cmp::min(rate, i64::max_value() as i128) as i64
+}
+
+///
+/// Returns the lower bound of a power-of-two range which contains `fee_rate`.
+///
+pub fn fee_rate_bucket(fee_rate: i64) -> i64 {
+    if fee_rate <= 0 {
+        return 0;
+    }
+    1 << (63 - fee_rate.leading_zeros())
+}
+
 /// Memory Pool of Transactions.
 pub struct Mempool {
     pool: HashMap<Hash, Transaction>,
     inputs: HashMap<Hash, Hash>,
     outputs: HashMap<Hash, Hash>,
+    /// The local time when a transaction was added to the mempool.
+    received: HashMap<Hash, Timestamp>,
+    /// The minimal fee rate required by recent micro blocks, zero for non-congested blocks.
+    fee_history: VecDeque<i64>,
 }
 
 impl Mempool {
@@ -46,10 +77,14 @@ impl Mempool {
         let pool: HashMap<Hash, Transaction> = HashMap::new();
         let inputs: HashMap<Hash, Hash> = HashMap::new();
         let outputs: HashMap<Hash, Hash> = HashMap::new();
+        let received: HashMap<Hash, Timestamp> = HashMap::new();
+        let fee_history: VecDeque<i64> = VecDeque::with_capacity(FEE_HISTORY_LEN);
         return Self {
             pool,
             inputs,
             outputs,
+            received,
+            fee_history,
         };
     }
 
@@ -67,6 +102,69 @@ impl Mempool {
         self.inputs.get(input_hash)
     }
 
+    ///
+    /// Returns transactions which claim the same inputs or outputs as `tx`.
+    ///
+    pub fn conflicts(&self, tx: &Transaction) -> HashSet<Hash> {
+        let mut tx_hashes: HashSet<Hash> = HashSet::new();
+        for input_hash in tx.txins() {
+            if let Some(tx_hash) = self.inputs.get(input_hash) {
+                tx_hashes.insert(tx_hash.clone());
+            }
+        }
+        for output in tx.txouts() {
+            let output_hash = Hash::digest(output);
+            if let Some(tx_hash) = self.outputs.get(&output_hash) {
+                tx_hashes.insert(tx_hash.clone());
+            }
+        }
+        tx_hashes
+    }
+
+    ///
+    /// Checks if `tx` can replace all conflicting transactions in the mempool.
+    ///
+    /// Only payment transactions can be replaced and only by a payment transaction
+    /// which has a higher fee rate than every replaced transaction and pays
+    /// at least `min_fee_increment` more than all replaced transactions together.
+    /// Returns false if there is nothing to replace.
+    ///
+    pub fn can_replace(&self, tx: &Transaction, min_fee_increment: i64) -> bool {
+        match tx {
+            Transaction::PaymentTransaction(_) => {}
+            _ => return false,
+        }
+        let conflicts = self.conflicts(tx);
+        if conflicts.is_empty() {
+            return false;
+        }
+        let tx_fee_rate = fee_rate(tx);
+        let mut replaced_fee: i64 = 0;
+        for tx_hash in &conflicts {
+            let replaced_tx = self.pool.get(tx_hash).expect("transaction exists");
+            match replaced_tx {
+                Transaction::PaymentTransaction(_) => {}
+                _ => return false,
+            }
+            // Don't replace transactions which would be mined or kept first.
+            if fee_rate(replaced_tx) >= tx_fee_rate {
+                return false;
+            }
+            replaced_fee += replaced_tx.fee();
+        }
+        tx.fee() >= replaced_fee + std::cmp::max(min_fee_increment, 1)
+    }
+
+    ///
+    /// Removes all transactions which conflict with `tx` from the mempool.
+    ///
+    /// Returns removed transactions.
+    ///
+    pub fn replace(&mut self, tx: &Transaction) -> HashMap<Hash, Transaction> {
+        let tx_hashes = self.conflicts(tx);
+        self.prune_txs(tx_hashes)
+    }
+
     ///
     /// Checks if the mempool contains a transaction with claims `output_hash`.
     ///
@@ -88,6 +186,20 @@ impl Mempool {
         self.pool.get(tx_hash)
     }
 
+    ///
+    /// Returns the local time when the transaction was added to the mempool.
+    ///
+    pub fn get_received(&self, tx_hash: &Hash) -> Option<Timestamp> {
+        self.received.get(tx_hash).cloned()
+    }
+
+    ///
+    /// Returns an iterator over all transactions in the mempool.
+    ///
+    pub fn iter(&self) -> impl Iterator<Item = (&Hash, &Transaction)> {
+        self.pool.iter()
+    }
+
     ///
     /// Queues a transaction to the mempool.
     ///
@@ -102,10 +214,123 @@ impl Mempool {
             let exists = self.outputs.insert(output_hash, tx_hash.clone());
             assert!(exists.is_none());
         }
+        self.received.insert(tx_hash.clone(), Timestamp::now());
         let exists = self.pool.insert(tx_hash, tx);
         assert!(exists.is_none());
     }
 
+    ///
+    /// Removes a transaction from the mempool.
+    ///
+    /// Returns the removed transaction or None if it doesn't exist.
+    ///
+    pub fn remove_tx(&mut self, tx_hash: &Hash) -> Option<Transaction> {
+        if !self.pool.contains_key(tx_hash) {
+            return None;
+        }
+        let mut tx_hashes: HashSet<Hash> = HashSet::new();
+        tx_hashes.insert(tx_hash.clone());
+        self.prune_txs(tx_hashes).remove(tx_hash)
+    }
+
+    ///
+    /// Evict transactions with the lowest fee rate to fit a new transaction
+    /// into `max_inputs` and `max_outputs` limits.
+    ///
+    /// Only payment transactions with a fee rate lower than the fee rate of the new
+    /// transaction can be evicted. Returns evicted transactions or None if
+    /// it is not possible to free enough space. Nothing is evicted in this case.
+    ///
+    pub fn evict(
+        &mut self,
+        tx: &Transaction,
+        max_inputs: usize,
+        max_outputs: usize,
+    ) -> Option<HashMap<Hash, Transaction>> {
+        let mut inputs_len = self.inputs.len() + tx.txins().len();
+        let mut outputs_len = self.outputs.len() + tx.txouts().len();
+        if inputs_len <= max_inputs && outputs_len <= max_outputs {
+            return Some(HashMap::new());
+        }
+
+        let min_fee_rate = fee_rate(tx);
+        let mut candidates: Vec<(&Hash, &Transaction, i64)> = self
+            .pool
+            .iter()
+            .filter_map(|(hash, tx)| match tx {
+                Transaction::PaymentTransaction(_) => Some((hash, tx, fee_rate(tx))),
+                _ => None,
+            })
+            .filter(|(_hash, _tx, rate)| *rate < min_fee_rate)
+            .collect();
+        candidates.sort_by_key(|(_hash, _tx, rate)| *rate);
+
+        let mut tx_hashes: HashSet<Hash> = HashSet::new();
+        for (tx_hash, tx, _rate) in candidates {
+            if inputs_len <= max_inputs && outputs_len <= max_outputs {
+                break;
+            }
+            inputs_len -= tx.txins().len();
+            outputs_len -= tx.txouts().len();
+            tx_hashes.insert(tx_hash.clone());
+        }
+        if inputs_len > max_inputs || outputs_len > max_outputs {
+            return None;
+        }
+
+        let txs = self.prune_txs(tx_hashes);
+        for tx_hash in txs.keys() {
+            debug!("Evicted a transaction from mempool: tx={}", tx_hash);
+        }
+        Some(txs)
+    }
+
+    ///
+    /// Remember the fee rate of a new micro block.
+    /// Only congested blocks, which used at least a half of the block limits, affect the fee.
+    /// Reverted blocks are kept in the history because they still reflect the demand.
+    ///
+    pub fn push_micro_block(
+        &mut self,
+        block: &MicroBlock,
+        max_inputs_in_block: usize,
+        max_outputs_in_block: usize,
+    ) {
+        let inputs_len: usize = block.transactions.iter().map(|tx| tx.txins().len()).sum();
+        let outputs_len: usize = block.transactions.iter().map(|tx| tx.txouts().len()).sum();
+        let is_congested =
+            2 * inputs_len >= max_inputs_in_block || 2 * outputs_len >= max_outputs_in_block;
+        let rate = if is_congested {
+            block
+                .transactions
+                .iter()
+                .filter_map(|tx| match tx {
+                    Transaction::PaymentTransaction(_) => Some(fee_rate(tx)),
+                    _ => None,
+                })
+                .min()
+                .unwrap_or(0)
+        } else {
+            0
+        };
+        if self.fee_history.len() == FEE_HISTORY_LEN {
+            self.fee_history.pop_front();
+        }
+        self.fee_history.push_back(rate);
+    }
+
+    ///
+    /// Returns the median of fee rates required by recent micro blocks.
+    ///
+    pub fn estimate_fee_rate(&self) -> i64 {
+        let mut rates: Vec<i64> = self.fee_history.iter().cloned().collect();
+        if rates.is_empty() {
+            return 0;
+        }
+        rates.sort();
+        rates[rates.len() / 2]
+    }
+
     ///
     /// Re-add transactions after reverting the last micro block.
     ///
@@ -190,11 +415,39 @@ impl Mempool {
         statuses
     }
 
+    /// Find transactions committed by a macro block which wasn't built on top of our micro blocks.
+    ///
+    /// A transaction is committed if the block has one of its outputs or
+    /// its output was spent within the epoch by another committed transaction.
+    pub fn find_committed<'a, HashIterator>(&self, output_hashes: HashIterator) -> HashSet<Hash>
+    where
+        HashIterator: Iterator<Item = &'a Hash>,
+    {
+        let mut committed: HashSet<Hash> = HashSet::new();
+        let mut queue: Vec<Hash> = output_hashes
+            .filter_map(|output_hash| self.outputs.get(output_hash))
+            .cloned()
+            .collect();
+        while let Some(tx_hash) = queue.pop() {
+            if !committed.insert(tx_hash.clone()) {
+                continue;
+            }
+            let tx = self.pool.get(&tx_hash).expect("transaction exists");
+            for input_hash in tx.txins() {
+                if let Some(tx_hash2) = self.outputs.get(input_hash) {
+                    queue.push(tx_hash2.clone());
+                }
+            }
+        }
+        committed
+    }
+
     fn prune_txs(&mut self, tx_hashes: HashSet<Hash>) -> HashMap<Hash, Transaction> {
         let mut txs = HashMap::new();
         // Prune transactions.
         for tx_hash in tx_hashes {
             let tx = self.pool.remove(&tx_hash).expect("transaction exists");
+            self.received.remove(&tx_hash);
             for input_hash in tx.txins() {
                 if let Some(tx_hash2) = self.inputs.remove(input_hash) {
                     assert_eq!(tx_hash2, tx_hash);
@@ -246,6 +499,7 @@ impl Mempool {
         last_random: Hash,
         solution: Vec<u8>,
         block_reward: i64,
+        delegation_payouts: &[(scc::PublicKey, i64)],
         recipient_pkey: &scc::PublicKey,
         network_skey: &pbc::SecretKey,
         network_pkey: &pbc::PublicKey,
@@ -256,20 +510,20 @@ impl Mempool {
         let seed = mix(last_random, view_change);
         let random = pbc::make_VRF(network_skey, &seed);
 
-        // Sort transactions by fee.
+        // Sort transactions by fee per input/output, then by fee.
         // RestakeTransactions have high priority.
-        let mut pool: Vec<(&Hash, &Transaction, i64)> = self
+        let mut pool: Vec<(&Hash, &Transaction, (i64, i64))> = self
             .pool
             .iter()
             .map(|(hash, tx)| {
                 let prio = match tx {
-                    Transaction::RestakeTransaction(_) => i64::max_value(),
-                    _ => tx.fee(),
+                    Transaction::RestakeTransaction(_) => (i64::max_value(), i64::max_value()),
+                    _ => (fee_rate(tx), tx.fee()),
                 };
                 (hash, tx, prio)
             })
             .collect();
-        pool.sort_by_key(|(_h, _tx, prio)| -*prio);
+        pool.sort_by_key(|(_h, _tx, (rate, fee))| (-*rate, -*fee));
 
         //
         // Mempool Transactions.
@@ -318,8 +572,11 @@ impl Mempool {
             let mut txouts: Vec<Output> = Vec::new();
             let mut gamma = Fr::zero();
 
+            // Shares of delegators are paid from the reward of the leader.
+            let delegated: i64 = delegation_payouts.iter().map(|(_, amount)| amount).sum();
+
             // Create outputs for fee and rewards.
-            for (amount, comment) in vec![(block_reward + block_fee, "reward+fee")] {
+            for (amount, comment) in vec![(block_reward + block_fee - delegated, "reward+fee")] {
                 if amount <= 0 {
                     continue;
                 }
@@ -340,6 +597,17 @@ impl Mempool {
                 txouts.push(Output::PaymentOutput(output_fee));
             }
 
+            for (delegator_pkey, amount) in delegation_payouts {
+                let output = PublicPaymentOutput::new(delegator_pkey, *amount);
+                info!(
+                    "Created delegation reward UTXO: hash={}, recipient={}, amount={}",
+                    Hash::digest(&output),
+                    delegator_pkey,
+                    amount
+                );
+                txouts.push(Output::PublicPaymentOutput(output));
+            }
+
             let coinbase = CoinbaseTransaction {
                 block_reward,
                 block_fee,
@@ -444,6 +712,23 @@ mod test {
         assert!(mempool.contains_tx(&tx_hash1));
         assert!(mempool.contains_tx(&tx_hash2));
         assert_eq!(mempool.len(), 2);
+
+        //
+        // Removing.
+        //
+        assert!(mempool.get_received(&tx_hash1).is_some());
+        let removed = mempool.remove_tx(&tx_hash1).expect("exists");
+        assert_eq!(Hash::digest(&removed), tx_hash1);
+        assert!(!mempool.contains_tx(&tx_hash1));
+        assert!(mempool.get_received(&tx_hash1).is_none());
+        for input in &inputs1 {
+            assert!(!mempool.contains_input(&Hash::digest(input)));
+        }
+        for output in &outputs1 {
+            assert!(!mempool.contains_output(&Hash::digest(output)));
+        }
+        assert!(mempool.remove_tx(&tx_hash1).is_none());
+        assert_eq!(mempool.len(), 1);
     }
 
     #[test]
@@ -492,6 +777,39 @@ mod test {
         }
     }
 
+    #[test]
+    pub fn find_committed() {
+        let (skey, pkey) = scc::make_random_keys();
+        let mut mempool = Mempool::new();
+
+        let (tx1, _inputs1, outputs1) =
+            PaymentTransaction::new_test(&skey, &pkey, 100, 1, 50, 2, 0)
+                .expect("transaction valid");
+        let tx_hash1 = Hash::digest(&tx1);
+        mempool.push_tx(tx_hash1.clone(), tx1.into());
+        // Spends an output of tx1 within the same epoch.
+        let (mut tx2, _inputs2, outputs2) =
+            PaymentTransaction::new_test(&skey, &pkey, 50, 1, 50, 1, 0).expect("transaction valid");
+        tx2.txins[0] = Hash::digest(&outputs1[0]);
+        let tx_hash2 = Hash::digest(&tx2);
+        mempool.push_tx(tx_hash2.clone(), tx2.into());
+        // Not included into the block.
+        let (tx3, _inputs3, _outputs3) =
+            PaymentTransaction::new_test(&skey, &pkey, 100, 1, 100, 1, 0)
+                .expect("transaction valid");
+        let tx_hash3 = Hash::digest(&tx3);
+        mempool.push_tx(tx_hash3.clone(), tx3.into());
+
+        let output_hashes = vec![Hash::digest(&outputs2[0])];
+        let committed = mempool.find_committed(output_hashes.iter());
+        assert_eq!(committed.len(), 2);
+        assert!(committed.contains(&tx_hash1));
+        assert!(committed.contains(&tx_hash2));
+        assert!(!committed.contains(&tx_hash3));
+        let no_outputs: Vec<Hash> = Vec::new();
+        assert!(mempool.find_committed(no_outputs.iter()).is_empty());
+    }
+
     #[test]
     pub fn rollback_tx() {
         let (skey, pkey) = scc::make_random_keys();
@@ -571,6 +889,7 @@ mod test {
             Hash::digest("test"),
             solution,
             reward,
+            &[],
             &recipient_pkey,
             &network_skey,
             &network_pkey,
@@ -595,11 +914,234 @@ mod test {
         }
         //
         // Used transactions. Order is important:
-        //   - tx_hash3 has fee = 4
-        //   - tx_hash1 has fee = 2
+        //   - tx_hash3 has fee = 4 and fee rate = 1
+        //   - tx_hash1 has fee = 2 and fee rate = 0
         //   - tx_hash2 is not included because of max_utxo_in_block.
         //
         assert_eq!(Hash::digest(&block.transactions[1]), tx_hash3);
         assert_eq!(Hash::digest(&block.transactions[2]), tx_hash1);
     }
+
+    #[test]
+    fn fee_rate_ordering() {
+        let (recipient_skey, recipient_pkey) = scc::make_random_keys();
+        let (network_skey, network_pkey) = pbc::make_random_keys();
+        let mut mempool = Mempool::new();
+
+        // fee = 30, fee rate = 3000.
+        let (tx1, _inputs1, _outputs1) =
+            PaymentTransaction::new_test(&recipient_skey, &recipient_pkey, 8, 5, 2, 5, 30)
+                .expect("transaction valid");
+        // fee = 20, fee rate = 10000.
+        let (tx2, _inputs2, _outputs2) =
+            PaymentTransaction::new_test(&recipient_skey, &recipient_pkey, 25, 1, 5, 1, 20)
+                .expect("transaction valid");
+        let tx_hash1 = Hash::digest(&tx1);
+        let tx_hash2 = Hash::digest(&tx2);
+        mempool.push_tx(tx_hash1.clone(), tx1.clone().into());
+        mempool.push_tx(tx_hash2.clone(), tx2.clone().into());
+
+        let block = mempool.create_block(
+            Hash::digest(&1u64),
+            1,
+            5,
+            0,
+            None,
+            Hash::digest("test"),
+            vec![1, 2, 3, 4],
+            10,
+            &[],
+            &recipient_pkey,
+            &network_skey,
+            &network_pkey,
+            100500,
+            100500,
+            Timestamp::now(),
+        );
+        assert_eq!(block.transactions.len(), 3);
+        assert_eq!(Hash::digest(&block.transactions[1]), tx_hash2);
+        assert_eq!(Hash::digest(&block.transactions[2]), tx_hash1);
+    }
+
+    #[test]
+    fn eviction() {
+        let (skey, pkey) = scc::make_random_keys();
+        let mut mempool = Mempool::new();
+
+        // fee rate = 1000.
+        let (tx1, _inputs1, _outputs1) =
+            PaymentTransaction::new_test(&skey, &pkey, 12, 1, 10, 1, 2).expect("transaction valid");
+        // fee rate = 5000.
+        let (tx2, _inputs2, _outputs2) =
+            PaymentTransaction::new_test(&skey, &pkey, 20, 1, 10, 1, 10)
+                .expect("transaction valid");
+        let tx_hash1 = Hash::digest(&tx1);
+        let tx_hash2 = Hash::digest(&tx2);
+        mempool.push_tx(tx_hash1.clone(), tx1.clone().into());
+        mempool.push_tx(tx_hash2.clone(), tx2.clone().into());
+
+        // Enough space - nothing to evict.
+        let (tx3, _inputs3, _outputs3) =
+            PaymentTransaction::new_test(&skey, &pkey, 14, 1, 10, 1, 4).expect("transaction valid");
+        let evicted = mempool.evict(&tx3.clone().into(), 3, 3).expect("fits");
+        assert!(evicted.is_empty());
+        assert_eq!(mempool.len(), 2);
+
+        // fee rate = 2000, only tx1 can be evicted.
+        let evicted = mempool.evict(&tx3.clone().into(), 2, 2).expect("fits");
+        assert_eq!(evicted.len(), 1);
+        assert!(evicted.contains_key(&tx_hash1));
+        assert!(!mempool.contains_tx(&tx_hash1));
+        assert!(mempool.contains_tx(&tx_hash2));
+
+        // fee rate = 2000, tx2 has a higher fee rate.
+        let evicted = mempool.evict(&tx3.clone().into(), 1, 1);
+        assert!(evicted.is_none());
+        assert!(mempool.contains_tx(&tx_hash2));
+        assert_eq!(mempool.len(), 1);
+    }
+
+    #[test]
+    fn replace_by_fee() {
+        let (skey, pkey) = scc::make_random_keys();
+        let mut mempool = Mempool::new();
+
+        let (tx1, inputs1, outputs1) = PaymentTransaction::new_test(&skey, &pkey, 20, 1, 10, 1, 10)
+            .expect("transaction valid");
+        let tx_hash1 = Hash::digest(&tx1);
+        let tx1: Transaction = tx1.into();
+        assert!(mempool.conflicts(&tx1).is_empty());
+        assert!(!mempool.can_replace(&tx1, 5));
+        mempool.push_tx(tx_hash1.clone(), tx1.clone());
+
+        // The same inputs and the same fee.
+        let (output2, gamma2) = Output::new_payment(&pkey, 10).expect("keys are valid");
+        let tx2: Transaction = PaymentTransaction::new(&skey, &inputs1, &[output2], &gamma2, 10)
+            .expect("transaction valid")
+            .into();
+        let conflicts = mempool.conflicts(&tx2);
+        assert_eq!(conflicts.len(), 1);
+        assert!(conflicts.contains(&tx_hash1));
+        assert!(!mempool.can_replace(&tx2, 5));
+
+        // The same inputs and a higher fee, but less than the increment.
+        let (output2, gamma2) = Output::new_payment(&pkey, 8).expect("keys are valid");
+        let tx2: Transaction = PaymentTransaction::new(&skey, &inputs1, &[output2], &gamma2, 12)
+            .expect("transaction valid")
+            .into();
+        assert!(!mempool.can_replace(&tx2, 5));
+        assert!(mempool.can_replace(&tx2, 2));
+
+        // The same inputs and a higher fee, but a lower fee rate.
+        let mut outputs2: Vec<Output> = Vec::new();
+        let mut gamma2 = Fr::zero();
+        for _ in 0..4 {
+            let (output, gamma) = Output::new_payment(&pkey, 1).expect("keys are valid");
+            outputs2.push(output);
+            gamma2 += gamma;
+        }
+        let tx2: Transaction = PaymentTransaction::new(&skey, &inputs1, &outputs2, &gamma2, 16)
+            .expect("transaction valid")
+            .into();
+        assert!(fee_rate(&tx2) < fee_rate(&tx1));
+        assert!(!mempool.can_replace(&tx2, 5));
+
+        // The same inputs and a higher fee.
+        let (output3, gamma3) = Output::new_payment(&pkey, 5).expect("keys are valid");
+        let tx3: Transaction = PaymentTransaction::new(&skey, &inputs1, &[output3], &gamma3, 15)
+            .expect("transaction valid")
+            .into();
+        let tx_hash3 = Hash::digest(&tx3);
+        assert!(mempool.can_replace(&tx3, 5));
+        let replaced = mempool.replace(&tx3);
+        assert_eq!(replaced.len(), 1);
+        assert!(replaced.contains_key(&tx_hash1));
+        assert!(!mempool.contains_tx(&tx_hash1));
+        for output in &outputs1 {
+            assert!(!mempool.contains_output(&Hash::digest(output)));
+        }
+        assert_eq!(mempool.len(), 0);
+        mempool.push_tx(tx_hash3.clone(), tx3);
+        assert!(mempool.contains_tx(&tx_hash3));
+        for input in &inputs1 {
+            assert_eq!(
+                mempool.get_tx_by_input(&Hash::digest(input)),
+                Some(&tx_hash3)
+            );
+        }
+    }
+
+    #[test]
+    fn small_fee_rate() {
+        let (skey, pkey) = scc::make_random_keys();
+        // One input and two outputs.
+        let (tx, _inputs, _outputs) =
+            PaymentTransaction::new_test(&skey, &pkey, 11, 1, 5, 2, 1).expect("transaction valid");
+        assert_eq!(fee_rate(&tx.into()), 333);
+    }
+
+    #[test]
+    fn fee_rate_buckets() {
+        assert_eq!(fee_rate_bucket(0), 0);
+        assert_eq!(fee_rate_bucket(-1), 0);
+        assert_eq!(fee_rate_bucket(1), 1);
+        assert_eq!(fee_rate_bucket(2), 2);
+        assert_eq!(fee_rate_bucket(3), 2);
+        assert_eq!(fee_rate_bucket(1000), 512);
+        assert_eq!(fee_rate_bucket(1024), 1024);
+        assert_eq!(fee_rate_bucket(i64::max_value()), 1 << 62);
+    }
+
+    #[test]
+    fn fee_estimation() {
+        let (skey, pkey) = scc::make_random_keys();
+        let (network_skey, network_pkey) = pbc::make_random_keys();
+        let mut mempool = Mempool::new();
+        assert_eq!(mempool.estimate_fee_rate(), 0);
+
+        // fee rate = 5000.
+        let (tx1, _inputs1, _outputs1) =
+            PaymentTransaction::new_test(&skey, &pkey, 20, 1, 10, 1, 10)
+                .expect("transaction valid");
+        // fee rate = 10000.
+        let (tx2, _inputs2, _outputs2) =
+            PaymentTransaction::new_test(&skey, &pkey, 30, 1, 10, 1, 20)
+                .expect("transaction valid");
+        mempool.push_tx(Hash::digest(&tx1), tx1.into());
+        mempool.push_tx(Hash::digest(&tx2), tx2.into());
+        // Two inputs and three outputs, including coinbase.
+        let block = mempool.create_block(
+            Hash::digest(&1u64),
+            1,
+            5,
+            0,
+            None,
+            Hash::digest("test"),
+            vec![1, 2, 3, 4],
+            10,
+            &[],
+            &pkey,
+            &network_skey,
+            &network_pkey,
+            100500,
+            100500,
+            Timestamp::now(),
+        );
+        assert_eq!(block.transactions.len(), 3);
+
+        // Non-congested block.
+        mempool.push_micro_block(&block, 100, 100);
+        assert_eq!(mempool.estimate_fee_rate(), 0);
+
+        // Congested blocks.
+        mempool.push_micro_block(&block, 4, 4);
+        mempool.push_micro_block(&block, 4, 4);
+        assert_eq!(mempool.estimate_fee_rate(), 5000);
+
+        // History is limited.
+        for _ in 0..FEE_HISTORY_LEN {
+            mempool.push_micro_block(&block, 100, 100);
+        }
+        assert_eq!(mempool.estimate_fee_rate(), 0);
+    }
 }
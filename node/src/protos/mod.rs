@@ -29,6 +29,9 @@ use stegos_serialization::traits::*;
 use stegos_blockchain::protos::*;
 include!(concat!(env!("OUT_DIR"), "/protos/mod.rs"));
 
+use crate::light::{
+    LightMessage, RequestLightBlocks, RequestOutputs, ResponseLightBlocks, ResponseOutputs,
+};
 use crate::loader::{ChainLoaderMessage, RequestBlocks, ResponseBlocks};
 use failure::{format_err, Error};
 use protobuf::RepeatedField;
@@ -99,6 +102,118 @@ impl ProtoConvert for ChainLoaderMessage {
     }
 }
 
+impl ProtoConvert for RequestLightBlocks {
+    type Proto = light::RequestLightBlocks;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = light::RequestLightBlocks::new();
+        proto.set_epoch(self.epoch);
+        proto
+    }
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let epoch = proto.get_epoch();
+        Ok(RequestLightBlocks { epoch })
+    }
+}
+
+impl ProtoConvert for ResponseLightBlocks {
+    type Proto = light::ResponseLightBlocks;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = light::ResponseLightBlocks::new();
+        let blocks: Vec<_> = self.blocks.iter().map(ProtoConvert::into_proto).collect();
+        proto.set_blocks(RepeatedField::from_vec(blocks));
+        proto
+    }
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let blocks: Result<Vec<_>, _> = proto
+            .get_blocks()
+            .iter()
+            .map(ProtoConvert::from_proto)
+            .collect();
+        let blocks = blocks?;
+        Ok(ResponseLightBlocks { blocks })
+    }
+}
+
+impl ProtoConvert for RequestOutputs {
+    type Proto = light::RequestOutputs;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = light::RequestOutputs::new();
+        proto.set_block_hash(self.block_hash.into_proto());
+        proto.set_output_ids(self.output_ids.clone());
+        proto
+    }
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let block_hash = Hash::from_proto(proto.get_block_hash())?;
+        let output_ids = proto.get_output_ids().to_vec();
+        Ok(RequestOutputs {
+            block_hash,
+            output_ids,
+        })
+    }
+}
+
+impl ProtoConvert for ResponseOutputs {
+    type Proto = light::ResponseOutputs;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = light::ResponseOutputs::new();
+        proto.set_block_hash(self.block_hash.into_proto());
+        proto.set_output_ids(self.output_ids.clone());
+        let proofs: Vec<_> = self.proofs.iter().map(ProtoConvert::into_proto).collect();
+        proto.set_proofs(RepeatedField::from_vec(proofs));
+        proto
+    }
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let block_hash = Hash::from_proto(proto.get_block_hash())?;
+        let output_ids = proto.get_output_ids().to_vec();
+        let proofs: Result<Vec<_>, _> = proto
+            .get_proofs()
+            .iter()
+            .map(ProtoConvert::from_proto)
+            .collect();
+        let proofs = proofs?;
+        Ok(ResponseOutputs {
+            block_hash,
+            output_ids,
+            proofs,
+        })
+    }
+}
+
+impl ProtoConvert for LightMessage {
+    type Proto = light::LightMessage;
+    fn into_proto(&self) -> Self::Proto {
+        let mut proto = light::LightMessage::new();
+        match self {
+            LightMessage::RequestBlocks(r) => proto.set_request_blocks(r.into_proto()),
+            LightMessage::ResponseBlocks(r) => proto.set_response_blocks(r.into_proto()),
+            LightMessage::RequestOutputs(r) => proto.set_request_outputs(r.into_proto()),
+            LightMessage::ResponseOutputs(r) => proto.set_response_outputs(r.into_proto()),
+        }
+        proto
+    }
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let ref body = proto
+            .body
+            .as_ref()
+            .ok_or_else(|| format_err!("No variants in LightMessage found"))?;
+        let light_message = match body {
+            light::LightMessage_oneof_body::request_blocks(ref r) => {
+                LightMessage::RequestBlocks(RequestLightBlocks::from_proto(r)?)
+            }
+            light::LightMessage_oneof_body::response_blocks(ref r) => {
+                LightMessage::ResponseBlocks(ResponseLightBlocks::from_proto(r)?)
+            }
+            light::LightMessage_oneof_body::request_outputs(ref r) => {
+                LightMessage::RequestOutputs(RequestOutputs::from_proto(r)?)
+            }
+            light::LightMessage_oneof_body::response_outputs(ref r) => {
+                LightMessage::ResponseOutputs(ResponseOutputs::from_proto(r)?)
+            }
+        };
+        Ok(light_message)
+    }
+}
+
 type TXIN = Hash;
 type UTXO = PaymentOutput;
 
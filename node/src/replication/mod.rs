@@ -34,14 +34,25 @@ use rand::seq::SliceRandom;
 use rand::thread_rng;
 use std::collections::HashMap;
 use std::time::Duration;
-use stegos_blockchain::{Block, Blockchain};
+use stegos_blockchain::{Block, Blockchain, StateSnapshot};
 use stegos_network::{Network, PeerId, ReplicationEvent};
 use tokio_timer::{clock, Delay};
 
+/// Data received from the upstream.
+pub(super) enum ReplicationRow {
+    /// A snapshot of the blockchain state.
+    Snapshot(StateSnapshot),
+    /// A batch of blocks.
+    Blocks(Vec<Block>),
+}
+
 pub(super) struct Replication {
     /// My Peer ID.
     peer_id: PeerId,
 
+    /// Request a state snapshot instead of full blocks on the empty blockchain.
+    fast_sync: bool,
+
     /// My current epoch.
     epoch: u64,
 
@@ -70,6 +81,7 @@ impl Replication {
     pub(super) fn new(
         epoch: u64,
         offset: u32,
+        fast_sync: bool,
         peer_id: PeerId,
         network: Network,
         events: mpsc::UnboundedReceiver<ReplicationEvent>,
@@ -79,6 +91,7 @@ impl Replication {
         Self {
             epoch,
             offset,
+            fast_sync,
             peer_id,
             peers,
             periodic_delay,
@@ -136,7 +149,7 @@ impl Replication {
     ///
     /// Polls events.
     ///
-    pub(super) fn poll(&mut self, chain: &Blockchain) -> Async<Option<Vec<Block>>> {
+    pub(super) fn poll(&mut self, chain: &Blockchain) -> Async<Option<ReplicationRow>> {
         trace!("Poll");
 
         // Process replication events.
@@ -158,7 +171,12 @@ impl Replication {
                     ReplicationEvent::Connected { peer_id, rx, tx } => {
                         assert_ne!(peer_id, self.peer_id);
                         let peer = self.peers.get_mut(&peer_id).expect("peer is known");
-                        peer.connected(chain.epoch(), chain.offset(), rx, tx);
+                        // Fast sync is possible only for the empty blockchain.
+                        let fast_sync = self.fast_sync
+                            && chain.epoch() == 1
+                            && chain.offset() == 0
+                            && chain.snapshot_epoch().is_none();
+                        peer.connected(chain.epoch(), chain.offset(), fast_sync, rx, tx);
                     }
                     ReplicationEvent::Accepted { peer_id, rx, tx } => {
                         assert_ne!(peer_id, self.peer_id);
@@ -183,8 +201,8 @@ impl Replication {
         let mut has_upstream = false;
         for (_peer_id, peer) in self.peers.iter_mut() {
             match peer.poll(chain) {
-                Async::Ready(blocks) => {
-                    return Async::Ready(Some(blocks));
+                Async::Ready(row) => {
+                    return Async::Ready(Some(row));
                 }
                 Async::NotReady => {}
             }
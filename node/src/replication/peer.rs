@@ -22,6 +22,7 @@
 // SOFTWARE.
 
 use super::protos::{ReplicationRequest, ReplicationResponse};
+use super::ReplicationRow;
 use crate::replication::api::PeerInfo;
 use futures::sync::mpsc;
 use futures::{task, Async, AsyncSink, Sink, Stream};
@@ -145,6 +146,7 @@ impl Peer {
 
     ///
     /// Moves to Connected state.
+    /// Requests a state snapshot if `fast_sync` is true.
     ///
     /// # Panics
     ///
@@ -154,6 +156,7 @@ impl Peer {
         &mut self,
         epoch: u64,
         offset: u32,
+        fast_sync: bool,
         rx: mpsc::Receiver<Vec<u8>>,
         mut tx: mpsc::Sender<Vec<u8>>,
     ) {
@@ -166,7 +169,11 @@ impl Peer {
                 return self.disconnected();
             }
         };
-        let request = ReplicationRequest::Subscribe { epoch, offset };
+        let request = if fast_sync {
+            ReplicationRequest::FastSync { epoch, offset }
+        } else {
+            ReplicationRequest::Subscribe { epoch, offset }
+        };
         trace!("[{}] <- {:?}", peer_id, request);
         let request = request.into_buffer().unwrap();
         let new_state = match tx.try_send(request) {
@@ -486,7 +493,7 @@ impl Peer {
     ///
     /// The state machine.
     ///
-    pub(super) fn poll(&mut self, chain: &Blockchain) -> Async<Vec<Block>> {
+    pub(super) fn poll(&mut self, chain: &Blockchain) -> Async<ReplicationRow> {
         match self {
             //--------------------------------------------------------------------------------------
             // Discovered
@@ -573,14 +580,14 @@ impl Peer {
                     } => (peer_id, multiaddr, rx, tx),
                     _ => unreachable!("Expected Connected state"),
                 };
-                let new_state = match response {
+                let (new_state, snapshot) = match response {
                     ReplicationResponse::Subscribed {
                         current_epoch,
                         current_offset,
                     } => {
                         debug!("[{}] Receiving", peer_id);
                         let now = clock::now();
-                        Peer::Receiving {
+                        let new_state = Peer::Receiving {
                             peer_id,
                             multiaddr,
                             last_clock: now.clone(),
@@ -591,7 +598,34 @@ impl Peer {
                             offset: current_offset,
                             bytes_received: 0,
                             blocks_received: 0,
-                        }
+                        };
+                        (new_state, None)
+                    }
+                    ReplicationResponse::Snapshot {
+                        current_epoch,
+                        current_offset,
+                        snapshot,
+                    } => {
+                        debug!(
+                            "[{}] -> Snapshot {{ epoch = {} }}",
+                            peer_id,
+                            snapshot.epoch()
+                        );
+                        debug!("[{}] Receiving", peer_id);
+                        let now = clock::now();
+                        let new_state = Peer::Receiving {
+                            peer_id,
+                            multiaddr,
+                            last_clock: now.clone(),
+                            start_clock: now,
+                            tx,
+                            rx,
+                            epoch: current_epoch,
+                            offset: current_offset,
+                            bytes_received: 0,
+                            blocks_received: 0,
+                        };
+                        (new_state, Some(snapshot))
                     }
                     response => {
                         let error = format!(
@@ -600,16 +634,20 @@ impl Peer {
                         );
                         error!("[{}] {}", peer_id, error);
                         let error = std::io::Error::new(std::io::ErrorKind::InvalidData, error);
-                        Peer::Failed {
+                        let new_state = Peer::Failed {
                             peer_id,
                             multiaddr,
                             last_clock: clock::now(),
                             error,
-                        }
+                        };
+                        (new_state, None)
                     }
                 };
                 std::mem::replace(self, new_state);
-                Async::NotReady
+                match snapshot {
+                    Some(snapshot) => Async::Ready(ReplicationRow::Snapshot(snapshot)),
+                    None => Async::NotReady,
+                }
             }
 
             //--------------------------------------------------------------------------------------
@@ -681,45 +719,73 @@ impl Peer {
                     } => (peer_id, multiaddr, rx, tx),
                     _ => unreachable!("Expected Accepted state"),
                 };
-                match request {
-                    ReplicationRequest::Subscribe { epoch, offset } => {
-                        if epoch > chain.epoch() {
-                            trace!("[{}] Subscribe from the future: epoch={}, offset={}, local_epoch={}, local_offset={}",
-                                   peer_id, epoch, offset, chain.epoch(), chain.offset());
-                            let new_state = Self::registered(peer_id, multiaddr);
-                            std::mem::replace(self, new_state);
-                            return Async::NotReady;
-                        }
-                        let response = ReplicationResponse::Subscribed {
-                            current_epoch: chain.epoch(),
-                            current_offset: chain.offset(),
-                        };
-                        trace!("[{}] <- {:?}", peer_id, response);
-                        let response = response.into_buffer().unwrap();
-                        match tx.try_send(response) {
-                            Ok(()) => {
-                                debug!("[{}] Sending", peer_id);
-                                let new_state = Peer::Sending {
-                                    peer_id,
-                                    multiaddr,
-                                    last_clock: clock::now(),
-                                    start_clock: clock::now(),
-                                    tx,
-                                    rx,
-                                    epoch,
-                                    offset,
-                                    bytes_sent: 0,
-                                    blocks_sent: 0,
-                                };
-                                std::mem::replace(self, new_state);
+                let (epoch, offset, snapshot) = match request {
+                    ReplicationRequest::Subscribe { epoch, offset } => (epoch, offset, None),
+                    ReplicationRequest::FastSync { epoch, offset } => {
+                        match chain.create_snapshot() {
+                            // Continue with blocks after the snapshot.
+                            Ok(snapshot) if snapshot.epoch() >= epoch => {
+                                (snapshot.epoch() + 1, 0, Some(snapshot))
                             }
-                            Err(mpsc::TrySendError { .. }) => {
-                                let new_state = Self::registered(peer_id, multiaddr);
-                                std::mem::replace(self, new_state);
-                                return Async::NotReady;
+                            Ok(_snapshot) => (epoch, offset, None),
+                            Err(e) => {
+                                debug!("[{}] Snapshot is not available: {}", peer_id, e);
+                                (epoch, offset, None)
                             }
                         }
                     }
+                };
+                if epoch > chain.epoch() {
+                    trace!("[{}] Subscribe from the future: epoch={}, offset={}, local_epoch={}, local_offset={}",
+                           peer_id, epoch, offset, chain.epoch(), chain.offset());
+                    let new_state = Self::registered(peer_id, multiaddr);
+                    std::mem::replace(self, new_state);
+                    return Async::NotReady;
+                }
+                if let Some(snapshot_epoch) = chain.snapshot_epoch() {
+                    if epoch <= snapshot_epoch {
+                        trace!("[{}] Subscribe before the snapshot: epoch={}, offset={}, snapshot_epoch={}",
+                               peer_id, epoch, offset, snapshot_epoch);
+                        let new_state = Self::registered(peer_id, multiaddr);
+                        std::mem::replace(self, new_state);
+                        return Async::NotReady;
+                    }
+                }
+                let response = match snapshot {
+                    Some(snapshot) => ReplicationResponse::Snapshot {
+                        current_epoch: chain.epoch(),
+                        current_offset: chain.offset(),
+                        snapshot,
+                    },
+                    None => ReplicationResponse::Subscribed {
+                        current_epoch: chain.epoch(),
+                        current_offset: chain.offset(),
+                    },
+                };
+                trace!("[{}] <- {:?}", peer_id, response);
+                let response = response.into_buffer().unwrap();
+                match tx.try_send(response) {
+                    Ok(()) => {
+                        debug!("[{}] Sending", peer_id);
+                        let new_state = Peer::Sending {
+                            peer_id,
+                            multiaddr,
+                            last_clock: clock::now(),
+                            start_clock: clock::now(),
+                            tx,
+                            rx,
+                            epoch,
+                            offset,
+                            bytes_sent: 0,
+                            blocks_sent: 0,
+                        };
+                        std::mem::replace(self, new_state);
+                    }
+                    Err(mpsc::TrySendError { .. }) => {
+                        let new_state = Self::registered(peer_id, multiaddr);
+                        std::mem::replace(self, new_state);
+                        return Async::NotReady;
+                    }
                 }
                 Async::NotReady
             }
@@ -865,7 +931,7 @@ impl Peer {
                 if blocks.is_empty() {
                     Async::NotReady
                 } else {
-                    Async::Ready(blocks)
+                    Async::Ready(ReplicationRow::Blocks(blocks))
                 }
             }
 
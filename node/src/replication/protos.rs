@@ -25,12 +25,13 @@ use crate::protos::replication;
 use failure::Error;
 use serde_derive::{Deserialize, Serialize};
 use stegos_blockchain::protos::ProtoError;
-use stegos_blockchain::Block;
+use stegos_blockchain::{Block, StateSnapshot};
 use stegos_serialization::traits::*;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(super) enum ReplicationRequest {
     Subscribe { epoch: u64, offset: u32 },
+    FastSync { epoch: u64, offset: u32 },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,6 +45,11 @@ pub(super) enum ReplicationResponse {
         current_offset: u32,
         block: Block,
     },
+    Snapshot {
+        current_epoch: u64,
+        current_offset: u32,
+        snapshot: StateSnapshot,
+    },
 }
 
 impl ProtoConvert for ReplicationRequest {
@@ -57,6 +63,12 @@ impl ProtoConvert for ReplicationRequest {
                 request.set_offset(*offset);
                 proto.set_subscribe(request);
             }
+            ReplicationRequest::FastSync { epoch, offset } => {
+                let mut request = replication::FastSync::new();
+                request.set_epoch(*epoch);
+                request.set_offset(*offset);
+                proto.set_fast_sync(request);
+            }
         }
         proto
     }
@@ -68,6 +80,12 @@ impl ProtoConvert for ReplicationRequest {
                 let request = ReplicationRequest::Subscribe { epoch, offset };
                 Ok(request)
             }
+            Some(replication::ReplicationRequest_oneof_request::fast_sync(ref fast_sync)) => {
+                let epoch = fast_sync.get_epoch();
+                let offset = fast_sync.get_offset();
+                let request = ReplicationRequest::FastSync { epoch, offset };
+                Ok(request)
+            }
             None => {
                 return Err(
                     ProtoError::MissingField("request".to_string(), "request".to_string()).into(),
@@ -102,6 +120,17 @@ impl ProtoConvert for ReplicationResponse {
                 response.set_block(block.into_proto());
                 proto.set_block(response);
             }
+            ReplicationResponse::Snapshot {
+                current_epoch,
+                current_offset,
+                snapshot,
+            } => {
+                let mut response = replication::Snapshot::new();
+                response.set_current_epoch(*current_epoch);
+                response.set_current_offset(*current_offset);
+                response.set_snapshot(snapshot.into_proto());
+                proto.set_snapshot(response);
+            }
         }
         proto
     }
@@ -127,6 +156,17 @@ impl ProtoConvert for ReplicationResponse {
                 };
                 Ok(response)
             }
+            Some(replication::ReplicationResponse_oneof_response::snapshot(ref snapshot)) => {
+                let current_epoch = snapshot.get_current_epoch();
+                let current_offset = snapshot.get_current_offset();
+                let snapshot = StateSnapshot::from_proto(snapshot.get_snapshot())?;
+                let response = ReplicationResponse::Snapshot {
+                    current_epoch,
+                    current_offset,
+                    snapshot,
+                };
+                Ok(response)
+            }
             None => {
                 return Err(
                     ProtoError::MissingField("response".to_string(), "block".to_string()).into(),
@@ -149,6 +189,11 @@ mod tests {
                     epoch.hash(state);
                     offset.hash(state);
                 }
+                ReplicationRequest::FastSync { epoch, offset } => {
+                    "ReplicationRequest::FastSync".hash(state);
+                    epoch.hash(state);
+                    offset.hash(state);
+                }
             }
         }
     }
@@ -174,6 +219,16 @@ mod tests {
                     current_offset.hash(state);
                     block.hash(state);
                 }
+                ReplicationResponse::Snapshot {
+                    current_epoch,
+                    current_offset,
+                    snapshot,
+                } => {
+                    "ReplicationResponse::Snapshot".hash(state);
+                    current_epoch.hash(state);
+                    current_offset.hash(state);
+                    snapshot.block_hash().hash(state);
+                }
             }
         }
     }
@@ -194,6 +249,12 @@ mod tests {
             offset: 12345,
         };
         roundtrip(&request);
+
+        let request = ReplicationRequest::FastSync {
+            epoch: 1,
+            offset: 0,
+        };
+        roundtrip(&request);
     }
 
     #[test]
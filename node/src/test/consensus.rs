@@ -1507,6 +1507,12 @@ fn invalid_prevotes() {
             assert_consensus_state(&info, &mut node.node_service);
         }
 
+        // The pre-vote for another block is a proof of equivocation.
+        let cheater = r.parts.1.first().node_service.network_pkey;
+        for node in r.parts.1.iter_mut().skip(1) {
+            assert!(node.node_service.cheating_proofs.contains_key(&cheater));
+        }
+
         s.poll();
         s.filter_broadcast(&[crate::CONSENSUS_TOPIC]);
     });
@@ -30,6 +30,7 @@ use std::collections::HashSet;
 use stegos_blockchain::Block;
 use stegos_blockchain::ChainInfo;
 use stegos_blockchain::Output;
+use stegos_blockchain::PaymentTransaction;
 use stegos_blockchain::ValidatorAwardState;
 use stegos_consensus::optimistic::ViewChangeMessage;
 
@@ -727,6 +728,362 @@ fn service_award_round_without_participants(s: &mut Sandbox) {
     s.assert_synchronized();
 }
 
+#[test]
+fn broadcast_signed_transaction() {
+    use stegos_blockchain::PaymentTransaction;
+    use stegos_serialization::traits::ProtoConvert;
+
+    Sandbox::start(Default::default(), |mut s| {
+        s.poll();
+
+        // Spend the genesis payment.
+        let keychain = s.keychains[0].clone();
+        let chain = s.first().chain();
+        let (input, amount) = chain
+            .unspent()
+            .filter_map(|output_hash| chain.output_by_hash(output_hash).unwrap())
+            .find_map(|output| {
+                let amount = match &output {
+                    Output::PaymentOutput(o) => {
+                        o.decrypt_payload(&keychain.account_pkey, &keychain.account_skey)
+                            .ok()?
+                            .amount
+                    }
+                    _ => return None,
+                };
+                Some((output, amount))
+            })
+            .expect("genesis payment");
+        let fee = s.first().node_service.cfg.min_payment_fee;
+        let (output, outputs_gamma) =
+            Output::new_payment(&keychain.account_pkey, amount - fee).unwrap();
+        let tx: Transaction = PaymentTransaction::new(
+            &keychain.account_skey,
+            &[input],
+            &[output],
+            &outputs_gamma,
+            fee,
+        )
+        .unwrap()
+        .into();
+        let tx_hash = Hash::digest(&tx);
+
+        let node = s.first_mut();
+
+        // Invalid encoding.
+        let mut rx = node.node.request(NodeRequest::BroadcastRawTransaction {
+            data: vec![1, 2, 3],
+        });
+        node.poll();
+        match rx.poll().unwrap() {
+            Async::Ready(NodeResponse::Error { .. }) => {}
+            e => panic!("Unexpected response: {:?}", e),
+        }
+        assert!(!node.node_service.mempool.contains_tx(&tx_hash));
+
+        // Invalid transaction is not relayed.
+        let mut invalid = tx.clone();
+        match &mut invalid {
+            Transaction::PaymentTransaction(tx) => tx.fee += 1,
+            _ => unreachable!(),
+        }
+        let mut rx = node
+            .node
+            .request(NodeRequest::BroadcastTransaction { tx: invalid });
+        node.poll();
+        match rx.poll().unwrap() {
+            Async::Ready(NodeResponse::TransactionBroadcasted {
+                status: TransactionStatus::Rejected { .. },
+                ..
+            }) => {}
+            e => panic!("Unexpected response: {:?}", e),
+        }
+        assert!(node
+            .network_service
+            .try_get_broadcast_raw(crate::TX_TOPIC)
+            .is_none());
+
+        // Protobuf.
+        let mut rx = node.node.request(NodeRequest::BroadcastRawTransaction {
+            data: tx.into_buffer().unwrap(),
+        });
+        node.poll();
+        match rx.poll().unwrap() {
+            Async::Ready(NodeResponse::TransactionBroadcasted {
+                tx_hash: tx_hash2,
+                status: TransactionStatus::Accepted {},
+            }) => assert_eq!(tx_hash2, tx_hash),
+            e => panic!("Unexpected response: {:?}", e),
+        }
+        assert!(node.node_service.mempool.contains_tx(&tx_hash));
+        let tx2: Transaction = node.network_service.get_broadcast(crate::TX_TOPIC);
+        assert_eq!(Hash::digest(&tx2), tx_hash);
+
+        // JSON, the same transaction is already accepted.
+        let mut rx = node
+            .node
+            .request(NodeRequest::BroadcastTransaction { tx: tx.clone() });
+        node.poll();
+        match rx.poll().unwrap() {
+            Async::Ready(NodeResponse::TransactionBroadcasted {
+                tx_hash: tx_hash2,
+                status: TransactionStatus::Accepted {},
+            }) => assert_eq!(tx_hash2, tx_hash),
+            e => panic!("Unexpected response: {:?}", e),
+        }
+
+        s.filter_broadcast(&[crate::TX_TOPIC]);
+    });
+}
+
+/// Creates a transaction which spends the genesis payment.
+fn spend_genesis_payment(s: &Sandbox) -> Transaction {
+    let fee = s.first().node_service.cfg.min_payment_fee;
+    spend_genesis_payment_with_fee(s, fee)
+}
+
+/// Creates a transaction which spends the genesis payment with the specified fee.
+fn spend_genesis_payment_with_fee(s: &Sandbox, fee: i64) -> Transaction {
+    let keychain = &s.keychains[0];
+    let chain = s.first().chain();
+    let (input, amount) = chain
+        .unspent()
+        .filter_map(|output_hash| chain.output_by_hash(output_hash).unwrap())
+        .find_map(|output| {
+            let amount = match &output {
+                Output::PaymentOutput(o) => {
+                    o.decrypt_payload(&keychain.account_pkey, &keychain.account_skey)
+                        .ok()?
+                        .amount
+                }
+                _ => return None,
+            };
+            Some((output, amount))
+        })
+        .expect("genesis payment");
+    let (output, outputs_gamma) =
+        Output::new_payment(&keychain.account_pkey, amount - fee).unwrap();
+    PaymentTransaction::new(
+        &keychain.account_skey,
+        &[input],
+        &[output],
+        &outputs_gamma,
+        fee,
+    )
+    .unwrap()
+    .into()
+}
+
+fn transaction_status(node: &mut NodeSandbox, tx_hash: Hash) -> Option<TransactionStatus> {
+    let mut rx = node
+        .node
+        .request(NodeRequest::TransactionStatus { tx_hash });
+    node.poll();
+    match rx.poll().unwrap() {
+        Async::Ready(NodeResponse::TransactionStatus {
+            tx_hash: tx_hash2,
+            status,
+        }) => {
+            assert_eq!(tx_hash2, tx_hash);
+            Some(status)
+        }
+        Async::Ready(NodeResponse::Error { .. }) => None,
+        e => panic!("Unexpected response: {:?}", e),
+    }
+}
+
+#[test]
+fn transaction_status_lookup() {
+    Sandbox::start(Default::default(), |mut s| {
+        s.poll();
+
+        let tx = spend_genesis_payment(&s);
+        let tx_hash = Hash::digest(&tx);
+        assert_eq!(transaction_status(s.first_mut(), tx_hash), None);
+
+        // Mempool.
+        let node = s.first_mut();
+        let mut rx = node
+            .node
+            .request(NodeRequest::BroadcastTransaction { tx: tx.clone() });
+        node.poll();
+        rx.poll().unwrap();
+        s.broadcast(crate::TX_TOPIC);
+        assert_eq!(
+            transaction_status(s.first_mut(), tx_hash),
+            Some(TransactionStatus::Accepted {})
+        );
+
+        // Micro block.
+        let epoch = s.first().chain().epoch();
+        let offset = s.first().chain().offset();
+        s.skip_micro_block();
+        assert_eq!(
+            transaction_status(s.first_mut(), tx_hash),
+            Some(TransactionStatus::Prepared { epoch, offset })
+        );
+
+        // Rollback.
+        s.rollback_microblock();
+        assert_eq!(
+            transaction_status(s.first_mut(), tx_hash),
+            Some(TransactionStatus::Rollback { epoch, offset })
+        );
+
+        // Macro block.
+        for _offset in offset..s.config.chain.micro_blocks_in_epoch {
+            s.poll();
+            s.skip_micro_block();
+        }
+        s.skip_macro_block();
+        assert_eq!(
+            transaction_status(s.first_mut(), tx_hash),
+            Some(TransactionStatus::Committed { epoch })
+        );
+    });
+}
+
+#[test]
+fn replaced_transaction_status() {
+    Sandbox::start(Default::default(), |mut s| {
+        s.poll();
+
+        let fee = s.first().node_service.cfg.min_payment_fee;
+        let tx = spend_genesis_payment_with_fee(&s, fee);
+        let tx_hash = Hash::digest(&tx);
+        let new_tx = spend_genesis_payment_with_fee(&s, 2 * fee);
+        let new_tx_hash = Hash::digest(&new_tx);
+
+        let node = s.first_mut();
+        let mut rx = node
+            .node
+            .request(NodeRequest::BroadcastTransaction { tx: tx.clone() });
+        node.poll();
+        rx.poll().unwrap();
+        let mut rx = node
+            .node
+            .request(NodeRequest::BroadcastTransaction { tx: new_tx });
+        node.poll();
+        match rx.poll().unwrap() {
+            Async::Ready(NodeResponse::TransactionBroadcasted {
+                status: TransactionStatus::Accepted {},
+                ..
+            }) => {}
+            e => panic!("Unexpected response: {:?}", e),
+        }
+        assert!(!node.node_service.mempool.contains_tx(&tx_hash));
+        assert_eq!(
+            transaction_status(s.first_mut(), tx_hash),
+            Some(TransactionStatus::Replaced {
+                replaced_by: new_tx_hash
+            })
+        );
+        assert_eq!(
+            transaction_status(s.first_mut(), new_tx_hash),
+            Some(TransactionStatus::Accepted {})
+        );
+
+        s.filter_broadcast(&[crate::TX_TOPIC]);
+    });
+}
+
+#[test]
+fn removed_transaction_status() {
+    Sandbox::start(Default::default(), |mut s| {
+        s.poll();
+
+        let tx = spend_genesis_payment(&s);
+        let tx_hash = Hash::digest(&tx);
+
+        let node = s.first_mut();
+        let mut rx = node
+            .node
+            .request(NodeRequest::BroadcastTransaction { tx: tx.clone() });
+        node.poll();
+        rx.poll().unwrap();
+        let mut rx = node
+            .node
+            .request(NodeRequest::RemoveTransaction { tx_hash });
+        node.poll();
+        match rx.poll().unwrap() {
+            Async::Ready(NodeResponse::TransactionRemoved { tx_hash: tx_hash2 }) => {
+                assert_eq!(tx_hash2, tx_hash);
+            }
+            e => panic!("Unexpected response: {:?}", e),
+        }
+        match transaction_status(s.first_mut(), tx_hash) {
+            Some(TransactionStatus::Rejected { .. }) => {}
+            e => panic!("Unexpected status: {:?}", e),
+        }
+
+        s.filter_broadcast(&[crate::TX_TOPIC]);
+    });
+}
+
+fn transaction_info(node: &mut NodeSandbox, tx_hash: Hash) -> Option<ExtendedTransaction> {
+    let mut rx = node.node.request(NodeRequest::TransactionInfo { tx_hash });
+    node.poll();
+    match rx.poll().unwrap() {
+        Async::Ready(NodeResponse::TransactionInfo(info)) => Some(info),
+        Async::Ready(NodeResponse::Error { .. }) => None,
+        e => panic!("Unexpected response: {:?}", e),
+    }
+}
+
+// CASE explorer indexes survive the end of epoch.
+// Assert that a transaction can be found after the macro block removed its micro block.
+#[test]
+fn transaction_info_after_macro_block() {
+    let mut cfg: ChainConfig = Default::default();
+    cfg.explorer_indexes = true;
+    let config = SandboxConfig {
+        chain: cfg,
+        ..Default::default()
+    };
+
+    Sandbox::start(config, |mut s| {
+        s.poll();
+
+        let tx = spend_genesis_payment(&s);
+        let tx_hash = Hash::digest(&tx);
+        assert!(transaction_info(s.first_mut(), tx_hash).is_none());
+
+        let node = s.first_mut();
+        let mut rx = node
+            .node
+            .request(NodeRequest::BroadcastTransaction { tx: tx.clone() });
+        node.poll();
+        rx.poll().unwrap();
+        s.broadcast(crate::TX_TOPIC);
+
+        // Micro block.
+        let epoch = s.first().chain().epoch();
+        let offset = s.first().chain().offset();
+        s.skip_micro_block();
+        let block_hash = s.first().chain().last_block_hash();
+        let info = transaction_info(s.first_mut(), tx_hash).expect("indexed");
+        assert_eq!(info.epoch, epoch);
+        assert_eq!(info.offset, offset);
+        assert_eq!(info.block_hash, block_hash);
+
+        // Macro block.
+        for _offset in offset + 1..s.config.chain.micro_blocks_in_epoch {
+            s.poll();
+            s.skip_micro_block();
+        }
+        s.skip_macro_block();
+        assert_eq!(s.first().chain().epoch(), epoch + 1);
+        for node in s.nodes.iter_mut() {
+            let info = transaction_info(node, tx_hash).expect("indexed");
+            assert_eq!(info.tx_hash, tx_hash);
+            assert_eq!(info.epoch, epoch);
+            assert_eq!(info.offset, offset);
+            assert_eq!(info.block_hash, block_hash);
+            assert_eq!(Hash::digest(&info.transaction), tx_hash);
+        }
+    });
+}
+
 // CASE service award with 0 difficulty.
 // Assert that we have one winner, and this winner is the same as notification said.
 #[test]
@@ -813,6 +1170,7 @@ fn view_change_from_future() {
             .map(|chain_info| {
                 ViewChangeMessage::new(
                     chain_info,
+                    None,
                     sender.validator_id().unwrap() as u32,
                     &sender.node_service.network_skey,
                 )
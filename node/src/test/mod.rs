@@ -644,8 +644,12 @@ pub trait Api<'p> {
         for node in self.iter_mut() {
             // chain: ChainInfo, validator_id: ValidatorId, skey: &pbc::SecretKey
             let validator_id = node.validator_id().unwrap() as u32;
-            let msg =
-                ViewChangeMessage::new(chain_info, validator_id, &node.node_service.network_skey);
+            let msg = ViewChangeMessage::new(
+                chain_info,
+                None,
+                validator_id,
+                &node.node_service.network_skey,
+            );
             view_changes.push(msg)
         }
         let signatures = view_changes
@@ -53,6 +53,8 @@ pub(crate) fn validate_external_transaction(
             Output::PaymentOutput(_o) => payment_fee,
             Output::PublicPaymentOutput(_o) => payment_fee,
             Output::StakeOutput(_o) => stake_fee,
+            Output::MultisigOutput(_o) => payment_fee,
+            Output::HtlcOutput(_o) => payment_fee,
         };
     }
     if tx.fee() < min_fee {
@@ -61,8 +63,9 @@ pub(crate) fn validate_external_transaction(
 
     let mut inputs: Vec<Output> = Vec::new();
 
-    // TODO: allow transaction with overlapping inputs/outputs in mempool.
-    // See https://github.com/stegos/stegos/issues/826.
+    // Payment transactions in mempool can be replaced by paying a higher fee.
+    // Each replacement must pay at least one more minimal fee to be relayed.
+    let replace = mempool.can_replace(tx, payment_fee);
 
     // Check for overlapping inputs in mempool.
     for input_hash in tx.txins() {
@@ -75,7 +78,7 @@ pub(crate) fn validate_external_transaction(
         };
 
         // Check that the input is not claimed by other transactions.
-        if mempool.contains_input(input_hash) {
+        if !replace && mempool.contains_input(input_hash) {
             return Err(TransactionError::MissingInput(tx_hash, input_hash.clone()).into());
         }
 
@@ -86,7 +89,9 @@ pub(crate) fn validate_external_transaction(
     for output in tx.txouts() {
         let output_hash = Hash::digest(output);
         // Check that the output is unique and don't overlap with other transactions.
-        if mempool.contains_output(&output_hash) || chain.contains_output(&output_hash) {
+        if (!replace && mempool.contains_output(&output_hash))
+            || chain.contains_output(&output_hash)
+        {
             return Err(TransactionError::OutputHashCollision(tx_hash, output_hash).into());
         }
         output.validate()?;
@@ -101,7 +106,9 @@ pub(crate) fn validate_external_transaction(
     // Check the monetary balance, Bulletpoofs/amounts and signature.
     match tx {
         Transaction::RestakeTransaction(tx) => tx.validate(&inputs)?,
-        Transaction::PaymentTransaction(tx) => tx.validate(&inputs)?,
+        Transaction::PaymentTransaction(tx) => {
+            tx.validate(&inputs, chain.last_macro_block_timestamp())?
+        }
         Transaction::SlashingTransaction(..)
         | Transaction::CoinbaseTransaction(..)
         | Transaction::ServiceAwardTransaction(..) => {
@@ -159,6 +166,8 @@ mod test {
             match output {
                 Output::PaymentOutput(ref _o) => inputs.push(output),
                 Output::PublicPaymentOutput(ref _o) => inputs.push(output),
+                Output::MultisigOutput(ref _o) => inputs.push(output),
+                Output::HtlcOutput(ref _o) => inputs.push(output),
                 Output::StakeOutput(ref _o) => stakes.push(output),
             }
         }
@@ -311,6 +320,25 @@ mod test {
                 _ => panic!(),
             }
 
+            // Replace by a higher fee.
+            let tx3 = {
+                let fee3 = fee + 1;
+                let (output3, outputs3_gamma) =
+                    Output::new_payment(&account_pkey, amount - fee3).unwrap();
+                PaymentTransaction::new(&account_skey, &inputs, &[output3], &outputs3_gamma, fee3)
+                    .unwrap()
+                    .into()
+            };
+            validate_external_transaction(
+                &tx3,
+                &mempool,
+                &chain,
+                timestamp,
+                payment_fee,
+                stake_fee,
+            )
+            .expect("transaction is valid");
+
             let input_hashes: Vec<Hash> = inputs.iter().map(Hash::digest).collect();
             let output_hashes: Vec<Hash> = outputs.iter().map(Hash::digest).collect();
             mempool.prune(input_hashes.iter(), output_hashes.iter());
@@ -29,8 +29,8 @@ use std::path::{Path, PathBuf};
 use std::process;
 use std::str::FromStr;
 use stegos_blockchain::{
-    chain_to_prefix, create_multi_signature, election, mix, Block, ChainConfig, MacroBlock, Output,
-    PaymentOutput, PaymentPayloadData, StakeOutput, StakersGroup, Timestamp,
+    chain_to_prefix, create_multi_signature, election, mix, validate_prefix, Block, ChainConfig,
+    MacroBlock, Output, PaymentOutput, PaymentPayloadData, StakeOutput, StakersGroup, Timestamp,
 };
 use stegos_crypto::hash::Hash;
 use stegos_crypto::{pbc, scc};
@@ -96,6 +96,13 @@ fn main() {
                 .help("Specify chain to use: mainnet, testnet or dev")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("prefix")
+                .long("prefix")
+                .value_name("PREFIX")
+                .help("Address prefix of a custom chain")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("owner")
                 .short("o")
@@ -185,8 +192,14 @@ fn main() {
 
     info!("Generating genesis for chain: {} ...", chain);
 
-    stegos_crypto::set_network_prefix(chain_to_prefix(chain))
-        .expect("Network prefix not initialised.");
+    let prefix: &'static str = match args.value_of("prefix") {
+        Some(prefix) => {
+            validate_prefix(prefix).expect("invalid prefix");
+            Box::leak(prefix.to_string().into_boxed_str())
+        }
+        None => chain_to_prefix(chain).expect("unknown chain, please specify --prefix"),
+    };
+    stegos_crypto::set_network_prefix(prefix).expect("Network prefix not initialised.");
 
     let mut stakers: StakersGroup = Vec::with_capacity(keys as usize);
     let mut outputs: Vec<Output> = Vec::with_capacity(1 + keys as usize);
@@ -333,7 +346,9 @@ fn main() {
 
     // Write the block to the disk.
     let block_data = Block::MacroBlock(block).into_buffer().unwrap();
-    let genesis_path = PathBuf::from("chains").join(chain).join("genesis.bin");
+    let genesis_dir = PathBuf::from("chains").join(chain);
+    fs::create_dir_all(&genesis_dir).expect("failed to create chain directory");
+    let genesis_path = genesis_dir.join("genesis.bin");
     fs::write(&genesis_path, &block_data).expect("failed to write genesis block");
     info!("Wrote {:?}", &genesis_path);
 
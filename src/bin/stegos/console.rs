@@ -128,6 +128,7 @@ fn parse_money(amount: &str) -> Result<i64, Error> {
         .map_err(|e| format_err!("{}", e))
 }
 
+/// The default fee until changed by `set fee`.
 const PAYMENT_FEE: i64 = 1_000; // 0.001 STG
 
 pub enum Formatter {
@@ -176,6 +177,8 @@ pub struct ConsoleService {
     stdin_th: thread::JoinHandle<()>,
     /// Display formatter.
     formatter: Formatter,
+    /// Fee per UTXO, changed by `set fee`.
+    payment_fee: i64,
 }
 
 impl ConsoleService {
@@ -193,9 +196,11 @@ impl ConsoleService {
         let client = WebSocketClient::new(uri, api_token);
         let account_id = Arc::new(Mutex::new("1".to_string()));
 
+        // Custom chains are resolved using the node.
+        let chain = chain.filter(|chain| chain_to_prefix(chain).is_ok());
         if let Some(chain) = &chain {
             debug!("Initialising cli for chain = {}", chain);
-            stegos_crypto::set_network_prefix(chain_to_prefix(&chain))
+            stegos_crypto::set_network_prefix(chain_to_prefix(&chain).unwrap())
                 .expect("Network prefix not initialised.");
         }
         let stdin_th = if atty::is(atty::Stream::Stdin) {
@@ -215,6 +220,7 @@ impl ConsoleService {
             stdin,
             stdin_th,
             formatter,
+            payment_fee: PAYMENT_FEE,
         }
     }
 
@@ -328,6 +334,8 @@ impl ConsoleService {
         eprintln!("show history [STARTING DATE] - print history since date");
         eprintln!("show election - show consensus state");
         eprintln!("show escrow - print escrow");
+        eprintln!("show fee - estimate the suggested fee");
+        eprintln!("set fee FEE - use FEE in μSTG per each created UTXO");
         eprintln!("show replication - show replication status");
         eprintln!("change upstream - change the current replication upstream");
         eprintln!("show recovery - print recovery information");
@@ -576,7 +584,7 @@ impl ConsoleService {
 
             let (public, snowball, comment, payment_fee, with_certificate) =
                 match caps.name("arguments") {
-                    None => (false, false, String::new(), PAYMENT_FEE, false),
+                    None => (false, false, String::new(), self.payment_fee, false),
 
                     Some(m) => {
                         let caps = match PAY_ARGUMENTS_RE.captures(m.as_str()) {
@@ -609,7 +617,7 @@ impl ConsoleService {
                                     }
                                 }
                             }
-                            None => PAYMENT_FEE, // use the default value.
+                            None => self.payment_fee, // use the suggested value.
                         };
                         (public, snowball, comment, payment_fee, certificate)
                     }
@@ -731,7 +739,7 @@ impl ConsoleService {
                 }
             };
             let amount: i64 = 0;
-            let payment_fee = PAYMENT_FEE;
+            let payment_fee = self.payment_fee;
             let comment = caps.name("msg").unwrap().as_str().to_string();
             assert!(comment.len() > 0);
 
@@ -744,7 +752,7 @@ impl ConsoleService {
             };
             self.send_account_request(request)?
         } else if msg.starts_with("stake all") {
-            let payment_fee = PAYMENT_FEE;
+            let payment_fee = self.payment_fee;
             let request = AccountRequest::StakeAll { payment_fee };
             self.send_account_request(request)?
         } else if msg.starts_with("stake remote ") {
@@ -765,7 +773,7 @@ impl ConsoleService {
                     return Ok(true);
                 }
             };
-            let payment_fee = PAYMENT_FEE;
+            let payment_fee = self.payment_fee;
             let request = AccountRequest::StakeRemote {
                 amount,
                 payment_fee,
@@ -789,14 +797,14 @@ impl ConsoleService {
                     return Ok(true);
                 }
             };
-            let payment_fee = PAYMENT_FEE;
+            let payment_fee = self.payment_fee;
             let request = AccountRequest::Stake {
                 amount,
                 payment_fee,
             };
             self.send_account_request(request)?
         } else if msg == "unstake" {
-            let payment_fee = PAYMENT_FEE;
+            let payment_fee = self.payment_fee;
             let request = AccountRequest::UnstakeAll { payment_fee };
             self.send_account_request(request)?
         } else if msg.starts_with("unstake ") {
@@ -817,7 +825,7 @@ impl ConsoleService {
                     return Ok(true);
                 }
             };
-            let payment_fee = PAYMENT_FEE;
+            let payment_fee = self.payment_fee;
             let request = AccountRequest::Unstake {
                 amount,
                 payment_fee,
@@ -827,7 +835,7 @@ impl ConsoleService {
             let request = AccountRequest::RestakeAll {};
             self.send_account_request(request)?
         } else if msg == "cloak" {
-            let payment_fee = PAYMENT_FEE;
+            let payment_fee = self.payment_fee;
             let request = AccountRequest::CloakAll { payment_fee };
             self.send_account_request(request)?
         } else if msg == "show version" {
@@ -848,6 +856,26 @@ impl ConsoleService {
         } else if msg == "show escrow" {
             let request = NodeRequest::EscrowInfo {};
             self.send_node_request(request)?
+        } else if msg == "show fee" {
+            let request = NodeRequest::EstimateFee {};
+            self.send_node_request(request)?
+        } else if msg.starts_with("set fee ") {
+            let caps = match STAKE_COMMAND_RE.captures(&msg[8..]) {
+                Some(c) => c,
+                None => {
+                    Self::help();
+                    return Ok(true);
+                }
+            };
+            let payment_fee = caps.name("amount").unwrap().as_str();
+            match parse_money(payment_fee) {
+                Ok(payment_fee) => {
+                    self.payment_fee = payment_fee;
+                    eprintln!("Using fee {} μSTG per UTXO", payment_fee);
+                }
+                Err(e) => eprintln!("Invalid fee '{}': {}", payment_fee, e),
+            }
+            return Ok(true);
         } else if msg == "show replication" {
             let request = NodeRequest::ReplicationInfo {};
             self.send_node_request(request)?
@@ -1048,17 +1076,19 @@ impl ConsoleService {
                 debug!("ChainNameResolver::Resolving");
                 match self.client.poll().unwrap() {
                     Async::Ready(response) => {
-                        let chain = match response.unwrap() {
+                        let (chain, prefix) = match response.unwrap() {
                             Response {
-                                kind: ResponseKind::NodeResponse(NodeResponse::ChainName { name }),
+                                kind:
+                                    ResponseKind::NodeResponse(NodeResponse::ChainName { name, prefix }),
                                 ..
-                            } => name,
+                            } => (name, prefix),
                             response => {
                                 panic!("Wrong reponse to chain name request = {:?}", response)
                             }
                         };
                         debug!("Initialising cli for chain = {}", chain);
-                        stegos_crypto::set_network_prefix(chain_to_prefix(&chain))
+                        let prefix: &'static str = Box::leak(prefix.into_boxed_str());
+                        stegos_crypto::set_network_prefix(prefix)
                             .expect("Network prefix not initialised.");
                         self.chain = ChainNameResolver::Set(chain);
                         Ok(Async::Ready(()))
@@ -22,6 +22,7 @@
 // SOFTWARE.
 
 use failure::Fail;
+use serde::de::DeserializeOwned;
 use serde_derive::{Deserialize, Serialize};
 use std::fs::File;
 use std::io;
@@ -29,7 +30,7 @@ use std::io::ErrorKind;
 use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::result::Result;
-use stegos_blockchain::ConsistencyCheck;
+use stegos_blockchain::{ChainConfig, ConsistencyCheck};
 use stegos_network::NetworkConfig;
 use stegos_node::NodeConfig;
 use toml;
@@ -71,10 +72,14 @@ impl Default for Config {
 pub struct GeneralConfig {
     /// Chain name.
     pub chain: String,
+    /// Custom chain definition file, overrides `chain`.
+    pub chain_spec: PathBuf,
     /// Data directory.
     pub data_dir: PathBuf,
     /// Force strict checking (BP + BLS + VRF) of blockchain on the disk.
     pub consistency_check: ConsistencyCheck,
+    /// Maintain secondary indexes for block explorer queries.
+    pub explorer_indexes: bool,
     /// Log4RS configuration file
     pub log_config: PathBuf,
     /// Prometheus exporter endpoint
@@ -91,12 +96,14 @@ impl Default for GeneralConfig {
             .unwrap_or(PathBuf::from(r"data"));
         GeneralConfig {
             chain: "testnet".to_string(),
+            chain_spec: PathBuf::new(),
             data_dir,
             consistency_check: if cfg!(debug_assertions) {
                 ConsistencyCheck::Full
             } else {
                 ConsistencyCheck::None
             },
+            explorer_indexes: false,
             log_config: PathBuf::new(),
             prometheus_endpoint: "".to_string(),
             api_endpoint: "127.0.0.1:3145".to_string(),
@@ -118,6 +125,21 @@ pub enum ConfigError {
     ParseError(toml::de::Error),
 }
 
+/// A custom chain definition.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ChainSpec {
+    /// Chain name.
+    pub name: String,
+    /// Bech32 prefix of addresses.
+    pub prefix: String,
+    /// Genesis block file, relative to the chain definition file.
+    pub genesis: PathBuf,
+    /// Overrides of the default chain configuration.
+    #[serde(default)]
+    pub config: ChainConfig,
+}
+
 ///
 /// Load configuration file
 ///
@@ -130,6 +152,17 @@ pub enum ConfigError {
 /// Returns ConfigError on error.
 ///
 pub fn from_file<P: AsRef<Path>>(cfg_path: P) -> Result<Config, ConfigError> {
+    from_toml_file(cfg_path)
+}
+
+///
+/// Load a custom chain definition.
+///
+pub fn chain_spec_from_file<P: AsRef<Path>>(spec_path: P) -> Result<ChainSpec, ConfigError> {
+    from_toml_file(spec_path)
+}
+
+fn from_toml_file<T: DeserializeOwned, P: AsRef<Path>>(cfg_path: P) -> Result<T, ConfigError> {
     // Open configuration file
     let mut f = match File::open(cfg_path) {
         // The file is readable
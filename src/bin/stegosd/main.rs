@@ -46,14 +46,15 @@ use std::str::FromStr;
 use std::{fs, process};
 use stegos_api::{load_or_create_api_token, WebSocketServer};
 use stegos_blockchain::{
-    chain_to_prefix, initialize_chain, Blockchain, ConsistencyCheck, Timestamp,
+    chain_to_prefix, initialize_chain, load_genesis, validate_prefix, Blockchain, ChainConfig,
+    ConsistencyCheck, LightBlockchain, MacroBlock, Timestamp,
 };
 use stegos_crypto::hash::Hash;
 use stegos_keychain::keyfile::load_network_keys;
-use stegos_network::{Libp2pNetwork, NETWORK_STATUS_TOPIC};
-use stegos_node::NodeService;
+use stegos_network::{Libp2pNetwork, Network, NETWORK_STATUS_TOPIC};
+use stegos_node::{LightNodeService, NodeConfig, NodeService};
 use stegos_wallet::WalletService;
-use tokio::runtime::Runtime;
+use tokio::runtime::{Runtime, TaskExecutor};
 use tokio_timer::clock;
 
 /// The default file name for configuration
@@ -254,6 +255,11 @@ fn load_configuration(args: &ArgMatches<'_>) -> Result<config::Config, Error> {
         cfg.general.chain = chain.to_string();
     }
 
+    // Override global.chain_spec via command-line or environment.
+    if let Some(chain_spec) = args.value_of_os("chain-spec") {
+        cfg.general.chain_spec = PathBuf::from(chain_spec);
+    }
+
     // Override global.data_dir via command-line or environment.
     if let Some(data_dir) = args.value_of_os("data-dir") {
         cfg.general.data_dir = PathBuf::from(data_dir);
@@ -271,6 +277,27 @@ fn load_configuration(args: &ArgMatches<'_>) -> Result<config::Config, Error> {
         cfg.general.consistency_check = ConsistencyCheck::Full;
     }
 
+    // Override node.pruning_epochs via command-line or environment.
+    if let Some(pruning_epochs) = args.value_of("pruning-epochs") {
+        cfg.node.pruning_epochs = u64::from_str(pruning_epochs)
+            .map_err(|e| format_err!("Invalid pruning epochs '{}': {}", pruning_epochs, e))?;
+    }
+
+    // Override global.explorer_indexes via command-line.
+    if args.is_present("explorer-indexes") {
+        cfg.general.explorer_indexes = true;
+    }
+
+    // Override node.fast_sync via command-line.
+    if args.is_present("fast-sync") {
+        cfg.node.fast_sync = true;
+    }
+
+    // Override node.light via command-line.
+    if args.is_present("light") {
+        cfg.node.light = true;
+    }
+
     // Override network.endpoint via command-line or environment.
     if let Some(endpoint) = args.value_of("node-endpoint") {
         cfg.network.endpoint = endpoint.to_string();
@@ -296,7 +323,10 @@ fn load_configuration(args: &ArgMatches<'_>) -> Result<config::Config, Error> {
     }
 
     // Use default SRV record for the chain
-    if cfg.general.chain != "dev" && cfg.network.seed_pool == "" {
+    if cfg.general.chain != "dev"
+        && cfg.general.chain_spec == PathBuf::new()
+        && cfg.network.seed_pool == ""
+    {
         cfg.network.seed_pool =
             format!("_stegos._tcp.{}.stegos.com", cfg.general.chain).to_string();
     }
@@ -328,8 +358,12 @@ fn load_configuration(args: &ArgMatches<'_>) -> Result<config::Config, Error> {
         })?;
     }
 
-    // Disable [node] sections.
-    if cfg.general.chain == "mainnet" && cfg.node != Default::default() {
+    // Disable [node] sections, except of the parameter change to vote for.
+    let node_cfg = NodeConfig {
+        proposal: None,
+        ..cfg.node.clone()
+    };
+    if cfg.general.chain == "mainnet" && node_cfg != Default::default() {
         return Err(format_err!(
             "Can't override [node] options for {}",
             cfg.general.chain
@@ -343,6 +377,41 @@ fn load_configuration(args: &ArgMatches<'_>) -> Result<config::Config, Error> {
     Ok(cfg)
 }
 
+/// Load a built-in chain or a custom chain definition.
+/// Returns the chain name, the genesis block, the chain configuration and the address prefix.
+fn load_chain(
+    cfg: &GeneralConfig,
+) -> Result<(String, MacroBlock, ChainConfig, &'static str), Error> {
+    if cfg.chain_spec == PathBuf::new() {
+        let prefix = chain_to_prefix(&cfg.chain)?;
+        let (genesis, chain_cfg) = initialize_chain(&cfg.chain)?;
+        return Ok((cfg.chain.clone(), genesis, chain_cfg, prefix));
+    }
+
+    let spec = config::chain_spec_from_file(&cfg.chain_spec)
+        .map_err(|e| format_err!("Failed to load chain spec {:?}: {}", cfg.chain_spec, e))?;
+    if spec.name.is_empty() || chain_to_prefix(&spec.name).is_ok() {
+        return Err(format_err!(
+            "Invalid chain name '{}' in {:?}: a custom chain needs a new name",
+            spec.name,
+            cfg.chain_spec
+        ));
+    }
+    validate_prefix(&spec.prefix)?;
+    let genesis_path = cfg
+        .chain_spec
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(&spec.genesis);
+    let genesis = load_genesis(&genesis_path)?;
+    let prefix: &'static str = Box::leak(spec.prefix.into_boxed_str());
+    info!(
+        "Loaded custom chain: name={}, prefix={}, spec={:?}",
+        spec.name, prefix, cfg.chain_spec
+    );
+    Ok((spec.name, genesis, spec.config, prefix))
+}
+
 fn run() -> Result<(), Error> {
     let name = "Stegos Node";
     let version = format!(
@@ -448,6 +517,14 @@ fn run() -> Result<(), Error> {
                 .help("Specify chain to use: testnet or dev")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("chain-spec")
+                .long("chain-spec")
+                .env("STEGOS_CHAIN_SPEC")
+                .value_name("FILE")
+                .help("Load a custom chain definition: name, address prefix, genesis and config")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("verbose")
                 .help("Change verbosity level")
@@ -465,10 +542,38 @@ fn run() -> Result<(), Error> {
                 .help("Force recovery using blocks saved on disk, rather than Snapshot.")
                 .long("recover"),
         )
+        .arg(
+            Arg::with_name("pruning-epochs")
+                .long("pruning-epochs")
+                .env("STEGOS_PRUNING_EPOCHS")
+                .value_name("EPOCHS")
+                .help("Keep only the last EPOCHS epochs of full blocks, 0 to keep the full history")
+                .takes_value(true)
+                .validator(|epochs| {
+                    u64::from_str(&epochs)
+                        .map(|_| ())
+                        .map_err(|e| format!("{}", e))
+                }),
+        )
+        .arg(
+            Arg::with_name("explorer-indexes")
+                .help("Maintain secondary indexes of transactions, inputs, outputs and recipients")
+                .long("explorer-indexes"),
+        )
+        .arg(
+            Arg::with_name("fast-sync")
+                .help("Download a verified state snapshot instead of replaying all blocks")
+                .long("fast-sync"),
+        )
+        .arg(
+            Arg::with_name("light")
+                .help("Keep only block headers and output canaries, download outputs on demand")
+                .long("light"),
+        )
         .get_matches();
 
     // Parse configuration
-    let cfg = load_configuration(&args)?;
+    let mut cfg = load_configuration(&args)?;
 
     // Initialize logger
     let _log = load_logger_configuration(&args, &cfg.general.data_dir, &cfg.general.log_config)?;
@@ -476,6 +581,10 @@ fn run() -> Result<(), Error> {
     info!("{} {}", name, version);
     debug!("Configuration:\n{}", serde_yaml::to_string(&cfg).unwrap());
 
+    // Load the chain definition.
+    let (chain, genesis, mut chain_cfg, prefix) = load_chain(&cfg.general)?;
+    cfg.general.chain = chain;
+
     // Append chain name if dir default.
     // But keep root_dir for api.token unchanged.
     let root_dir = cfg.general.data_dir.clone();
@@ -503,8 +612,7 @@ fn run() -> Result<(), Error> {
             .map_err(|e| format_err!("Failed to create {:?}: {}", accounts_dir, e))?
     }
 
-    stegos_crypto::set_network_prefix(chain_to_prefix(&cfg.general.chain))
-        .expect("Network prefix not initialised.");
+    stegos_crypto::set_network_prefix(prefix).expect("Network prefix not initialised.");
 
     // Initialize keychain
     let network_skey_file = data_dir.join("network.skey");
@@ -534,12 +642,33 @@ fn run() -> Result<(), Error> {
     }
 
     // Initialize blockchain
-    let (genesis, chain_cfg) = initialize_chain(&cfg.general.chain)?;
+    chain_cfg.explorer_indexes = cfg.general.explorer_indexes;
     info!(
         "Using '{}' chain, genesis={}",
         cfg.general.chain,
         Hash::digest(&genesis)
     );
+
+    if cfg.node.light {
+        // Light node - the wallet and the API server need the full blockchain.
+        let chain = LightBlockchain::new(chain_cfg, &chain_dir, genesis)?;
+        let (mut node_service, _node) = LightNodeService::new(
+            cfg.node.clone(),
+            chain,
+            network.clone(),
+            cfg.general.chain.clone(),
+        )?;
+        info!("Running in light mode, wallet and API server are disabled");
+        spawn_on_network_ready(&mut rt, &network, move |executor| {
+            // TODO: how to handle errors here?
+            node_service.init().expect("shit happens");
+            executor.spawn(node_service);
+        })?;
+        rt.block_on(network_service)
+            .expect("errors are handled earlier");
+        return Ok(());
+    }
+
     let timestamp = Timestamp::now();
     let chain = Blockchain::new(
         chain_cfg.clone(),
@@ -571,6 +700,7 @@ fn run() -> Result<(), Error> {
         node.clone(),
         rt.executor(),
         chain_cfg.stake_epochs,
+        chain_cfg.unbonding_epochs,
         cfg.node.max_inputs_in_tx,
         epoch,
     )?;
@@ -592,6 +722,24 @@ fn run() -> Result<(), Error> {
     }
 
     // Start all services when network is ready.
+    spawn_on_network_ready(&mut rt, &network, move |executor| {
+        // TODO: how to handle errors here?
+        node_service.init().expect("shit happens");
+        executor.spawn(node_service);
+    })?;
+
+    // Start main event loop
+    rt.block_on(network_service)
+        .expect("errors are handled earlier");
+
+    Ok(())
+}
+
+/// Invoke `start` when network is ready.
+fn spawn_on_network_ready<F>(rt: &mut Runtime, network: &Network, start: F) -> Result<(), Error>
+where
+    F: FnOnce(TaskExecutor) + Send + 'static,
+{
     let executor = rt.executor();
     let network_ready_future = network
         .subscribe(&NETWORK_STATUS_TOPIC)?
@@ -607,17 +755,10 @@ fn run() -> Result<(), Error> {
         })
         .and_then(move |()| {
             info!("Network is ready");
-            // TODO: how to handle errors here?
-            node_service.init().expect("shit happens");
-            executor.spawn(node_service);
+            start(executor);
             Ok(())
         });
     rt.spawn(network_ready_future);
-
-    // Start main event loop
-    rt.block_on(network_service)
-        .expect("errors are handled earlier");
-
     Ok(())
 }
 
@@ -688,6 +829,62 @@ mod tests {
         .expect("dev looks like unloadable.");
     }
 
+    #[test]
+    fn is_custom_chain_loadable() {
+        let _ = simple_logger::init_with_level(log::Level::Debug);
+        let spec_dir = TempDir::new("test").unwrap();
+        fs::write(
+            spec_dir.path().join("genesis.bin"),
+            &include_bytes!("../../../chains/dev/genesis.bin")[..],
+        )
+        .unwrap();
+        let spec_path = spec_dir.path().join("chain.toml");
+        let mut cfg: GeneralConfig = Default::default();
+        cfg.chain_spec = spec_path.clone();
+
+        // Built-in names can't be reused.
+        fs::write(
+            &spec_path,
+            "name = \"testnet\"\nprefix = \"stx\"\ngenesis = \"genesis.bin\"\n",
+        )
+        .unwrap();
+        assert!(load_chain(&cfg).is_err());
+
+        // Invalid prefix.
+        fs::write(
+            &spec_path,
+            "name = \"staging\"\nprefix = \"St X\"\ngenesis = \"genesis.bin\"\n",
+        )
+        .unwrap();
+        assert!(load_chain(&cfg).is_err());
+
+        fs::write(
+            &spec_path,
+            "name = \"staging\"\nprefix = \"stx\"\ngenesis = \"genesis.bin\"\n\
+             [config]\nawards_difficulty = 3\nstake_epochs = 1\n",
+        )
+        .unwrap();
+        let (chain, genesis, chain_cfg, prefix) = load_chain(&cfg).expect("custom chain");
+        assert_eq!(chain, "staging");
+        assert_eq!(prefix, "stx");
+        assert_eq!(chain_cfg.awards_difficulty, 3);
+        assert_eq!(chain_cfg.stake_epochs, 1);
+        assert_eq!(
+            chain_cfg.micro_blocks_in_epoch,
+            ChainConfig::default().micro_blocks_in_epoch
+        );
+        let timestamp = Timestamp::now();
+        let chain_dir = TempDir::new("test").unwrap();
+        Blockchain::new(
+            chain_cfg,
+            chain_dir.path(),
+            ConsistencyCheck::Full,
+            genesis,
+            timestamp,
+        )
+        .expect("custom chain looks like unloadable.");
+    }
+
     #[test]
     fn log_test() {
         simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
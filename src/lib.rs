@@ -72,7 +72,7 @@ fn init(
             .map_err(|e| format_err!("Failed to create {:?}: {}", accounts_dir, e))?
     }
 
-    stegos_crypto::set_network_prefix(chain_to_prefix(&chain_name))
+    stegos_crypto::set_network_prefix(chain_to_prefix(&chain_name)?)
         .expect("Network prefix not initialised.");
 
     // Initialize keychain
@@ -127,6 +127,7 @@ fn init(
         node.clone(),
         rt.executor(),
         chain_cfg.stake_epochs,
+        chain_cfg.unbonding_epochs,
         node_cfg.max_inputs_in_tx,
         epoch,
     )?;
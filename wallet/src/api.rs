@@ -25,6 +25,7 @@ pub use crate::snowball::State as SnowballStatus;
 use serde_derive::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 pub use stegos_blockchain::PaymentPayloadData;
+pub use stegos_blockchain::PaymentTransaction;
 pub use stegos_blockchain::StakeInfo;
 use stegos_blockchain::Timestamp;
 use stegos_crypto::hash::Hash;
@@ -56,6 +57,8 @@ pub enum OutputInfo {
     Payment(PaymentInfo),
     PublicPayment(PublicPaymentInfo),
     Staked(StakeInfo),
+    Multisig(MultisigInfo),
+    Htlc(HtlcInfo),
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
@@ -81,6 +84,28 @@ pub struct PublicPaymentInfo {
     pub recipient: scc::PublicKey,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MultisigInfo {
+    pub output_hash: Hash,
+    pub amount: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_timestamp: Option<Timestamp>,
+    pub recipients: Vec<scc::PublicKey>,
+    pub threshold: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct HtlcInfo {
+    pub output_hash: Hash,
+    pub amount: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_timestamp: Option<Timestamp>,
+    pub recipient: scc::PublicKey,
+    pub refunder: scc::PublicKey,
+    pub hash_lock: Hash,
+    pub deadline: Timestamp,
+}
+
 ///
 /// Information about balance.
 ///
@@ -90,6 +115,16 @@ pub struct Balance {
     pub current: i64,
     /// Funds can spend right now.
     pub available: i64,
+    /// Funds are time-locked and can't be spent yet.
+    #[serde(default)]
+    pub locked: i64,
+    /// Stakes waiting for the end of the unbonding period, included into `current`.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub unbonding: i64,
+}
+
+fn is_zero(value: &i64) -> bool {
+    *value == 0
 }
 
 ///
@@ -104,7 +139,16 @@ pub struct AccountBalance {
     pub public_payment: Balance,
     /// StakeUTXO.
     pub stake: Balance,
-    /// PaymentUTXO + PublicPaymentUTXO + StakeUTXO.
+    /// StakeUTXO delegated to third-party validators.
+    #[serde(default)]
+    pub delegated: Balance,
+    /// MultisigUTXO, shared with other co-signers and not included into total.
+    #[serde(default)]
+    pub multisig: Balance,
+    /// HtlcUTXO, which can be claimed or refunded and not included into total.
+    #[serde(default)]
+    pub htlc: Balance,
+    /// PaymentUTXO + PublicPaymentUTXO + StakeUTXO, including delegated.
     #[serde(flatten)]
     pub total: Balance,
     /// Is account balance finalized (was updated before last macroblock).
@@ -139,6 +183,10 @@ pub enum AccountNotification {
     SpentPublic(PublicPaymentInfo),
     Staked(StakeInfo),
     Unstaked(StakeInfo),
+    ReceivedMultisig(MultisigInfo),
+    SpentMultisig(MultisigInfo),
+    ReceivedHtlc(HtlcInfo),
+    SpentHtlc(HtlcInfo),
 }
 
 #[derive(Eq, PartialEq, Debug, Clone, Serialize, Deserialize)]
@@ -182,6 +230,12 @@ pub enum AccountRequest {
         payment_fee: i64,
         comment: String,
     },
+    /// Replace a pending transaction by the same transaction with a higher fee.
+    /// `fee` is the new total fee, the difference is taken from the change.
+    BumpFee {
+        tx_hash: Hash,
+        fee: i64,
+    },
     StakeAll {
         payment_fee: i64,
     },
@@ -200,10 +254,77 @@ pub enum AccountRequest {
     UnstakeAll {
         payment_fee: i64,
     },
+    /// Move a part of active stake into the unbonding state.
+    /// It can be unstaked after the unbonding period.
+    Unbond {
+        amount: i64,
+        payment_fee: i64,
+    },
+    /// Delegate money to a third-party validator and share its rewards.
+    Delegate {
+        validator: pbc::PublicKey,
+        amount: i64,
+        payment_fee: i64,
+    },
+    /// Withdraw expired delegations from a validator.
+    /// NOTE: amount must include PAYMENT_FEE.
+    Undelegate {
+        validator: pbc::PublicKey,
+        amount: i64,
+        payment_fee: i64,
+    },
     RestakeAll {},
     CloakAll {
         payment_fee: i64,
     },
+    /// Send money to a new multi-signature (m-of-n) UTXO.
+    MultisigPayment {
+        recipients: Vec<scc::PublicKey>,
+        threshold: u32,
+        amount: i64,
+        payment_fee: i64,
+    },
+    /// Create and sign a transaction spending a multi-signature UTXO.
+    MultisigSpend {
+        output_hash: Hash,
+        recipient: scc::PublicKey,
+        amount: i64,
+        payment_fee: i64,
+    },
+    /// Add our signature to a transaction created by other co-signer.
+    /// The transaction is signed only if it pays `amount` to `recipient` with `fee`.
+    MultisigSign {
+        tx: PaymentTransaction,
+        recipient: scc::PublicKey,
+        amount: i64,
+        fee: i64,
+    },
+    /// Send a transaction signed by enough co-signers.
+    MultisigSend {
+        tx: PaymentTransaction,
+    },
+    /// Send money to a new hash-time-locked UTXO.
+    /// `hash_lock` is the SHA-256 hash of the secret preimage.
+    HtlcPayment {
+        recipient: scc::PublicKey,
+        hash_lock: Hash,
+        deadline: Timestamp,
+        amount: i64,
+        payment_fee: i64,
+    },
+    /// Claim a hash-time-locked UTXO by revealing the preimage of its hash_lock.
+    HtlcClaim {
+        output_hash: Hash,
+        #[serde(deserialize_with = "stegos_crypto::utils::vec_deserialize_from_hex")]
+        #[serde(serialize_with = "stegos_crypto::utils::vec_serialize_to_hex")]
+        preimage: Vec<u8>,
+        payment_fee: i64,
+    },
+    /// Take back an unclaimed hash-time-locked UTXO after its deadline.
+    HtlcRefund {
+        output_hash: Hash,
+        payment_fee: i64,
+    },
     AccountInfo {},
     BalanceInfo {},
     UnspentInfo {},
@@ -272,12 +393,20 @@ pub enum AccountResponse {
     #[serde(skip)]
     Disabled,
     TransactionCreated(TransactionInfo),
+    MultisigTransaction {
+        tx_hash: Hash,
+        tx: PaymentTransaction,
+    },
     BalanceInfo(AccountBalance),
     AccountInfo(AccountInfo),
     UnspentInfo {
         public_payments: Vec<PublicPaymentInfo>,
         payments: Vec<PaymentInfo>,
         stakes: Vec<StakeInfo>,
+        #[serde(default)]
+        multisigs: Vec<MultisigInfo>,
+        #[serde(default)]
+        htlcs: Vec<HtlcInfo>,
     },
     HistoryInfo {
         log: Vec<LogEntryInfo>,
@@ -344,6 +473,18 @@ impl From<StakeInfo> for OutputInfo {
     }
 }
 
+impl From<MultisigInfo> for OutputInfo {
+    fn from(pi: MultisigInfo) -> OutputInfo {
+        OutputInfo::Multisig(pi)
+    }
+}
+
+impl From<HtlcInfo> for OutputInfo {
+    fn from(pi: HtlcInfo) -> OutputInfo {
+        OutputInfo::Htlc(pi)
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
@@ -22,6 +22,8 @@
 // SOFTWARE.
 
 use failure::Fail;
+use stegos_blockchain::Timestamp;
+use stegos_crypto::hash::Hash;
 use stegos_crypto::scc;
 
 #[derive(Debug, Fail, PartialEq, Eq)]
@@ -55,4 +57,56 @@ pub enum WalletError {
     NothingToRestake,
     #[fail(display = "Snowball is busy")]
     SnowballBusy,
+    #[fail(display = "Multi-signature UTXO not found: utxo={}", _0)]
+    MultisigOutputNotFound(Hash),
+    #[fail(display = "Nothing to sign: no multi-signature UTXOs of this account")]
+    NothingToSign,
+    #[fail(
+        display = "Invalid multi-signature threshold: threshold={}, recipients={}",
+        _0, _1
+    )]
+    InvalidMultisigThreshold(u32, usize),
+    #[fail(display = "Duplicate multi-signature recipient: recipient={}", _0)]
+    DuplicateMultisigRecipient(scc::PublicKey),
+    #[fail(display = "Multi-signature UTXO is already being spent: utxo={}", _0)]
+    MultisigOutputPending(Hash),
+    #[fail(
+        display = "Unexpected output in multi-signature transaction: tx={}, utxo={}",
+        _0, _1
+    )]
+    UnexpectedMultisigOutput(Hash, Hash),
+    #[fail(
+        display = "Multi-signature transaction doesn't match: tx={}, expected_amount={}, amount={}, expected_fee={}, fee={}",
+        _0, _1, _2, _3, _4
+    )]
+    MultisigTransactionMismatch(Hash, i64, i64, i64, i64),
+    #[fail(display = "HTLC UTXO not found: utxo={}", _0)]
+    HtlcOutputNotFound(Hash),
+    #[fail(display = "Invalid HTLC preimage: utxo={}", _0)]
+    InvalidHtlcPreimage(Hash),
+    #[fail(display = "Only recipient can claim HTLC: utxo={}", _0)]
+    NotHtlcRecipient(Hash),
+    #[fail(display = "Only refunder can refund HTLC: utxo={}", _0)]
+    NotHtlcRefunder(Hash),
+    #[fail(display = "HTLC deadline has passed: utxo={}, deadline={}", _0, _1)]
+    HtlcExpired(Hash, Timestamp),
+    #[fail(
+        display = "HTLC deadline has not passed yet: utxo={}, deadline={}",
+        _0, _1
+    )]
+    HtlcNotExpired(Hash, Timestamp),
+    #[fail(display = "Transaction is not pending: tx={}", _0)]
+    TransactionNotPending(Hash),
+    #[fail(
+        display = "New fee must exceed the current fee by at least {}: current={}, new={}",
+        _2, _0, _1
+    )]
+    FeeNotIncreased(i64, i64, i64),
+    #[fail(display = "Transaction has no change to pay the fee from: tx={}", _0)]
+    NoChangeToBumpFee(Hash),
+    #[fail(
+        display = "{} tokens of change is not enough to add {} to the fee",
+        _0, _1
+    )]
+    NoEnoughChange(i64, i64),
 }
@@ -65,6 +65,8 @@ use tokio::runtime::TaskExecutor;
 use tokio_timer::{clock, Interval};
 
 const STAKE_FEE: i64 = 0;
+// Sic: synchronize this value with NodeConfig::min_payment_fee.
+const MIN_FEE_INCREMENT: i64 = 1_000;
 const RESEND_TX_INTERVAL: Duration = Duration::from_secs(2 * 60);
 const PENDING_UTXO_TIME: Duration = Duration::from_secs(5 * 60);
 const CHECK_PENDING_UTXO: Duration = Duration::from_secs(10);
@@ -143,6 +145,8 @@ struct UnsealedAccountService {
     network_pkey: pbc::PublicKey,
     /// Lifetime of stake.
     stake_epochs: u64,
+    /// Lifetime of unbonding stake.
+    unbonding_epochs: u64,
     /// Maximum allowed count of input UTXOs (from Node config)
     max_inputs_in_tx: usize,
 
@@ -180,6 +184,8 @@ struct UnsealedAccountService {
     // Response from mempool about transaction.
     //
     transaction_response: Option<oneshot::Receiver<NodeResponse>>,
+    /// (new_tx_hash, old_tx_hash) of the fee bump waiting for `transaction_response`.
+    replacement: Option<(Hash, Hash)>,
 
     //
     // Api subscribers
@@ -208,6 +214,7 @@ impl UnsealedAccountService {
         network: Network,
         node: Node,
         stake_epochs: u64,
+        unbonding_epochs: u64,
         max_inputs_in_tx: usize,
         subscribers: Vec<mpsc::UnboundedSender<AccountNotification>>,
         events: mpsc::UnboundedReceiver<AccountEvent>,
@@ -225,6 +232,7 @@ impl UnsealedAccountService {
         let (database, epoch) = AccountDatabase::open(&database_dir);
         debug!("Opened database: epoch={}", epoch);
         let transaction_response = None;
+        let replacement = None;
         let resend_tx = Interval::new(clock::now(), RESEND_TX_INTERVAL);
         let check_pending_utxos = Interval::new(clock::now(), CHECK_PENDING_UTXO);
         let chain_notifications = ChainSubscription::new(&node, epoch, 0);
@@ -247,6 +255,7 @@ impl UnsealedAccountService {
             check_pending_utxos,
             snowball,
             stake_epochs,
+            unbonding_epochs,
             max_inputs_in_tx,
             last_macro_block_timestamp,
             network,
@@ -255,6 +264,7 @@ impl UnsealedAccountService {
             events,
             chain_notifications,
             transaction_response,
+            replacement,
         }
     }
 
@@ -321,12 +331,122 @@ impl UnsealedAccountService {
         Ok(payment_info.to_info(self.epoch))
     }
 
+    /// Replace a pending transaction by the same transaction with a higher fee.
+    /// The fee difference is taken from the change output.
+    /// The old transaction is marked as replaced once the node accepts the new one.
+    fn bump_fee(&mut self, tx_hash: &Hash, fee: i64) -> Result<TransactionInfo, Error> {
+        let old_tx = self
+            .database
+            .pending_txs()
+            .filter_map(Result::ok)
+            .find(|tx| Hash::digest(&tx.tx) == *tx_hash)
+            .ok_or_else(|| WalletError::TransactionNotPending(*tx_hash))?;
+        // The new transaction has the same inputs and at most the same outputs,
+        // so a higher fee also means a higher fee rate.
+        if fee < old_tx.tx.fee + MIN_FEE_INCREMENT {
+            return Err(WalletError::FeeNotIncreased(old_tx.tx.fee, fee, MIN_FEE_INCREMENT).into());
+        }
+        let delta = fee - old_tx.tx.fee;
+
+        // Find the change.
+        let change_index = old_tx
+            .outputs
+            .iter()
+            .position(|o| match o {
+                OutputValue::Payment(o) => o.is_change,
+                _ => false,
+            })
+            .ok_or_else(|| WalletError::NoChangeToBumpFee(*tx_hash))?;
+        let old_change = match &old_tx.outputs[change_index] {
+            OutputValue::Payment(o) => o.clone(),
+            _ => unreachable!(),
+        };
+        if old_change.amount < delta {
+            return Err(WalletError::NoEnoughChange(old_change.amount, delta).into());
+        }
+
+        // Resolve inputs and restore the sum of outputs gamma.
+        let mut inputs: Vec<Output> = Vec::with_capacity(old_tx.tx.txins.len());
+        let mut inputs_gamma = scc::Fr::zero();
+        for input_hash in &old_tx.tx.txins {
+            let input = self
+                .database
+                .get_unspent(input_hash)?
+                .ok_or_else(|| WalletError::TransactionNotPending(*tx_hash))?
+                .to_output();
+            if let Output::PaymentOutput(o) = &input {
+                inputs_gamma += o
+                    .decrypt_payload(&self.account_pkey, &self.account_skey)?
+                    .gamma;
+            }
+            inputs.push(input);
+        }
+        let mut outputs_gamma = inputs_gamma - old_tx.tx.gamma;
+        outputs_gamma -= old_change
+            .output
+            .decrypt_payload(&self.account_pkey, &self.account_skey)?
+            .gamma;
+
+        // Re-create the change.
+        let mut outputs: Vec<Output> = old_tx
+            .tx
+            .txouts
+            .iter()
+            .filter(|o| Hash::digest(*o) != Hash::digest(&old_change.output))
+            .cloned()
+            .collect();
+        let mut extended_outputs = old_tx.outputs.clone();
+        extended_outputs.remove(change_index);
+        let change = old_change.amount - delta;
+        if change > 0 {
+            let (output, gamma, _rvalue) = PaymentOutput::with_payload(
+                None,
+                &self.account_pkey,
+                change,
+                old_change.data.clone(),
+            )?;
+            info!(
+                "Created change UTXO: hash={}, change={}",
+                Hash::digest(&output),
+                change
+            );
+            let extended_output = PaymentValue {
+                output: output.clone(),
+                amount: change,
+                ..old_change
+            };
+            extended_outputs.push(extended_output.into());
+            outputs.push(output.into());
+            outputs_gamma += gamma;
+        }
+
+        let tx =
+            PaymentTransaction::new(&self.account_skey, &inputs, &outputs, &outputs_gamma, fee)?;
+        let new_tx_hash = Hash::digest(&tx);
+        info!(
+            "Bumped transaction fee: tx={}, new_tx={}, old_fee={}, fee={}",
+            tx_hash, new_tx_hash, old_tx.tx.fee, fee
+        );
+
+        let payment_info = TransactionValue {
+            tx: tx.clone(),
+            status: TransactionStatus::Created {},
+            outputs: extended_outputs,
+        };
+        self.database
+            .push_outgoing(Timestamp::now(), payment_info.clone())?;
+        self.send_transaction(tx.into())?;
+        self.replacement = Some((new_tx_hash, *tx_hash));
+        Ok(payment_info.to_info(self.epoch))
+    }
+
     /// Returns an iterator over available payment outputs.
     fn available_payment_outputs<'a>(&'a self) -> impl Iterator<Item = (PaymentOutput, i64)> + 'a {
         self.database
             .iter_unspent()
             .filter_map(|(k, v)| v.payment().map(|v| (k, v)))
             .filter(move |(h, _)| self.pending_payments.get(h).is_none())
+            .filter(move |(_, v)| !self.is_locked(v.locked_timestamp))
             .inspect(|(h, _)| trace!("Using PaymentOutput: hash={}", h))
             .map(|(_, v)| (v.output, v.amount))
     }
@@ -339,15 +459,57 @@ impl UnsealedAccountService {
             .iter_unspent()
             .filter_map(|(k, v)| v.public_payment().map(|v| (k, v)))
             .filter(move |(h, _)| self.pending_payments.get(h).is_none())
+            .filter(move |(_, v)| !self.is_locked(v.output.locked_timestamp))
             .inspect(|(h, _)| trace!("Using PublicPaymentOutput: hash={}", h))
             .map(|(_, v)| v.output)
     }
 
+    /// Returns true if UTXO with this time-lock can't be spent yet.
+    /// Uses the same clock as validators, so UTXO unlocks with the first macro block after the lock.
+    fn is_locked(&self, locked_timestamp: Option<Timestamp>) -> bool {
+        match locked_timestamp {
+            Some(locked_timestamp) => locked_timestamp > self.last_macro_block_timestamp,
+            None => false,
+        }
+    }
+
     /// Returns an iterator over available stake outputs.
     fn available_stake_outputs<'a>(&'a self) -> impl Iterator<Item = StakeOutput> + 'a {
         self.database
             .iter_unspent()
             .filter_map(|(_k, v)| v.stake())
+            .filter(|v| !v.output.delegated)
+            // All stake unspent utxo should be with info about active epoch.
+            .filter_map(|v| v.active_until_epoch.map(|epoch| (v.output, epoch)))
+            .filter(move |(_v, epoch)| *epoch <= self.epoch)
+            .map(|(o, _e)| o)
+    }
+
+    /// Returns an iterator over own stake outputs, which can be moved into the unbonding state.
+    fn bonded_stake_outputs<'a>(&'a self) -> impl Iterator<Item = StakeOutput> + 'a {
+        self.database
+            .iter_unspent()
+            .filter(move |(k, _v)| self.pending_payments.get(k).is_none())
+            .filter_map(|(_k, v)| v.stake())
+            .filter(|v| !v.output.delegated && !v.output.unbonding)
+            .filter(|v| v.active_until_epoch.is_some())
+            .map(|v| v.output)
+    }
+
+    /// Returns an iterator over available stake outputs delegated to the validator.
+    fn available_delegated_outputs<'a>(
+        &'a self,
+        validator: &'a pbc::PublicKey,
+    ) -> impl Iterator<Item = StakeOutput> + 'a {
+        self.database
+            .iter_unspent()
+            .filter_map(|(_k, v)| v.stake())
+            .filter(move |v| v.output.delegated && &v.output.validator == validator)
+            .filter(move |v| {
+                self.pending_payments
+                    .get(&Hash::digest(&v.output))
+                    .is_none()
+            })
             // All stake unspent utxo should be with info about active epoch.
             .filter_map(|v| v.active_until_epoch.map(|epoch| (v.output, epoch)))
             .filter(move |(_v, epoch)| *epoch <= self.epoch)
@@ -521,7 +683,7 @@ impl UnsealedAccountService {
         amount: i64,
         payment_fee: i64,
         network_pkey: pbc::PublicKey,
-        network_skey: pbc::SecretKey,
+        network_skey: Option<pbc::SecretKey>,
     ) -> Result<TransactionInfo, Error> {
         let payment_balance = self.balance().payment;
         if amount > payment_balance.available {
@@ -537,7 +699,7 @@ impl UnsealedAccountService {
             &self.account_skey,
             &self.account_pkey,
             &network_pkey,
-            &network_skey,
+            network_skey.as_ref(),
             unspent_iter,
             amount,
             payment_fee,
@@ -567,7 +729,7 @@ impl UnsealedAccountService {
         let network_skey_file = self.account_dir.join("network.skey");
         let (network_skey, network_pkey) =
             load_network_keypair(&network_skey_file, &network_pkey_file)?;
-        self.stake_inner(amount, payment_fee, network_pkey, network_skey)
+        self.stake_inner(amount, payment_fee, network_pkey, Some(network_skey))
     }
 
     /// Stake money into the escrow.
@@ -576,10 +738,65 @@ impl UnsealedAccountService {
             amount,
             payment_fee,
             self.network_pkey,
-            self.network_skey.clone(),
+            Some(self.network_skey.clone()),
         )
     }
 
+    /// Delegate money to a third-party validator.
+    fn delegate(
+        &mut self,
+        validator: pbc::PublicKey,
+        amount: i64,
+        payment_fee: i64,
+    ) -> Result<TransactionInfo, Error> {
+        self.stake_inner(amount, payment_fee, validator, None)
+    }
+
+    /// Withdraw expired delegations from a validator.
+    /// NOTE: amount must include PAYMENT_FEE.
+    fn undelegate(
+        &mut self,
+        validator: pbc::PublicKey,
+        amount: i64,
+        payment_fee: i64,
+    ) -> Result<TransactionInfo, Error> {
+        let available: i64 = self
+            .available_delegated_outputs(&validator)
+            .map(|o| o.amount)
+            .sum();
+        if amount > available {
+            let delegated_balance = self.balance().delegated;
+            return Err(WalletError::NoEnoughToStake(delegated_balance.current, available).into());
+        }
+
+        let unspent_iter = self.available_delegated_outputs(&validator);
+        let (tx, outputs) = create_unstaking_transaction(
+            &self.account_skey,
+            &self.account_pkey,
+            &validator,
+            None,
+            unspent_iter,
+            amount,
+            payment_fee,
+            STAKE_FEE,
+            self.max_inputs_in_tx,
+        )?;
+        let payment_info = TransactionValue::new_stake(tx.clone(), outputs);
+        self.database
+            .push_outgoing(Timestamp::now(), payment_info.clone())?;
+
+        let time = clock::now();
+        for input in &tx.txins {
+            assert!(self
+                .pending_payments
+                .insert(*input, PendingOutput { time })
+                .is_none());
+        }
+
+        self.send_transaction(tx.into())?;
+        Ok(payment_info.to_info(self.epoch))
+    }
+
     /// Unstake money from the escrow.
     /// NOTE: amount must include PAYMENT_FEE.
     fn unstake(&mut self, amount: i64, payment_fee: i64) -> Result<TransactionInfo, Error> {
@@ -594,6 +811,25 @@ impl UnsealedAccountService {
 
         let unspent_iter = self.available_stake_outputs();
         let (tx, outputs) = create_unstaking_transaction(
+            &self.account_skey,
+            &self.account_pkey,
+            &self.network_pkey,
+            Some(&self.network_skey),
+            unspent_iter,
+            amount,
+            payment_fee,
+            STAKE_FEE,
+            self.max_inputs_in_tx,
+        )?;
+        let payment_info = TransactionValue::new_stake(tx.clone(), outputs);
+        self.send_transaction(tx.into())?;
+        Ok(payment_info.to_info(self.epoch))
+    }
+
+    /// Move a part of stake into the unbonding state.
+    fn unbond(&mut self, amount: i64, payment_fee: i64) -> Result<TransactionInfo, Error> {
+        let unspent_iter = self.bonded_stake_outputs();
+        let (tx, outputs) = create_unbonding_transaction(
             &self.account_skey,
             &self.account_pkey,
             &self.network_pkey,
@@ -605,6 +841,17 @@ impl UnsealedAccountService {
             self.max_inputs_in_tx,
         )?;
         let payment_info = TransactionValue::new_stake(tx.clone(), outputs);
+        self.database
+            .push_outgoing(Timestamp::now(), payment_info.clone())?;
+
+        let time = clock::now();
+        for input in &tx.txins {
+            assert!(self
+                .pending_payments
+                .insert(*input, PendingOutput { time })
+                .is_none());
+        }
+
         self.send_transaction(tx.into())?;
         Ok(payment_info.to_info(self.epoch))
     }
@@ -721,6 +968,7 @@ impl UnsealedAccountService {
                 data,
                 output: output.clone(),
                 is_change: false,
+                locked_timestamp: None,
             };
             gamma -= output_gamma;
             txouts.push(output.into());
@@ -736,6 +984,8 @@ impl UnsealedAccountService {
             gamma,
             fee,
             sig: scc::SchnorrSig::new(),
+            multisigs: Vec::new(),
+            htlc_preimages: Vec::new(),
         };
 
         //
@@ -745,7 +995,8 @@ impl UnsealedAccountService {
         let sign_skey: scc::SecretKey = sign_skey.into();
         tx.sig = scc::sign_hash(&tx_hash, &sign_skey);
         drop(sign_skey);
-        tx.validate(&txins_expanded).expect("Invalid TX created");
+        tx.validate(&txins_expanded, self.last_macro_block_timestamp)
+            .expect("Invalid TX created");
         info!(
             "Created cloak transaction: tx={}, amount={}, fee={}",
             tx_hash, amount, fee
@@ -771,6 +1022,260 @@ impl UnsealedAccountService {
         Ok(tx_info)
     }
 
+    /// Send money to a new multi-signature UTXO.
+    fn multisig_payment(
+        &mut self,
+        recipients: &[scc::PublicKey],
+        threshold: u32,
+        amount: i64,
+        payment_fee: i64,
+    ) -> Result<TransactionInfo, Error> {
+        if threshold == 0 || threshold as usize > recipients.len() {
+            return Err(WalletError::InvalidMultisigThreshold(threshold, recipients.len()).into());
+        }
+        for (i, recipient) in recipients.iter().enumerate() {
+            if recipients[..i].contains(recipient) {
+                return Err(WalletError::DuplicateMultisigRecipient(*recipient).into());
+            }
+        }
+        let payment_balance = self.balance().payment;
+        if amount > payment_balance.available {
+            return Err(WalletError::NoEnoughToPay(
+                payment_balance.current,
+                payment_balance.available,
+            )
+            .into());
+        }
+
+        let unspent_iter = self.available_payment_outputs();
+        let (inputs, outputs, gamma, extended_outputs, fee) = create_multisig_transaction(
+            &self.account_pkey,
+            recipients,
+            threshold,
+            unspent_iter,
+            amount,
+            payment_fee,
+            self.max_inputs_in_tx,
+        )?;
+
+        let tx = PaymentTransaction::new(&self.account_skey, &inputs, &outputs, &gamma, fee)?;
+        let payment_info = TransactionValue::new_payment(tx.clone(), extended_outputs);
+
+        self.database
+            .push_outgoing(Timestamp::now(), payment_info.clone())?;
+
+        let time = clock::now();
+        for input in &tx.txins {
+            assert!(self
+                .pending_payments
+                .insert(*input, PendingOutput { time })
+                .is_none());
+        }
+
+        let tx: Transaction = tx.into();
+        self.send_transaction(tx.clone())?;
+        metrics::WALLET_CREATEAD_PAYMENTS
+            .with_label_values(&[&String::from(&self.account_pkey)])
+            .inc();
+
+        Ok(payment_info.to_info(self.epoch))
+    }
+
+    /// Create a transaction spending a multi-signature UTXO and sign it.
+    fn multisig_spend(
+        &mut self,
+        output_hash: &Hash,
+        recipient: &scc::PublicKey,
+        amount: i64,
+        payment_fee: i64,
+    ) -> Result<PaymentTransaction, Error> {
+        let value = match self.database.get_unspent(output_hash)? {
+            Some(OutputValue::Multisig(value)) => value,
+            _ => return Err(WalletError::MultisigOutputNotFound(*output_hash).into()),
+        };
+        let mut tx = create_multisig_spending_transaction(
+            &self.account_skey,
+            &value.output,
+            recipient,
+            amount,
+            payment_fee,
+        )?;
+        tx.sign_multisig(output_hash, &self.account_skey);
+        Ok(tx)
+    }
+
+    /// Add our signature to a transaction spending multi-signature UTXOs.
+    /// The transaction must pay `amount` to `recipient` with `fee`, as confirmed by the co-signer.
+    fn multisig_sign(
+        &mut self,
+        mut tx: PaymentTransaction,
+        recipient: &scc::PublicKey,
+        amount: i64,
+        fee: i64,
+    ) -> Result<PaymentTransaction, Error> {
+        let mut inputs = Vec::new();
+        for txin in &tx.txins {
+            if let Some(OutputValue::Multisig(value)) = self.database.get_unspent(txin)? {
+                inputs.push((*txin, value.output));
+            }
+        }
+        if inputs.is_empty() {
+            return Err(WalletError::NothingToSign.into());
+        }
+        let multisig_inputs: Vec<MultisigOutput> =
+            inputs.iter().map(|(_, output)| output.clone()).collect();
+        check_multisig_spending_transaction(&tx, &multisig_inputs, recipient, amount, fee)?;
+        for (txin, _output) in &inputs {
+            tx.sign_multisig(txin, &self.account_skey);
+        }
+        info!(
+            "Signed multisig transaction: tx={}, signatures={}",
+            Hash::digest(&tx),
+            tx.multisigs.len()
+        );
+        Ok(tx)
+    }
+
+    /// Send a transaction spending multi-signature UTXOs.
+    fn multisig_send(&mut self, tx: PaymentTransaction) -> Result<TransactionInfo, Error> {
+        let tx_hash = Hash::digest(&tx);
+        let mut multisig_txins = Vec::new();
+        for txin in &tx.txins {
+            if let Some(OutputValue::Multisig(_)) = self.database.get_unspent(txin)? {
+                if self.pending_payments.contains_key(txin) {
+                    return Err(WalletError::MultisigOutputPending(*txin).into());
+                }
+                multisig_txins.push(*txin);
+            }
+        }
+        let time = clock::now();
+        for txin in multisig_txins {
+            assert!(self
+                .pending_payments
+                .insert(txin, PendingOutput { time })
+                .is_none());
+        }
+        let info = TransactionInfo {
+            tx_hash,
+            fee: tx.fee,
+            outputs: Vec::new(),
+            inputs: tx.txins.clone(),
+            status: TransactionStatus::Created {},
+        };
+        info!("Sending multisig transaction: tx={}", tx_hash);
+        self.send_transaction(tx.into())?;
+        Ok(info)
+    }
+
+    /// Send money to a new hash-time-locked UTXO.
+    fn htlc_payment(
+        &mut self,
+        recipient: &scc::PublicKey,
+        hash_lock: Hash,
+        deadline: Timestamp,
+        amount: i64,
+        payment_fee: i64,
+    ) -> Result<TransactionInfo, Error> {
+        let payment_balance = self.balance().payment;
+        if amount > payment_balance.available {
+            return Err(WalletError::NoEnoughToPay(
+                payment_balance.current,
+                payment_balance.available,
+            )
+            .into());
+        }
+
+        let unspent_iter = self.available_payment_outputs();
+        let (inputs, outputs, gamma, extended_outputs, fee) = create_htlc_transaction(
+            &self.account_pkey,
+            recipient,
+            hash_lock,
+            deadline,
+            unspent_iter,
+            amount,
+            payment_fee,
+            self.max_inputs_in_tx,
+        )?;
+
+        let tx = PaymentTransaction::new(&self.account_skey, &inputs, &outputs, &gamma, fee)?;
+        let payment_info = TransactionValue::new_payment(tx.clone(), extended_outputs);
+
+        self.database
+            .push_outgoing(Timestamp::now(), payment_info.clone())?;
+
+        let time = clock::now();
+        for input in &tx.txins {
+            assert!(self
+                .pending_payments
+                .insert(*input, PendingOutput { time })
+                .is_none());
+        }
+
+        let tx: Transaction = tx.into();
+        self.send_transaction(tx.clone())?;
+        metrics::WALLET_CREATEAD_PAYMENTS
+            .with_label_values(&[&String::from(&self.account_pkey)])
+            .inc();
+
+        Ok(payment_info.to_info(self.epoch))
+    }
+
+    /// Claim a hash-time-locked UTXO with `preimage` or refund it without.
+    fn htlc_spend(
+        &mut self,
+        output_hash: &Hash,
+        preimage: Option<Vec<u8>>,
+        payment_fee: i64,
+    ) -> Result<TransactionInfo, Error> {
+        let value = match self.database.get_unspent(output_hash)? {
+            Some(OutputValue::Htlc(value)) => value,
+            _ => return Err(WalletError::HtlcOutputNotFound(*output_hash).into()),
+        };
+        let output = &value.output;
+        // Deadlines are checked against the last macro block, the same as validators do.
+        // Refunds become possible only after the first macro block past the deadline.
+        match &preimage {
+            Some(preimage) => {
+                if output.recipient != self.account_pkey {
+                    return Err(WalletError::NotHtlcRecipient(*output_hash).into());
+                }
+                if !output.check_preimage(preimage) {
+                    return Err(WalletError::InvalidHtlcPreimage(*output_hash).into());
+                }
+                if self.last_macro_block_timestamp >= output.deadline {
+                    return Err(WalletError::HtlcExpired(*output_hash, output.deadline).into());
+                }
+            }
+            None => {
+                if output.refunder != self.account_pkey {
+                    return Err(WalletError::NotHtlcRefunder(*output_hash).into());
+                }
+                if self.last_macro_block_timestamp < output.deadline {
+                    return Err(WalletError::HtlcNotExpired(*output_hash, output.deadline).into());
+                }
+            }
+        }
+
+        let (tx, extended_output) = create_htlc_spending_transaction(
+            &self.account_skey,
+            &self.account_pkey,
+            output,
+            preimage,
+            payment_fee,
+        )?;
+        let payment_info = TransactionValue::new_payment(tx.clone(), vec![extended_output.into()]);
+
+        self.database
+            .push_outgoing(Timestamp::now(), payment_info.clone())?;
+
+        let time = clock::now();
+        self.pending_payments
+            .insert(*output_hash, PendingOutput { time });
+
+        self.send_transaction(tx.into())?;
+        Ok(payment_info.to_info(self.epoch))
+    }
+
     /// Change the password.
     fn change_password(&mut self, new_password: String) -> Result<(), Error> {
         let account_skey_file = self.account_dir.join("account.skey");
@@ -795,47 +1300,97 @@ impl UnsealedAccountService {
             match val {
                 OutputValue::Payment(PaymentValue {
                     amount,
-                    output: PaymentOutput { .. },
+                    locked_timestamp,
                     ..
                 }) => {
                     balance.payment.current += amount;
+                    if self.is_locked(locked_timestamp) {
+                        balance.payment.locked += amount;
+                        continue;
+                    }
                     if self.pending_payments.get(&hash).is_some() {
                         continue;
                     }
                     balance.payment.available += amount;
                 }
                 OutputValue::PublicPayment(PublicPaymentValue {
-                    output: PublicPaymentOutput { amount, .. },
+                    output:
+                        PublicPaymentOutput {
+                            amount,
+                            locked_timestamp,
+                            ..
+                        },
                     ..
                 }) => {
                     balance.public_payment.current += amount;
+                    if self.is_locked(locked_timestamp) {
+                        balance.public_payment.locked += amount;
+                        continue;
+                    }
                     if self.pending_payments.get(&hash).is_some() {
                         continue;
                     }
                     balance.public_payment.available += amount;
                 }
                 OutputValue::Stake(StakeValue {
-                    output: StakeOutput { amount, .. },
+                    output:
+                        StakeOutput {
+                            amount,
+                            delegated,
+                            unbonding,
+                            ..
+                        },
                     active_until_epoch,
                     ..
                 }) => {
-                    balance.stake.current += amount;
+                    let stake = if delegated {
+                        &mut balance.delegated
+                    } else {
+                        &mut balance.stake
+                    };
+                    stake.current += amount;
                     if self.pending_payments.get(&hash).is_some() {
                         continue;
                     }
                     if let Some(active_until_epoch) = active_until_epoch {
                         if active_until_epoch >= self.epoch + 1 {
+                            if unbonding {
+                                stake.unbonding += amount;
+                            }
                             continue;
                         }
                     }
-                    balance.stake.available += amount;
+                    stake.available += amount;
+                }
+                OutputValue::Multisig(MultisigValue {
+                    output: MultisigOutput { amount, .. },
+                }) => {
+                    balance.multisig.current += amount;
+                    if self.pending_payments.get(&hash).is_some() {
+                        continue;
+                    }
+                    balance.multisig.available += amount;
+                }
+                OutputValue::Htlc(HtlcValue {
+                    output: HtlcOutput { amount, .. },
+                }) => {
+                    balance.htlc.current += amount;
+                    if self.pending_payments.get(&hash).is_some() {
+                        continue;
+                    }
+                    balance.htlc.available += amount;
                 }
             }
         }
-        balance.total.current =
-            balance.payment.current + balance.stake.current + balance.public_payment.current;
-        balance.total.available =
-            balance.payment.available + balance.stake.available + balance.public_payment.available;
+        balance.total.current = balance.payment.current
+            + balance.stake.current
+            + balance.delegated.current
+            + balance.public_payment.current;
+        balance.total.available = balance.payment.available
+            + balance.stake.available
+            + balance.delegated.available
+            + balance.public_payment.available;
+        balance.total.locked = balance.payment.locked + balance.public_payment.locked;
         assert!(balance.total.available <= balance.total.current);
         balance.is_final = !self.current_epoch_balance_changed || !self.pending_payments.is_empty();
         balance
@@ -879,8 +1434,12 @@ impl UnsealedAccountService {
         let hash = Hash::digest(&output);
         match output {
             Output::PaymentOutput(o) => {
-                if let Ok(PaymentPayload { amount, data, .. }) =
-                    o.decrypt_payload(&self.account_pkey, &self.account_skey)
+                if let Ok(PaymentPayload {
+                    amount,
+                    data,
+                    locked_timestamp,
+                    ..
+                }) = o.decrypt_payload(&self.account_pkey, &self.account_skey)
                 {
                     assert!(amount >= 0);
                     info!(
@@ -895,6 +1454,7 @@ impl UnsealedAccountService {
                         data: data.clone(),
                         rvalue: None,
                         is_change: false,
+                        locked_timestamp,
                     };
 
                     if let Err(e) = self
@@ -948,7 +1508,11 @@ impl UnsealedAccountService {
                 if &o.recipient != &self.account_pkey {
                     return;
                 }
-                let active_until_epoch = epoch + self.stake_epochs;
+                let active_until_epoch = if o.unbonding {
+                    epoch + self.unbonding_epochs
+                } else {
+                    epoch + self.stake_epochs
+                };
                 info!(
                     "Staked money to escrow: hash={}, amount={}, active_until_epoch={}",
                     hash, o.amount, active_until_epoch
@@ -970,6 +1534,64 @@ impl UnsealedAccountService {
                     .expect("Cannot write to database.");
                 self.notify(AccountNotification::Staked(info));
             }
+            Output::MultisigOutput(o) => {
+                if !o.recipients.contains(&self.account_pkey) {
+                    return;
+                }
+                info!(
+                    "Received multisig payment: utxo={}, amount={}, threshold={}",
+                    hash, o.amount, o.threshold
+                );
+                self.current_epoch_balance_changed = true;
+                let value = MultisigValue { output: o.clone() };
+
+                if let Err(e) = self
+                    .database
+                    .push_incomming(block_timestamp, value.clone().into())
+                {
+                    error!("Error when adding incomming tx = {}", e)
+                }
+
+                let info = value.to_info(None);
+                let missing = self
+                    .database
+                    .get_unspent(&hash)
+                    .expect("Cannot read database");
+                assert!(missing.is_none());
+                self.database
+                    .insert_unspent(value.into())
+                    .expect("Cannot write to database.");
+                self.notify(AccountNotification::ReceivedMultisig(info));
+            }
+            Output::HtlcOutput(o) => {
+                if &o.recipient != &self.account_pkey && &o.refunder != &self.account_pkey {
+                    return;
+                }
+                info!(
+                    "Received HTLC payment: utxo={}, amount={}, deadline={}",
+                    hash, o.amount, o.deadline
+                );
+                self.current_epoch_balance_changed = true;
+                let value = HtlcValue { output: o.clone() };
+
+                if let Err(e) = self
+                    .database
+                    .push_incomming(block_timestamp, value.clone().into())
+                {
+                    error!("Error when adding incomming tx = {}", e)
+                }
+
+                let info = value.to_info(None);
+                let missing = self
+                    .database
+                    .get_unspent(&hash)
+                    .expect("Cannot read database");
+                assert!(missing.is_none());
+                self.database
+                    .insert_unspent(value.into())
+                    .expect("Cannot write to database.");
+                self.notify(AccountNotification::ReceivedHtlc(info));
+            }
         };
     }
 
@@ -1044,6 +1666,29 @@ impl UnsealedAccountService {
                     _ => panic!("Inconsistent account state"),
                 }
             }
+            OutputValue::Multisig(m) => {
+                let o = &m.output;
+                assert!(o.recipients.contains(&self.account_pkey), "is my utxo");
+                info!("Spent multisig payment: utxo={}, amount={}", hash, o.amount);
+                self.database
+                    .remove_unspent(&hash)
+                    .expect("Cannot write database");
+                let info = m.to_info(self.pending_payments.get(&hash));
+                self.notify(AccountNotification::SpentMultisig(info));
+            }
+            OutputValue::Htlc(h) => {
+                let o = &h.output;
+                assert!(
+                    o.recipient == self.account_pkey || o.refunder == self.account_pkey,
+                    "is my utxo"
+                );
+                info!("Spent HTLC payment: utxo={}, amount={}", hash, o.amount);
+                self.database
+                    .remove_unspent(&hash)
+                    .expect("Cannot write database");
+                let info = h.to_info(self.pending_payments.get(&hash));
+                self.notify(AccountNotification::SpentHtlc(info));
+            }
         }
     }
 
@@ -1076,7 +1721,13 @@ impl UnsealedAccountService {
         if let Some((ref mut snowball, _)) = &mut self.snowball {
             snowball.change_facilitator(self.facilitator_pkey.clone());
         }
+        // Some time-locked outputs may become available.
+        let saved_balance = self.balance();
         self.last_macro_block_timestamp = last_macro_block_timestamp;
+        let balance = self.balance();
+        if saved_balance != balance {
+            self.notify_balance_changed(balance);
+        }
         let updated_statuses = self
             .database
             .finalize_epoch(epoch)
@@ -1258,6 +1909,20 @@ impl From<Result<TransactionInfo, Error>> for AccountResponse {
     }
 }
 
+impl From<Result<PaymentTransaction, Error>> for AccountResponse {
+    fn from(r: Result<PaymentTransaction, Error>) -> Self {
+        match r {
+            Ok(tx) => AccountResponse::MultisigTransaction {
+                tx_hash: Hash::digest(&tx),
+                tx,
+            },
+            Err(e) => AccountResponse::Error {
+                error: format!("{}", e),
+            },
+        }
+    }
+}
+
 impl From<Vec<LogEntryInfo>> for AccountResponse {
     fn from(log: Vec<LogEntryInfo>) -> Self {
         AccountResponse::HistoryInfo { log }
@@ -1295,10 +1960,21 @@ impl Future for UnsealedAccountService {
         if let Some(mut transaction_response) = self.transaction_response.take() {
             match transaction_response.poll().expect("connected") {
                 Async::Ready(response) => {
+                    let replacement = self.replacement.take();
                     match response {
                         NodeResponse::AddTransaction { hash, status } => {
                             // Recover state.
                             self.on_tx_status(&hash, &status);
+                            // The fee bump has been accepted by mempool.
+                            if let Some((new_tx_hash, old_tx_hash)) = replacement {
+                                if let TransactionStatus::Accepted {} = status {
+                                    assert_eq!(new_tx_hash, hash);
+                                    let status = TransactionStatus::Replaced {
+                                        replaced_by: new_tx_hash,
+                                    };
+                                    self.on_tx_status(&old_tx_hash, &status);
+                                }
+                            }
                         }
                         NodeResponse::Error { error } => {
                             error!("Failed to get transaction status: {:?}", error);
@@ -1407,6 +2083,9 @@ impl Future for UnsealedAccountService {
                                 amount,
                                 payment_fee,
                             } => self.public_payment(&recipient, amount, payment_fee).into(),
+                            AccountRequest::BumpFee { tx_hash, fee } => {
+                                self.bump_fee(&tx_hash, fee).into()
+                            }
                             AccountRequest::StakeAll { payment_fee } => {
                                 self.stake_all(payment_fee).into()
                             }
@@ -1425,10 +2104,67 @@ impl Future for UnsealedAccountService {
                             AccountRequest::UnstakeAll { payment_fee } => {
                                 self.unstake_all(payment_fee).into()
                             }
+                            AccountRequest::Unbond {
+                                amount,
+                                payment_fee,
+                            } => self.unbond(amount, payment_fee).into(),
+                            AccountRequest::Delegate {
+                                validator,
+                                amount,
+                                payment_fee,
+                            } => self.delegate(validator, amount, payment_fee).into(),
+                            AccountRequest::Undelegate {
+                                validator,
+                                amount,
+                                payment_fee,
+                            } => self.undelegate(validator, amount, payment_fee).into(),
                             AccountRequest::RestakeAll {} => self.restake_all().into(),
                             AccountRequest::CloakAll { payment_fee } => {
                                 self.cloak_all(payment_fee).into()
                             }
+                            AccountRequest::MultisigPayment {
+                                recipients,
+                                threshold,
+                                amount,
+                                payment_fee,
+                            } => self
+                                .multisig_payment(&recipients, threshold, amount, payment_fee)
+                                .into(),
+                            AccountRequest::MultisigSpend {
+                                output_hash,
+                                recipient,
+                                amount,
+                                payment_fee,
+                            } => self
+                                .multisig_spend(&output_hash, &recipient, amount, payment_fee)
+                                .into(),
+                            AccountRequest::MultisigSign {
+                                tx,
+                                recipient,
+                                amount,
+                                fee,
+                            } => self.multisig_sign(tx, &recipient, amount, fee).into(),
+                            AccountRequest::MultisigSend { tx } => self.multisig_send(tx).into(),
+                            AccountRequest::HtlcPayment {
+                                recipient,
+                                hash_lock,
+                                deadline,
+                                amount,
+                                payment_fee,
+                            } => self
+                                .htlc_payment(&recipient, hash_lock, deadline, amount, payment_fee)
+                                .into(),
+                            AccountRequest::HtlcClaim {
+                                output_hash,
+                                preimage,
+                                payment_fee,
+                            } => self
+                                .htlc_spend(&output_hash, Some(preimage), payment_fee)
+                                .into(),
+                            AccountRequest::HtlcRefund {
+                                output_hash,
+                                payment_fee,
+                            } => self.htlc_spend(&output_hash, None, payment_fee).into(),
                             AccountRequest::AccountInfo {} => {
                                 let account_info = AccountInfo {
                                     account_pkey: self.account_pkey.clone(),
@@ -1444,6 +2180,8 @@ impl Future for UnsealedAccountService {
                                 let mut public_payments = Vec::new();
                                 let mut stakes = Vec::new();
                                 let mut payments = Vec::new();
+                                let mut multisigs = Vec::new();
+                                let mut htlcs = Vec::new();
                                 for utxo in self.database.iter_unspent() {
                                     match utxo.1 {
                                         OutputValue::Stake(s) => stakes.push(s.to_info(self.epoch)),
@@ -1451,12 +2189,18 @@ impl Future for UnsealedAccountService {
                                             .push(p.to_info(self.pending_payments.get(&utxo.0))),
                                         OutputValue::PublicPayment(p) => public_payments
                                             .push(p.to_info(self.pending_payments.get(&utxo.0))),
+                                        OutputValue::Multisig(m) => multisigs
+                                            .push(m.to_info(self.pending_payments.get(&utxo.0))),
+                                        OutputValue::Htlc(h) => htlcs
+                                            .push(h.to_info(self.pending_payments.get(&utxo.0))),
                                     }
                                 }
                                 AccountResponse::UnspentInfo {
                                     public_payments,
                                     payments,
                                     stakes,
+                                    multisigs,
+                                    htlcs,
                                 }
                             }
                             AccountRequest::HistoryInfo {
@@ -1665,6 +2409,8 @@ struct SealedAccountService {
     network_pkey: pbc::PublicKey,
     /// Lifetime of stake.
     stake_epochs: u64,
+    /// Lifetime of unbonding stake.
+    unbonding_epochs: u64,
     /// Maximum allowed count of input UTXOs
     max_inputs_in_tx: usize,
 
@@ -1691,6 +2437,7 @@ impl SealedAccountService {
         network: Network,
         node: Node,
         stake_epochs: u64,
+        unbonding_epochs: u64,
         max_inputs_in_tx: usize,
         subscribers: Vec<mpsc::UnboundedSender<AccountNotification>>,
         events: mpsc::UnboundedReceiver<AccountEvent>,
@@ -1702,6 +2449,7 @@ impl SealedAccountService {
             network_skey,
             network_pkey,
             stake_epochs,
+            unbonding_epochs,
             max_inputs_in_tx,
             node,
             network,
@@ -1810,6 +2558,7 @@ impl Future for AccountService {
                         sealed.network,
                         sealed.node,
                         sealed.stake_epochs,
+                        sealed.unbonding_epochs,
                         sealed.max_inputs_in_tx,
                         sealed.subscribers,
                         sealed.events,
@@ -1849,6 +2598,7 @@ impl Future for AccountService {
                         unsealed.network,
                         unsealed.node,
                         unsealed.stake_epochs,
+                        unsealed.unbonding_epochs,
                         unsealed.max_inputs_in_tx,
                         unsealed.subscribers,
                         unsealed.events,
@@ -1873,6 +2623,7 @@ impl AccountService {
         network: Network,
         node: Node,
         stake_epochs: u64,
+        unbonding_epochs: u64,
         max_inputs_in_tx: usize,
     ) -> Result<(Self, Account), KeyError> {
         let account_pkey_file = account_dir.join("account.pkey");
@@ -1888,6 +2639,7 @@ impl AccountService {
             network,
             node,
             stake_epochs,
+            unbonding_epochs,
             max_inputs_in_tx,
             subscribers,
             events,
@@ -1949,6 +2701,7 @@ pub struct WalletService {
     node: Node,
     executor: TaskExecutor,
     stake_epochs: u64,
+    unbonding_epochs: u64,
     max_inputs_in_tx: usize,
     accounts: HashMap<AccountId, AccountHandle>,
     subscribers: Vec<mpsc::UnboundedSender<WalletNotification>>,
@@ -1966,6 +2719,7 @@ impl WalletService {
         node: Node,
         executor: TaskExecutor,
         stake_epochs: u64,
+        unbonding_epochs: u64,
         max_inputs_in_tx: usize,
         last_epoch: u64,
     ) -> Result<(Self, Wallet), Error> {
@@ -1980,6 +2734,7 @@ impl WalletService {
             node,
             executor,
             stake_epochs,
+            unbonding_epochs,
             max_inputs_in_tx,
             accounts: HashMap::new(),
             subscribers,
@@ -2061,6 +2816,7 @@ impl WalletService {
             self.network.clone(),
             self.node.clone(),
             self.stake_epochs,
+            self.unbonding_epochs,
             self.max_inputs_in_tx,
         )?;
         let account_notifications = account.subscribe();
@@ -31,10 +31,12 @@ use stegos_blockchain::protos::*;
 use stegos_crypto::protos::*;
 include!(concat!(env!("OUT_DIR"), "/protos/mod.rs"));
 use super::storage::{LogEntry, OutputValue, PaymentValue, TransactionValue};
-use crate::storage::{PublicPaymentValue, StakeValue};
+use crate::storage::{HtlcValue, MultisigValue, PublicPaymentValue, StakeValue};
 use stegos_blockchain::{
-    PaymentOutput, PaymentPayloadData, PaymentTransaction, PublicPaymentOutput, StakeOutput,
+    HtlcOutput, MultisigOutput, PaymentOutput, PaymentPayloadData, PaymentTransaction,
+    PublicPaymentOutput, StakeOutput,
 };
+use stegos_crypto::hash::Hash;
 use stegos_crypto::scc::{Fr, PublicKey};
 use stegos_node::TransactionStatus;
 
@@ -91,6 +93,9 @@ impl ProtoConvert for PaymentValue {
             msg.set_rvalue(rvalue.into_proto());
         }
         msg.set_is_change(self.is_change);
+        if let Some(locked_timestamp) = self.locked_timestamp {
+            msg.set_locked_timestamp(locked_timestamp.into());
+        }
         msg
     }
 
@@ -105,6 +110,10 @@ impl ProtoConvert for PaymentValue {
             None
         };
         let recipient = PublicKey::from_proto(proto.get_recipient())?;
+        let locked_timestamp = match proto.get_locked_timestamp() {
+            0 => None,
+            t => Some(t.into()),
+        };
         let value = PaymentValue {
             output,
             amount,
@@ -112,6 +121,7 @@ impl ProtoConvert for PaymentValue {
             is_change,
             rvalue,
             recipient,
+            locked_timestamp,
         };
 
         Ok(value)
@@ -158,6 +168,38 @@ impl ProtoConvert for StakeValue {
     }
 }
 
+impl ProtoConvert for MultisigValue {
+    type Proto = account_log::MultisigValue;
+    fn into_proto(&self) -> Self::Proto {
+        let mut msg = account_log::MultisigValue::new();
+        msg.set_output(self.output.into_proto());
+        msg
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let output = MultisigOutput::from_proto(proto.get_output())?;
+        let value = MultisigValue { output };
+
+        Ok(value)
+    }
+}
+
+impl ProtoConvert for HtlcValue {
+    type Proto = account_log::HtlcValue;
+    fn into_proto(&self) -> Self::Proto {
+        let mut msg = account_log::HtlcValue::new();
+        msg.set_output(self.output.into_proto());
+        msg
+    }
+
+    fn from_proto(proto: &Self::Proto) -> Result<Self, Error> {
+        let output = HtlcOutput::from_proto(proto.get_output())?;
+        let value = HtlcValue { output };
+
+        Ok(value)
+    }
+}
+
 impl ProtoConvert for OutputValue {
     type Proto = account_log::OutputValue;
     fn into_proto(&self) -> Self::Proto {
@@ -166,6 +208,8 @@ impl ProtoConvert for OutputValue {
             OutputValue::Payment(p) => msg.set_payment(p.into_proto()),
             OutputValue::PublicPayment(p) => msg.set_public_payment(p.into_proto()),
             OutputValue::Stake(s) => msg.set_stake(s.into_proto()),
+            OutputValue::Multisig(m) => msg.set_multisig(m.into_proto()),
+            OutputValue::Htlc(h) => msg.set_htlc(h.into_proto()),
         }
         msg
     }
@@ -184,6 +228,14 @@ impl ProtoConvert for OutputValue {
                 let output = PublicPaymentValue::from_proto(msg)?;
                 output.into()
             }
+            Some(account_log::OutputValue_oneof_enum_value::multisig(ref msg)) => {
+                let output = MultisigValue::from_proto(msg)?;
+                output.into()
+            }
+            Some(account_log::OutputValue_oneof_enum_value::htlc(ref msg)) => {
+                let output = HtlcValue::from_proto(msg)?;
+                output.into()
+            }
             None => {
                 return Err(ProtoError::MissingField(
                     "enum_value".to_string(),
@@ -237,6 +289,9 @@ impl ProtoConvert for TransactionValue {
                 epoch_with_offset.set_offset(offset);
                 status.set_rollback(epoch_with_offset);
             }
+            TransactionStatus::Replaced { ref replaced_by } => {
+                status.set_replaced(replaced_by.into_proto());
+            }
         }
         msg.set_status(status);
         msg
@@ -282,6 +337,10 @@ impl ProtoConvert for TransactionValue {
                 };
                 TransactionStatus::Conflicted { epoch, offset }
             }
+            Some(account_log::TransactionStatus_oneof_enum_value::replaced(ref msg)) => {
+                let replaced_by = Hash::from_proto(msg)?;
+                TransactionStatus::Replaced { replaced_by }
+            }
             None => {
                 return Err(ProtoError::MissingField(
                     "enum_value".to_string(),
@@ -372,5 +431,14 @@ mod tests {
             },
         };
         roundtrip(&request);
+
+        let request = TransactionValue {
+            tx: tx.clone(),
+            outputs: vec![],
+            status: TransactionStatus::Replaced {
+                replaced_by: Hash::digest("replacement"),
+            },
+        };
+        roundtrip(&request);
     }
 }
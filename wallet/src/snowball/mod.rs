@@ -101,6 +101,7 @@ use std::mem;
 use std::time::Duration;
 use stegos_blockchain::Output;
 use stegos_blockchain::PaymentTransaction;
+use stegos_blockchain::Timestamp;
 use stegos_blockchain::{PaymentOutput, PaymentPayloadData};
 use stegos_crypto::bulletproofs::{simple_commit, validate_range_proof};
 use stegos_crypto::dicemix::*;
@@ -1789,8 +1790,10 @@ impl Snowball {
         // Check that super-transaction signature validates
         // against transaction contents, just like a validator would do.
 
+        // Time-locks are checked against the local time here,
+        // validators will re-check them against the last macro block.
         let inputs = self.collect_txin_outputs(&self.trans.txins);
-        match self.trans.validate(&inputs) {
+        match self.trans.validate(&inputs, Timestamp::now()) {
             Ok(_) => true,
             Err(err) => {
                 sdebug!(self, "Validation error: {:?}", err);
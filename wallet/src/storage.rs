@@ -33,8 +33,8 @@ use std::mem;
 use std::path::Path;
 use std::time::{Duration, Instant};
 use stegos_blockchain::{
-    Output, PaymentOutput, PaymentPayloadData, PaymentTransaction, PublicPaymentOutput,
-    RestakeTransaction, StakeOutput, Timestamp,
+    HtlcOutput, MultisigOutput, Output, PaymentOutput, PaymentPayloadData, PaymentTransaction,
+    PublicPaymentOutput, RestakeTransaction, StakeOutput, Timestamp,
 };
 use stegos_crypto::hash::{Hash, Hashable, Hasher};
 use stegos_crypto::scc::{Fr, PublicKey};
@@ -722,6 +722,8 @@ pub enum OutputValue {
     Payment(PaymentValue),
     PublicPayment(PublicPaymentValue),
     Stake(StakeValue),
+    Multisig(MultisigValue),
+    Htlc(HtlcValue),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -733,6 +735,8 @@ pub struct PaymentValue {
     pub data: PaymentPayloadData,
     pub rvalue: Option<Fr>,
     pub is_change: bool,
+    /// Private time-lock from the encrypted payload.
+    pub locked_timestamp: Option<Timestamp>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -750,6 +754,16 @@ pub struct StakeValue {
     pub active_until_epoch: Option<u64>,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MultisigValue {
+    pub output: MultisigOutput,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HtlcValue {
+    pub output: HtlcOutput,
+}
+
 impl TransactionValue {
     pub fn new_payment(tx: PaymentTransaction, outputs: Vec<OutputValue>) -> TransactionValue {
         assert!(tx.txouts.len() <= 2);
@@ -841,6 +855,20 @@ impl OutputValue {
             _ => None,
         }
     }
+
+    pub fn multisig(self) -> Option<MultisigValue> {
+        match self {
+            OutputValue::Multisig(m) => Some(m),
+            _ => None,
+        }
+    }
+
+    pub fn htlc(self) -> Option<HtlcValue> {
+        match self {
+            OutputValue::Htlc(h) => Some(h),
+            _ => None,
+        }
+    }
 }
 
 //
@@ -918,6 +946,35 @@ impl StakeValue {
             amount: self.output.amount,
             active_until_epoch: self.active_until_epoch,
             is_active,
+            delegated: self.output.delegated,
+        }
+    }
+}
+
+impl MultisigValue {
+    pub fn to_info(&self, pending: Option<&PendingOutput>) -> MultisigInfo {
+        let pending_timestamp = pending_timestamp(pending);
+        MultisigInfo {
+            output_hash: Hash::digest(&self.output),
+            amount: self.output.amount,
+            pending_timestamp,
+            recipients: self.output.recipients.clone(),
+            threshold: self.output.threshold,
+        }
+    }
+}
+
+impl HtlcValue {
+    pub fn to_info(&self, pending: Option<&PendingOutput>) -> HtlcInfo {
+        let pending_timestamp = pending_timestamp(pending);
+        HtlcInfo {
+            output_hash: Hash::digest(&self.output),
+            amount: self.output.amount,
+            pending_timestamp,
+            recipient: self.output.recipient,
+            refunder: self.output.refunder,
+            hash_lock: self.output.hash_lock,
+            deadline: self.output.deadline,
         }
     }
 }
@@ -928,6 +985,8 @@ impl OutputValue {
             OutputValue::Payment(o) => o.to_info(None).into(),
             OutputValue::PublicPayment(o) => o.to_info(None).into(),
             OutputValue::Stake(o) => o.to_info(epoch).into(),
+            OutputValue::Multisig(o) => o.to_info(None).into(),
+            OutputValue::Htlc(o) => o.to_info(None).into(),
         }
     }
 
@@ -936,6 +995,8 @@ impl OutputValue {
             OutputValue::Payment(o) => o.output.clone().into(),
             OutputValue::PublicPayment(o) => o.output.clone().into(),
             OutputValue::Stake(o) => o.output.clone().into(),
+            OutputValue::Multisig(o) => o.output.clone().into(),
+            OutputValue::Htlc(o) => o.output.clone().into(),
         }
     }
 }
@@ -962,6 +1023,18 @@ impl From<StakeValue> for OutputValue {
     }
 }
 
+impl From<MultisigValue> for OutputValue {
+    fn from(value: MultisigValue) -> OutputValue {
+        OutputValue::Multisig(value)
+    }
+}
+
+impl From<HtlcValue> for OutputValue {
+    fn from(value: HtlcValue) -> OutputValue {
+        OutputValue::Htlc(value)
+    }
+}
+
 //
 // Hashable implementations
 //
@@ -982,6 +1055,8 @@ impl Hashable for OutputValue {
             OutputValue::Payment(v) => v.hash(hasher),
             OutputValue::PublicPayment(v) => v.hash(hasher),
             OutputValue::Stake(v) => v.hash(hasher),
+            OutputValue::Multisig(v) => v.hash(hasher),
+            OutputValue::Htlc(v) => v.hash(hasher),
         }
     }
 }
@@ -994,6 +1069,9 @@ impl Hashable for PaymentValue {
         self.data.hash(hasher);
         self.is_change.hash(hasher);
         self.rvalue.hash(hasher);
+        if let Some(locked_timestamp) = &self.locked_timestamp {
+            locked_timestamp.hash(hasher);
+        }
     }
 }
 
@@ -1010,6 +1088,18 @@ impl Hashable for StakeValue {
     }
 }
 
+impl Hashable for MultisigValue {
+    fn hash(&self, hasher: &mut Hasher) {
+        self.output.hash(hasher);
+    }
+}
+
+impl Hashable for HtlcValue {
+    fn hash(&self, hasher: &mut Hasher) {
+        self.output.hash(hasher);
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -1049,6 +1139,7 @@ mod test {
             serno: id as i64,
             amount: 10,
             recipient: PublicKey::zero(),
+            locked_timestamp: None,
         };
         let value = PublicPaymentValue { output };
         OutputValue::PublicPayment(value)
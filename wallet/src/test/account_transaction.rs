@@ -131,6 +131,110 @@ fn create_tx() {
     });
 }
 
+#[test]
+fn bump_fee() {
+    Sandbox::start(Default::default(), |mut s| {
+        let mut accounts = genesis_accounts(&mut s);
+
+        s.poll();
+        let recipient = accounts[1].account_service.account_pkey;
+
+        let mut notification = accounts[0].account.subscribe();
+        let rx = accounts[0].account.request(AccountRequest::Payment {
+            recipient,
+            amount: 10,
+            payment_fee: PAYMENT_FEE,
+            comment: "Test".to_string(),
+            with_certificate: false,
+        });
+        accounts[0].poll();
+        let (old_tx, old_fee) = match get_request(rx) {
+            AccountResponse::TransactionCreated(tx) => (tx.tx_hash, tx.fee),
+            _ => panic!("Wrong respnse to payment request"),
+        };
+
+        s.filter_unicast(&[stegos_node::CHAIN_LOADER_TOPIC]);
+        s.poll();
+        s.broadcast(stegos_node::TX_TOPIC);
+        accounts[0].poll();
+        match get_notification(&mut notification) {
+            AccountNotification::TransactionStatus {
+                tx_hash,
+                status: TransactionStatus::Accepted {},
+            } => assert_eq!(tx_hash, old_tx),
+            _ => unreachable!(),
+        }
+
+        // The fee must be increased.
+        let rx = accounts[0].account.request(AccountRequest::BumpFee {
+            tx_hash: old_tx,
+            fee: old_fee,
+        });
+        accounts[0].poll();
+        match get_request(rx) {
+            AccountResponse::Error { .. } => {}
+            _ => panic!("Wrong respnse to bump fee request"),
+        }
+
+        // The fee must be increased by at least the minimal payment fee.
+        let rx = accounts[0].account.request(AccountRequest::BumpFee {
+            tx_hash: old_tx,
+            fee: old_fee + PAYMENT_FEE - 1,
+        });
+        accounts[0].poll();
+        match get_request(rx) {
+            AccountResponse::Error { .. } => {}
+            _ => panic!("Wrong respnse to bump fee request"),
+        }
+
+        let fee = old_fee + PAYMENT_FEE;
+        let rx = accounts[0].account.request(AccountRequest::BumpFee {
+            tx_hash: old_tx,
+            fee,
+        });
+        accounts[0].poll();
+        let new_tx = match get_request(rx) {
+            AccountResponse::TransactionCreated(tx) => {
+                assert_eq!(tx.fee, fee);
+                tx.tx_hash
+            }
+            _ => panic!("Wrong respnse to bump fee request"),
+        };
+
+        // The old transaction is replaced only after the node accepts the new one.
+        s.poll();
+        s.broadcast(stegos_node::TX_TOPIC);
+        accounts[0].poll();
+        match get_notification(&mut notification) {
+            AccountNotification::TransactionStatus {
+                tx_hash,
+                status: TransactionStatus::Accepted {},
+            } => assert_eq!(tx_hash, new_tx),
+            _ => unreachable!(),
+        }
+        match get_notification(&mut notification) {
+            AccountNotification::TransactionStatus {
+                tx_hash,
+                status: TransactionStatus::Replaced { replaced_by },
+            } => {
+                assert_eq!(tx_hash, old_tx);
+                assert_eq!(replaced_by, new_tx);
+            }
+            _ => unreachable!(),
+        }
+        s.skip_micro_block();
+
+        accounts[0].poll();
+        match get_notification(&mut notification) {
+            AccountNotification::TransactionStatus {
+                tx_hash,
+                status: TransactionStatus::Prepared { .. },
+            } => assert_eq!(tx_hash, new_tx),
+            _ => unreachable!(),
+        }
+    });
+}
+
 fn unwrap_payment(output: OutputInfo) -> PaymentInfo {
     match output {
         OutputInfo::Payment(p) => p,
@@ -799,6 +903,8 @@ fn precondition_each_account_has_tokens(
                     Balance {
                         current: balance,
                         available: _,
+                        locked: _,
+                        unbonding: _,
                     },
                 ..
             }) => {
@@ -61,6 +61,7 @@ struct AccountSandbox {
 impl AccountSandbox {
     pub fn new(
         stake_epochs: u64,
+        unbonding_epochs: u64,
         max_inputs_in_tx: usize,
         keys: KeyChain,
         node: &mut NodeSandbox,
@@ -97,6 +98,7 @@ impl AccountSandbox {
             network,
             node.node.clone(),
             stake_epochs,
+            unbonding_epochs,
             max_inputs_in_tx,
             subscribers,
             events,
@@ -122,6 +124,7 @@ impl AccountSandbox {
 
     pub fn new_genesis(s: &mut Sandbox, node_id: usize, path: Option<TempDir>) -> AccountSandbox {
         let stake_epochs = s.config.chain.stake_epochs;
+        let unbonding_epochs = s.config.chain.unbonding_epochs;
         let max_inputs_in_tx = s.config.node.max_inputs_in_tx;
         let keys = s.keychains[node_id].clone();
         // genesis accounts should reuse the same network.
@@ -131,6 +134,7 @@ impl AccountSandbox {
         let node = &mut s.nodes[node_id];
         Self::new(
             stake_epochs,
+            unbonding_epochs,
             max_inputs_in_tx,
             keys,
             node,
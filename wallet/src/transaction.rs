@@ -24,7 +24,9 @@
 use crate::change::*;
 use crate::error::*;
 use crate::snowball::ProposedUTXO;
-use crate::storage::{OutputValue, PaymentValue, PublicPaymentValue, StakeValue};
+use crate::storage::{
+    HtlcValue, MultisigValue, OutputValue, PaymentValue, PublicPaymentValue, StakeValue,
+};
 use failure::Error;
 use log::*;
 use serde_derive::Serialize;
@@ -239,6 +241,7 @@ where
                 amount,
                 data: data.into(),
                 is_change: false,
+                locked_timestamp: None,
             };
 
             (output1.into(), gamma1, extended_output.into())
@@ -286,6 +289,7 @@ where
             amount: change,
             data: data.into(),
             is_change: true,
+            locked_timestamp: None,
         };
         extended_outputs.push(extended_output.into());
         outputs.push(output2.into());
@@ -305,12 +309,349 @@ where
     Ok((inputs, outputs, gamma, extended_outputs, fee))
 }
 
+/// Create a new payment transaction to a multi-signature UTXO.
+pub(crate) fn create_multisig_transaction<'a, UnspentIter>(
+    sender_pkey: &PublicKey,
+    recipients: &[PublicKey],
+    threshold: u32,
+    unspent_iter: UnspentIter,
+    amount: i64,
+    payment_fee: i64,
+    max_inputs_in_tx: usize,
+) -> Result<(Vec<Output>, Vec<Output>, Fr, Vec<OutputValue>, i64), Error>
+where
+    UnspentIter: Iterator<Item = (PaymentOutput, i64)>,
+{
+    if amount < 0 {
+        return Err(WalletError::NegativeAmount(amount).into());
+    }
+
+    trace!("Creating multisig UTXO...");
+    let output1 = MultisigOutput::new(recipients, threshold, amount);
+    output1.validate()?;
+    info!(
+        "Created multisig UTXO: hash={}, recipients={:?}, threshold={}, amount={}",
+        Hash::digest(&output1),
+        recipients,
+        threshold,
+        amount
+    );
+    let extended_output = MultisigValue {
+        output: output1.clone(),
+    };
+
+    create_uncloaked_transaction(
+        sender_pkey,
+        output1.into(),
+        extended_output.into(),
+        unspent_iter,
+        amount,
+        payment_fee,
+        max_inputs_in_tx,
+    )
+}
+
+/// Create a new payment transaction to a hash-time-locked UTXO.
+pub(crate) fn create_htlc_transaction<'a, UnspentIter>(
+    sender_pkey: &PublicKey,
+    recipient: &PublicKey,
+    hash_lock: Hash,
+    deadline: Timestamp,
+    unspent_iter: UnspentIter,
+    amount: i64,
+    payment_fee: i64,
+    max_inputs_in_tx: usize,
+) -> Result<(Vec<Output>, Vec<Output>, Fr, Vec<OutputValue>, i64), Error>
+where
+    UnspentIter: Iterator<Item = (PaymentOutput, i64)>,
+{
+    if amount < 0 {
+        return Err(WalletError::NegativeAmount(amount).into());
+    }
+
+    trace!("Creating HTLC UTXO...");
+    let output1 = HtlcOutput::new(recipient, sender_pkey, hash_lock, deadline, amount);
+    output1.validate()?;
+    info!(
+        "Created HTLC UTXO: hash={}, recipient={}, hash_lock={}, deadline={}, amount={}",
+        Hash::digest(&output1),
+        recipient,
+        hash_lock,
+        deadline,
+        amount
+    );
+    let extended_output = HtlcValue {
+        output: output1.clone(),
+    };
+
+    create_uncloaked_transaction(
+        sender_pkey,
+        output1.into(),
+        extended_output.into(),
+        unspent_iter,
+        amount,
+        payment_fee,
+        max_inputs_in_tx,
+    )
+}
+
+/// Create a new payment transaction to an uncloaked UTXO.
+fn create_uncloaked_transaction<'a, UnspentIter>(
+    sender_pkey: &PublicKey,
+    output1: Output,
+    extended_output: OutputValue,
+    unspent_iter: UnspentIter,
+    amount: i64,
+    payment_fee: i64,
+    max_inputs_in_tx: usize,
+) -> Result<(Vec<Output>, Vec<Output>, Fr, Vec<OutputValue>, i64), Error>
+where
+    UnspentIter: Iterator<Item = (PaymentOutput, i64)>,
+{
+    //
+    // Find inputs
+    //
+
+    trace!("Checking for available funds in the account...");
+    let fee = 2 * payment_fee;
+    let (inputs, fee, change) = find_utxo(unspent_iter, amount, fee, max_inputs_in_tx)?;
+    let inputs: Vec<Output> = inputs
+        .into_iter()
+        .map(|o| Output::PaymentOutput(o.clone()))
+        .collect();
+    assert!(!inputs.is_empty());
+    for input in &inputs {
+        debug!("Use UTXO: hash={}", Hash::digest(input));
+    }
+
+    //
+    // Create outputs
+    //
+
+    let mut outputs: Vec<Output> = Vec::<Output>::with_capacity(2);
+    let mut extended_outputs = Vec::with_capacity(2);
+
+    outputs.push(output1);
+    extended_outputs.push(extended_output);
+
+    // The first UTXO is uncloaked.
+    let mut gamma = Fr::zero();
+
+    if change > 0 {
+        // Create an output for change
+        trace!("Creating change UTXO...");
+        let data = PaymentPayloadData::Comment("Change".to_string());
+        let (output2, gamma2, _rvalue) =
+            PaymentOutput::with_payload(None, sender_pkey, change, data.clone())?;
+        info!(
+            "Created change UTXO: hash={}, recipient={}, change={}, data={:?}",
+            Hash::digest(&output2),
+            sender_pkey,
+            change,
+            data
+        );
+        let extended_output = PaymentValue {
+            output: output2.clone(),
+            rvalue: None,
+            recipient: *sender_pkey,
+            amount: change,
+            data: data.into(),
+            is_change: true,
+            locked_timestamp: None,
+        };
+        extended_outputs.push(extended_output.into());
+        outputs.push(output2.into());
+        gamma += gamma2;
+    }
+
+    info!(
+        "Created payment transaction: amount={}, withdrawn={}, change={}, fee={}",
+        amount,
+        amount + change + fee,
+        change,
+        fee
+    );
+
+    assert_eq!(extended_outputs.len(), outputs.len());
+    Ok((inputs, outputs, gamma, extended_outputs, fee))
+}
+
+/// Create a new transaction which spends a multi-signature UTXO.
+///
+/// The remainder goes back to a new multi-signature UTXO with the same
+/// recipients and threshold. The transaction must be co-signed
+/// using `PaymentTransaction::sign_multisig()` before sending.
+pub(crate) fn create_multisig_spending_transaction(
+    sender_skey: &SecretKey,
+    input: &MultisigOutput,
+    recipient: &PublicKey,
+    amount: i64,
+    payment_fee: i64,
+) -> Result<PaymentTransaction, Error> {
+    if amount < 0 {
+        return Err(WalletError::NegativeAmount(amount).into());
+    }
+
+    let mut fee = payment_fee;
+    let mut change = input.amount - amount - fee;
+    if change < 0 {
+        return Err(WalletError::NoEnoughToPay(input.amount, input.amount).into());
+    } else if change > payment_fee {
+        // Pay for the change UTXO.
+        fee += payment_fee;
+        change -= payment_fee;
+    } else {
+        // Not enough to pay for the change UTXO - burn the remainder as fee.
+        fee += change;
+        change = 0;
+    }
+
+    let inputs = vec![Output::MultisigOutput(input.clone())];
+    let mut outputs: Vec<Output> = Vec::<Output>::with_capacity(2);
+
+    // Co-signers must be able to check the recipient and the amount.
+    let gamma = Fr::zero();
+    let output1 = PublicPaymentOutput::new(recipient, amount);
+    info!(
+        "Created public payment UTXO: hash={}, recipient={}, amount={}",
+        Hash::digest(&output1),
+        recipient,
+        amount
+    );
+    outputs.push(output1.into());
+
+    if change > 0 {
+        let output2 = MultisigOutput::new(&input.recipients, input.threshold, change);
+        info!(
+            "Created multisig change UTXO: hash={}, change={}",
+            Hash::digest(&output2),
+            change
+        );
+        outputs.push(output2.into());
+    }
+
+    let tx = PaymentTransaction::new(sender_skey, &inputs, &outputs, &gamma, fee)?;
+    info!(
+        "Created multisig spending transaction: tx={}, input={}, amount={}, change={}, fee={}",
+        Hash::digest(&tx),
+        Hash::digest(input),
+        amount,
+        change,
+        fee
+    );
+    Ok(tx)
+}
+
+/// Check that a transaction spending multi-signature `inputs` pays exactly `amount`
+/// to `recipient` with `fee` and returns the change to the same co-signers.
+pub(crate) fn check_multisig_spending_transaction(
+    tx: &PaymentTransaction,
+    inputs: &[MultisigOutput],
+    recipient: &PublicKey,
+    amount: i64,
+    fee: i64,
+) -> Result<(), Error> {
+    let tx_hash = Hash::digest(tx);
+    let mut paid: i64 = 0;
+    for output in &tx.txouts {
+        match output {
+            Output::PublicPaymentOutput(o)
+                if o.recipient == *recipient && o.locked_timestamp.is_none() =>
+            {
+                paid += o.amount;
+            }
+            Output::MultisigOutput(o)
+                if inputs
+                    .iter()
+                    .all(|i| i.recipients == o.recipients && i.threshold == o.threshold) => {}
+            _ => {
+                return Err(
+                    WalletError::UnexpectedMultisigOutput(tx_hash, Hash::digest(output)).into(),
+                );
+            }
+        }
+    }
+    if paid != amount || tx.fee != fee {
+        return Err(
+            WalletError::MultisigTransactionMismatch(tx_hash, amount, paid, fee, tx.fee).into(),
+        );
+    }
+    Ok(())
+}
+
+/// Create a new transaction which spends a hash-time-locked UTXO to the account.
+///
+/// Claims UTXO if `preimage` is provided, refunds it otherwise.
+pub(crate) fn create_htlc_spending_transaction(
+    sender_skey: &SecretKey,
+    sender_pkey: &PublicKey,
+    input: &HtlcOutput,
+    preimage: Option<Vec<u8>>,
+    payment_fee: i64,
+) -> Result<(PaymentTransaction, PaymentValue), Error> {
+    let amount = input.amount - payment_fee;
+    if amount <= 0 {
+        return Err(WalletError::AmountTooSmall(payment_fee, input.amount).into());
+    }
+
+    let input_hash = Hash::digest(input);
+    let (comment, htlc_preimages) = match preimage {
+        Some(preimage) => {
+            let htlc_preimage = HtlcPreimage {
+                txin: input_hash,
+                preimage,
+            };
+            ("HTLC claim", vec![htlc_preimage])
+        }
+        None => ("HTLC refund", Vec::new()),
+    };
+
+    let inputs = [Output::HtlcOutput(input.clone())];
+    let data = PaymentPayloadData::Comment(comment.to_string());
+    let (output, gamma, _rvalue) =
+        PaymentOutput::with_payload(None, sender_pkey, amount, data.clone())?;
+    info!(
+        "Created payment UTXO: hash={}, recipient={}, amount={}, data={:?}",
+        Hash::digest(&output),
+        sender_pkey,
+        amount,
+        data
+    );
+    let extended_output = PaymentValue {
+        output: output.clone(),
+        rvalue: None,
+        recipient: *sender_pkey,
+        amount,
+        data,
+        is_change: false,
+        locked_timestamp: None,
+    };
+
+    let tx = PaymentTransaction::with_htlc_preimages(
+        sender_skey,
+        &inputs,
+        &[output.into()],
+        &gamma,
+        payment_fee,
+        htlc_preimages,
+    )?;
+    info!(
+        "Created HTLC spending transaction: tx={}, input={}, amount={}, fee={}",
+        Hash::digest(&tx),
+        input_hash,
+        amount,
+        payment_fee
+    );
+    Ok((tx, extended_output))
+}
+
 /// Create a new staking transaction.
+/// Money is delegated to the validator if `validator_skey` is not known.
 pub(crate) fn create_staking_transaction<'a, UnspentIter>(
     sender_skey: &SecretKey,
     sender_pkey: &PublicKey,
     validator_pkey: &pbc::PublicKey,
-    validator_skey: &pbc::SecretKey,
+    validator_skey: Option<&pbc::SecretKey>,
     unspent_iter: UnspentIter,
     amount: i64,
     payment_fee: i64,
@@ -367,7 +708,12 @@ where
 
     // Create an output for staking.
     trace!("Creating stake UTXO...");
-    let output1 = StakeOutput::new(sender_pkey, validator_skey, validator_pkey, amount)?;
+    let output1 = match validator_skey {
+        Some(validator_skey) => {
+            StakeOutput::new(sender_pkey, validator_skey, validator_pkey, amount)?
+        }
+        None => StakeOutput::new_delegated(sender_pkey, validator_pkey, amount)?,
+    };
     info!(
         "Created stake UTXO: hash={}, recipient={}, validator={}, amount={}",
         Hash::digest(&output1),
@@ -402,6 +748,7 @@ where
             recipient: *sender_pkey,
             amount: change,
             is_change: true,
+            locked_timestamp: None,
         };
 
         extended_outputs.push(extended_output.into());
@@ -426,12 +773,13 @@ where
 }
 
 /// Create a new unstaking transaction.
+/// The rest of delegated stakes is delegated again if `validator_skey` is not known.
 /// NOTE: amount must include PAYMENT_FEE.
 pub(crate) fn create_unstaking_transaction<'a, UnspentIter>(
     sender_skey: &SecretKey,
     sender_pkey: &PublicKey,
     validator_pkey: &pbc::PublicKey,
-    validator_skey: &pbc::SecretKey,
+    validator_skey: Option<&pbc::SecretKey>,
     unspent_iter: UnspentIter,
     amount: i64,
     payment_fee: i64,
@@ -507,6 +855,7 @@ where
         recipient: *sender_pkey,
         amount,
         is_change: false,
+        locked_timestamp: None,
     };
 
     extended_outputs.push(extended_output.into());
@@ -518,7 +867,12 @@ where
         // Create an output for staking.
         assert_eq!(fee, payment_fee + stake_fee);
         trace!("Creating stake UTXO...");
-        let output2 = StakeOutput::new(sender_pkey, validator_skey, validator_pkey, change)?;
+        let output2 = match validator_skey {
+            Some(validator_skey) => {
+                StakeOutput::new(sender_pkey, validator_skey, validator_pkey, change)?
+            }
+            None => StakeOutput::new_delegated(sender_pkey, validator_pkey, change)?,
+        };
         info!(
             "Created stake UTXO: hash={}, validator={}, amount={}",
             Hash::digest(&output2),
@@ -544,6 +898,106 @@ where
     Ok((tx, extended_outputs))
 }
 
+/// Create a new unbonding transaction, which moves a part of active stakes
+/// into the unbonding state.
+/// NOTE: fee is paid from the stake.
+pub(crate) fn create_unbonding_transaction<'a, UnspentIter>(
+    sender_skey: &SecretKey,
+    sender_pkey: &PublicKey,
+    validator_pkey: &pbc::PublicKey,
+    validator_skey: &pbc::SecretKey,
+    unspent_iter: UnspentIter,
+    amount: i64,
+    payment_fee: i64,
+    stake_fee: i64,
+    max_inputs_in_tx: usize,
+) -> Result<(PaymentTransaction, Vec<OutputValue>), Error>
+where
+    UnspentIter: Iterator<Item = StakeOutput>,
+{
+    if amount <= 0 {
+        return Err(WalletError::NegativeAmount(amount).into());
+    }
+
+    debug!(
+        "Creating an unbonding transaction: recipient={:?}, validator={:?}, amount={}",
+        sender_pkey, validator_pkey, amount
+    );
+
+    //
+    // Find inputs
+    //
+
+    trace!("Checking for staked money in the account...");
+    let unspent_iter = unspent_iter.map(|o| {
+        let amount = o.amount;
+        (o, amount)
+    });
+    let fee = payment_fee + stake_fee;
+    let (inputs, fee, change) = find_utxo(unspent_iter, amount, fee, max_inputs_in_tx)?;
+    let inputs: Vec<Output> = inputs
+        .into_iter()
+        .map(|o| Output::StakeOutput(o.clone()))
+        .collect();
+
+    debug!(
+        "Transaction preview: recipient={:?}, validator={:?}, unbonding={}, stake={}, fee={}",
+        sender_pkey, validator_pkey, amount, change, fee
+    );
+    for input in &inputs {
+        debug!("Use stake UTXO: hash={:?}", Hash::digest(input));
+    }
+
+    //
+    // Create outputs
+    //
+
+    let mut outputs: Vec<Output> = Vec::<Output>::with_capacity(2);
+    let mut extended_outputs = Vec::with_capacity(2);
+
+    trace!("Creating unbonding UTXO...");
+    let output1 = StakeOutput::new_unbonding(sender_pkey, validator_skey, validator_pkey, amount)?;
+    info!(
+        "Created unbonding UTXO: hash={}, validator={}, amount={}",
+        Hash::digest(&output1),
+        validator_pkey,
+        amount
+    );
+    let extended_output = StakeValue {
+        output: output1.clone(),
+        active_until_epoch: None,
+    };
+    extended_outputs.push(extended_output.into());
+    outputs.push(output1.into());
+
+    if change > 0 {
+        trace!("Creating stake UTXO...");
+        let output2 = StakeOutput::new(sender_pkey, validator_skey, validator_pkey, change)?;
+        info!(
+            "Created stake UTXO: hash={}, validator={}, amount={}",
+            Hash::digest(&output2),
+            validator_pkey,
+            change
+        );
+        let extended_output = StakeValue {
+            output: output2.clone(),
+            active_until_epoch: None,
+        };
+        extended_outputs.push(extended_output.into());
+        outputs.push(output2.into());
+    }
+
+    trace!("Signing transaction...");
+    let tx = PaymentTransaction::new(&sender_skey, &inputs, &outputs, &Fr::zero(), fee)?;
+    let tx_hash = Hash::digest(&tx);
+    info!(
+        "Signed unbonding transaction: hash={}, validator={}, unbonding={}, stake={}, fee={}",
+        tx_hash, validator_pkey, amount, change, fee
+    );
+
+    Ok((tx, extended_outputs))
+}
+
 /// Create a restaking transaction.
 pub(crate) fn create_restaking_transaction<'a, UnspentIter>(
     _sender_skey: &SecretKey,
@@ -566,7 +1020,7 @@ where
     for input in stakes_iter {
         debug!(
             "Unstake: hash={}, validator={}, amount={}",
-            Hash::digest(&input),
+            Hash::digest(input),
             validator_pkey,
             input.amount
         );
@@ -633,7 +1087,7 @@ pub mod tests {
             &skey,
             &pkey,
             &validator_pkey,
-            &validator_skey,
+            Some(&validator_skey),
             unspent.clone().into_iter(),
             stake - payment_fee,
             payment_fee,
@@ -641,7 +1095,7 @@ pub mod tests {
             max_inputs_in_tx,
         )
         .expect("tx is created");
-        tx.validate(&inputs).expect("tx is valid");
+        tx.validate(&inputs, Timestamp::now()).expect("tx is valid");
         assert_eq!(tx.fee, full_fee);
         assert_eq!(tx.txouts.len(), 1);
         match &tx.txouts.first().unwrap() {
@@ -659,7 +1113,7 @@ pub mod tests {
             &skey,
             &pkey,
             &validator_pkey,
-            &validator_skey,
+            Some(&validator_skey),
             unspent.clone().into_iter(),
             unstake,
             payment_fee,
@@ -667,7 +1121,7 @@ pub mod tests {
             max_inputs_in_tx,
         )
         .expect("tx is created");
-        tx.validate(&inputs).expect("tx is valid");
+        tx.validate(&inputs, Timestamp::now()).expect("tx is valid");
         assert_eq!(tx.fee, full_fee);
         assert_eq!(tx.txouts.len(), 2);
         match &tx.txouts[0] {
@@ -690,7 +1144,7 @@ pub mod tests {
             &skey,
             &pkey,
             &validator_pkey,
-            &validator_skey,
+            Some(&validator_skey),
             unspent.clone().into_iter(),
             payment_fee - 1,
             payment_fee,
@@ -708,7 +1162,7 @@ pub mod tests {
             &skey,
             &pkey,
             &validator_pkey,
-            &validator_skey,
+            Some(&validator_skey),
             unspent.clone().into_iter(),
             payment_fee,
             payment_fee,
@@ -727,7 +1181,7 @@ pub mod tests {
             &skey,
             &pkey,
             &validator_pkey,
-            &validator_skey,
+            Some(&validator_skey),
             unspent.clone().into_iter(),
             unstake,
             payment_fee,
@@ -743,4 +1197,218 @@ pub mod tests {
             _ => panic!(),
         }
     }
+
+    /// Check moving of a part of stake into the unbonding state.
+    #[test]
+    fn unbonding_transactions() {
+        let payment_fee: i64 = 1;
+        let stake_fee: i64 = 0;
+        let max_inputs_in_tx: usize = 3;
+
+        let (skey, pkey) = make_random_keys();
+        let (validator_skey, validator_pkey) = pbc::make_random_keys();
+
+        let stake: i64 = 100;
+        let output = StakeOutput::new(&pkey, &validator_skey, &validator_pkey, stake)
+            .expect("keys are valid");
+        let inputs = [Output::StakeOutput(output.clone())];
+        let unspent: Vec<StakeOutput> = vec![output];
+
+        let unbonding = 30;
+        let (tx, _) = create_unbonding_transaction(
+            &skey,
+            &pkey,
+            &validator_pkey,
+            &validator_skey,
+            unspent.clone().into_iter(),
+            unbonding,
+            payment_fee,
+            stake_fee,
+            max_inputs_in_tx,
+        )
+        .expect("tx is created");
+        tx.validate(&inputs, Timestamp::now()).expect("tx is valid");
+        assert_eq!(tx.fee, payment_fee);
+        assert_eq!(tx.txouts.len(), 2);
+        match &tx.txouts[0] {
+            Output::StakeOutput(o) => {
+                assert!(o.unbonding);
+                assert_eq!(o.amount, unbonding);
+            }
+            _ => panic!("invalid tx"),
+        }
+        match &tx.txouts[1] {
+            Output::StakeOutput(o) => {
+                assert!(!o.unbonding);
+                assert_eq!(o.amount, stake - unbonding - payment_fee);
+            }
+            _ => panic!("invalid tx"),
+        }
+
+        // Unbond all of the stake.
+        let (tx, _) = create_unbonding_transaction(
+            &skey,
+            &pkey,
+            &validator_pkey,
+            &validator_skey,
+            unspent.into_iter(),
+            stake - payment_fee,
+            payment_fee,
+            stake_fee,
+            max_inputs_in_tx,
+        )
+        .expect("tx is created");
+        tx.validate(&inputs, Timestamp::now()).expect("tx is valid");
+        assert_eq!(tx.txouts.len(), 1);
+    }
+
+    /// Check spending of multi-signature UTXO.
+    #[test]
+    fn multisig_transactions() {
+        let payment_fee: i64 = 1;
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let (skey1, pkey1) = make_random_keys();
+        let (skey2, pkey2) = make_random_keys();
+        let (_skey3, pkey3) = make_random_keys();
+        let (_recipient_skey, recipient_pkey) = make_random_keys();
+
+        let amount: i64 = 100;
+        let output = MultisigOutput::new(&[pkey1, pkey2, pkey3], 2, amount);
+        let output_hash = Hash::digest(&output);
+        let inputs = [Output::MultisigOutput(output.clone())];
+
+        // Spend a part of UTXO, the remainder goes to a new multisig UTXO.
+        let spend: i64 = 10;
+        let mut tx = create_multisig_spending_transaction(
+            &skey1,
+            &output,
+            &recipient_pkey,
+            spend,
+            payment_fee,
+        )
+        .expect("tx is created");
+        assert_eq!(tx.fee, 2 * payment_fee);
+        assert_eq!(tx.txouts.len(), 2);
+        match &tx.txouts[1] {
+            Output::MultisigOutput(o) => {
+                assert_eq!(o.recipients, output.recipients);
+                assert_eq!(o.threshold, output.threshold);
+                assert_eq!(o.amount, amount - spend - 2 * payment_fee);
+            }
+            _ => panic!("invalid tx"),
+        }
+
+        // Not enough signatures.
+        tx.sign_multisig(&output_hash, &skey1);
+        tx.validate(&inputs, Timestamp::now()).unwrap_err();
+
+        // The same co-signer twice.
+        tx.sign_multisig(&output_hash, &skey1);
+        assert_eq!(tx.multisigs.len(), 1);
+        tx.validate(&inputs, Timestamp::now()).unwrap_err();
+
+        // Co-signers check the transaction before signing.
+        let fee = 2 * payment_fee;
+        check_multisig_spending_transaction(&tx, &[output.clone()], &recipient_pkey, spend, fee)
+            .expect("tx matches");
+        for (recipient, spend, fee) in &[
+            (recipient_pkey, spend + 1, fee),
+            (recipient_pkey, spend, fee + 1),
+            (pkey3, spend, fee),
+        ] {
+            check_multisig_spending_transaction(&tx, &[output.clone()], recipient, *spend, *fee)
+                .unwrap_err();
+        }
+        let other = MultisigOutput::new(&[pkey1, pkey2], 2, amount);
+        check_multisig_spending_transaction(&tx, &[other], &recipient_pkey, spend, fee)
+            .unwrap_err();
+
+        // 2-of-3.
+        tx.sign_multisig(&output_hash, &skey2);
+        tx.validate(&inputs, Timestamp::now()).expect("tx is valid");
+
+        // Spend everything except fee.
+        let tx = create_multisig_spending_transaction(
+            &skey1,
+            &output,
+            &recipient_pkey,
+            amount - payment_fee,
+            payment_fee,
+        )
+        .expect("tx is created");
+        assert_eq!(tx.fee, payment_fee);
+        assert_eq!(tx.txouts.len(), 1);
+
+        // Not enough money.
+        let e = create_multisig_spending_transaction(
+            &skey1,
+            &output,
+            &recipient_pkey,
+            amount,
+            payment_fee,
+        )
+        .unwrap_err();
+        match e.downcast::<WalletError>().unwrap() {
+            WalletError::NoEnoughToPay(..) => {}
+            e => panic!("{}", e),
+        }
+    }
+
+    /// Check claiming and refunding of hash-time-locked UTXO.
+    #[test]
+    fn htlc_transactions() {
+        let payment_fee: i64 = 1;
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let (recipient_skey, recipient_pkey) = make_random_keys();
+        let (refunder_skey, refunder_pkey) = make_random_keys();
+        let preimage = b"secret".to_vec();
+        let deadline = Timestamp::now();
+        let amount: i64 = 100;
+        let output = HtlcOutput::new(
+            &recipient_pkey,
+            &refunder_pkey,
+            htlc_hash_lock(&preimage),
+            deadline,
+            amount,
+        );
+        let inputs = [Output::HtlcOutput(output.clone())];
+
+        // Claim.
+        let (tx, value) = create_htlc_spending_transaction(
+            &recipient_skey,
+            &recipient_pkey,
+            &output,
+            Some(preimage),
+            payment_fee,
+        )
+        .expect("tx is created");
+        assert_eq!(value.amount, amount - payment_fee);
+        assert_eq!(tx.htlc_preimages.len(), 1);
+        tx.validate(&inputs, deadline - std::time::Duration::from_secs(1))
+            .expect("tx is valid");
+
+        // Refund.
+        let (tx, value) = create_htlc_spending_transaction(
+            &refunder_skey,
+            &refunder_pkey,
+            &output,
+            None,
+            payment_fee,
+        )
+        .expect("tx is created");
+        assert_eq!(value.recipient, refunder_pkey);
+        assert!(tx.htlc_preimages.is_empty());
+        tx.validate(&inputs, deadline).expect("tx is valid");
+
+        // Fee is greater than amount.
+        let e =
+            create_htlc_spending_transaction(&refunder_skey, &refunder_pkey, &output, None, amount)
+                .unwrap_err();
+        match e.downcast::<WalletError>().unwrap() {
+            WalletError::AmountTooSmall(..) => {}
+            e => panic!("{}", e),
+        }
+    }
 }